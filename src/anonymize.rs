@@ -0,0 +1,411 @@
+//! Anonymizing exports, so ones that break parsing can be attached to issues.
+//!
+//! The file is changed token by token and everything else is left as it
+//! was: delimiters, quoting, line endings and the encoding. Words become
+//! made up ones of the same length and case, digits become random ones and
+//! IBANs become fakes with a valid checksum. The same word or IBAN is always
+//! replaced by the same fake, so rules matching payees still group alike.
+//!
+//! Kept are what the parsers rely on: header lines, dates and times, xml
+//! markup, json keys, the field tags and codes of MT940 and a vocabulary of
+//! booking texts and codes. Balances don't add up anymore afterwards.
+
+use std::{collections::HashMap, fs, io::Cursor, path::PathBuf, sync::LazyLock};
+
+use encoding_rs::WINDOWS_1252;
+use fastrand::Rng;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use regex::{Captures, Regex};
+use rusty_money::iso;
+use tracing::{info, warn};
+
+use crate::inputs::{zipped, Format};
+
+/// Words kept as they are, compared in lowercase.
+const VOCABULARY: &[&str] = &[
+    // Booking texts
+    "abschluss",
+    "bargeld",
+    "bargeldauszahlung",
+    "basislastschrift",
+    "dauerauftrag",
+    "einzahlung",
+    "entgelt",
+    "gehalt",
+    "gutschrift",
+    "kartenzahlung",
+    "lastschrift",
+    "lohn",
+    "rente",
+    "überweisung",
+    "ueberweisung",
+    "umbuchung",
+    "zinsen",
+    "sepa",
+    "echtzeitüberweisung",
+    "card",
+    "credit",
+    "debit",
+    "deposit",
+    "fee",
+    "interest",
+    "payment",
+    "refund",
+    "transfer",
+    "withdrawal",
+    // Preambles and footers
+    "girokonto",
+    "iban",
+    "bic",
+    "kontoinhaber",
+    "kontonummer",
+    "kontostand",
+    "tage",
+    "umsätze",
+    "zeitraum",
+    "soll",
+    "haben",
+    // Codes of camt, MT940 and SEPA purposes
+    "book",
+    "crdt",
+    "dbit",
+    "pdng",
+    "pmnt",
+    "rcdt",
+    "icdt",
+    "rddt",
+    "iddt",
+    "esct",
+    "dmct",
+    "nchg",
+    "nddt",
+    "nmsc",
+    "nonref",
+    "nsto",
+    "ntrf",
+    "notprovided",
+    "eref",
+    "kref",
+    "mref",
+    "cred",
+    "svwz",
+    "abwa",
+    "abwe",
+    // json
+    "true",
+    "false",
+    "null",
+];
+
+/// Tokens kept as they are, anything else in a line is free for fakes.
+static KEPT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        // xml markup and json keys
+        r#"<[^>]*>|"[^"\n]*"\s*:"#,
+        // MT940 field tags, the transaction code of :86: and its subfields
+        r"|^:86:\d{3}|^:\d{2}[A-Z]?:|\?\d{2}",
+        // Dates and times
+        r"|\b\d{4}-\d{2}-\d{2}(?:T\d{2}:\d{2}(?::\d{2})?)?\b",
+        r"|\b\d{1,2}\.\d{1,2}\.\d{2,4}\b|\b\d{1,2}/\d{1,2}/\d{2,4}\b",
+        r"|\b\d{1,2}:\d{2}(?::\d{2})?\b",
+        // Event codes, as in T0006 of PayPal
+        r"|\b[A-Z]\d{4}\b",
+    ))
+    .unwrap()
+});
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?P<iban>\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]){11,30}\b)",
+        r"|(?P<word>\p{L}+)|(?P<digits>\d+)",
+    ))
+    .unwrap()
+});
+/// The dates, sign and code of MT940 transactions, only the amount is free.
+static MT940_TRANSACTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(:61:\d{6}(?:\d{4})?R?[CD][A-Z]?)([\d,]+)(N[A-Z0-9]{3})?").unwrap()
+});
+/// The sign, date and currency of MT940 balances.
+static MT940_BALANCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^:6[0-5][FM]?:[CD]\d{6}[A-Z]{3}").unwrap());
+
+/// Options for anonymizing an export.
+#[derive(Debug, clap::Args)]
+pub struct Anonymize {
+    /// Format of the input, to check the result still reads the same
+    /// [default: detected from its content]
+    #[arg(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// Export to anonymize
+    pub input: PathBuf,
+    /// File the anonymized export is written to
+    #[arg(short, long)]
+    pub output: PathBuf,
+    /// Seed of the fakes, the same seed gives the same fakes
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl Anonymize {
+    pub fn run(&self) -> Result<()> {
+        let content = fs::read(&self.input)
+            .into_diagnostic()
+            .wrap_err("Failed opening input file")?;
+        let format = self.format.clone().or_else(|| Format::detect(&content));
+        if zipped::is_zip(&self.input) || format.as_ref().is_some_and(|f| f.name() == "pdf") {
+            return Err(miette!("Only text exports can be anonymized"));
+        }
+
+        let mut anonymizer = Anonymizer::new(self.seed);
+        let anonymized = anonymizer.anonymize(&content);
+        fs::write(&self.output, &anonymized)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed writing {}", self.output.display()))?;
+
+        let Some(format) = format else {
+            warn!("Format not detected, the result is not checked");
+            return Ok(());
+        };
+        let (before, after) = (read(&format, content), read(&format, anonymized));
+        info!(
+            format = format.name(),
+            records = after.0,
+            errors = after.1,
+            "Anonymized export"
+        );
+        if before != after {
+            warn!(
+                ?before,
+                ?after,
+                "The anonymized export reads differently, check it still shows the problem"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Records and errors reading the export.
+fn read(format: &Format, content: Vec<u8>) -> (usize, usize) {
+    format
+        .read(Box::new(Cursor::new(content)))
+        .fold((0, 0), |(records, errors), record| match record {
+            Ok(_) => (records + 1, errors),
+            Err(_) => (records, errors + 1),
+        })
+}
+
+struct Anonymizer {
+    rng: Rng,
+    words: HashMap<String, String>,
+    ibans: HashMap<String, String>,
+}
+
+impl Anonymizer {
+    fn new(seed: Option<u64>) -> Self {
+        Self {
+            rng: seed.map_or_else(Rng::new, Rng::with_seed),
+            words: HashMap::new(),
+            ibans: HashMap::new(),
+        }
+    }
+
+    fn anonymize(&mut self, content: &[u8]) -> Vec<u8> {
+        // Exports are utf-8 or, most German ones, Windows-1252
+        let (text, utf8) = match std::str::from_utf8(content) {
+            Ok(text) => (text.to_string(), true),
+            Err(_) => (WINDOWS_1252.decode(content).0.into_owned(), false),
+        };
+
+        let anonymized: String = text
+            .split_inclusive('\n')
+            .map(|line| self.line(line))
+            .collect();
+        match utf8 {
+            true => anonymized.into_bytes(),
+            false => WINDOWS_1252.encode(&anonymized).0.into_owned(),
+        }
+    }
+
+    fn line(&mut self, line: &str) -> String {
+        if is_header(line) {
+            return line.to_string();
+        }
+        if let Some(caps) = MT940_TRANSACTION.captures(line) {
+            let (all, prefix) = (&caps[0], &caps[1]);
+            let amount = self.digits(&caps[2]);
+            let code = caps.get(3).map_or("", |c| c.as_str());
+            return format!(
+                "{}{}{}{}",
+                prefix,
+                amount,
+                code,
+                self.text(&line[all.len()..])
+            );
+        }
+        if let Some(balance) = MT940_BALANCE.find(line) {
+            return format!("{}{}", balance.as_str(), self.text(&line[balance.end()..]));
+        }
+        self.text(line)
+    }
+
+    /// Replaces the tokens of `text` outside of kept ones.
+    fn text(&mut self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        for kept in KEPT.find_iter(text) {
+            result.push_str(&self.free(&text[last..kept.start()]));
+            result.push_str(kept.as_str());
+            last = kept.end();
+        }
+        result.push_str(&self.free(&text[last..]));
+        result
+    }
+
+    fn free(&mut self, text: &str) -> String {
+        TOKEN
+            .replace_all(text, |caps: &Captures| {
+                if let Some(iban) = caps.name("iban") {
+                    self.iban(iban.as_str())
+                } else if let Some(word) = caps.name("word") {
+                    self.word(word.as_str())
+                } else {
+                    self.digits(&caps[0])
+                }
+            })
+            .into_owned()
+    }
+
+    fn word(&mut self, word: &str) -> String {
+        let lower = word.to_lowercase();
+        let currency = word.len() == 3 && iso::find(word).is_some();
+        // Single letters are markers like S and H
+        if word.chars().count() == 1 || currency || VOCABULARY.contains(&lower.as_str()) {
+            return word.to_string();
+        }
+
+        let fake = match self.words.get(&lower) {
+            Some(fake) => fake.clone(),
+            None => {
+                let fake = self.fake_word(lower.chars().count());
+                self.words.insert(lower, fake.clone());
+                fake
+            }
+        };
+        word.chars()
+            .zip(fake.chars())
+            .map(|(original, fake)| match original.is_uppercase() {
+                true => fake.to_ascii_uppercase(),
+                false => fake,
+            })
+            .collect()
+    }
+
+    /// A pronounceable word of `len` letters.
+    fn fake_word(&mut self, len: usize) -> String {
+        const CONSONANTS: &[u8] = b"bdfghklmnprstvwz";
+        const VOWELS: &[u8] = b"aeiou";
+        let offset = self.rng.usize(0..2);
+        (0..len)
+            .map(|idx| {
+                let letters = match (idx + offset) % 2 {
+                    0 => CONSONANTS,
+                    _ => VOWELS,
+                };
+                letters[self.rng.usize(0..letters.len())] as char
+            })
+            .collect()
+    }
+
+    fn digits(&mut self, digits: &str) -> String {
+        let mut seen = 0;
+        digits
+            .chars()
+            .map(|c| {
+                if !c.is_ascii_digit() {
+                    return c;
+                }
+                seen += 1;
+                // No leading zeros where there were none
+                match seen == 1 && c != '0' {
+                    true => self.rng.char('1'..='9'),
+                    false => self.rng.char('0'..='9'),
+                }
+            })
+            .collect()
+    }
+
+    /// A fake of the same country and length, with a valid checksum.
+    fn iban(&mut self, iban: &str) -> String {
+        if let Some(fake) = self.ibans.get(iban) {
+            return fake.clone();
+        }
+
+        let country = &iban[..2];
+        let bban: String = iban[4..]
+            .chars()
+            .map(|c| match c.is_ascii_digit() {
+                true => self.rng.char('0'..='9'),
+                false => c,
+            })
+            .collect();
+        let check = 98 - mod97(&format!("{}{}00", bban.replace(' ', ""), country));
+        let fake = format!("{}{:02}{}", country, check, bban);
+        self.ibans.insert(iban.to_string(), fake.clone());
+        fake
+    }
+}
+
+/// Header lines have three or more fields and no digits.
+fn is_header(line: &str) -> bool {
+    let fields = [';', ',', '\t']
+        .iter()
+        .map(|d| line.matches(*d).count())
+        .max()
+        .unwrap_or(0);
+    fields >= 2 && !line.chars().any(|c| c.is_ascii_digit())
+}
+
+/// The remainder of the IBAN checksum, letters counting as 10 to 35.
+pub fn mod97(text: &str) -> u32 {
+    text.chars()
+        .filter_map(|c| c.to_digit(36))
+        .fold(0, |rest, value| match value {
+            0..=9 => (rest * 10 + value) % 97,
+            _ => (rest * 100 + value) % 97,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_anonymize() {
+        let input = "Kontoinhaber;Max Mustermann\n\
+            Buchungstag;Wert;Umsatzart;Begünstigter / Auftraggeber;IBAN;Betrag\n\
+            7.3.2024;7.3.2024;SEPA Lastschrift;Max Mustermann;DE02120300000000202051;-25,88\n\
+            :61:2401021229D25,88NDDTNONREF\n";
+
+        let mut anonymizer = Anonymizer::new(Some(7));
+        let anonymized = anonymizer.anonymize(&WINDOWS_1252.encode(input).0);
+        let anonymized = WINDOWS_1252.decode(&anonymized).0.into_owned();
+        let lines: Vec<&str> = anonymized.lines().collect();
+
+        assert_eq!(lines[1], input.lines().nth(1).unwrap());
+        let fields: Vec<&str> = lines[2].split(';').collect();
+        assert_eq!(&fields[..3], &["7.3.2024", "7.3.2024", "SEPA Lastschrift"]);
+        assert_ne!(fields[3], "Max Mustermann");
+        assert_eq!(fields[3].len(), "Max Mustermann".len());
+        // The same name gets the same fake
+        assert!(lines[0].ends_with(fields[3]));
+        assert_ne!(fields[4], "DE02120300000000202051");
+        assert!(fields[4].starts_with("DE"));
+        assert_eq!(mod97(&format!("{}{}", &fields[4][4..], &fields[4][..4])), 1);
+        assert!(fields[5].starts_with('-') && fields[5].len() == 6);
+        assert!(lines[3].starts_with(":61:2401021229D"));
+        assert!(lines[3].ends_with("NDDTNONREF"));
+
+        assert_eq!(mod97("120300000000202051DE02"), 1);
+    }
+}
@@ -0,0 +1,546 @@
+//! Archive of original exports.
+//!
+//! With `--archive <dir>` every converted input is copied into the archive
+//! together with a sidecar file holding the options it was converted with.
+//! `hbconv reapply` converts all archived exports again, picking up changed
+//! rules and config, and either rewrites the outputs or shows what would
+//! change. Each output is written once from the records of all exports
+//! converted into it, even if they were appended to before. The sqlite and
+//! xhb outputs keep what else they have, the records they already know get
+//! updated. Written apart, for a diff or into `--output-dir`, they start
+//! from a copy of the original. The `firefly3` and `actual` outputs are left
+//! out of a diff, as are binary outputs, and the credentials of the former,
+//! which are not archived, are read from the environment.
+
+use std::{
+    fs,
+    path::{self, Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use tracing::info;
+
+use crate::{
+    convert::{self, Args},
+    homebank::Record,
+    inputs::url,
+    outputs::{FanOut, Output, OutputFormat, OutputOptions},
+    report::HashedFile,
+};
+
+const SIDECAR_EXTENSION: &str = "hbconv.json";
+
+/// Sidecar describing one archived export.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ArchiveEntry {
+    /// Where the export was converted from
+    pub original: PathBuf,
+    pub archived_at: DateTime<Utc>,
+    /// Options of the conversion, with absolute paths
+    pub args: Args,
+}
+
+/// Options for re-running archived conversions.
+#[derive(Debug, clap::Args)]
+pub struct Reapply {
+    /// Archive directory given to `--archive` before
+    #[arg(long, env = "HBCONV_ARCHIVE")]
+    pub archive: PathBuf,
+    /// Print a diff against the existing outputs instead of overwriting them
+    #[arg(long)]
+    pub diff: bool,
+    /// Write the outputs into this directory instead of their original place,
+    /// keeping the subdirectories they were in
+    #[arg(long, conflicts_with = "diff")]
+    pub output_dir: Option<PathBuf>,
+    /// Config file to use instead of the one of the original conversion
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    /// Rule files to use instead of the ones of the original conversion
+    #[arg(long)]
+    pub rules: Vec<PathBuf>,
+    /// Personal access token for `firefly3` outputs, which is not archived
+    #[arg(long, env, hide_env_values = true)]
+    pub firefly_token: Option<String>,
+    /// API key for `actual` outputs, which is not archived
+    #[arg(long, env, hide_env_values = true)]
+    pub actual_api_key: Option<String>,
+}
+
+/// Copies the input of a finished conversion, read as `content`, into `dir`.
+/// Archiving the same export twice keeps a single copy with the latest
+/// options.
+pub fn store(dir: &Path, args: &Args, content: &[u8]) -> Result<PathBuf> {
+    fs::create_dir_all(dir)
+        .into_diagnostic()
+        .wrap_err("Failed creating archive directory")?;
+
+    let hashed = HashedFile::of(&args.input, content);
+    let name = args
+        .input
+        .file_name()
+        .ok_or_else(|| miette!("Input {} has no file name", args.input.display()))?;
+    let mut archived = format!("{}-", &hashed.sha256[..12]);
+    archived.push_str(&name.to_string_lossy());
+    let archived = dir.join(archived);
+
+    fs::write(&archived, content)
+        .into_diagnostic()
+        .wrap_err("Failed copying input into the archive")?;
+
+    let mut args = args.clone();
+    if url::url(&args.input).is_none() {
+        args.input = absolute(&args.input)?;
+    }
+    args.output = args
+        .output
+        .iter()
+        .map(|p| absolute(p))
+        .collect::<Result<_>>()?;
+    args.config = args.config.as_deref().map(absolute).transpose()?;
+    args.rules = args
+        .rules
+        .iter()
+        .map(|p| absolute(p))
+        .collect::<Result<_>>()?;
+    args.report = None;
+    args.archive = None;
+
+    let entry = ArchiveEntry {
+        original: args.input.clone(),
+        archived_at: Utc::now(),
+        args,
+    };
+    let json = serde_json::to_string_pretty(&entry).into_diagnostic()?;
+    fs::write(sidecar_path(&archived), json + "\n")
+        .into_diagnostic()
+        .wrap_err("Failed writing archive sidecar")?;
+
+    info!(archived = %archived.display(), "Archived input");
+    Ok(archived)
+}
+
+/// All archived exports in `dir` with their options, oldest first.
+pub fn entries(dir: &Path) -> Result<Vec<(PathBuf, ArchiveEntry)>> {
+    let read = fs::read_dir(dir)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed reading archive {}", dir.display()))?;
+
+    let mut entries = Vec::new();
+    for file in read {
+        let path = file.into_diagnostic()?.path();
+        let Some(archived) = path
+            .to_str()
+            .and_then(|p| p.strip_suffix(&format!(".{}", SIDECAR_EXTENSION)))
+            .map(PathBuf::from)
+        else {
+            continue;
+        };
+
+        let json = fs::read_to_string(&path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed reading {}", path.display()))?;
+        let entry: ArchiveEntry = serde_json::from_str(&json)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed parsing {}", path.display()))?;
+        entries.push((archived, entry));
+    }
+    entries.sort_by_key(|(_, entry)| entry.archived_at);
+
+    Ok(entries)
+}
+
+/// One output of the reapplied conversions, with the records of all archived
+/// exports written to it.
+struct Target {
+    path: PathBuf,
+    /// The records with the options they are written with. Outputs merging
+    /// into what they have get one batch per conversion, as identical
+    /// records are only told apart from those of overlapping exports within
+    /// one. Others get a single batch, with the options of the latest
+    /// conversion.
+    batches: Vec<(OutputOptions, Vec<Record>)>,
+}
+
+impl Target {
+    fn format(&self) -> OutputFormat {
+        self.batches
+            .last()
+            .map(|(options, _)| options.format(&self.path))
+            .unwrap_or_else(|| OutputFormat::detect(&self.path))
+    }
+
+    /// Whether the output merges into what it has instead of being
+    /// rewritten, the sqlite and xhb files and the APIs.
+    fn merges(&self) -> bool {
+        let format = self.format();
+        format.is_api() || matches!(format, OutputFormat::Sqlite | OutputFormat::Xhb)
+    }
+}
+
+impl Reapply {
+    pub fn run(&self) -> Result<()> {
+        // Check all entries before converting any, a missing token must not
+        // leave the outputs half rewritten
+        let runs = entries(&self.archive)?
+            .into_iter()
+            .map(|(archived, entry)| Ok((entry.original, self.args(archived, entry.args)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut targets: Vec<Target> = Vec::new();
+        for (original, args) in runs {
+            if args.output.is_empty() {
+                info!(original = %original.display(), "Nothing to diff, only API outputs");
+                continue;
+            }
+            info!(original = %original.display(), "Reapplying conversion");
+            let converted = convert::records(&args)?;
+            for part in convert::parts(&args, converted.records)? {
+                for path in part.outputs {
+                    let batch = (args.output_options.clone(), part.records.clone());
+                    let idx = match targets.iter().position(|t| t.path == path) {
+                        Some(idx) => idx,
+                        None => {
+                            targets.push(Target {
+                                path,
+                                batches: Vec::new(),
+                            });
+                            targets.len() - 1
+                        }
+                    };
+                    let target = &mut targets[idx];
+                    let merges = target.merges();
+                    match target.batches.last_mut() {
+                        Some((options, records)) if !merges => {
+                            *options = batch.0;
+                            records.extend(batch.1);
+                        }
+                        _ => target.batches.push(batch),
+                    }
+                }
+            }
+        }
+
+        let scratch = std::env::temp_dir().join(format!("hbconv-reapply-{}", std::process::id()));
+        let base = common_dir(&targets);
+        for (idx, target) in targets.iter().enumerate() {
+            let encrypted = target.batches.iter().any(|(o, _)| !o.encrypt_to.is_empty());
+            if self.diff && (!target.format().is_text() || encrypted) {
+                info!(output = %target.path.display(), "Leaving binary output out of the diff");
+                continue;
+            }
+
+            let path = self.target_path(&scratch, &base, idx, target)?;
+            for (options, records) in &target.batches {
+                let mut output = FanOut::open(&[&path], options)?;
+                for record in records {
+                    output.write(record)?;
+                }
+                output.finish()?;
+                info!(output = %path.display(), count = records.len(), "Wrote records");
+            }
+
+            if self.diff {
+                print!("{}", diff(&target.path, &path)?);
+            }
+        }
+
+        if self.diff && scratch.exists() {
+            fs::remove_dir_all(&scratch)
+                .into_diagnostic()
+                .wrap_err("Failed removing temporary outputs")?;
+        }
+
+        Ok(())
+    }
+
+    /// The options of an archived conversion for running it again from
+    /// `archived`.
+    fn args(&self, archived: PathBuf, mut args: Args) -> Result<Args> {
+        args.input = archived;
+        args.interactive = false;
+        args.resume = false;
+        // Every output is written once from all conversions into it
+        args.output_options.append = false;
+        args.output_options.replace = true;
+        if self.config.is_some() {
+            args.config.clone_from(&self.config);
+        }
+        if !self.rules.is_empty() {
+            args.rules.clone_from(&self.rules);
+        }
+
+        let options = &mut args.output_options;
+        if self.diff {
+            // A diff must not send anything
+            let format = |p: &PathBuf| options.format(p);
+            args.output.retain(|p| !format(p).is_api());
+        } else {
+            options.firefly_token = self.firefly_token.clone();
+            options.actual_api_key = self.actual_api_key.clone();
+            check_credentials(&args.output, options)?;
+        }
+        Ok(args)
+    }
+
+    /// Where to write `target`, apart from the original for a diff or with
+    /// `--output-dir`, below which it keeps its place relative to `base`. API
+    /// outputs stay as they are.
+    fn target_path(
+        &self,
+        scratch: &Path,
+        base: &Path,
+        idx: usize,
+        target: &Target,
+    ) -> Result<PathBuf> {
+        let dir = match (self.diff, &self.output_dir) {
+            _ if target.format().is_api() => return Ok(target.path.clone()),
+            (true, _) => scratch.join(idx.to_string()),
+            (false, Some(dir)) => dir.clone(),
+            (false, None) => return Ok(target.path.clone()),
+        };
+        // Outputs in different directories keep them apart below `dir`
+        let path = match target.path.strip_prefix(base) {
+            Ok(relative) => dir.join(relative),
+            Err(_) => dir.join(target.path.file_name().unwrap_or_default()),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .into_diagnostic()
+                .wrap_err("Failed creating output directory")?;
+        }
+
+        // The sqlite and xhb files get a copy of the original to merge into
+        if target.merges() && target.path.exists() {
+            fs::copy(&target.path, &path)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed copying {}", target.path.display()))?;
+        }
+        Ok(path)
+    }
+}
+
+/// The deepest directory holding all file outputs of `targets`.
+fn common_dir(targets: &[Target]) -> PathBuf {
+    let mut dirs = targets
+        .iter()
+        .filter(|t| !t.format().is_api())
+        .filter_map(|t| t.path.parent());
+    let Some(first) = dirs.next() else {
+        return PathBuf::new();
+    };
+    dirs.fold(first.to_path_buf(), |common, dir| {
+        common
+            .components()
+            .zip(dir.components())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    })
+}
+
+/// Fails if an API output lacks the credentials it is sent with.
+fn check_credentials(outputs: &[PathBuf], options: &OutputOptions) -> Result<()> {
+    if options.dry_run {
+        return Ok(());
+    }
+    for output in outputs {
+        match options.format(output) {
+            OutputFormat::Firefly3 if options.firefly_token.is_none() => {
+                return Err(miette!(
+                    help = "Set FIREFLY_TOKEN or pass --firefly-token",
+                    "Reapplying to firefly3 needs the token, which is not archived"
+                ))
+            }
+            OutputFormat::Actual if options.actual_api_key.is_none() => {
+                return Err(miette!(
+                    help = "Set ACTUAL_API_KEY or pass --actual-api-key",
+                    "Reapplying to actual needs the API key, which is not archived"
+                ))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Unified diff between an existing output and its regenerated version.
+fn diff(old: &Path, new: &Path) -> Result<String> {
+    // A missing old output shows up as everything being added
+    let before = fs::read_to_string(old).unwrap_or_default();
+    let after = fs::read_to_string(new)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed reading {}", new.display()))?;
+
+    let old = old.display().to_string();
+    Ok(TextDiff::from_lines(&before, &after)
+        .unified_diff()
+        .header(&old, &old)
+        .to_string())
+}
+
+fn sidecar_path(archived: &Path) -> PathBuf {
+    let mut name = archived.as_os_str().to_owned();
+    name.push(".");
+    name.push(SIDECAR_EXTENSION);
+    PathBuf::from(name)
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    path::absolute(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed resolving {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: Args,
+    }
+
+    #[test]
+    fn test_store_and_list() {
+        let dir = std::env::temp_dir().join(format!("hbconv-archive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("export.csv");
+        fs::write(&input, "abc").unwrap();
+
+        let args = Cli::parse_from([
+            "hbconv",
+            "-f",
+            "postbank",
+            "-o",
+            "out.csv",
+            input.to_str().unwrap(),
+        ])
+        .args;
+        let archive = dir.join("archive");
+        let archived = store(&archive, &args, b"abc").unwrap();
+        // Archiving again does not duplicate the export
+        store(&archive, &args, b"abc").unwrap();
+
+        assert_eq!(archived.file_name().unwrap(), "ba7816bf8f01-export.csv");
+        let entries = entries(&archive).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, archived);
+        assert_eq!(entries[0].1.original, input);
+        assert!(entries[0].1.args.output[0].is_absolute());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reapply_args() {
+        let args = Cli::parse_from([
+            "hbconv",
+            "-f",
+            "postbank",
+            "-o",
+            "/out/all.csv",
+            "-o",
+            "firefly3",
+            "--append",
+            "--firefly-url",
+            "http://firefly",
+            "export.csv",
+        ])
+        .args;
+        let mut reapply = Reapply {
+            archive: PathBuf::from("archive"),
+            diff: true,
+            output_dir: None,
+            config: None,
+            rules: Vec::new(),
+            firefly_token: None,
+            actual_api_key: None,
+        };
+        let archived = PathBuf::from("archive/ba7816bf8f01-export.csv");
+
+        // A diff sends nothing
+        let diff = reapply.args(archived.clone(), args.clone()).unwrap();
+        assert!(!diff.output_options.append);
+        assert!(diff.output_options.replace);
+        assert_eq!(diff.output, vec![PathBuf::from("/out/all.csv")]);
+        assert_eq!(diff.input, archived);
+
+        // The token was not archived and has to be given again
+        reapply.diff = false;
+        assert!(reapply.args(archived.clone(), args.clone()).is_err());
+        reapply.firefly_token = Some("token".to_string());
+        let rewrite = reapply.args(archived, args).unwrap();
+        assert_eq!(rewrite.output.len(), 2);
+        assert_eq!(
+            rewrite.output_options.firefly_token.as_deref(),
+            Some("token")
+        );
+    }
+
+    #[test]
+    fn test_target_path_copies_merged() {
+        let dir = std::env::temp_dir().join(format!("hbconv-target-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("home.xhb");
+        fs::write(&original, "<homebank/>").unwrap();
+
+        let reapply = Reapply {
+            archive: PathBuf::from("archive"),
+            diff: false,
+            output_dir: Some(dir.join("out")),
+            config: None,
+            rules: Vec::new(),
+            firefly_token: None,
+            actual_api_key: None,
+        };
+        let target = |path: &Path| Target {
+            path: path.to_path_buf(),
+            batches: vec![(OutputOptions::default(), Vec::new())],
+        };
+
+        // The xhb output merges into the original, a csv one starts afresh
+        let path = reapply
+            .target_path(&dir, &dir, 0, &target(&original))
+            .unwrap();
+        assert_eq!(path, dir.join("out/home.xhb"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<homebank/>");
+        let path = reapply
+            .target_path(&dir, &dir, 1, &target(&dir.join("all.csv")))
+            .unwrap();
+        assert!(!path.exists());
+
+        // Outputs of the same name in different directories stay apart
+        let targets = [
+            target(&dir.join("2023/homebank.csv")),
+            target(&dir.join("2024/homebank.csv")),
+            target(Path::new("firefly3")),
+        ];
+        let base = common_dir(&targets);
+        assert_eq!(base, dir);
+        let paths: Vec<_> = targets[..2]
+            .iter()
+            .enumerate()
+            .map(|(idx, t)| reapply.target_path(&dir, &base, idx, t).unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                dir.join("out/2023/homebank.csv"),
+                dir.join("out/2024/homebank.csv")
+            ]
+        );
+        assert!(dir.join("out/2024").is_dir());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
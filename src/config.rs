@@ -0,0 +1,99 @@
+//! The optional configuration file.
+//!
+//! It is looked up at `--config`, falling back to
+//! `$XDG_CONFIG_HOME/hbconv/config.toml` if that exists.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use miette::{Context, IntoDiagnostic, Result};
+use serde::Deserialize;
+
+use crate::{
+    inputs::url::Download, pipeline::Stage, profile::Profile, rules::Rule, split::SplitRule,
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub pipeline: PipelineConfig,
+    pub rules: Vec<Rule>,
+    pub splits: Vec<SplitRule>,
+    /// Per account defaults, selected with `--profile`
+    pub profiles: BTreeMap<String, Profile>,
+    /// Credentials and headers for downloading `https://` inputs
+    pub downloads: Vec<Download>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// Order of the pipeline stages. Transformation stages left out are
+    /// disabled, `decode`, `parse` and `write` may be left out as well but
+    /// always run.
+    pub stages: Vec<Stage>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            stages: Stage::DEFAULT.to_vec(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the given config file or the default one. Returns the path it
+    /// was read from, if any.
+    pub fn load(path: Option<&Path>) -> Result<(Self, Option<PathBuf>)> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match dirs::config_dir().map(|d| d.join("hbconv").join("config.toml")) {
+                Some(path) if path.exists() => path,
+                _ => return Ok((Self::default(), None)),
+            },
+        };
+
+        let config = read_toml(&path)?;
+        Ok((config, Some(path)))
+    }
+}
+
+/// Reads and deserializes a toml file.
+pub fn read_toml<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let content = fs::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed reading {}", path.display()))?;
+
+    toml::from_str(&content)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed parsing {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config: Config = toml::from_str(
+            r#"
+            [pipeline]
+            stages = ["rules", "dedupe"]
+
+            [[rules]]
+            payee = "(?i)rewe"
+            category = "Food:Groceries"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.pipeline.stages, vec![Stage::Rules, Stage::Dedupe]);
+        assert_eq!(config.rules.len(), 1);
+    }
+}
@@ -0,0 +1,269 @@
+//! The conversion of a single input file.
+
+use std::{io, path::PathBuf};
+
+use miette::{miette, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    archive,
+    config::Config,
+    enrich::PayeeLookup,
+    homebank::Record,
+    inputs::{self, Format, Input, InputOptions},
+    logging,
+    outputs::{
+        partition::{Part, Split},
+        FanOut, Output, OutputOptions,
+    },
+    pipeline::{Pipeline, Stage, Transforms},
+    profile::Profile,
+    rates::Conversion,
+    report::{HashedFile, RunReport, Skipped},
+    review::{Outcome, Review},
+    rules::Rules,
+    split::Splitter,
+};
+
+#[derive(Debug, Clone, clap::Args, Deserialize, Serialize)]
+pub struct Args {
+    /// File to write to, may be given several times to write all of them at once.
+    /// Unless `--output-format` is given the extension picks the format (.ofx,
+    /// .qfx, .ledger, .journal, .hledger, .beancount, .bean, .ynab.csv,
+    /// .mmex.csv, .firefly.csv, .json, .jsonl, .xlsx, .sqlite, .parquet), homebank csv
+    /// otherwise. An existing HomeBank file (.xhb) gets the records appended,
+    /// `firefly3` pushes them to the Firefly III API and `actual` to Actual Budget
+    #[arg(short, long, env, required = true)]
+    pub output: Vec<PathBuf>,
+    /// Write several files per output, filling `{year}`, `{month}` and
+    /// `{account}` (the profile or the input file name, by-account the name of
+    /// each file read) in its path
+    #[arg(long, env, value_enum)]
+    pub split: Option<Split>,
+    #[command(flatten)]
+    pub output_options: OutputOptions,
+    // clap leaves the group of a struct with flattened fields empty, so
+    // `Option<Args>` never matched without adding a member by hand
+    #[arg(group = "Args")]
+    pub input: PathBuf,
+    /// Format of the input [default: detected from its content]
+    #[arg(short, long, env, value_enum)]
+    pub format: Option<Format>,
+    /// Sheet to read from spreadsheet inputs (.xlsx, .xls, .ods) [default: the first]
+    #[arg(long, env)]
+    pub sheet: Option<String>,
+    /// Only convert the files of zip inputs matching this glob, as in `*/giro_*.csv`
+    #[arg(long, env)]
+    pub inner: Option<String>,
+    /// Decrypt the input with gpg, keeping the plaintext in memory only
+    #[arg(long, env)]
+    pub decrypt: bool,
+    #[command(flatten)]
+    pub input_options: InputOptions,
+    /// Review every record and set its category and tags before writing
+    #[arg(short, long)]
+    pub interactive: bool,
+    /// Continue an aborted interactive review
+    #[arg(long, requires = "interactive")]
+    pub resume: bool,
+    /// Where to keep the interactive review progress [default: <INPUT>.review.jsonl]
+    #[arg(long, requires = "interactive")]
+    pub session: Option<PathBuf>,
+    /// Profile from the config file with the defaults of this account
+    #[arg(short, long, env = "HBCONV_PROFILE")]
+    pub profile: Option<String>,
+    /// Convert all amounts into this currency using the ECB reference rates
+    #[arg(long, env)]
+    pub convert_to: Option<String>,
+    /// Only use already cached exchange rates
+    #[arg(long, requires = "convert_to")]
+    pub offline: bool,
+    /// Config file [default: $XDG_CONFIG_HOME/hbconv/config.toml]
+    #[arg(short, long, env = "HBCONV_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Additional rule files, applied after the rules from the config file
+    #[arg(long)]
+    pub rules: Vec<PathBuf>,
+    /// Keep payee and memo as they are instead of repairing broken encodings
+    #[arg(long)]
+    pub no_repair: bool,
+    /// Shell command mapping `<payee>\t<iban>` on stdin to a better payee on stdout
+    #[arg(long, env)]
+    pub payee_lookup_cmd: Option<String>,
+    /// Write a json report about this run, including hashes of all inputs and outputs
+    #[arg(long, env)]
+    pub report: Option<PathBuf>,
+    /// Keep a copy of the input and these options in this directory, so
+    /// `hbconv reapply` can convert it again later
+    #[arg(long, env = "HBCONV_ARCHIVE")]
+    pub archive: Option<PathBuf>,
+}
+
+/// The records of a conversion before they get written, with the report on
+/// them and the content of the input.
+pub struct Converted<'a> {
+    pub records: Vec<Record>,
+    report: RunReport<'a, Args>,
+    content: Vec<u8>,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let Converted {
+        records,
+        mut report,
+        content,
+    } = records(args)?;
+
+    // Only open the outputs once review is done, an aborted review must not
+    // leave truncated files behind.
+    let written = records.len();
+    let mut outputs = Vec::new();
+    for part in parts(args, records)? {
+        let mut output = FanOut::open(&part.outputs, &args.output_options)?;
+        for record in &part.records {
+            output.write(record)?;
+        }
+        output.finish()?;
+        outputs.extend(part.outputs);
+    }
+    info!(count = written, outputs = outputs.len(), "Wrote records");
+
+    if let Some(path) = &args.report {
+        report.counts.written = written;
+        // Outputs pushing to an API leave no file to hash
+        for output in outputs.iter().filter(|o| o.is_file()) {
+            report.outputs.push(HashedFile::new(output)?);
+        }
+        report.write(path)?;
+    }
+
+    if let Some(dir) = &args.archive {
+        archive::store(dir, args, &content)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the input of `args` and runs its records through the pipeline and
+/// the interactive review.
+pub fn records(args: &Args) -> Result<Converted<'_>> {
+    let (config, config_path) = Config::load(args.config.as_deref())?;
+    let mut pipeline = Pipeline::new(config.pipeline.stages)?;
+    if args.no_repair {
+        pipeline.disable(Stage::Repair);
+    }
+    let profile = Profile::select(config.profiles, args.profile.as_deref())?;
+    let mut rules = Rules::new(config.rules)?;
+    for path in &args.rules {
+        rules.load(path)?;
+    }
+
+    let content = inputs::read(&args.input, &config.downloads)?;
+    let hashed = HashedFile::of(&args.input, &content);
+    let inputs = inputs::open(
+        &args.input,
+        content.clone(),
+        args.format.as_ref(),
+        args.sheet.as_deref(),
+        args.inner.as_deref(),
+        args.decrypt,
+        &args.input_options,
+    )?;
+    let mut formats: Vec<String> = inputs.iter().map(Input::format_name).collect();
+    formats.dedup();
+
+    let mut report = RunReport::new(args, formats.join(","));
+    report.inputs.push(hashed);
+    report.stages = pipeline.stages().to_vec();
+    for path in config_path.iter().chain(&args.rules) {
+        report.config_files.push(HashedFile::new(path)?);
+    }
+
+    let mut records = Vec::new();
+    let mut index = 0;
+    for input in inputs {
+        let read = records.len();
+        let source = input.account();
+        let format = input.format_name();
+        for record in input.records {
+            match record {
+                Ok(mut r) => {
+                    r.source = source.clone();
+                    profile.apply(&mut r);
+                    records.push(r);
+                }
+                Err(err) => {
+                    let error = logging::chain(&err);
+                    warn!(%error, "Skipping record");
+                    report.skipped.push(Skipped { index, error });
+                }
+            }
+            index += 1;
+        }
+        info!(
+            count = records.len() - read,
+            input = input.name,
+            format,
+            "Read records"
+        );
+    }
+    report.counts.read = records.len() + report.skipped.len();
+    report.counts.skipped = report.skipped.len();
+
+    let mut transforms = Transforms {
+        lookup: args.payee_lookup_cmd.clone().map(PayeeLookup::new),
+        rules,
+        splitter: Splitter::new(config.splits)?,
+        conversion: args
+            .convert_to
+            .as_deref()
+            .map(|target| Conversion::new(target, args.offline))
+            .transpose()?,
+        duplicates: 0,
+    };
+    records = pipeline.run(records, &mut transforms)?;
+    report.counts.duplicates = transforms.duplicates;
+
+    if args.interactive {
+        let session = args
+            .session
+            .clone()
+            .unwrap_or_else(|| Review::session_path(&args.input));
+        let review = Review::open(session, args.resume)?;
+        records = match review.run(records, io::stdin().lock(), io::stderr())? {
+            Outcome::Done(records) => records,
+            Outcome::Aborted => return Err(miette!("Interactive review aborted")),
+        };
+    }
+
+    Ok(Converted {
+        records,
+        report,
+        content,
+    })
+}
+
+/// The outputs of `args` with the records going into them, the templates
+/// expanded with `--split`.
+pub fn parts(args: &Args, records: Vec<Record>) -> Result<Vec<Part>> {
+    match args.split {
+        Some(split) => split.partition(records, &args.output, &account_name(args)),
+        None => Ok(vec![Part {
+            outputs: args.output.clone(),
+            records,
+        }]),
+    }
+}
+
+/// Name of the account the input belongs to, for splitting the outputs.
+fn account_name(args: &Args) -> String {
+    match &args.profile {
+        Some(profile) => profile.clone(),
+        None => args
+            .input
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
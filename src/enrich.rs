@@ -0,0 +1,141 @@
+//! Payee enrichment through a user supplied command.
+//!
+//! The command is run through the shell once per distinct payee. It gets
+//! `<payee>\t<iban>\n` on stdin (and both as `HBCONV_PAYEE` / `HBCONV_IBAN`
+//! in its environment) and whatever it prints becomes the new payee. Empty
+//! output or a failing command keeps the original payee.
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use miette::{miette, Context, IntoDiagnostic, Result};
+use tracing::{debug, warn};
+
+use crate::homebank::Record;
+
+pub struct PayeeLookup {
+    cmd: String,
+    cache: HashMap<(String, String), Option<String>>,
+}
+
+impl PayeeLookup {
+    pub fn new(cmd: String) -> Self {
+        Self {
+            cmd,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn enrich(&mut self, record: &mut Record) {
+        let key = (record.payee.clone(), record.iban.clone());
+
+        let payee = match self.cache.get(&key) {
+            Some(payee) => payee.clone(),
+            None => {
+                let payee = match self.lookup(&key.0, &key.1) {
+                    Ok(payee) => payee,
+                    Err(err) => {
+                        warn!(payee = %key.0, "{:?}", err);
+                        None
+                    }
+                };
+                self.cache.insert(key, payee.clone());
+                payee
+            }
+        };
+
+        if let Some(payee) = payee {
+            debug!(from = %record.payee, to = %payee, "Enriched payee");
+            record.payee = payee;
+        }
+    }
+
+    fn lookup(&self, payee: &str, iban: &str) -> Result<Option<String>> {
+        let mut child = shell(&self.cmd)
+            .env("HBCONV_PAYEE", payee)
+            .env("HBCONV_IBAN", iban)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .into_diagnostic()
+            .wrap_err("Failed starting payee lookup command")?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| miette!("Payee lookup command has no stdin"))?;
+        writeln!(stdin, "{}\t{}", payee, iban)
+            .into_diagnostic()
+            .wrap_err("Failed writing to payee lookup command")?;
+        drop(stdin);
+
+        let output = child
+            .wait_with_output()
+            .into_diagnostic()
+            .wrap_err("Failed waiting for payee lookup command")?;
+        if !output.status.success() {
+            return Err(miette!(
+                "Payee lookup command failed with {}",
+                output.status
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let payee = stdout.trim();
+
+        Ok((!payee.is_empty()).then(|| payee.to_string()))
+    }
+}
+
+#[cfg(unix)]
+fn shell(cmd: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(cmd);
+    command
+}
+
+#[cfg(windows)]
+fn shell(cmd: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(cmd);
+    command
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    fn record(payee: &str, iban: &str) -> Record {
+        Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: payee.to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            iban: iban.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut lookup = PayeeLookup::new(
+            r#"read payee iban; [ "$iban" = DE123 ] && echo "Known $payee""#.to_string(),
+        );
+
+        let mut known = record("Woopsie", "DE123");
+        lookup.enrich(&mut known);
+        assert_eq!(known.payee, "Known Woopsie");
+
+        let mut unknown = record("Doopsie", "DE456");
+        lookup.enrich(&mut unknown);
+        assert_eq!(unknown.payee, "Doopsie");
+    }
+}
@@ -0,0 +1,560 @@
+//! FinTS 3.0 with PIN/TAN, the online banking protocol of German banks.
+//!
+//! A first dialog synchronizes to get a customer system id and the TAN
+//! methods of the user. The second one fetches the statement with HKKAZ,
+//! which the bank sends as MT940, following touchdown points until it sent
+//! everything. TANs are asked for on the terminal, decoupled methods like
+//! pushTAN are polled until approved in the app.
+
+use std::{
+    io::{self, BufRead, Write},
+    mem, thread,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Local, NaiveDate};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use tracing::{debug, info, warn};
+
+const COUNTRY: &str = "280";
+/// Security function of the one step procedure, only allowed to sync
+const ONE_STEP: &str = "999";
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct FintsOptions {
+    /// FinTS server url of the bank
+    #[arg(long, env)]
+    pub fints_url: Option<String>,
+    /// Bankleitzahl of the bank
+    #[arg(long, env)]
+    pub fints_blz: Option<String>,
+    /// Login name or user id for online banking
+    #[arg(long, env)]
+    pub fints_user: Option<String>,
+    /// Online banking PIN [default: asked for]
+    #[arg(long, env, hide_env_values = true)]
+    pub fints_pin: Option<String>,
+    /// IBAN of the account to fetch
+    #[arg(long, env)]
+    pub fints_iban: Option<String>,
+    /// BIC of the account to fetch
+    #[arg(long, env)]
+    pub fints_bic: Option<String>,
+    /// Security function of the TAN method, as in 942 [default: the first
+    /// one allowed]
+    #[arg(long, env)]
+    pub fints_tan_method: Option<String>,
+    /// Product id registered with the Deutsche Kreditwirtschaft, which banks
+    /// require from every FinTS client
+    #[arg(long, env)]
+    pub fints_product_id: Option<String>,
+}
+
+/// One segment, its data elements split into groups.
+#[derive(Debug, Clone, PartialEq)]
+struct Segment(Vec<Vec<String>>);
+
+impl Segment {
+    fn name(&self) -> &str {
+        self.get(0, 0)
+    }
+
+    fn version(&self) -> u32 {
+        self.get(0, 2).parse().unwrap_or_default()
+    }
+
+    fn get(&self, element: usize, group: usize) -> &str {
+        self.0
+            .get(element)
+            .and_then(|e| e.get(group))
+            .map_or("", String::as_str)
+    }
+}
+
+/// A segment to send, numbered when the message is built.
+struct Request {
+    name: &'static str,
+    version: u32,
+    body: String,
+}
+
+struct Response {
+    segments: Vec<Segment>,
+}
+
+impl Response {
+    fn segment(&self, name: &str) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.name() == name)
+    }
+
+    /// Return codes of the message and its segments, each as code,
+    /// reference, text and parameters.
+    fn codes(&self) -> impl Iterator<Item = &Vec<String>> {
+        self.segments
+            .iter()
+            .filter(|s| matches!(s.name(), "HIRMG" | "HIRMS"))
+            .flat_map(|s| s.0.iter().skip(1))
+    }
+
+    fn code(&self, code: &str) -> Option<&Vec<String>> {
+        self.codes().find(|c| c.first().is_some_and(|c| c == code))
+    }
+
+    fn check(&self) -> Result<()> {
+        for code in self.codes() {
+            let number = code.first().map_or("", String::as_str);
+            let text = code.get(2).map_or("", String::as_str);
+            debug!(code = number, text, "FinTS return code");
+            if number.starts_with('9') {
+                return Err(miette!("The bank answered {}: {}", number, text));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '?' | '@' | ':' | '+' | '\'') {
+            escaped.push('?');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Splits a message into segments, keeping binary data (`@len@...`) whole.
+/// As messages are ISO 8859-1, every char stands for one byte.
+fn parse(message: &str) -> Result<Vec<Segment>> {
+    let chars: Vec<char> = message.chars().collect();
+    let (mut segments, mut elements, mut groups) = (Vec::new(), Vec::new(), Vec::new());
+    let mut current = String::new();
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        match chars[idx] {
+            '?' => {
+                current.extend(chars.get(idx + 1));
+                idx += 1;
+            }
+            '@' if current.is_empty() => {
+                let end = chars[idx + 1..]
+                    .iter()
+                    .position(|c| *c == '@')
+                    .map(|p| p + idx + 1)
+                    .ok_or_else(|| miette!("Unterminated binary length"))?;
+                let len: usize = chars[idx + 1..end]
+                    .iter()
+                    .collect::<String>()
+                    .parse()
+                    .into_diagnostic()
+                    .wrap_err("Invalid binary length")?;
+                let data = chars
+                    .get(end + 1..end + 1 + len)
+                    .ok_or_else(|| miette!("Binary data cut short"))?;
+                current.extend(data);
+                idx = end + len;
+            }
+            ':' => groups.push(mem::take(&mut current)),
+            '+' => {
+                groups.push(mem::take(&mut current));
+                elements.push(mem::take(&mut groups));
+            }
+            '\'' => {
+                groups.push(mem::take(&mut current));
+                elements.push(mem::take(&mut groups));
+                segments.push(Segment(mem::take(&mut elements)));
+            }
+            c => current.push(c),
+        }
+        idx += 1;
+    }
+
+    Ok(segments)
+}
+
+fn prompt(label: &str) -> Result<String> {
+    eprint!("{}: ", label);
+    io::stderr().flush().into_diagnostic()?;
+    let mut line = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .into_diagnostic()
+        .wrap_err("Failed reading from the terminal")?;
+    Ok(line.trim().to_string())
+}
+
+struct Dialog {
+    url: String,
+    blz: String,
+    user: String,
+    pin: String,
+    product_id: String,
+    system_id: String,
+    security_function: String,
+    dialog_id: String,
+    message_number: u32,
+    hkkaz_version: u32,
+    hktan_version: u32,
+    /// Business segments the bank wants a TAN for
+    tan_required: Vec<String>,
+}
+
+impl Dialog {
+    fn start(&mut self) {
+        self.dialog_id = "0".to_string();
+        self.message_number = 1;
+    }
+
+    fn hkidn(&self) -> Request {
+        Request {
+            name: "HKIDN",
+            version: 2,
+            body: format!(
+                "{}:{}+{}+{}+1",
+                COUNTRY,
+                escape(&self.blz),
+                escape(&self.user),
+                escape(&self.system_id)
+            ),
+        }
+    }
+
+    fn hkvvb(&self) -> Request {
+        Request {
+            name: "HKVVB",
+            version: 3,
+            body: format!(
+                "0+0+0+{}+{}",
+                escape(&self.product_id),
+                env!("CARGO_PKG_VERSION")
+            ),
+        }
+    }
+
+    fn hktan(&self, body: String) -> Request {
+        Request {
+            name: "HKTAN",
+            version: self.hktan_version,
+            body,
+        }
+    }
+
+    fn message(&self, requests: &[Request], tan: Option<&str>) -> String {
+        let now = Local::now();
+        let (date, time) = (now.format("%Y%m%d"), now.format("%H%M%S"));
+        let control = now.timestamp_subsec_nanos() % 100_000_000;
+        let key = format!("{}:{}:{}", COUNTRY, escape(&self.blz), escape(&self.user));
+        let system_id = escape(&self.system_id);
+
+        let mut signed = format!(
+            "HNSHK:2:4+PIN:2+{}+{}+1+1+1::{}+1+1:{}:{}+1:999:1+6:10:16+{}:S:0:0'",
+            self.security_function, control, system_id, date, time, key
+        );
+        let mut number = 3;
+        for request in requests {
+            signed.push_str(&format!(
+                "{}:{}:{}+{}'",
+                request.name, number, request.version, request.body
+            ));
+            number += 1;
+        }
+        let auth = match tan {
+            Some(tan) => format!("{}:{}", escape(&self.pin), escape(tan)),
+            None => escape(&self.pin),
+        };
+        signed.push_str(&format!("HNSHA:{}:2+{}++{}'", number, control, auth));
+
+        // PIN/TAN leaves the encryption to TLS, the envelope is still needed
+        let body = format!(
+            "HNVSK:998:3+PIN:2+998+1+1::{}+1:{}:{}+2:2:13:@8@00000000:5:1+{}:V:0:0+0'HNVSD:999:1+@{}@{}'",
+            system_id,
+            date,
+            time,
+            key,
+            signed.chars().count(),
+            signed
+        );
+        let header_rest = format!("+300+{}+{}'", escape(&self.dialog_id), self.message_number);
+        let trailer = format!("HNHBS:{}:1+{}'", number + 1, self.message_number);
+        let size =
+            "HNHBK:1:3+".len() + 12 + header_rest.len() + body.chars().count() + trailer.len();
+
+        format!("HNHBK:1:3+{:012}{}{}{}", size, header_rest, body, trailer)
+    }
+
+    fn send(&mut self, requests: &[Request], tan: Option<&str>) -> Result<Response> {
+        let message = self.message(requests, tan);
+        let bytes: Vec<u8> = message
+            .chars()
+            .map(|c| u8::try_from(c).unwrap_or(b'?'))
+            .collect();
+
+        let reply = ureq::post(&self.url)
+            .set("Content-Type", "text/plain")
+            .send_string(&STANDARD.encode(bytes))
+            .into_diagnostic()
+            .wrap_err("Failed sending FinTS message")?
+            .into_string()
+            .into_diagnostic()
+            .wrap_err("Failed reading FinTS response")?;
+        let reply: String = reply.split_whitespace().collect();
+        let reply = STANDARD
+            .decode(reply)
+            .into_diagnostic()
+            .wrap_err("FinTS response is not base64")?;
+        self.message_number += 1;
+
+        let mut segments = Vec::new();
+        for segment in parse(&reply.iter().map(|b| *b as char).collect::<String>())? {
+            match segment.name() {
+                "HNVSD" => segments.extend(parse(segment.get(1, 0))?),
+                _ => segments.push(segment),
+            }
+        }
+        if let Some(header) = segments.iter().find(|s| s.name() == "HNHBK") {
+            self.dialog_id = header.get(3, 0).to_string();
+        }
+
+        let response = Response { segments };
+        response.check()?;
+        Ok(response)
+    }
+
+    /// Sends the requests, getting a TAN if the bank asks for one.
+    fn send_authorized(&mut self, requests: &[Request]) -> Result<Response> {
+        let response = self.send(requests, None)?;
+        if response.code("0030").is_none() {
+            return Ok(response);
+        }
+
+        let hitan = response
+            .segment("HITAN")
+            .ok_or_else(|| miette!("The bank asked for a TAN without a challenge"))?;
+        let reference = escape(hitan.get(3, 0));
+        let challenge = hitan.get(4, 0).to_string();
+
+        if response.code("3955").is_some() {
+            eprintln!("{}", challenge);
+            eprintln!("Waiting for the approval in the banking app...");
+            loop {
+                thread::sleep(Duration::from_secs(3));
+                let poll = self.hktan(format!("S++++{}+N", reference));
+                let response = self.send(&[poll], None)?;
+                if response.code("3956").is_none() {
+                    return Ok(response);
+                }
+            }
+        }
+
+        eprintln!("{}", challenge);
+        let tan = prompt("TAN")?;
+        let submit = self.hktan(format!("2++++{}+N", reference));
+        self.send(&[submit], Some(&tan))
+    }
+
+    /// Takes the segment versions and PIN/TAN parameters of the bank.
+    fn learn(&mut self, response: &Response) {
+        let versions = |name: &str, supported: &[u32]| {
+            response
+                .segments
+                .iter()
+                .filter(|s| s.name() == name)
+                .map(Segment::version)
+                .filter(|v| supported.contains(v))
+                .max()
+        };
+        if let Some(version) = versions("HIKAZS", &[5, 6, 7]) {
+            self.hkkaz_version = version;
+        }
+        if let Some(version) = versions("HITANS", &[6, 7]) {
+            self.hktan_version = version;
+        }
+
+        // Pairs of segment and whether it needs a TAN, behind the PIN rules
+        if let Some(hipins) = response.segment("HIPINS") {
+            let parameters = hipins.0.get(4).map_or(&[][..], |p| p.as_slice());
+            self.tan_required = parameters
+                .get(5..)
+                .unwrap_or_default()
+                .chunks(2)
+                .filter(|pair| pair.get(1).is_some_and(|j| j == "J"))
+                .map(|pair| pair[0].clone())
+                .collect();
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        let end = Request {
+            name: "HKEND",
+            version: 1,
+            body: escape(&self.dialog_id),
+        };
+        self.send(&[end], None).map(|_| ())
+    }
+}
+
+/// The MT940 statement of the account from `from` to `to`.
+pub fn fetch(options: &FintsOptions, from: NaiveDate, to: NaiveDate) -> Result<Vec<u8>> {
+    let missing = |name| miette!("Fetching with FinTS needs --{}", name);
+    let url = options
+        .fints_url
+        .clone()
+        .ok_or_else(|| missing("fints-url"))?;
+    let blz = options
+        .fints_blz
+        .clone()
+        .ok_or_else(|| missing("fints-blz"))?;
+    let user = options
+        .fints_user
+        .clone()
+        .ok_or_else(|| missing("fints-user"))?;
+    let iban = options
+        .fints_iban
+        .clone()
+        .ok_or_else(|| missing("fints-iban"))?;
+    let product_id = options
+        .fints_product_id
+        .clone()
+        .ok_or_else(|| missing("fints-product-id"))?;
+    let pin = match &options.fints_pin {
+        Some(pin) => pin.clone(),
+        None => prompt("PIN")?,
+    };
+
+    let mut dialog = Dialog {
+        url,
+        blz,
+        user,
+        pin,
+        product_id,
+        system_id: "0".to_string(),
+        security_function: ONE_STEP.to_string(),
+        dialog_id: String::new(),
+        message_number: 1,
+        hkkaz_version: 6,
+        hktan_version: 6,
+        tan_required: Vec::new(),
+    };
+
+    dialog.start();
+    let sync = Request {
+        name: "HKSYN",
+        version: 3,
+        body: "0".to_string(),
+    };
+    let response = dialog.send(&[dialog.hkidn(), dialog.hkvvb(), sync], None)?;
+    dialog.learn(&response);
+    if let Some(hisyn) = response.segment("HISYN") {
+        dialog.system_id = hisyn.get(1, 0).to_string();
+    }
+    let allowed: Vec<String> = response
+        .code("3920")
+        .map(|c| c.iter().skip(3).cloned().collect())
+        .unwrap_or_default();
+    dialog.end()?;
+
+    dialog.security_function = match &options.fints_tan_method {
+        Some(method) => method.clone(),
+        None => {
+            let method = allowed.first().cloned().unwrap_or(ONE_STEP.to_string());
+            info!(method, ?allowed, "Picked TAN method");
+            method
+        }
+    };
+    let two_step = dialog.security_function != ONE_STEP;
+
+    dialog.start();
+    let mut init = vec![dialog.hkidn(), dialog.hkvvb()];
+    if two_step {
+        init.push(dialog.hktan("4+HKIDN".to_string()));
+    }
+    dialog.send_authorized(&init)?;
+
+    let account = match dialog.hkkaz_version {
+        7 => format!(
+            "{}:{}:{}::{}:{}",
+            escape(&iban),
+            escape(options.fints_bic.as_deref().unwrap_or_default()),
+            account_number(&iban),
+            COUNTRY,
+            escape(&dialog.blz)
+        ),
+        _ => format!(
+            "{}::{}:{}",
+            account_number(&iban),
+            COUNTRY,
+            escape(&dialog.blz)
+        ),
+    };
+
+    let mut statement = Vec::new();
+    let mut touchdown: Option<String> = None;
+    loop {
+        let mut body = format!(
+            "{}+N+{}+{}",
+            account,
+            from.format("%Y%m%d"),
+            to.format("%Y%m%d")
+        );
+        if let Some(touchdown) = &touchdown {
+            body.push_str(&format!("++{}", escape(touchdown)));
+        }
+        let mut requests = vec![Request {
+            name: "HKKAZ",
+            version: dialog.hkkaz_version,
+            body,
+        }];
+        if two_step && dialog.tan_required.iter().any(|s| s == "HKKAZ") {
+            requests.push(dialog.hktan("4+HKKAZ".to_string()));
+        }
+
+        let response = dialog.send_authorized(&requests)?;
+        match response.segment("HIKAZ") {
+            Some(hikaz) => statement.extend(hikaz.get(1, 0).chars().map(|c| c as u8)),
+            None => warn!("The bank sent no statement"),
+        }
+        // More to come when the bank hands out a touchdown point
+        touchdown = response.code("3040").and_then(|c| c.get(3)).cloned();
+        if touchdown.is_none() {
+            break;
+        }
+    }
+    dialog.end()?;
+
+    Ok(statement)
+}
+
+/// The account number within the IBAN, German IBANs putting it behind the
+/// Bankleitzahl.
+fn account_number(iban: &str) -> String {
+    let number = iban.get(12..).unwrap_or_default().trim_start_matches('0');
+    escape(number)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let message = "HNHBK:1:3+000000000123+300+D?+1+2'HIRMG:2:2+0010::Nachricht entgegengenommen.'HIRMS:3:2:4+3040::Es liegen weitere Informationen vor.:A1B2'HIKAZ:4:7:5+@12@:20:X'?+:86:+'";
+        let segments = parse(message).unwrap();
+
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments[0].get(3, 0), "D+1");
+        assert_eq!(segments[2].version(), 2);
+        assert_eq!(segments[3].get(1, 0), ":20:X'?+:86:");
+
+        let response = Response { segments };
+        assert_eq!(response.code("3040").unwrap()[3], "A1B2");
+        assert!(response.check().is_ok());
+        assert_eq!(escape("a+b:c?'"), "a?+b?:c???'");
+        assert_eq!(account_number("DE02120300000000202051"), "202051");
+    }
+}
@@ -0,0 +1,190 @@
+//! The GoCardless Bank Account Data API (formerly Nordigen), covering
+//! banks all over Europe through open banking.
+//!
+//! Access to a bank account is granted through a requisition. Without
+//! `--gocardless-requisition` a linked one for the institution is looked
+//! up, or a new one is created and its link printed, to be opened in the
+//! browser. The booked transactions are saved as the API returns them.
+
+use std::{thread, time::Duration};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+const API: &str = "https://bankaccountdata.gocardless.com/api/v2";
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct GocardlessOptions {
+    /// Secret id of the GoCardless user secret
+    #[arg(long, env)]
+    pub gocardless_secret_id: Option<String>,
+    /// Secret key of the GoCardless user secret
+    #[arg(long, env, hide_env_values = true)]
+    pub gocardless_secret_key: Option<String>,
+    /// Institution id of the bank, as in `SPARKASSE_KARLSRUHE_KARSDE66`
+    #[arg(long, env)]
+    pub gocardless_institution: Option<String>,
+    /// Requisition granting access to the accounts [default: a linked one
+    /// of the institution]
+    #[arg(long, env)]
+    pub gocardless_requisition: Option<String>,
+    /// Account id to fetch, needed if the requisition links several
+    #[arg(long, env)]
+    pub gocardless_account: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Requisition {
+    id: String,
+    status: String,
+    institution_id: String,
+    #[serde(default)]
+    accounts: Vec<String>,
+    #[serde(default)]
+    link: String,
+}
+
+/// A page of a listing, `next` pointing to the following one.
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    next: Option<String>,
+    results: Vec<T>,
+}
+
+struct Client {
+    token: String,
+}
+
+impl Client {
+    fn new(secret_id: &str, secret_key: &str) -> Result<Self> {
+        let response: Value = ureq::post(&format!("{}/token/new/", API))
+            .send_json(json!({ "secret_id": secret_id, "secret_key": secret_key }))
+            .into_diagnostic()
+            .wrap_err("Failed getting a GoCardless access token")?
+            .into_json()
+            .into_diagnostic()?;
+        let token = response["access"]
+            .as_str()
+            .ok_or_else(|| miette!("GoCardless sent no access token"))?;
+
+        Ok(Self {
+            token: token.to_string(),
+        })
+    }
+
+    fn get<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T> {
+        let url = match url.starts_with("https://") {
+            true => url.to_string(),
+            false => format!("{}{}", API, url),
+        };
+        ureq::get(&url)
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .call()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed requesting {}", url))?
+            .into_json()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed reading {}", url))
+    }
+
+    fn requisitions(&self) -> Result<Vec<Requisition>> {
+        let mut requisitions = Vec::new();
+        let mut next = Some("/requisitions/".to_string());
+        while let Some(url) = next {
+            let page: Page<Requisition> = self.get(&url)?;
+            requisitions.extend(page.results);
+            next = page.next;
+        }
+        Ok(requisitions)
+    }
+
+    /// Creates a requisition and waits until the user linked it.
+    fn link(&self, institution: &str) -> Result<Requisition> {
+        let requisition: Requisition = ureq::post(&format!("{}/requisitions/", API))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .send_json(json!({
+                "institution_id": institution,
+                "redirect": "https://gocardless.com",
+            }))
+            .into_diagnostic()
+            .wrap_err("Failed creating a GoCardless requisition")?
+            .into_json()
+            .into_diagnostic()?;
+
+        eprintln!("Grant access to your account at {}", requisition.link);
+        loop {
+            thread::sleep(Duration::from_secs(5));
+            let requisition: Requisition =
+                self.get(&format!("/requisitions/{}/", requisition.id))?;
+            match requisition.status.as_str() {
+                "LN" => {
+                    info!(id = requisition.id, "Linked requisition");
+                    return Ok(requisition);
+                }
+                "RJ" | "EX" | "SU" => {
+                    return Err(miette!(
+                        "Requisition {} ended with status {}",
+                        requisition.id,
+                        requisition.status
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The booked transactions of the account from `from` to `to`, as json.
+pub fn fetch(options: &GocardlessOptions, from: NaiveDate, to: NaiveDate) -> Result<Vec<u8>> {
+    let missing = |name| miette!("Fetching from GoCardless needs --{}", name);
+    let client = Client::new(
+        options
+            .gocardless_secret_id
+            .as_deref()
+            .ok_or_else(|| missing("gocardless-secret-id"))?,
+        options
+            .gocardless_secret_key
+            .as_deref()
+            .ok_or_else(|| missing("gocardless-secret-key"))?,
+    )?;
+
+    let requisition = match &options.gocardless_requisition {
+        Some(id) => client.get(&format!("/requisitions/{}/", id))?,
+        None => {
+            let institution = options
+                .gocardless_institution
+                .as_deref()
+                .ok_or_else(|| missing("gocardless-institution"))?;
+            let linked = client
+                .requisitions()?
+                .into_iter()
+                .find(|r| r.institution_id == institution && r.status == "LN");
+            match linked {
+                Some(requisition) => requisition,
+                None => client.link(institution)?,
+            }
+        }
+    };
+
+    let account = match (&options.gocardless_account, requisition.accounts.as_slice()) {
+        (Some(account), _) => account.clone(),
+        (None, [account]) => account.clone(),
+        (None, accounts) => {
+            return Err(miette!(
+                help = format!("Accounts of the requisition: {}", accounts.join(", ")),
+                "Pick the account with --gocardless-account"
+            ))
+        }
+    };
+
+    let transactions: Value = client.get(&format!(
+        "/accounts/{}/transactions/?date_from={}&date_to={}",
+        account,
+        from.format("%Y-%m-%d"),
+        to.format("%Y-%m-%d")
+    ))?;
+    serde_json::to_vec_pretty(&transactions).into_diagnostic()
+}
@@ -0,0 +1,249 @@
+//! Statements emailed as attachments, fetched from an IMAP mailbox.
+//!
+//! Messages of the folder not marked processed yet are searched, the
+//! matching attachments of all of them are saved together as a zip and every
+//! attachment is converted on its own. The messages are only marked once the
+//! conversion succeeded, so a failed run picks them up again.
+
+use std::{
+    io::{Cursor, Write},
+    net::TcpStream,
+};
+
+use chrono::{Days, NaiveDate};
+use glob::Pattern;
+use imap::Session;
+use mailparse::{parse_mail, ParsedMail};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use native_tls::{TlsConnector, TlsStream};
+use tracing::{debug, info};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+/// Extensions of attachments converted without `--imap-attachment`.
+const EXTENSIONS: &[&str] = &["csv", "pdf", "xml", "sta", "xls", "xlsx", "ods"];
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct ImapOptions {
+    /// Host of the IMAP server, connected to with TLS
+    #[arg(long, env)]
+    pub imap_server: Option<String>,
+    #[arg(long, env, default_value_t = 993)]
+    pub imap_port: u16,
+    #[arg(long, env)]
+    pub imap_user: Option<String>,
+    #[arg(long, env, hide_env_values = true)]
+    pub imap_password: Option<String>,
+    /// Folder the statements arrive in
+    #[arg(long, env, default_value = "INBOX")]
+    pub imap_folder: String,
+    /// Only messages from this sender
+    #[arg(long, env)]
+    pub imap_from: Option<String>,
+    /// Glob for the file names of attachments to convert [default: csv, pdf,
+    /// xml, sta and spreadsheets]
+    #[arg(long, env)]
+    pub imap_attachment: Option<String>,
+    /// Keyword marking messages as processed
+    #[arg(long, env, default_value = "$Hbconv")]
+    pub imap_keyword: String,
+}
+
+/// Messages whose attachments were fetched, to mark once they are converted.
+#[derive(Debug)]
+pub struct Messages {
+    uids: Vec<u32>,
+}
+
+/// The attachments of new messages from `from` to `to` as zip, none if
+/// there are no new statements.
+pub fn fetch(
+    options: &ImapOptions,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Option<(Vec<u8>, Messages)>> {
+    let pattern = options
+        .imap_attachment
+        .as_deref()
+        .map(Pattern::new)
+        .transpose()
+        .into_diagnostic()
+        .wrap_err("Invalid glob for attachments")?;
+    let mut session = connect(options)?;
+
+    let mut query = format!(
+        "UNKEYWORD {} SINCE {} BEFORE {}",
+        options.imap_keyword,
+        from.format("%d-%b-%Y"),
+        to.checked_add_days(Days::new(1))
+            .unwrap_or(to)
+            .format("%d-%b-%Y")
+    );
+    if let Some(sender) = &options.imap_from {
+        query.push_str(&format!(" FROM \"{}\"", sender.replace('"', "")));
+    }
+    let mut uids: Vec<u32> = session
+        .uid_search(&query)
+        .into_diagnostic()
+        .wrap_err("Failed searching messages")?
+        .into_iter()
+        .collect();
+    uids.sort();
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut processed = Vec::new();
+    for uid in uids {
+        let fetches = session
+            .uid_fetch(uid.to_string(), "BODY.PEEK[]")
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed fetching message {}", uid))?;
+        let Some(body) = fetches.iter().find_map(|f| f.body()) else {
+            continue;
+        };
+
+        let found = attachments(body, pattern.as_ref())?;
+        debug!(uid, attachments = found.len(), "Fetched message");
+        if found.is_empty() {
+            continue;
+        }
+        for (name, content) in found {
+            // Banks name every statement the same
+            writer
+                .start_file(format!("{}-{}", uid, name), SimpleFileOptions::default())
+                .into_diagnostic()?;
+            writer.write_all(&content).into_diagnostic()?;
+        }
+        processed.push(uid);
+    }
+    session.logout().into_diagnostic()?;
+
+    if processed.is_empty() {
+        return Ok(None);
+    }
+    info!(messages = processed.len(), "Fetched attachments");
+    let content = writer.finish().into_diagnostic()?.into_inner();
+    Ok(Some((content, Messages { uids: processed })))
+}
+
+/// Marks the converted messages as processed.
+pub fn mark(options: &ImapOptions, messages: &Messages) -> Result<()> {
+    let mut session = connect(options)?;
+    let uids: Vec<String> = messages.uids.iter().map(u32::to_string).collect();
+    session
+        .uid_store(uids.join(","), format!("+FLAGS ({})", options.imap_keyword))
+        .into_diagnostic()
+        .wrap_err("Failed marking messages as processed")?;
+    session.logout().into_diagnostic()
+}
+
+fn connect(options: &ImapOptions) -> Result<Session<TlsStream<TcpStream>>> {
+    let missing = |name| miette!("Fetching from IMAP needs --{}", name);
+    let server = options
+        .imap_server
+        .as_deref()
+        .ok_or_else(|| missing("imap-server"))?;
+    let user = options
+        .imap_user
+        .as_deref()
+        .ok_or_else(|| missing("imap-user"))?;
+    let password = options
+        .imap_password
+        .as_deref()
+        .ok_or_else(|| missing("imap-password"))?;
+
+    let tls = TlsConnector::new().into_diagnostic()?;
+    let client = imap::connect((server, options.imap_port), server, &tls)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed connecting to {}", server))?;
+    let mut session = client
+        .login(user, password)
+        .map_err(|(err, _)| err)
+        .into_diagnostic()
+        .wrap_err("Failed logging in to the IMAP server")?;
+    session
+        .select(&options.imap_folder)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed opening folder {}", options.imap_folder))?;
+    Ok(session)
+}
+
+/// File names and contents of the attachments of the message matching
+/// `pattern`, or a statement extension without one.
+fn attachments(raw: &[u8], pattern: Option<&Pattern>) -> Result<Vec<(String, Vec<u8>)>> {
+    let mail = parse_mail(raw)
+        .into_diagnostic()
+        .wrap_err("Failed parsing message")?;
+
+    let mut attachments = Vec::new();
+    for part in mail.parts() {
+        let Some(name) = file_name(part) else {
+            continue;
+        };
+        let matched = match pattern {
+            Some(pattern) => pattern.matches(&name),
+            None => name
+                .rsplit_once('.')
+                .is_some_and(|(_, e)| EXTENSIONS.contains(&e.to_lowercase().as_str())),
+        };
+        if matched {
+            let content = part
+                .get_body_raw()
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed decoding attachment {}", name))?;
+            attachments.push((name, content));
+        }
+    }
+    Ok(attachments)
+}
+
+/// The file name of an attachment, without any directories of it.
+fn file_name(part: &ParsedMail) -> Option<String> {
+    let disposition = part.get_content_disposition();
+    let name = disposition
+        .params
+        .get("filename")
+        .or_else(|| part.ctype.params.get("name"))?;
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_attachments() {
+        let raw = b"From: bank@example.com\r\n\
+Subject: Kontoauszug\r\n\
+Content-Type: multipart/mixed; boundary=\"b\"\r\n\
+\r\n\
+--b\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+Ihr Kontoauszug liegt bei.\r\n\
+--b\r\n\
+Content-Type: text/csv; name=\"umsaetze.csv\"\r\n\
+Content-Disposition: attachment; filename=\"umsaetze.csv\"\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+QnVjaHVuZ3N0YWc7V2VydDtVbXNhdHphcnQK\r\n\
+--b\r\n\
+Content-Type: image/png; name=\"logo.png\"\r\n\
+\r\n\
+png\r\n\
+--b--\r\n";
+
+        let found = attachments(raw, None).unwrap();
+        assert_eq!(
+            found,
+            vec![(
+                "umsaetze.csv".to_string(),
+                b"Buchungstag;Wert;Umsatzart\n".to_vec()
+            )]
+        );
+
+        let pattern = Pattern::new("*.png").unwrap();
+        assert_eq!(attachments(raw, Some(&pattern)).unwrap().len(), 1);
+    }
+}
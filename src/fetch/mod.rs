@@ -0,0 +1,132 @@
+//! Fetching statements online instead of downloading exports by hand.
+//!
+//! The statement is saved to the input path as the bank sent it, then
+//! converted like any downloaded export. It can be archived and reapplied
+//! the same way.
+
+pub mod fints;
+pub mod gocardless;
+pub mod imap;
+pub mod paypal;
+pub mod plaid;
+
+use std::fs;
+
+use chrono::{Days, Local, NaiveDate};
+use clap::ValueEnum;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use tracing::info;
+
+use crate::{
+    convert::{self, Args},
+    inputs::{zipped, Format},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Provider {
+    /// FinTS 3.0 with PIN/TAN, offered by most German banks
+    Fints,
+    /// GoCardless Bank Account Data, open banking for most European banks
+    Gocardless,
+    /// Plaid, for banks in the US and Canada
+    Plaid,
+    /// The PayPal Reporting API
+    Paypal,
+    /// Statements emailed as attachments, from an IMAP mailbox
+    Imap,
+}
+
+impl Provider {
+    /// The input format of the fetched statements, none if it is detected.
+    fn format(&self) -> Option<Format> {
+        match self {
+            Provider::Fints => Some(Format::Mt940),
+            Provider::Gocardless => Some(Format::Gocardless),
+            Provider::Plaid => Some(Format::Plaid),
+            Provider::Paypal => Some(Format::PaypalApi),
+            // Each attachment is detected on its own
+            Provider::Imap => None,
+        }
+    }
+}
+
+/// Options for fetching and converting a statement.
+#[derive(Debug, clap::Args)]
+// The provider decides the format of the statement
+#[command(mut_arg("format", |a| a.default_value("mt940").required(false).hide(true)))]
+#[command(mut_arg("input", |a| a.value_name("STATEMENT").help("File the fetched statement is saved to")))]
+pub struct Fetch {
+    #[arg(long, env, value_enum, default_value_t = Provider::Fints)]
+    pub provider: Provider,
+    /// First day to fetch [default: 89 days ago, banks ask for a TAN beyond]
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// Last day to fetch [default: today]
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+    #[command(flatten)]
+    pub fints: fints::FintsOptions,
+    #[command(flatten)]
+    pub gocardless: gocardless::GocardlessOptions,
+    #[command(flatten)]
+    pub plaid: plaid::PlaidOptions,
+    #[command(flatten)]
+    pub paypal: paypal::PaypalOptions,
+    #[command(flatten)]
+    pub imap: imap::ImapOptions,
+    #[command(flatten)]
+    pub args: Args,
+}
+
+impl Fetch {
+    pub fn run(mut self) -> Result<()> {
+        let to = self.to.unwrap_or_else(|| Local::now().date_naive());
+        let from = self
+            .from
+            .unwrap_or_else(|| to.checked_sub_days(Days::new(89)).unwrap_or(to));
+
+        let mut plaid_state = None;
+        let mut messages = None;
+        let statement = match self.provider {
+            Provider::Fints => fints::fetch(&self.fints, from, to)?,
+            Provider::Gocardless => gocardless::fetch(&self.gocardless, from, to)?,
+            Provider::Plaid => {
+                let (statement, state) = plaid::fetch(&self.plaid, &self.args.input)?;
+                plaid_state = Some(state);
+                statement
+            }
+            Provider::Paypal => paypal::fetch(&self.paypal, from, to)?,
+            Provider::Imap => {
+                if !zipped::is_zip(&self.args.input) {
+                    return Err(miette!(
+                        help = "Name the statement like attachments.zip",
+                        "Attachments are saved together as a zip"
+                    ));
+                }
+                let Some((statement, fetched)) = imap::fetch(&self.imap, from, to)? else {
+                    info!("No new statements");
+                    return Ok(());
+                };
+                messages = Some(fetched);
+                statement
+            }
+        };
+        fs::write(&self.args.input, statement)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                format!("Failed saving statement to {}", self.args.input.display())
+            })?;
+        info!(path = %self.args.input.display(), %from, %to, "Fetched statement");
+
+        self.args.format = self.provider.format();
+        convert::run(&self.args)?;
+
+        if let Some(state) = plaid_state {
+            plaid::store(&self.plaid, &self.args.input, &state)?;
+        }
+        if let Some(messages) = messages {
+            imap::mark(&self.imap, &messages)?;
+        }
+        Ok(())
+    }
+}
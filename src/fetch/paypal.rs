@@ -0,0 +1,92 @@
+//! The PayPal Reporting API, for the transactions of a PayPal account.
+//!
+//! Access is granted through the client credentials of a REST app with the
+//! transaction search permission. The API only searches 31 days at once, so
+//! longer ranges are fetched in chunks. All pages are saved together.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Days, NaiveDate};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde_json::{json, Value};
+use tracing::info;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct PaypalOptions {
+    /// Client id of the PayPal REST app
+    #[arg(long, env)]
+    pub paypal_client_id: Option<String>,
+    /// Secret of the PayPal REST app
+    #[arg(long, env, hide_env_values = true)]
+    pub paypal_secret: Option<String>,
+    /// Use the PayPal sandbox instead of live accounts
+    #[arg(long, env)]
+    pub paypal_sandbox: bool,
+}
+
+/// The transactions of the account from `from` to `to`, as json.
+pub fn fetch(options: &PaypalOptions, from: NaiveDate, to: NaiveDate) -> Result<Vec<u8>> {
+    let missing = |name| miette!("Fetching from PayPal needs --{}", name);
+    let client_id = options
+        .paypal_client_id
+        .as_deref()
+        .ok_or_else(|| missing("paypal-client-id"))?;
+    let secret = options
+        .paypal_secret
+        .as_deref()
+        .ok_or_else(|| missing("paypal-secret"))?;
+    let api = match options.paypal_sandbox {
+        true => "https://api-m.sandbox.paypal.com",
+        false => "https://api-m.paypal.com",
+    };
+
+    let credentials = STANDARD.encode(format!("{}:{}", client_id, secret));
+    let response: Value = ureq::post(&format!("{}/v1/oauth2/token", api))
+        .set("Authorization", &format!("Basic {}", credentials))
+        .send_form(&[("grant_type", "client_credentials")])
+        .into_diagnostic()
+        .wrap_err("Failed getting a PayPal access token")?
+        .into_json()
+        .into_diagnostic()?;
+    let token = response["access_token"]
+        .as_str()
+        .ok_or_else(|| miette!("PayPal sent no access token"))?;
+
+    let mut transactions = Vec::new();
+    let mut start = from;
+    while start <= to {
+        let end = start.checked_add_days(Days::new(30)).unwrap_or(to).min(to);
+        let (mut page, mut pages) = (1, 1);
+        while page <= pages {
+            let response: Value = ureq::get(&format!("{}/v1/reporting/transactions", api))
+                .set("Authorization", &format!("Bearer {}", token))
+                .query("start_date", &format!("{}T00:00:00Z", start))
+                .query("end_date", &format!("{}T23:59:59Z", end))
+                .query("fields", "all")
+                .query("page_size", "500")
+                .query("page", &page.to_string())
+                .call()
+                .into_diagnostic()
+                .wrap_err("Failed searching PayPal transactions")?
+                .into_json()
+                .into_diagnostic()
+                .wrap_err("Failed reading PayPal response")?;
+
+            if let Some(details) = response["transaction_details"].as_array() {
+                transactions.extend(details.iter().cloned());
+            }
+            pages = response["total_pages"].as_u64().unwrap_or(1);
+            page += 1;
+        }
+
+        let Some(next) = end.succ_opt() else {
+            break;
+        };
+        start = next;
+    }
+
+    info!(
+        transactions = transactions.len(),
+        "Fetched PayPal transactions"
+    );
+    serde_json::to_vec_pretty(&json!({ "transaction_details": transactions })).into_diagnostic()
+}
@@ -0,0 +1,137 @@
+//! Plaid, covering banks in the US and Canada.
+//!
+//! Transactions are synced with `/transactions/sync`, which hands out
+//! everything added since the cursor of the last run. The cursor is kept in
+//! the state file and only moved on once the conversion succeeded, so a
+//! failed run syncs the same transactions again. `--from` and `--to` don't
+//! apply.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct PlaidOptions {
+    /// Plaid environment, `sandbox` or `production`
+    #[arg(long, env, default_value = "production")]
+    pub plaid_env: String,
+    /// Client id of the Plaid team
+    #[arg(long, env)]
+    pub plaid_client_id: Option<String>,
+    /// Secret of the Plaid environment
+    #[arg(long, env, hide_env_values = true)]
+    pub plaid_secret: Option<String>,
+    /// Access token of the linked item
+    #[arg(long, env, hide_env_values = true)]
+    pub plaid_access_token: Option<String>,
+    /// Only keep transactions of this account id [default: all accounts of
+    /// the item]
+    #[arg(long, env)]
+    pub plaid_account: Option<String>,
+    /// State file keeping the sync cursor [default: <STATEMENT>.plaid-state.json]
+    #[arg(long, env)]
+    pub plaid_state: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct State {
+    cursor: Option<String>,
+}
+
+impl PlaidOptions {
+    fn state_path(&self, statement: &Path) -> PathBuf {
+        self.plaid_state.clone().unwrap_or_else(|| {
+            let mut path = statement.as_os_str().to_owned();
+            path.push(".plaid-state.json");
+            PathBuf::from(path)
+        })
+    }
+}
+
+/// The transactions added since the last run, as json, and the state to
+/// store once they are converted.
+pub fn fetch(options: &PlaidOptions, statement: &Path) -> Result<(Vec<u8>, State)> {
+    let missing = |name| miette!("Fetching from Plaid needs --{}", name);
+    let client_id = options
+        .plaid_client_id
+        .as_deref()
+        .ok_or_else(|| missing("plaid-client-id"))?;
+    let secret = options
+        .plaid_secret
+        .as_deref()
+        .ok_or_else(|| missing("plaid-secret"))?;
+    let access_token = options
+        .plaid_access_token
+        .as_deref()
+        .ok_or_else(|| missing("plaid-access-token"))?;
+
+    let state_path = options.state_path(statement);
+    let mut state: State = match fs::read_to_string(&state_path) {
+        Ok(content) => serde_json::from_str(&content)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed parsing {}", state_path.display()))?,
+        Err(_) => State::default(),
+    };
+
+    let url = format!("https://{}.plaid.com/transactions/sync", options.plaid_env);
+    let (mut added, mut modified, mut removed) = (Vec::new(), 0, 0);
+    loop {
+        let mut body = json!({
+            "client_id": client_id,
+            "secret": secret,
+            "access_token": access_token,
+            "count": 500,
+        });
+        if let Some(cursor) = &state.cursor {
+            body["cursor"] = json!(cursor);
+        }
+
+        let page: Value = ureq::post(&url)
+            .send_json(body)
+            .into_diagnostic()
+            .wrap_err("Failed syncing Plaid transactions")?
+            .into_json()
+            .into_diagnostic()
+            .wrap_err("Failed reading Plaid response")?;
+
+        for transaction in page["added"].as_array().into_iter().flatten() {
+            let account = transaction["account_id"].as_str();
+            if options.plaid_account.is_none() || options.plaid_account.as_deref() == account {
+                added.push(transaction.clone());
+            }
+        }
+        modified += page["modified"].as_array().map_or(0, Vec::len);
+        removed += page["removed"].as_array().map_or(0, Vec::len);
+        state.cursor = page["next_cursor"].as_str().map(str::to_string);
+
+        if !page["has_more"].as_bool().unwrap_or_default() {
+            break;
+        }
+    }
+
+    info!(added = added.len(), "Synced Plaid transactions");
+    if modified + removed > 0 {
+        warn!(
+            modified,
+            removed, "Plaid changed transactions of earlier runs, fix them in HomeBank by hand"
+        );
+    }
+
+    let statement = serde_json::to_vec_pretty(&json!({ "added": added })).into_diagnostic()?;
+    Ok((statement, state))
+}
+
+/// Remembers the cursor for the next run.
+pub fn store(options: &PlaidOptions, statement: &Path, state: &State) -> Result<()> {
+    let path = options.state_path(statement);
+    let content = serde_json::to_string_pretty(state).into_diagnostic()?;
+    fs::write(&path, content)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed writing {}", path.display()))
+}
@@ -0,0 +1,162 @@
+//! Encrypted inputs and outputs through `gpg`.
+//!
+//! Inputs are decrypted into memory and outputs are piped through gpg on
+//! their way into the file, so the plaintext never touches the disk. Keys,
+//! agent and trust are whatever gpg is set up with, `GNUPGHOME` included.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    thread,
+};
+
+use miette::{miette, Context, IntoDiagnostic, Result};
+use tracing::error;
+
+/// Extensions of encrypted files, left out when looking at the extension
+/// of the contained file.
+const EXTENSIONS: &[&str] = &[".gpg", ".pgp", ".asc"];
+
+/// The decrypted `content` of the input `name`.
+pub fn decrypt(name: &str, content: Vec<u8>) -> Result<Vec<u8>> {
+    let mut child = Command::new("gpg")
+        .args(["--quiet", "--decrypt"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .into_diagnostic()
+        .wrap_err("Failed starting gpg")?;
+
+    // Written beside reading, gpg blocks once the output pipe is full
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| miette!("gpg has no stdin"))?;
+    let writer = thread::spawn(move || stdin.write_all(&content));
+    let output = child
+        .wait_with_output()
+        .into_diagnostic()
+        .wrap_err("Failed waiting for gpg")?;
+    // A failed write shows as gpg failing
+    let _ = writer.join();
+
+    if !output.status.success() {
+        return Err(miette!(
+            "gpg failed decrypting {} with {}",
+            name,
+            output.status
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// The name of the contained file, as in `export.csv` for `export.csv.gpg`.
+pub fn inner_name(name: &str) -> &str {
+    EXTENSIONS
+        .iter()
+        .find_map(|e| name.strip_suffix(e))
+        .unwrap_or(name)
+}
+
+/// A file written through `gpg --encrypt`. Flushing ends the input of gpg
+/// and waits until it wrote the file, outputs only flush once they are done.
+pub struct Encrypted {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl Encrypted {
+    pub fn create(path: &Path, recipients: &[String]) -> Result<Self> {
+        let file = File::create(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed creating {}", path.display()))?;
+
+        let mut child = Command::new("gpg")
+            .args(["--batch", "--yes", "--encrypt"])
+            .args(recipients.iter().flat_map(|r| ["--recipient", r]))
+            .stdin(Stdio::piped())
+            .stdout(file)
+            .spawn()
+            .into_diagnostic()
+            .wrap_err("Failed starting gpg")?;
+        let stdin = child.stdin.take();
+
+        Ok(Self { child, stdin })
+    }
+}
+
+impl Write for Encrypted {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.stdin {
+            Some(stdin) => stdin.write(buf),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Closing stdin lets gpg write the rest of the file
+        if self.stdin.take().is_none() {
+            return Ok(());
+        }
+        let status = self.child.wait()?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(io::Error::other(format!(
+                "gpg failed encrypting the output with {}",
+                status
+            ))),
+        }
+    }
+}
+
+impl Drop for Encrypted {
+    fn drop(&mut self) {
+        // Outputs failing halfway don't flush
+        if let Err(err) = self.flush() {
+            error!(%err, "Failed encrypting the output");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, process::Command};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("hbconv-gpg-{}", std::process::id()));
+        let home = dir.join("gnupg");
+        fs::create_dir_all(&home).unwrap();
+        // No other test runs gpg
+        std::env::set_var("GNUPGHOME", &home);
+        let generated = Command::new("gpg")
+            .args(["--batch", "--passphrase", "", "--quick-gen-key"])
+            .args(["hbconv@example.com", "default", "default", "never"])
+            .output();
+        if !generated.is_ok_and(|o| o.status.success()) {
+            // No gpg to test with
+            fs::remove_dir_all(&dir).unwrap();
+            return;
+        }
+
+        let path = dir.join("out.csv.gpg");
+        let mut encrypted = Encrypted::create(&path, &["hbconv@example.com".to_string()]).unwrap();
+        encrypted.write_all(b"2024-03-07;8;;Woopsie\n").unwrap();
+        encrypted.flush().unwrap();
+
+        let content = fs::read(&path).unwrap();
+        assert_eq!(
+            decrypt("out.csv.gpg", content).unwrap(),
+            b"2024-03-07;8;;Woopsie\n"
+        );
+        assert_eq!(inner_name("out.csv.gpg"), "out.csv");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,18 +2,23 @@
 //!
 //! Docs are taken from http://homebank.free.fr/help/misc-csvformat.html#txn .
 
-use std::io;
+use std::{collections::HashMap, io};
 
 use chrono::NaiveDate;
-use csv::{Writer, WriterBuilder};
+use csv::{Terminator, Writer, WriterBuilder};
 use miette::{Context, IntoDiagnostic, Result};
-use rusty_money::{iso::Currency, Money};
+use rusty_money::{
+    iso::{Currency, EUR},
+    Money,
+};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[repr(u8)]
 pub enum Payment {
+    /// Not told by the export, the profile fills in its payment
     None = 0,
     CreditCard = 1,
     Check = 2,
@@ -29,7 +34,26 @@ pub enum Payment {
     DirectDebit = 11,
 }
 
-#[derive(Debug)]
+/// Layout of the written csv file. HomeBank reads both `;` and `,`.
+#[derive(Debug, Clone)]
+pub struct CsvStyle {
+    pub delimiter: u8,
+    /// Start with a `date;payment;info;...` line
+    pub header: bool,
+    pub crlf: bool,
+}
+
+impl Default for CsvStyle {
+    fn default() -> Self {
+        Self {
+            delimiter: b';',
+            header: false,
+            crlf: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Record {
     pub date: NaiveDate,
     pub payment: Payment,
@@ -40,18 +64,121 @@ pub struct Record {
     pub category: String,
     // tags separated by space
     pub tags: Vec<String>,
+    // iban of the other party, not part of the homebank format
+    pub iban: String,
+    // category lines of a split transaction, adding up to amount
+    pub splits: Vec<SplitLine>,
+    // account of the input file the record was read from, for --split by-account
+    pub source: String,
+}
+
+/// An empty record of zero euro on 1970-01-01.
+impl Default for Record {
+    fn default() -> Self {
+        Self {
+            date: NaiveDate::default(),
+            payment: Payment::None,
+            info: String::new(),
+            payee: String::new(),
+            memo: String::new(),
+            amount: Money::from_minor(0, EUR),
+            category: String::new(),
+            tags: Vec::new(),
+            iban: String::new(),
+            splits: Vec::new(),
+            source: String::new(),
+        }
+    }
+}
+
+/// One category line of a split transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitLine {
+    pub amount: Money<'static, Currency>,
+    pub category: String,
+    pub memo: String,
 }
 
 impl Record {
-    pub fn writer<W: io::Write>(writer: W) -> Writer<W> {
+    pub fn writer<W: io::Write>(writer: W, style: &CsvStyle) -> Writer<W> {
         WriterBuilder::new()
-            .delimiter(b';')
-            .has_headers(false)
+            .delimiter(style.delimiter)
+            .has_headers(style.header)
+            .terminator(if style.crlf {
+                Terminator::CRLF
+            } else {
+                Terminator::Any(b'\n')
+            })
             .from_writer(writer)
     }
 
-    pub fn write<W: io::Write>(self, writer: &mut Writer<W>) -> Result<()> {
-        let ir: RecordIR = self.into();
+    /// Replaces the amount and the amounts of the split lines by `f` of them.
+    /// Outputs write split records by their lines, so changing only the
+    /// amount would leave them adding up to the old one.
+    pub fn map_amounts(
+        &mut self,
+        f: impl Fn(&Money<'static, Currency>) -> Money<'static, Currency>,
+    ) {
+        self.amount = f(&self.amount);
+        for split in &mut self.splits {
+            split.amount = f(&split.amount);
+        }
+    }
+
+    /// One record for each split line, with its amount, category and memo,
+    /// for outputs writing no split transactions. A record without lines is
+    /// its only one.
+    pub fn lines(&self) -> Vec<Record> {
+        if self.splits.is_empty() {
+            return vec![self.clone()];
+        }
+        self.splits
+            .iter()
+            .map(|line| Record {
+                amount: line.amount.clone(),
+                category: line.category.clone(),
+                memo: match line.memo.is_empty() {
+                    true => self.memo.clone(),
+                    false => line.memo.clone(),
+                },
+                splits: Vec::new(),
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// A hash over everything identifying the transaction. It is stable
+    /// across runs, but identical transactions share the same fingerprint.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        for field in [
+            self.date.to_string().as_str(),
+            &self.amount.amount().normalize().to_string(),
+            self.amount.currency().iso_alpha_code,
+            &self.payee,
+            &self.iban,
+            &self.memo,
+            &self.info,
+        ] {
+            hasher.update(field.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// The fingerprint, shortened to fit the id fields of the outputs, with
+    /// the count of identical records `seen` before. Identical transactions
+    /// on the same day are legit and must not be dropped as duplicates.
+    pub fn occurrence_id(&self, seen: &mut HashMap<String, usize>) -> String {
+        let fingerprint = self.fingerprint();
+        let n = seen.entry(fingerprint.clone()).or_default();
+        let id = format!("{}-{}", &fingerprint[..32], n);
+        *n += 1;
+        id
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+        let ir: RecordIR = self.clone().into();
 
         writer
             .serialize(ir)
@@ -75,19 +202,44 @@ struct RecordIR {
 
 impl From<Record> for RecordIR {
     fn from(value: Record) -> Self {
+        // Homebank reads the lines of a split from memo, amount and
+        // category, separated by `||`
+        if !value.splits.is_empty() {
+            let join = |f: fn(&SplitLine) -> String| -> String {
+                value.splits.iter().map(f).collect::<Vec<_>>().join("||")
+            };
+            return Self {
+                date: value.date.format("%Y-%m-%d").to_string(),
+                payment: value.payment as u8,
+                memo: join(|s| s.memo.clone()),
+                amount: join(|s| format_amount(&s.amount)),
+                category: join(|s| s.category.clone()),
+                info: value.info,
+                payee: value.payee,
+                tags: value.tags.join(" "),
+            };
+        }
+
         Self {
             date: value.date.format("%Y-%m-%d").to_string(),
             payment: value.payment as u8,
             info: value.info,
             payee: value.payee,
             memo: value.memo,
-            amount: value.amount.to_string(),
+            amount: format_amount(&value.amount),
             category: value.category,
             tags: value.tags.join(" "),
         }
     }
 }
 
+/// Formats an amount the way Homebank expects it: no currency symbol, no
+/// thousands separator and a ',' as decimal separator.
+fn format_amount(amount: &Money<'static, Currency>) -> String {
+    let exponent = amount.currency().exponent as usize;
+    format!("{:.*}", exponent, amount.amount()).replace('.', ",")
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -96,7 +248,7 @@ mod test {
 
     #[test]
     fn test_basic_deser() {
-        let expected = b"2015-02-04;0;;;Some cash;-40,00;Bill:Withdrawal of cash;tag1 tag2\n2015-02-04;1;;;Internet DSL;-45,00;Inline service/Internet;tag2 my-tag3\n";
+        let expected = b"2015-02-04;0;;;Some cash;40,00;Bill:Withdrawal of cash;tag1 tag2\n2015-02-04;1;;;Internet DSL;-45,00;Inline service/Internet;tag2 my-tag3\n";
 
         let date =
             NaiveDate::parse_from_str("2015-02-04", "%Y-%m-%d").expect("Failed parsing date");
@@ -104,23 +256,20 @@ mod test {
         let data = vec![
             Record {
                 date,
-                payment: Payment::None,
-                info: "".to_string(),
-                payee: "".to_string(),
                 memo: "Some cash".to_string(),
                 amount: Money::from_str("40,00", EUR).expect("Failed parsing money"),
                 category: "Bill:Withdrawal of cash".to_string(),
                 tags: vec!["tag1".to_string(), "tag2".to_string()],
+                ..Default::default()
             },
             Record {
                 date,
                 payment: Payment::CreditCard,
-                info: "".to_string(),
-                payee: "".to_string(),
                 memo: "Internet DSL".to_string(),
                 amount: Money::from_str("-45,00", EUR).expect("Failed parsing money"),
                 category: "Inline service/Internet".to_string(),
                 tags: vec!["tag2".to_string(), "my-tag3".to_string()],
+                ..Default::default()
             },
         ];
 
@@ -143,4 +292,84 @@ mod test {
 
         assert_eq!(writer, expected);
     }
+
+    #[test]
+    fn test_styled_writer() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2015, 2, 4).unwrap(),
+            memo: "Some cash".to_string(),
+            amount: Money::from_str("-40,00", EUR).expect("Failed parsing money"),
+            ..Default::default()
+        };
+        let style = CsvStyle {
+            delimiter: b',',
+            header: true,
+            crlf: true,
+        };
+
+        let mut writer = Record::writer(Vec::new(), &style);
+        record.write(&mut writer).unwrap();
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+
+        assert_eq!(
+            written,
+            "date,payment,info,payee,memo,amount,category,tags\r\n2015-02-04,0,,,Some cash,\"-40,00\",,\r\n"
+        );
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2015, 2, 4).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "PayPal".to_string(),
+            memo: "Order".to_string(),
+            amount: Money::from_str("-10,35", EUR).unwrap(),
+            splits: vec![
+                SplitLine {
+                    amount: Money::from_str("-10,00", EUR).unwrap(),
+                    category: "Shopping".to_string(),
+                    memo: "Order".to_string(),
+                },
+                SplitLine {
+                    amount: Money::from_str("-0,35", EUR).unwrap(),
+                    category: "Fees".to_string(),
+                    memo: "Fee".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut writer = Record::writer(Vec::new(), &CsvStyle::default());
+        record.write(&mut writer).unwrap();
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+
+        assert_eq!(
+            written,
+            "2015-02-04;8;;PayPal;Order||Fee;-10,00||-0,35;Shopping||Fees;\n"
+        );
+
+        let lines: Vec<_> = record
+            .lines()
+            .into_iter()
+            .map(|r| (r.category, r.memo, r.amount.to_string(), r.splits.len()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    "Shopping".to_string(),
+                    "Order".to_string(),
+                    "-€10,00".to_string(),
+                    0
+                ),
+                (
+                    "Fees".to_string(),
+                    "Fee".to_string(),
+                    "-€0,35".to_string(),
+                    0
+                )
+            ]
+        );
+    }
 }
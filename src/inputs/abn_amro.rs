@@ -0,0 +1,272 @@
+//! The TAB download of ABN AMRO, tab separated without header: account,
+//! currency, `YYYYMMDD` transaction date, start and end balance, value
+//! date, amount with a decimal comma and a description.
+//!
+//! The description holds the SEPA fields, either as `/TRTP/.../NAME/...`
+//! or as labels like `Naam:` and `Omschrijving:` in padded columns. The
+//! remittance information among them is read like SEPA purposes elsewhere.
+//! Card payments and withdrawals name the merchant after date and time.
+
+use std::{collections::HashMap, io::Read, sync::LazyLock, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use regex::Regex;
+use rust_decimal::Decimal;
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::Purpose,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+/// The columns of the download, which it does not name itself.
+const HEADER: &str = "Rekeningnummer\tMuntsoort\tTransactiedatum\tBeginsaldo\tEindsaldo\t\
+    Rentedatum\tTransactiebedrag\tOmschrijving";
+
+/// A row as the download writes them: account, currency and date.
+static ROW: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\d{6,10}\t[A-Z]{3}\t\d{8}\t").unwrap());
+
+/// The labels of the SEPA fields, the slash separated ones and the padded.
+static FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"/(?:TRTP|IBAN|BIC|NAME|REMI|EREF|MARF|CSID|ORDP|BENM|ID|ADDR)/|",
+        r"\b(?:IBAN|BIC|Naam|Omschrijving|Kenmerk|Machtiging|Incassant):"
+    ))
+    .unwrap()
+});
+
+/// The merchant of card payments, after date and time or the card type.
+static MERCHANT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^[BG]EA,?\s+(?:\S+\s+)*?(?:\d{2}\.\d{2}\.\d{2}/\d{2}[.:]\d{2}\s+|Betaalpas\s+)([^,]+)",
+    )
+    .unwrap()
+});
+
+/// Whether the start of an input looks like an ABN AMRO TAB download.
+pub fn is_abn_amro(head: &str) -> bool {
+    head.lines().next().is_some_and(|l| ROW.is_match(l))
+}
+
+#[derive(Debug, Deserialize)]
+struct AbnAmroIR {
+    #[serde(rename = "Muntsoort")]
+    muntsoort: String,
+    #[serde(rename = "Transactiedatum")]
+    transactiedatum: String,
+    #[serde(rename = "Transactiebedrag")]
+    transactiebedrag: String,
+    #[serde(rename = "Omschrijving", default)]
+    omschrijving: String,
+}
+
+/// The text in front of the first label and the labelled fields.
+fn fields(text: &str) -> (String, HashMap<String, String>) {
+    let mut labels = FIELD.find_iter(text).peekable();
+    let lead = match labels.peek() {
+        Some(first) => &text[..first.start()],
+        None => text,
+    };
+    let mut fields = HashMap::new();
+    while let Some(label) = labels.next() {
+        let end = labels.peek().map_or(text.len(), |next| next.start());
+        let value = text[label.end()..end]
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        fields.insert(
+            label
+                .as_str()
+                .trim_matches(|c| c == '/' || c == ':')
+                .to_string(),
+            value.join(" "),
+        );
+    }
+    (
+        lead.split_whitespace().collect::<Vec<_>>().join(" "),
+        fields,
+    )
+}
+
+fn payment(text: &str) -> Payment {
+    let lower = text.to_lowercase();
+    if lower.starts_with("bea") {
+        Payment::DebitCard
+    } else if lower.starts_with("gea") {
+        Payment::Cash
+    } else if lower.contains("incasso") {
+        Payment::DirectDebit
+    } else if lower.contains("periodieke") {
+        Payment::StandingOrder
+    } else if lower.contains("overboeking") || lower.contains("ideal") {
+        Payment::BankTransfer
+    } else if lower.contains("abn amro bank n.v.") {
+        Payment::FinancialInstitutionFee
+    } else {
+        Payment::None
+    }
+}
+
+impl TryFrom<AbnAmroIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: AbnAmroIR) -> Result<Self> {
+        let currency = iso::find(&value.muntsoort)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.muntsoort))?;
+        let (lead, mut fields) = fields(&value.omschrijving);
+        let mut take = |labels: &[&str]| {
+            labels
+                .iter()
+                .find_map(|l| fields.remove(*l))
+                .unwrap_or_default()
+        };
+        let kind = take(&["TRTP"]);
+        let purpose = Purpose::parse(&take(&["REMI", "Omschrijving"]));
+        let reference = take(&["EREF", "Kenmerk"]);
+        let payee = match MERCHANT.captures(&value.omschrijving) {
+            Some(c) => c[1].trim().to_string(),
+            None => take(&["NAME", "Naam"]),
+        };
+        let memo = match purpose.text.is_empty() && payee.is_empty() {
+            true => lead.clone(),
+            false => purpose.text,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.transactiedatum, "%Y%m%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&format!("{} {}", lead, kind)),
+            info: match reference.as_str() {
+                "" | "NOTPROVIDED" => purpose.end_to_end.unwrap_or_default(),
+                _ => reference,
+            },
+            payee,
+            memo,
+            amount: Money::from_decimal(decimal_de(&value.transactiebedrag)?, currency),
+            iban: take(&["IBAN"]),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct AbnAmroIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl AbnAmroIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let text = format!("{}\n{}", HEADER, text);
+                convert(
+                    csv_rows::<_, AbnAmroIR>(text.as_bytes(), b'\t', HEADER),
+                    "abn-amro",
+                )
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for AbnAmroIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample download of `rows` in EUR.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::new();
+    let mut saldo = Decimal::new(500000, 2);
+    for row in rows {
+        let date = row.date.format("%Y%m%d");
+        let start = saldo;
+        saldo += row.amount;
+        let description = match row.kind {
+            Kind::Card => format!(
+                "BEA, Betaalpas                   {},PAS123 NR:AB1234, {} AMSTERDAM",
+                row.payee,
+                row.date.format("%d.%m.%y/10:00")
+            ),
+            Kind::Cash => format!(
+                "GEA, Betaalpas                   {},PAS123 NR:AB1234, {}",
+                row.payee,
+                row.date.format("%d.%m.%y/10:00")
+            ),
+            Kind::DirectDebit => format!(
+                "/TRTP/SEPA Incasso algemeen doorlopend/CSID/NL12ZZZ123/NAME/{}/MARF/M-1\
+                /REMI/{}/IBAN/{}/BIC/INGBNL2A/EREF/{}",
+                row.payee, row.purpose, row.iban, row.reference
+            ),
+            Kind::StandingOrder => format!(
+                "/TRTP/SEPA Periodieke overb./IBAN/{}/BIC/INGBNL2A/NAME/{}/REMI/{}/EREF/{}",
+                row.iban, row.payee, row.purpose, row.reference
+            ),
+            Kind::Transfer | Kind::Salary => format!(
+                "/TRTP/SEPA OVERBOEKING/IBAN/{}/BIC/INGBNL2A/NAME/{}/REMI/{}/EREF/{}",
+                row.iban, row.payee, row.purpose, row.reference
+            ),
+        };
+        out.push_str(&format!(
+            "123456789\tEUR\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            date,
+            start.to_string().replace('.', ","),
+            saldo.to_string().replace('.', ","),
+            date,
+            row.amount_de(),
+            description
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "123456789\tEUR\t20240307\t5000,00\t4974,12\t20240307\t-25,88\tBEA   NR:AB1234   07.03.24/18.30 Albert Heijn 1234,PAS123\n\
+            123456789\tEUR\t20240308\t4974,12\t4932,12\t20240308\t-42,00\tSEPA Incasso algemeen doorlopend Incassant: NL12ZZZ123        Naam: Vattenfall                 Machtiging: M-1                  Omschrijving: Energie maart      IBAN: NL20INGB0001234567         Kenmerk: REF-1\n\
+            123456789\tEUR\t20240325\t4932,12\t7432,12\t20240325\t2500,00\t/TRTP/SEPA OVERBOEKING/IBAN/NL02ABNA0123456789/BIC/ABNANL2A/NAME/Werkgever BV/REMI/Salaris maart/EREF/NOTPROVIDED\n";
+
+        assert!(is_abn_amro(input));
+        let records: Vec<Record> = AbnAmroIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Albert Heijn 1234");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payee, "Vattenfall");
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[1].memo, "Energie maart");
+        assert_eq!(records[1].info, "REF-1");
+        assert_eq!(records[1].iban, "NL20INGB0001234567");
+        assert_eq!(records[2].payee, "Werkgever BV");
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+        assert_eq!(records[2].info, "");
+        assert_eq!(records[2].amount, Money::from_str("2500,00", EUR).unwrap());
+    }
+}
@@ -0,0 +1,150 @@
+//! The statement export of the Advanzia Gebührenfrei Mastercard, as csv or
+//! through the Excel file.
+//!
+//! Charges and credits come in separate columns, both without a sign. The
+//! monthly repayment is a credit like a refund, only its details tell them
+//! apart.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Datum;Details;Belastung;Gutschrift";
+
+#[derive(Debug, Deserialize)]
+struct AdvanziaIR {
+    #[serde(rename = "Datum")]
+    datum: String,
+    #[serde(rename = "Details")]
+    details: String,
+    #[serde(rename = "Belastung", default)]
+    belastung: String,
+    #[serde(rename = "Gutschrift", default)]
+    gutschrift: String,
+}
+
+impl TryFrom<AdvanziaIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: AdvanziaIR) -> Result<Self> {
+        let amount = match value.belastung.is_empty() {
+            true => decimal_de(&value.gutschrift)?,
+            false => -decimal_de(&value.belastung)?,
+        };
+        let details = value.details.to_lowercase();
+        let payment = match details.contains("zahlung") || details.contains("überweisung") {
+            true if amount.is_sign_positive() => Payment::BankTransfer,
+            _ => Payment::CreditCard,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.datum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting datum into datetime")?,
+            payment,
+            payee: value.details,
+            amount: Money::from_decimal(amount, EUR),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct AdvanziaIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl AdvanziaIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, AdvanziaIR>(text.as_bytes(), b';', HEADER),
+                "advanzia",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for AdvanziaIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Gebührenfrei Mastercard Gold;\nKartennummer;5412 **** **** 1234\n\n\
+        Datum;Details;Belastung;Gutschrift\n",
+    );
+    for row in rows {
+        let details = match row.kind {
+            Kind::Salary | Kind::Transfer if row.amount.is_sign_positive() => {
+                "Zahlung erhalten - Danke".to_string()
+            }
+            _ => row.payee.clone(),
+        };
+        let amount = row.amount.abs().to_string().replace('.', ",");
+        let (belastung, gutschrift) = match row.amount.is_sign_negative() {
+            true => (amount, String::new()),
+            false => (String::new(), amount),
+        };
+        out.push_str(&format!(
+            "{};{};{};{}\n",
+            row.date.format("%d.%m.%Y"),
+            details,
+            belastung,
+            gutschrift
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Gebührenfrei Mastercard Gold;\nKartennummer;5412 **** **** 1234\n\n\
+            Datum;Details;Belastung;Gutschrift\n\
+            07.03.2024;REWE MARKT BERLIN;1.025,88;\n\
+            06.03.2024;AMAZON.DE Erstattung;;12,99\n\
+            05.03.2024;Zahlung erhalten - Danke;;250,00\n";
+
+        let records: Vec<Record> = AdvanziaIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "REWE MARKT BERLIN");
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::CreditCard);
+        assert_eq!(records[1].amount, Money::from_str("12,99", EUR).unwrap());
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+    }
+}
@@ -0,0 +1,202 @@
+//! Exports of the Amazon Visa credit card, issued by the Landesbank Berlin
+//! (LBB) and since 2024 by Zinia.
+//!
+//! The LBB export only has a description, the Zinia one a type for each
+//! row. Either tells purchases from payments onto the card, which are
+//! often partial ones, and from interest, which becomes a bank fee.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const LBB_HEADER: &str = "Umsatzdatum;Buchungsdatum;Beschreibung";
+const ZINIA_HEADER: &str = "Transaktionsdatum;Buchungsdatum;Händler";
+
+#[derive(Debug, Deserialize)]
+struct LbbIR {
+    #[serde(rename = "Umsatzdatum")]
+    umsatzdatum: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZiniaIR {
+    #[serde(rename = "Transaktionsdatum")]
+    transaktionsdatum: String,
+    #[serde(rename = "Händler")]
+    händler: String,
+    #[serde(rename = "Typ")]
+    typ: String,
+    #[serde(rename = "Betrag (EUR)")]
+    betrag: String,
+}
+
+/// The payment method of a row, told by its type or description.
+fn payment(text: &str) -> Payment {
+    let text = text.to_lowercase();
+    if text.contains("zinsen") {
+        Payment::FinancialInstitutionFee
+    } else if ["zahlung", "lastschrift", "überweisung"]
+        .iter()
+        .any(|w| text.contains(w))
+    {
+        // Payments onto the card, full or partial
+        Payment::BankTransfer
+    } else {
+        Payment::CreditCard
+    }
+}
+
+fn record(date: &str, payment: Payment, payee: String, amount: &str) -> Result<Record> {
+    Ok(Record {
+        date: NaiveDate::parse_from_str(date, "%d.%m.%Y")
+            .into_diagnostic()
+            .wrap_err("Failed converting date into datetime")?,
+        payment,
+        payee,
+        amount: Money::from_decimal(decimal_de(amount)?, EUR),
+        ..Default::default()
+    })
+}
+
+impl TryFrom<LbbIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: LbbIR) -> Result<Self> {
+        record(
+            &value.umsatzdatum,
+            payment(&value.beschreibung),
+            value.beschreibung,
+            &value.betrag,
+        )
+    }
+}
+
+impl TryFrom<ZiniaIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: ZiniaIR) -> Result<Self> {
+        let payment = match value.typ.as_str() {
+            "Einkauf" | "Gutschrift" => Payment::CreditCard,
+            typ => payment(typ),
+        };
+        record(
+            &value.transaktionsdatum,
+            payment,
+            value.händler,
+            &value.betrag,
+        )
+    }
+}
+
+pub struct AmazonVisaIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl AmazonVisaIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) if text.contains(ZINIA_HEADER) => convert(
+                csv_rows::<_, ZiniaIR>(text.as_bytes(), b';', ZINIA_HEADER),
+                "amazon visa",
+            ),
+            Ok(text) => convert(
+                csv_rows::<_, LbbIR>(text.as_bytes(), b';', LBB_HEADER),
+                "amazon visa",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for AmazonVisaIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in the layout of Zinia.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Amazon Visa;Kartennummer 4000 **** **** 1234\n\n\
+        Transaktionsdatum;Buchungsdatum;Händler;Typ;Betrag (EUR)\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let typ = match (row.kind, row.amount.is_sign_negative()) {
+            (Kind::Salary | Kind::Transfer, false) => "Zahlung",
+            (_, false) => "Gutschrift",
+            (_, true) => "Einkauf",
+        };
+        out.push_str(&format!(
+            "{};{};{};{};{}\n",
+            date,
+            date,
+            row.payee,
+            typ,
+            row.amount_de()
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let zinia = "Transaktionsdatum;Buchungsdatum;Händler;Typ;Betrag (EUR)\n\
+            07.03.2024;08.03.2024;AMAZON.DE;Einkauf;-25,88\n\
+            05.03.2024;05.03.2024;Ihre Zahlung;Teilzahlung;50,00\n\
+            01.03.2024;01.03.2024;Sollzinsen;Zinsen;-3,12\n";
+        let records: Vec<Record> = AmazonVisaIter::new(zinia.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "AMAZON.DE");
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+        assert_eq!(records[2].payment, Payment::FinancialInstitutionFee);
+
+        let lbb = "Kreditkarte:;4000 **** **** 1234\n\n\
+            Umsatzdatum;Buchungsdatum;Beschreibung;Betrag;Punkte\n\
+            07.03.2023;08.03.2023;AMAZON.DE;-25,88;25\n\
+            05.03.2023;05.03.2023;Lastschrift;100,00;0\n";
+        let records: Vec<Record> = AmazonVisaIter::new(WINDOWS_1252.encode(lbb).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+    }
+}
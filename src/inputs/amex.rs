@@ -0,0 +1,180 @@
+//! The activity export of American Express Germany, as csv or through the
+//! Excel file, with English or German headers.
+//!
+//! Charges are written positive and payments onto the card negative, the
+//! other way round than HomeBank. The csv is comma separated with
+//! `DD/MM/YYYY` dates and decimal points for the English headers, decimal
+//! commas for the German ones.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+/// The starts of header lines, the English csv with decimal points first.
+const HEADERS: [&str; 4] = [
+    "Date,Description",
+    "Datum,Beschreibung",
+    "Date;Description",
+    "Datum;Beschreibung",
+];
+
+#[derive(Debug, Deserialize)]
+struct AmexIR {
+    #[serde(rename = "Datum", alias = "Date")]
+    datum: String,
+    #[serde(rename = "Beschreibung", alias = "Description")]
+    beschreibung: String,
+    #[serde(rename = "Betrag", alias = "Amount")]
+    betrag: String,
+    #[serde(rename = "Referenz", alias = "Reference", default)]
+    referenz: String,
+    /// Whether the amount has a decimal point, not set from the export
+    #[serde(skip)]
+    points: bool,
+}
+
+impl TryFrom<AmexIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: AmexIR) -> Result<Self> {
+        let date = NaiveDate::parse_from_str(&value.datum, "%d/%m/%Y")
+            .or_else(|_| NaiveDate::parse_from_str(&value.datum, "%d.%m.%Y"))
+            .into_diagnostic()
+            .wrap_err("Failed converting datum into datetime")?;
+        let amount = match value.points {
+            true => decimal(&value.betrag, '.')?,
+            false => decimal_de(&value.betrag)?,
+        };
+        let text = value.beschreibung.to_lowercase();
+        let payment = match text.contains("zahlung erhalten") || text.contains("payment received") {
+            true => Payment::Deposit,
+            false => Payment::CreditCard,
+        };
+
+        Ok(Self {
+            date,
+            payment,
+            info: value.referenz.trim_matches('\'').to_string(),
+            payee: value.beschreibung,
+            amount: Money::from_decimal(-amount, EUR),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct AmexIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl AmexIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let plain = text.replace('"', "");
+                match HEADERS.iter().position(|h| plain.contains(h)) {
+                    Some(index) => {
+                        let header = HEADERS[index];
+                        let delimiter = match header.contains(';') {
+                            true => b';',
+                            false => b',',
+                        };
+                        let mut rows = csv_rows::<_, AmexIR>(text.as_bytes(), delimiter, header);
+                        for ir in rows.iter_mut().flatten() {
+                            ir.points = index == 0;
+                        }
+                        convert(rows, "amex")
+                    }
+                    None => vec![Err(miette!("No amex header line"))],
+                }
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for AmexIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, the csv with German headers.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from("Datum,Beschreibung,Karteninhaber,Konto #,Betrag,Referenz\n");
+    for row in rows {
+        let text = match row.kind {
+            Kind::Salary | Kind::Transfer if row.amount.is_sign_positive() => {
+                "ZAHLUNG ERHALTEN. BESTEN DANK.".to_string()
+            }
+            _ => row.payee.to_uppercase(),
+        };
+        out.push_str(&format!(
+            "{},{},MAX MUSTERMANN,-11004,\"{}\",'{}'\n",
+            row.date.format("%d/%m/%Y"),
+            text,
+            (-row.amount).to_string().replace('.', ","),
+            row.reference
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let german = "Datum,Beschreibung,Karteninhaber,Konto #,Betrag,Referenz\n\
+            07/03/2024,REWE MARKT BERLIN,MAX MUSTERMANN,-11004,\"25,88\",'AT240670001'\n\
+            05/03/2024,ZAHLUNG ERHALTEN. BESTEN DANK.,MAX MUSTERMANN,-11004,\"-250,00\",'AT240650002'\n";
+        let records: Vec<Record> = AmexIter::new(german.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "REWE MARKT BERLIN");
+        assert_eq!(records[0].info, "AT240670001");
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::Deposit);
+        assert_eq!(records[1].amount, Money::from_str("250,00", EUR).unwrap());
+
+        let english = "Date,Description,Card Member,Account #,Amount\n\
+            07/03/2024,APPLE.COM/BILL,MAX MUSTERMANN,-11004,1234.50\n";
+        let records: Vec<Record> = AmexIter::new(english.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records[0].amount, Money::from_str("-1234,50", EUR).unwrap());
+
+        let excel = "Datum;Beschreibung;Karteninhaber;Konto #;Betrag\n\
+            07.03.2024;APPLE.COM/BILL;MAX MUSTERMANN;-11004;9,99\n";
+        let records: Vec<Record> = AmexIter::new(excel.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records[0].amount, Money::from_str("-9,99", EUR).unwrap());
+    }
+}
@@ -0,0 +1,166 @@
+//! The credit card export of Barclays Germany, an Excel file read through
+//! its csv rendering.
+//!
+//! A block of card, holder and balance comes before the header. The record
+//! takes the day of the transaction, the booking day only if it is missing,
+//! and the merchant from `Beschreibung`. Pending transactions are left out.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Referenznummer;Transaktionsdatum;Buchungsdatum";
+
+#[derive(Debug, Deserialize)]
+struct BarclaysIR {
+    #[serde(rename = "Referenznummer")]
+    referenznummer: String,
+    #[serde(rename = "Transaktionsdatum")]
+    transaktionsdatum: String,
+    #[serde(rename = "Buchungsdatum")]
+    buchungsdatum: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Typ", default)]
+    typ: String,
+    #[serde(rename = "Status", default)]
+    status: String,
+    #[serde(rename = "Originalbetrag", default)]
+    originalbetrag: String,
+}
+
+impl TryFrom<BarclaysIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: BarclaysIR) -> Result<Self> {
+        let date = match value.transaktionsdatum.is_empty() {
+            true => &value.buchungsdatum,
+            false => &value.transaktionsdatum,
+        };
+        let payment = match value.typ.as_str() {
+            // Payments onto the card
+            "Einzahlung" | "Zahlung" => Payment::BankTransfer,
+            _ => Payment::CreditCard,
+        };
+        // Only foreign currencies tell something the amount does not
+        let memo = match value.originalbetrag.contains("EUR") || value.originalbetrag.contains('€')
+        {
+            true => String::new(),
+            false => value.originalbetrag,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(date, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting transaktionsdatum into datetime")?,
+            payment,
+            info: value.referenznummer,
+            payee: value.beschreibung,
+            memo,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, EUR),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct BarclaysIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl BarclaysIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, BarclaysIR>(text.as_bytes(), b';', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.status == "Ausstehend"));
+                convert(rows, "barclays")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for BarclaysIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, as rendered from the Excel file.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Barclays Visa;\nKarteninhaber;Max Mustermann\nKontostand;-123,45 €\n\n\
+        Referenznummer;Transaktionsdatum;Buchungsdatum;Betrag;Beschreibung;Typ;Status;\
+        Kartennummer;Originalbetrag\n",
+    );
+    for row in rows {
+        let typ = match row.amount.is_sign_negative() {
+            true => "Belastung",
+            false => "Gutschrift",
+        };
+        out.push_str(&format!(
+            "{};{};{};{} €;{};{};Bearbeitet;4000 **** **** 1234;\n",
+            row.reference,
+            row.date.format("%d.%m.%Y"),
+            row.date.format("%d.%m.%Y"),
+            row.amount_de(),
+            row.payee.to_uppercase(),
+            typ
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Barclays Visa;\nKarteninhaber;Max Mustermann\nKontostand;-123,45 €\n\n\
+            Referenznummer;Transaktionsdatum;Buchungsdatum;Betrag;Beschreibung;Typ;Status;Kartennummer;Originalbetrag\n\
+            1;08.03.2024;;-5,00 €;WOOPSIE;Belastung;Ausstehend;4000 **** **** 1234;\n\
+            2;06.03.2024;07.03.2024;-9,31 €;APPLE.COM/BILL;Belastung;Bearbeitet;4000 **** **** 1234;-10,00 USD\n\
+            3;;05.03.2024;100,00 €;Einzahlung Danke;Einzahlung;Bearbeitet;4000 **** **** 1234;\n";
+
+        let records: Vec<Record> = BarclaysIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 6).unwrap()
+        );
+        assert_eq!(records[0].payee, "APPLE.COM/BILL");
+        assert_eq!(records[0].memo, "-10,00 USD");
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[0].amount, Money::from_str("-9,31", EUR).unwrap());
+        assert_eq!(
+            records[1].date,
+            NaiveDate::from_ymd_opt(2024, 3, 5).unwrap()
+        );
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+    }
+}
@@ -0,0 +1,164 @@
+//! The current account export of Barclays UK, comma separated with
+//! `DD/MM/YYYY` dates and amounts in GBP with decimal points.
+//!
+//! The memo holds the other party and then the reference, apart by a tab
+//! or a run of spaces. The subcategory tells the payment method.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::GBP, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Number,Date,Account,Amount";
+
+#[derive(Debug, Deserialize)]
+struct BarclaysUkIR {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Subcategory", default)]
+    subcategory: String,
+    #[serde(rename = "Memo", default)]
+    memo: String,
+}
+
+fn payment(subcategory: &str) -> Payment {
+    match subcategory.to_lowercase().replace(' ', "").as_str() {
+        "payment" | "debit" | "cardpurchase" => Payment::DebitCard,
+        "directdebit" => Payment::DirectDebit,
+        "standingorder" => Payment::StandingOrder,
+        "cashmachine" | "countercredit" => Payment::Cash,
+        "ft" | "billpayment" | "bgc" | "transfer" => Payment::BankTransfer,
+        "charges" | "interest" => Payment::FinancialInstitutionFee,
+        _ => Payment::None,
+    }
+}
+
+/// The other party and the reference of the memo.
+fn split_memo(memo: &str) -> (String, String) {
+    let memo = memo.trim();
+    let at = memo.find('\t').or_else(|| memo.find("  "));
+    match at {
+        Some(at) => (
+            memo[..at].trim().to_string(),
+            memo[at..].split_whitespace().collect::<Vec<_>>().join(" "),
+        ),
+        None => (memo.to_string(), String::new()),
+    }
+}
+
+impl TryFrom<BarclaysUkIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: BarclaysUkIR) -> Result<Self> {
+        let amount = decimal(&value.amount, '.')?;
+        let (payee, memo) = split_memo(&value.memo);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(value.date.trim(), "%d/%m/%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.subcategory),
+            payee,
+            memo,
+            amount: Money::from_decimal(amount, GBP),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct BarclaysUkIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl BarclaysUkIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, BarclaysUkIR>(text.as_bytes(), b',', HEADER),
+                "barclays-uk",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for BarclaysUkIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` in GBP.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from("Number,Date,Account,Amount,Subcategory,Memo\n");
+    for row in rows {
+        let subcategory = match row.kind {
+            Kind::Card => "PAYMENT",
+            Kind::DirectDebit => "DIRECTDEBIT",
+            Kind::StandingOrder => "Standing Order",
+            Kind::Cash => "Cash Machine",
+            Kind::Transfer | Kind::Salary => "FT",
+        };
+        out.push_str(&format!(
+            " ,{},20-32-06 13572468,{},{},{}\t{}\n",
+            row.date.format("%d/%m/%Y"),
+            row.amount,
+            subcategory,
+            row.payee,
+            row.purpose
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Number,Date,Account,Amount,Subcategory,Memo\n\
+            ,07/03/2024,20-32-06 13572468,-25.88,PAYMENT,TESCO STORES 1234\tON 06 MAR BCC\n\
+            ,08/03/2024,20-32-06 13572468,-42.00,DIRECTDEBIT,THAMES WATER          TW123456 DDR\n\
+            ,25/03/2024,20-32-06 13572468,2500.00,FT,EMPLOYER LTD\n";
+
+        let records: Vec<Record> = BarclaysUkIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "TESCO STORES 1234");
+        assert_eq!(records[0].memo, "ON 06 MAR BCC");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25.88", GBP).unwrap());
+        assert_eq!(records[1].payee, "THAMES WATER");
+        assert_eq!(records[1].memo, "TW123456 DDR");
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[2].payee, "EMPLOYER LTD");
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+    }
+}
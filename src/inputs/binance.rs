@@ -0,0 +1,219 @@
+//! The "Transaction History" export of Binance, comma separated with
+//! decimal points and one row per change of a coin balance.
+//!
+//! Only rows changing a fiat balance are read. Deposits, withdrawals and
+//! card payments become a record each. Crypto trades would flood HomeBank
+//! with a record per fill, so they are left out unless asked for with
+//! `--include-trades` and then summed up into one "Trading" record per day
+//! and currency, in the category given by `--trade-category`.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{self, convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "User_ID,UTC_Time,Account,Operation";
+/// The operation of the daily summary of trades.
+const TRADING: &str = "Trading";
+
+#[derive(Debug, Deserialize)]
+struct BinanceIR {
+    #[serde(rename = "UTC_Time")]
+    time: String,
+    #[serde(rename = "Operation")]
+    operation: String,
+    #[serde(rename = "Coin")]
+    coin: String,
+    #[serde(rename = "Change")]
+    change: String,
+    #[serde(rename = "Remark", default)]
+    remark: String,
+    #[serde(skip)]
+    trade_category: String,
+}
+
+impl BinanceIR {
+    fn payment(&self) -> Option<Payment> {
+        match self.operation.as_str() {
+            "Deposit" | "Withdraw" | "Fiat Deposit" | "Fiat Withdraw" => {
+                Some(Payment::BankTransfer)
+            }
+            "Binance Card Spending" | "Card Cashback" => Some(Payment::DebitCard),
+            _ => None,
+        }
+    }
+
+    fn is_trade(&self) -> bool {
+        self.payment().is_none()
+    }
+
+    fn date(&self) -> Result<NaiveDate> {
+        let date = self.time.split_whitespace().next().unwrap_or_default();
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(date, "%y-%m-%d"))
+            .into_diagnostic()
+            .wrap_err("Failed converting date into datetime")
+    }
+}
+
+impl TryFrom<BinanceIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: BinanceIR) -> Result<Self> {
+        let currency =
+            iso::find(&value.coin).ok_or_else(|| miette!("Unknown currency '{}'", value.coin))?;
+        let memo = match value.remark.is_empty() {
+            true => value.operation.clone(),
+            false => format!("{} {}", value.operation, value.remark),
+        };
+
+        Ok(Self {
+            date: value.date()?,
+            payment: value.payment().unwrap_or(Payment::ElectronicPayment),
+            payee: "Binance".to_string(),
+            memo,
+            amount: Money::from_decimal(decimal(&value.change, '.')?, currency),
+            category: value.trade_category,
+            ..Default::default()
+        })
+    }
+}
+
+/// Sums up the trades of each day and currency into one row in place of
+/// the first of them.
+fn daily(rows: Vec<Result<BinanceIR>>) -> Vec<Result<BinanceIR>> {
+    let mut out: Vec<Result<BinanceIR>> = Vec::new();
+    for ir in rows {
+        let ir = match ir {
+            Ok(ir) if ir.is_trade() => ir,
+            other => {
+                out.push(other);
+                continue;
+            }
+        };
+        let date = ir.date().ok();
+        let summary = out
+            .iter_mut()
+            .flatten()
+            .find(|sum| sum.operation == TRADING && sum.coin == ir.coin && sum.date().ok() == date);
+        match summary {
+            Some(sum) => {
+                let change =
+                    decimal(&sum.change, '.').and_then(|a| Ok(a + decimal(&ir.change, '.')?));
+                match change {
+                    Ok(change) => sum.change = change.to_string(),
+                    Err(e) => out.push(Err(e)),
+                }
+            }
+            None => out.push(Ok(BinanceIR {
+                operation: TRADING.to_string(),
+                remark: String::new(),
+                ..ir
+            })),
+        }
+    }
+    out
+}
+
+pub struct BinanceIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl BinanceIter {
+    /// Reads the export, leaving out trades unless `trades` is the category
+    /// to give their daily summaries.
+    pub fn new<R: Read>(rdr: R, trades: Option<&str>) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, BinanceIR>(text.as_bytes(), b',', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if iso::find(&ir.coin).is_none()));
+                util::trades(&mut rows, trades, BinanceIR::is_trade, |ir| {
+                    &mut ir.trade_category
+                });
+                convert(daily(rows), "binance")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for BinanceIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` as fiat deposits and withdrawals.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from("User_ID,UTC_Time,Account,Operation,Coin,Change,Remark\n");
+    for row in rows {
+        let operation = match row.amount.is_sign_negative() {
+            true => "Fiat Withdraw",
+            false => "Fiat Deposit",
+        };
+        out.push_str(&format!(
+            "123456789,{} 10:00:00,Spot,{},EUR,{},{}\n",
+            row.date.format("%Y-%m-%d"),
+            operation,
+            row.amount,
+            row.reference
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "User_ID,UTC_Time,Account,Operation,Coin,Change,Remark\n\
+            123456789,2024-03-07 10:22:33,Spot,Fiat Deposit,EUR,500.00000000,SEPA\n\
+            123456789,2024-03-08 09:00:00,Spot,Transaction Spend,EUR,-100.00000000,\n\
+            123456789,2024-03-08 09:00:00,Spot,Transaction Buy,BTC,0.00160000,\n\
+            123456789,2024-03-08 09:00:00,Spot,Transaction Fee,EUR,-0.10000000,\n\
+            123456789,2024-03-08 15:00:00,Spot,Transaction Revenue,EUR,20.00000000,\n\
+            123456789,2024-03-09 12:00:00,Spot,Binance Card Spending,EUR,-12.50000000,\n";
+
+        let records: Vec<Record> = BinanceIter::new(input.as_bytes(), None)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].amount, Money::from_str("500,00", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::DebitCard);
+
+        let records: Vec<Record> = BinanceIter::new(input.as_bytes(), Some("Crypto"))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].memo, "Trading");
+        assert_eq!(records[1].category, "Crypto");
+        assert_eq!(records[1].amount, Money::from_str("-80,10", EUR).unwrap());
+    }
+}
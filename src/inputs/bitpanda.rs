@@ -0,0 +1,203 @@
+//! The transaction history export of Bitpanda, comma separated with decimal
+//! points below a preamble.
+//!
+//! Fiat deposits and withdrawals are read, crypto moving in or out of the
+//! account is left out. Buys and sells are left out too unless asked for
+//! with `--include-trades`, then with the fiat amount they cost or brought
+//! and the crypto amount in the memo, in the category given by
+//! `--trade-category`.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{self, convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Transaction ID,Timestamp,Transaction Type,In/Out";
+
+#[derive(Debug, Deserialize)]
+struct BitpandaIR {
+    #[serde(rename = "Transaction ID")]
+    id: String,
+    #[serde(rename = "Timestamp")]
+    timestamp: String,
+    #[serde(rename = "Transaction Type")]
+    kind: String,
+    #[serde(rename = "In/Out")]
+    direction: String,
+    #[serde(rename = "Amount Fiat")]
+    amount_fiat: String,
+    #[serde(rename = "Fiat")]
+    fiat: String,
+    #[serde(rename = "Amount Asset", default)]
+    amount_asset: String,
+    #[serde(rename = "Asset", default)]
+    asset: String,
+    #[serde(rename = "Asset class", default)]
+    asset_class: String,
+    #[serde(skip)]
+    trade_category: String,
+}
+
+impl BitpandaIR {
+    fn is_trade(&self) -> bool {
+        matches!(self.kind.as_str(), "buy" | "sell")
+    }
+
+    /// Whether fiat money moves in or out of the account.
+    fn is_fiat_transfer(&self) -> bool {
+        matches!(self.kind.as_str(), "deposit" | "withdrawal")
+            && self.asset_class.starts_with("Fiat")
+    }
+}
+
+impl TryFrom<BitpandaIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: BitpandaIR) -> Result<Self> {
+        let currency =
+            iso::find(&value.fiat).ok_or_else(|| miette!("Unknown currency '{}'", value.fiat))?;
+        let amount = decimal(&value.amount_fiat, '.')?;
+        // Amounts are written positive, the direction of trades is the one
+        // of the asset
+        let amount = match (value.kind.as_str(), value.direction.as_str()) {
+            ("sell", _) => amount,
+            ("buy", _) | (_, "outgoing") => -amount,
+            _ => amount,
+        };
+        let (payment, memo) = match value.is_trade() {
+            true => (
+                Payment::ElectronicPayment,
+                format!("{} {} {}", value.kind, value.amount_asset, value.asset),
+            ),
+            false => (Payment::BankTransfer, value.kind),
+        };
+        let date = value.timestamp.get(..10).unwrap_or(&value.timestamp);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            info: value.id,
+            payee: "Bitpanda".to_string(),
+            memo,
+            amount: Money::from_decimal(amount, currency),
+            category: value.trade_category,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct BitpandaIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl BitpandaIter {
+    /// Reads the export, leaving out trades unless `trades` is the category
+    /// to give them.
+    pub fn new<R: Read>(rdr: R, trades: Option<&str>) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, BitpandaIR>(text.as_bytes(), b',', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if !ir.is_trade() && !ir.is_fiat_transfer()));
+                util::trades(&mut rows, trades, BitpandaIR::is_trade, |ir| {
+                    &mut ir.trade_category
+                });
+                convert(rows, "bitpanda")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for BitpandaIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` as fiat deposits and withdrawals, with the
+/// preamble of the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Disclaimer: All data is without guarantee, errors and changes are reserved.\"\n\
+        \"Name:\",\"Max Mustermann\"\n\
+        \"Address:\",\"Musterstraße 1, 1010 Wien\"\n\
+        \"Transaction ID\",\"Timestamp\",\"Transaction Type\",\"In/Out\",\"Amount Fiat\",\"Fiat\",\
+        \"Amount Asset\",\"Asset\",\"Asset market price\",\"Asset market price currency\",\
+        \"Asset class\",\"Product ID\",\"Fee\",\"Fee asset\",\"Spread\",\"Spread Currency\",\
+        \"Tax Fiat\"\n",
+    );
+    for row in rows {
+        let (kind, direction) = match row.amount.is_sign_negative() {
+            true => ("withdrawal", "outgoing"),
+            false => ("deposit", "incoming"),
+        };
+        let amount = row.amount.abs();
+        out.push_str(&format!(
+            "{},{}T10:00:00+01:00,{},{},{},EUR,{},EUR,-,-,Fiat,-,0.00,EUR,-,-,0.00\n",
+            row.reference,
+            row.date.format("%Y-%m-%d"),
+            kind,
+            direction,
+            amount,
+            amount
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Disclaimer: All data is without guarantee, errors and changes are reserved.\"\n\
+            \"Transaction ID\",\"Timestamp\",\"Transaction Type\",\"In/Out\",\"Amount Fiat\",\"Fiat\",\"Amount Asset\",\"Asset\",\"Asset market price\",\"Asset market price currency\",\"Asset class\",\"Product ID\",\"Fee\",\"Fee asset\",\"Spread\",\"Spread Currency\",\"Tax Fiat\"\n\
+            D1,2024-03-07T10:22:33+01:00,deposit,incoming,500.00,EUR,500.00,EUR,-,-,Fiat,-,0.00,EUR,-,-,0.00\n\
+            T1,2024-03-08T09:00:00+01:00,buy,outgoing,150.00,EUR,0.0025,BTC,60000.00,EUR,Cryptocurrency,1,-,-,1.20,EUR,0.00\n\
+            R1,2024-03-09T09:00:00+01:00,reward,incoming,0.50,EUR,0.00001,BTC,60000.00,EUR,Cryptocurrency,1,-,-,-,-,0.00\n\
+            W1,2024-03-10T09:00:00+01:00,withdrawal,outgoing,100.00,EUR,100.00,EUR,-,-,Fiat,-,0.00,EUR,-,-,0.00\n";
+
+        let records: Vec<Record> = BitpandaIter::new(input.as_bytes(), None)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].amount, Money::from_str("500,00", EUR).unwrap());
+        assert_eq!(records[1].amount, Money::from_str("-100,00", EUR).unwrap());
+
+        let records: Vec<Record> = BitpandaIter::new(input.as_bytes(), Some("Crypto"))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].memo, "buy 0.0025 BTC");
+        assert_eq!(records[1].category, "Crypto");
+        assert_eq!(records[1].amount, Money::from_str("-150,00", EUR).unwrap());
+    }
+}
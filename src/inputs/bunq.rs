@@ -0,0 +1,163 @@
+//! The csv export of bunq, with ISO dates and, depending on the settings of
+//! the export, `;` and decimal commas or `,` and decimal points.
+//!
+//! Each row names the sub-account it was booked on, which becomes a tag so
+//! the sub-accounts can be filtered on in HomeBank.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Date";
+
+#[derive(Debug, Deserialize)]
+struct BunqIR {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Account")]
+    account: String,
+    #[serde(rename = "Counterparty", default)]
+    counterparty: String,
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+}
+
+impl TryFrom<BunqIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: BunqIR) -> Result<Self> {
+        // Tags are separated by spaces
+        let tags = match value.account.is_empty() {
+            true => Vec::new(),
+            false => vec![value
+                .account
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("-")
+                .to_lowercase()],
+        };
+
+        let point = match value.amount.contains(',') {
+            true => ',',
+            false => '.',
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: Payment::None,
+            payee: value.name,
+            memo: value.description,
+            amount: Money::from_decimal(decimal(&value.amount, point)?, EUR),
+            tags,
+            iban: value.counterparty,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct BunqIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl BunqIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let delimiter = match text.lines().next().unwrap_or_default().contains(';') {
+                    true => b';',
+                    false => b',',
+                };
+                convert(
+                    csv_rows::<_, BunqIR>(text.as_bytes(), delimiter, HEADER),
+                    "bunq",
+                )
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for BunqIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, with `;` and decimal commas.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Date\";\"Interest Date\";\"Amount\";\"Account\";\"Counterparty\";\"Name\";\"Description\"\n",
+    );
+    for row in rows {
+        let date = row.date.format("%Y-%m-%d");
+        out.push_str(&format!(
+            "\"{}\";\"{}\";\"{}\";\"Main\";\"{}\";\"{}\";\"{}\"\n",
+            date,
+            date,
+            row.amount_de(),
+            row.iban,
+            row.payee,
+            row.purpose
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Date\";\"Interest Date\";\"Amount\";\"Account\";\"Counterparty\";\"Name\";\"Description\"\n\
+            \"2024-03-07\";\"2024-03-07\";\"-25,88\";\"Main\";\"NL91ABNA0417164300\";\"Albert Heijn\";\"Boodschappen\"\n\
+            \"2024-03-08\";\"2024-03-08\";\"50,00\";\"Holiday Fund\";\"\";\"Max Mustermann\";\"Savings\"\n";
+
+        let records: Vec<Record> = BunqIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Albert Heijn");
+        assert_eq!(records[0].iban, "NL91ABNA0417164300");
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[0].tags, vec!["main"]);
+        assert_eq!(records[1].tags, vec!["holiday-fund"]);
+
+        let points = "Date,Interest Date,Amount,Account,Counterparty,Name,Description\n\
+            2024-03-07,2024-03-07,-1234.50,Main,,Landlord,Rent\n";
+        let records: Vec<Record> = BunqIter::new(points.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records[0].amount, Money::from_str("-1234,50", EUR).unwrap());
+    }
+}
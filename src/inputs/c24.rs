@@ -0,0 +1,153 @@
+//! The csv export of the C24 Smart account, UTF-8 and comma separated.
+//!
+//! Every row names the pocket, the sub-account, it was booked on. Pockets
+//! other than the main account become a tag, as in `urlaub-2024`, so a
+//! single account in HomeBank can still tell them apart.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::Record,
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Transaktionstyp,Buchungsdatum,Zahlungsempfänger";
+
+/// The pocket of the account itself, which needs no tag.
+const MAIN_POCKET: &str = "Hauptkonto";
+
+#[derive(Debug, Deserialize)]
+struct C24IR {
+    #[serde(rename = "Transaktionstyp")]
+    transaktionstyp: String,
+    #[serde(rename = "Buchungsdatum")]
+    buchungsdatum: String,
+    #[serde(rename = "Zahlungsempfänger")]
+    zahlungsempfänger: String,
+    #[serde(rename = "IBAN", default)]
+    iban: String,
+    #[serde(rename = "Verwendungszweck", default)]
+    verwendungszweck: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Pocket", default)]
+    pocket: String,
+}
+
+impl TryFrom<C24IR> for Record {
+    type Error = Report;
+
+    fn try_from(value: C24IR) -> Result<Self> {
+        // Tags are separated by spaces
+        let tags = match value.pocket.as_str() {
+            "" | MAIN_POCKET => Vec::new(),
+            pocket => vec![pocket
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("-")
+                .to_lowercase()],
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungsdatum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungsdatum into datetime")?,
+            payment: booking_payment(&value.transaktionstyp),
+            payee: value.zahlungsempfänger,
+            memo: value.verwendungszweck,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, EUR),
+            tags,
+            iban: value.iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct C24Iter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl C24Iter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(csv_rows::<_, C24IR>(text.as_bytes(), b',', HEADER), "c24"),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for C24Iter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, the standing orders saving into a pocket.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Transaktionstyp\",\"Buchungsdatum\",\"Zahlungsempfänger\",\"IBAN\",\"BIC\",\
+        \"Verwendungszweck\",\"Betrag\",\"Pocket\"\n",
+    );
+    for row in rows {
+        let pocket = match row.kind {
+            Kind::StandingOrder => "Urlaub 2024",
+            _ => MAIN_POCKET,
+        };
+        out.push_str(&format!(
+            "\"{}\",\"{}\",\"{}\",\"{}\",\"\",\"{}\",\"{}\",\"{}\"\n",
+            row.kind.booking_text(),
+            row.date.format("%d.%m.%Y"),
+            row.payee,
+            row.iban,
+            row.purpose,
+            row.amount_de(),
+            pocket
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Transaktionstyp\",\"Buchungsdatum\",\"Zahlungsempfänger\",\"IBAN\",\"BIC\",\"Verwendungszweck\",\"Betrag\",\"Pocket\"\n\
+            \"Lastschrift\",\"07.03.2024\",\"Stadtwerke\",\"DE02120300000000202051\",\"BYLADEM1001\",\"Strom März\",\"-1.025,88\",\"Hauptkonto\"\n\
+            \"Überweisung\",\"06.03.2024\",\"Reisebüro\",\"DE02120300000000202051\",\"\",\"Anzahlung\",\"-200,00\",\"Urlaub 2024\"\n";
+
+        let records: Vec<Record> = C24Iter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert!(records[0].tags.is_empty());
+        assert_eq!(records[1].tags, vec!["urlaub-2024".to_string()]);
+    }
+}
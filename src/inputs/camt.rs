@@ -0,0 +1,370 @@
+//! ISO 20022 camt.053 bank to customer statements.
+//!
+//! Every booked `<Ntry>` becomes a record. Batch bookings holding several
+//! `<TxDtls>` take the other party and references from the first of them.
+//! Pending entries are left out, they show up again once booked.
+
+use std::io::{BufReader, Read};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use quick_xml::{events::Event, Reader};
+use rusty_money::{
+    iso::{self, Currency},
+    Money,
+};
+use tracing::trace;
+
+use super::{sepa, sepa::Purpose, util::decimal, RecordIteratorRes};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+#[derive(Debug)]
+struct Camt {
+    buchungstag: NaiveDate,
+    _valuta: Option<NaiveDate>,
+    betrag: Money<'static, Currency>,
+    payment: Payment,
+    end_to_end: Option<String>,
+    name: String,
+    iban: String,
+    verwendungszweck: String,
+    buchungstext: String,
+}
+
+#[derive(Debug, Default)]
+struct CamtIR {
+    amount: String,
+    currency: String,
+    indicator: String,
+    status: String,
+    booking_date: String,
+    value_date: String,
+    booking_text: String,
+    bank_code: String,
+    transactions: usize,
+    end_to_end: String,
+    debtor: String,
+    debtor_iban: String,
+    creditor: String,
+    creditor_iban: String,
+    remittance: Vec<String>,
+}
+
+impl CamtIR {
+    /// Stores the text at `path` below `<Ntry>`.
+    fn set(&mut self, path: &[String], text: String) {
+        let path = path.join("/");
+        if let Some(tx) = path.strip_prefix("NtryDtls/TxDtls/") {
+            if self.transactions > 1 {
+                return;
+            }
+            match tx {
+                "Refs/EndToEndId" => self.end_to_end = text,
+                "RltdPties/Dbtr/Nm" | "RltdPties/Dbtr/Pty/Nm" => self.debtor = text,
+                "RltdPties/DbtrAcct/Id/IBAN" => self.debtor_iban = text,
+                "RltdPties/Cdtr/Nm" | "RltdPties/Cdtr/Pty/Nm" => self.creditor = text,
+                "RltdPties/CdtrAcct/Id/IBAN" => self.creditor_iban = text,
+                "RmtInf/Ustrd" => self.remittance.push(text),
+                _ => {}
+            }
+            return;
+        }
+
+        match path.as_str() {
+            "Amt" => self.amount = text,
+            "CdtDbtInd" => self.indicator = text,
+            "Sts" | "Sts/Cd" => self.status = text,
+            "BookgDt/Dt" | "BookgDt/DtTm" => self.booking_date = text,
+            "ValDt/Dt" | "ValDt/DtTm" => self.value_date = text,
+            "AddtlNtryInf" => self.booking_text = text,
+            "BkTxCd/Prtry/Cd" => self.bank_code = text,
+            _ => {}
+        }
+    }
+}
+
+fn date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d").into_diagnostic()
+}
+
+impl TryFrom<CamtIR> for Camt {
+    type Error = Report;
+
+    fn try_from(value: CamtIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        let mut betrag = decimal(&value.amount, '.')?;
+        let debit = value.indicator == "DBIT";
+        if debit {
+            betrag = -betrag;
+        }
+
+        // Proprietary codes look like `NDDT+105+...`, the GVC second
+        let gvc = value.bank_code.split('+').nth(1).unwrap_or_default();
+        let (name, iban) = match debit {
+            true => (value.creditor, value.creditor_iban),
+            false => (value.debtor, value.debtor_iban),
+        };
+        let end_to_end = Some(value.end_to_end).filter(|r| !r.is_empty() && r != "NOTPROVIDED");
+
+        Ok(Self {
+            buchungstag: date(&value.booking_date)
+                .wrap_err("Failed converting booking date into datetime")?,
+            _valuta: match value.value_date.is_empty() {
+                true => None,
+                false => Some(
+                    date(&value.value_date)
+                        .wrap_err("Failed converting value date into datetime")?,
+                ),
+            },
+            betrag: Money::from_decimal(betrag, currency),
+            payment: sepa::payment(gvc),
+            end_to_end,
+            name,
+            iban,
+            verwendungszweck: value.remittance.join(" "),
+            buchungstext: value.booking_text,
+        })
+    }
+}
+
+impl From<Camt> for Record {
+    fn from(val: Camt) -> Self {
+        let purpose = Purpose::parse(&val.verwendungszweck);
+        let memo = match purpose.text.is_empty() {
+            true => val.buchungstext,
+            false => purpose.text,
+        };
+
+        Self {
+            date: val.buchungstag,
+            payment: val.payment,
+            info: val.end_to_end.or(purpose.end_to_end).unwrap_or_default(),
+            payee: val.name,
+            memo,
+            amount: val.betrag,
+            iban: val.iban,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct CamtIter<R: Read> {
+    reader: Reader<BufReader<R>>,
+    buf: Vec<u8>,
+    /// Elements opened below `<Ntry>`
+    path: Vec<String>,
+    entry: Option<CamtIR>,
+}
+
+impl<R: Read> CamtIter<R> {
+    pub fn new(rdr: R) -> Self {
+        let mut reader = Reader::from_reader(BufReader::new(rdr));
+        reader.config_mut().trim_text(true);
+
+        Self {
+            reader,
+            buf: Vec::new(),
+            path: Vec::new(),
+            entry: None,
+        }
+    }
+
+    /// Reads up to the end of the next entry.
+    fn entry(&mut self) -> Result<Option<CamtIR>> {
+        loop {
+            self.buf.clear();
+            let event = self
+                .reader
+                .read_event_into(&mut self.buf)
+                .into_diagnostic()
+                .wrap_err("Failed reading camt xml")?;
+
+            match event {
+                Event::Eof => return Ok(None),
+                Event::Start(e) => {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                    match self.entry.as_mut() {
+                        None if name == "Ntry" => self.entry = Some(CamtIR::default()),
+                        None => {}
+                        Some(entry) => {
+                            if name == "Amt" && self.path.is_empty() {
+                                if let Some(ccy) = e.try_get_attribute("Ccy").into_diagnostic()? {
+                                    entry.currency =
+                                        ccy.unescape_value().into_diagnostic()?.into_owned();
+                                }
+                            }
+                            if name == "TxDtls" {
+                                entry.transactions += 1;
+                            }
+                            self.path.push(name);
+                        }
+                    }
+                }
+                Event::End(_) if self.entry.is_some() => match self.path.pop() {
+                    Some(_) => {}
+                    // Closing the entry itself
+                    None => return Ok(self.entry.take()),
+                },
+                Event::Text(e) => {
+                    if let Some(entry) = self.entry.as_mut() {
+                        let text = e.unescape().into_diagnostic()?.into_owned();
+                        entry.set(&self.path, text);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for CamtIter<R> {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ir = match self.entry() {
+                Ok(ir) => ir?,
+                Err(e) => return Some(Err(e)),
+            };
+            trace!(?ir, "Read camt entry");
+            if ir.status == "PDNG" {
+                continue;
+            }
+
+            return Some(
+                Camt::try_from(ir)
+                    .map(Record::from)
+                    .wrap_err("Failed converting camt entry"),
+            );
+        }
+    }
+}
+
+/// A sample statement of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+"#,
+    );
+    for row in rows {
+        let debit = row.amount.is_sign_negative();
+        let (code, indicator, party) = match (debit, row.kind) {
+            (_, Kind::DirectDebit) => ("NDDT", "DBIT", "Cdtr"),
+            (true, _) => ("NTRF", "DBIT", "Cdtr"),
+            (false, _) => ("NTRF", "CRDT", "Dbtr"),
+        };
+        let date = row.date.format("%Y-%m-%d");
+        out.push_str(&format!(
+            r#"      <Ntry>
+        <Amt Ccy="EUR">{amount}</Amt>
+        <CdtDbtInd>{indicator}</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>{date}</Dt></BookgDt>
+        <ValDt><Dt>{date}</Dt></ValDt>
+        <BkTxCd><Prtry><Cd>{code}+{gvc}</Cd><Issr>ZKA</Issr></Prtry></BkTxCd>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><EndToEndId>{reference}</EndToEndId></Refs>
+            <RltdPties>
+              <{party}><Nm>{payee}</Nm></{party}>
+              <{party}Acct><Id><IBAN>{iban}</IBAN></Id></{party}Acct>
+            </RltdPties>
+            <RmtInf><Ustrd>{purpose}</Ustrd></RmtInf>
+          </TxDtls>
+        </NtryDtls>
+        <AddtlNtryInf>{text}</AddtlNtryInf>
+      </Ntry>
+"#,
+            amount = row.amount.abs(),
+            gvc = row.kind.gvc(),
+            reference = row.reference,
+            payee = row.payee,
+            iban = row.iban,
+            purpose = row.purpose,
+            text = row.kind.booking_text(),
+        ));
+    }
+    out.push_str("    </Stmt>\n  </BkToCstmrStmt>\n</Document>\n");
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE12500105170648489890</IBAN></Id></Acct>
+      <Ntry>
+        <Amt Ccy="EUR">25.88</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2024-03-07</Dt></BookgDt>
+        <ValDt><Dt>2024-03-08</Dt></ValDt>
+        <BkTxCd><Prtry><Cd>NDDT+105+9310</Cd><Issr>ZKA</Issr></Prtry></BkTxCd>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><EndToEndId>4711</EndToEndId></Refs>
+            <RltdPties>
+              <Cdtr><Nm>Stadtwerke &amp; Co</Nm></Cdtr>
+              <CdtrAcct><Id><IBAN>DE02120300000000202051</IBAN></Id></CdtrAcct>
+            </RltdPties>
+            <RmtInf><Ustrd>Strom Maerz</Ustrd></RmtInf>
+          </TxDtls>
+        </NtryDtls>
+        <AddtlNtryInf>SEPA-BASISLASTSCHRIFT</AddtlNtryInf>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">100.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>PDNG</Sts>
+        <BookgDt><Dt>2024-03-09</Dt></BookgDt>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">100.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2024-03-10</Dt></BookgDt>
+        <AddtlNtryInf>BARGELDEINZAHLUNG</AddtlNtryInf>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>
+"#;
+
+        let records: Vec<Record> = CamtIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].payee, "Stadtwerke & Co");
+        assert_eq!(records[0].iban, "DE02120300000000202051");
+        assert_eq!(records[0].memo, "Strom Maerz");
+        assert_eq!(records[0].info, "4711");
+
+        assert_eq!(records[1].amount, Money::from_str("100", EUR).unwrap());
+        assert_eq!(records[1].memo, "BARGELDEINZAHLUNG");
+        assert_eq!(records[1].payee, "");
+    }
+}
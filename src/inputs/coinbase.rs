@@ -0,0 +1,231 @@
+//! The transaction history export of Coinbase, comma separated with
+//! decimal points and amounts written with their currency symbol.
+//!
+//! Only the rows moving fiat money are read: buys, sells, deposits and
+//! withdrawals. Buys and sells note the crypto amount in the memo and take
+//! the fiat subtotal as amount. Fees become a record of their own.
+//! Sends, receives, conversions and rewards stay in crypto and are left
+//! out.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+/// Newer exports start with the transaction id, older ones without it.
+const HEADERS: [&str; 2] = [
+    "ID,Timestamp,Transaction Type",
+    "Timestamp,Transaction Type,Asset",
+];
+/// The type given to the record of the fee of a row.
+const FEE: &str = "Fee";
+
+#[derive(Debug, Clone, Deserialize)]
+struct CoinbaseIR {
+    #[serde(rename = "ID", default)]
+    id: String,
+    #[serde(rename = "Timestamp")]
+    timestamp: String,
+    #[serde(rename = "Transaction Type")]
+    kind: String,
+    #[serde(rename = "Asset")]
+    asset: String,
+    #[serde(rename = "Quantity Transacted")]
+    quantity: String,
+    #[serde(rename = "Price Currency", alias = "Spot Price Currency")]
+    currency: String,
+    #[serde(rename = "Subtotal", default)]
+    subtotal: String,
+    #[serde(rename = "Fees and/or Spread", alias = "Fees", default)]
+    fees: String,
+    #[serde(rename = "Notes", default)]
+    notes: String,
+}
+
+impl CoinbaseIR {
+    /// The sign of the fiat amount of the row, none for those staying in
+    /// crypto.
+    fn sign(&self) -> Option<Decimal> {
+        match self.kind.as_str() {
+            "Buy" | "Advanced Trade Buy" | "Withdrawal" | FEE => Some(Decimal::NEGATIVE_ONE),
+            "Sell" | "Advanced Trade Sell" | "Deposit" => Some(Decimal::ONE),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<CoinbaseIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: CoinbaseIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        let sign = value.sign().unwrap_or_default();
+        let (amount, payment, memo) = match value.kind.as_str() {
+            FEE => (
+                decimal(&value.fees, '.')?,
+                Payment::FinancialInstitutionFee,
+                "Coinbase fee".to_string(),
+            ),
+            "Deposit" | "Withdrawal" => {
+                // Fiat movements may leave the subtotal empty
+                let amount = match decimal(&value.subtotal, '.')? {
+                    amount if amount.is_zero() => decimal(&value.quantity, '.')?,
+                    amount => amount,
+                };
+                (amount, Payment::BankTransfer, value.notes)
+            }
+            kind => (
+                decimal(&value.subtotal, '.')?,
+                Payment::ElectronicPayment,
+                format!(
+                    "{} {} {}",
+                    kind,
+                    value.quantity.trim_start_matches('-'),
+                    value.asset
+                ),
+            ),
+        };
+        let date = value.timestamp.get(..10).unwrap_or(&value.timestamp);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            info: value.id,
+            payee: "Coinbase".to_string(),
+            memo,
+            amount: Money::from_decimal(sign * amount.abs(), currency),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct CoinbaseIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl CoinbaseIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let plain = text.replace('"', "");
+                let header = HEADERS
+                    .into_iter()
+                    .find(|h| plain.contains(h))
+                    .unwrap_or(HEADERS[0]);
+                let mut rows = Vec::new();
+                for ir in csv_rows::<_, CoinbaseIR>(text.as_bytes(), b',', header) {
+                    match ir {
+                        Ok(ir) if ir.sign().is_none() => {}
+                        Ok(ir) => {
+                            let fee = match decimal(&ir.fees, '.') {
+                                Ok(fee) if !fee.is_zero() => Some(CoinbaseIR {
+                                    kind: FEE.to_string(),
+                                    ..ir.clone()
+                                }),
+                                _ => None,
+                            };
+                            rows.push(Ok(ir));
+                            rows.extend(fee.map(Ok));
+                        }
+                        Err(e) => rows.push(Err(e)),
+                    }
+                }
+                convert(rows, "coinbase")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for CoinbaseIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` as deposits and withdrawals, with the preamble
+/// of the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Transactions\n\
+        User,Max Mustermann,abc-123\n\
+        ID,Timestamp,Transaction Type,Asset,Quantity Transacted,Price Currency,\
+        Price at Transaction,Subtotal,Total (inclusive of fees and/or spread),\
+        Fees and/or Spread,Notes\n",
+    );
+    for row in rows {
+        let kind = match row.amount.is_sign_negative() {
+            true => "Withdrawal",
+            false => "Deposit",
+        };
+        let amount = row.amount.abs();
+        out.push_str(&format!(
+            "{},{} 10:00:00 UTC,{},EUR,{},EUR,€1.00,€{},€{},€0.00,{}\n",
+            row.reference,
+            row.date.format("%Y-%m-%d"),
+            kind,
+            amount,
+            amount,
+            amount,
+            row.payee
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Transactions\n\
+            User,Max Mustermann,abc-123\n\
+            ID,Timestamp,Transaction Type,Asset,Quantity Transacted,Price Currency,Price at Transaction,Subtotal,Total (inclusive of fees and/or spread),Fees and/or Spread,Notes\n\
+            cb-1,2024-03-07 10:22:33 UTC,Deposit,EUR,1000,EUR,€1.00,\"€1,000.00\",\"€1,000.00\",€0.00,Deposit from Sparkasse\n\
+            cb-2,2024-03-08 09:00:00 UTC,Buy,BTC,0.0025,EUR,\"€60,000.00\",€150.00,€152.24,€2.24,Bought 0.0025 BTC for €152.24 EUR\n\
+            cb-3,2024-03-09 09:00:00 UTC,Send,BTC,-0.001,EUR,\"€61,000.00\",€61.00,€61.00,€0.00,Sent 0.001 BTC\n\
+            cb-4,2024-03-10 09:00:00 UTC,Withdrawal,EUR,-100,EUR,€1.00,-€100.00,-€100.00,€0.00,Withdrawal to Sparkasse\n";
+
+        let records: Vec<Record> = CoinbaseIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].amount, Money::from_str("1000,00", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::ElectronicPayment);
+        assert_eq!(records[1].memo, "Buy 0.0025 BTC");
+        assert_eq!(records[1].amount, Money::from_str("-150,00", EUR).unwrap());
+        assert_eq!(records[2].payment, Payment::FinancialInstitutionFee);
+        assert_eq!(records[2].amount, Money::from_str("-2,24", EUR).unwrap());
+        assert_eq!(records[3].amount, Money::from_str("-100,00", EUR).unwrap());
+    }
+}
@@ -0,0 +1,244 @@
+//! Girokonto and Verrechnungskonto exports of the comdirect, in
+//! Windows-1252.
+//!
+//! The first account of the export is read, the sections of further
+//! accounts and the old balance below it are left out like pending rows.
+//! The `Buchungstext` combines the other party, its account and the
+//! purpose behind labels, as in `Auftraggeber: Erika Buchungstext: Rent
+//! Ref. 4711`.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Buchungstag;Wertstellung (Valuta);Vorgang";
+
+/// Labels inside a booking text.
+const LABELS: &[&str] = &[
+    "Auftraggeber:",
+    "Empfänger:",
+    "Kto/IBAN:",
+    "BLZ/BIC:",
+    "Buchungstext:",
+    "Ref.",
+];
+
+#[derive(Debug, Deserialize)]
+struct ComdirectIR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Vorgang")]
+    vorgang: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Umsatz in EUR")]
+    umsatz: String,
+}
+
+/// The value behind each label of a booking text, text before the first
+/// label under an empty one.
+fn labelled(text: &str) -> Vec<(&str, &str)> {
+    let mut starts: Vec<(usize, &str)> = LABELS
+        .iter()
+        .flat_map(|l| text.match_indices(l).collect::<Vec<_>>())
+        .collect();
+    starts.sort();
+
+    let mut values = vec![("", &text[..starts.first().map_or(text.len(), |s| s.0)])];
+    for (idx, (start, label)) in starts.iter().enumerate() {
+        let end = starts.get(idx + 1).map_or(text.len(), |(end, _)| *end);
+        values.push((*label, &text[start + label.len()..end]));
+    }
+    values
+        .into_iter()
+        .map(|(l, v)| (l, v.trim()))
+        .filter(|(_, v)| !v.is_empty())
+        .collect()
+}
+
+impl TryFrom<ComdirectIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: ComdirectIR) -> Result<Self> {
+        let date = NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%Y")
+            .into_diagnostic()
+            .wrap_err("Failed converting buchungstag into datetime")?;
+        let payment = match value.vorgang.as_str() {
+            "Visa-Umsatz" => Payment::CreditCard,
+            vorgang => booking_payment(vorgang),
+        };
+
+        let mut record = Self {
+            date,
+            payment,
+            amount: Money::from_decimal(decimal_de(&value.umsatz)?, EUR),
+            ..Default::default()
+        };
+        for (label, text) in labelled(&value.buchungstext) {
+            match label {
+                "Auftraggeber:" | "Empfänger:" => record.payee = text.to_string(),
+                "Kto/IBAN:" => record.iban = text.to_string(),
+                "Ref." => record.info = text.to_string(),
+                "BLZ/BIC:" => {}
+                _ => record.memo = text.to_string(),
+            }
+        }
+        // Card payments only name the merchant, before its location
+        if record.payee.is_empty() {
+            if let Some((merchant, _)) = record.memo.split_once("//") {
+                record.payee = merchant.trim().to_string();
+            }
+        }
+        Ok(record)
+    }
+}
+
+pub struct ComdirectIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl ComdirectIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows =
+                    csv_rows::<_, ComdirectIR>(first_section(&text).as_bytes(), b';', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.buchungstag == "offen"));
+                convert(rows, "comdirect")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+/// The lines of an export up to the end of the first account.
+fn first_section(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines
+        .iter()
+        .position(|l| l.starts_with("\"Buchungstag\""))
+        .unwrap_or(0);
+    lines
+        .iter()
+        .enumerate()
+        .take_while(|(idx, l)| {
+            *idx <= start
+                || !(l.trim_matches(['"', ';', ' ']).is_empty()
+                    || l.starts_with("\"Alter Kontostand"))
+        })
+        .map(|(_, l)| format!("{}\n", l))
+        .collect()
+}
+
+impl Iterator for ComdirectIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        ";\n\"Umsätze Girokonto\";\"Zeitraum: 30 Tage\";\n\
+        \"Neuer Kontostand\";\"1.000,00 EUR\";\n\n\
+        \"Buchungstag\";\"Wertstellung (Valuta)\";\"Vorgang\";\"Buchungstext\";\"Umsatz in EUR\";\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let party = match row.amount.is_sign_negative() {
+            true => "Empfänger",
+            false => "Auftraggeber",
+        };
+        let (vorgang, text) = match row.kind {
+            Kind::Card => (
+                "Kartenverfügung",
+                format!(
+                    "Buchungstext: {}//Musterstadt/DE Ref. {}",
+                    row.payee.to_uppercase(),
+                    row.reference
+                ),
+            ),
+            Kind::Cash => (
+                "Auszahlung GAA",
+                format!("Buchungstext: {} Ref. {}", row.purpose, row.reference),
+            ),
+            kind => (
+                match kind {
+                    Kind::DirectDebit => "Lastschrift / Belastung",
+                    Kind::StandingOrder => "Dauerauftrag",
+                    Kind::Salary => "Lohn, Gehalt, Rente",
+                    _ => "Übertrag / Überweisung",
+                },
+                format!(
+                    "{}: {} Kto/IBAN: {} BLZ/BIC: COBADEFFXXX Buchungstext: {} Ref. {}",
+                    party, row.payee, row.iban, row.purpose, row.reference
+                ),
+            ),
+        };
+        out.push_str(&format!(
+            "\"{}\";\"{}\";\"{}\";\"{}\";\"{}\";\n",
+            date,
+            date,
+            vorgang,
+            text,
+            row.amount_de()
+        ));
+    }
+    out.push_str("\n\"Alter Kontostand\";\"1.000,00 EUR\";\n");
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = ";\n\"Umsätze Girokonto\";\"Zeitraum: 30 Tage\";\n\"Neuer Kontostand\";\"1.234,56 EUR\";\n\n\
+            \"Buchungstag\";\"Wertstellung (Valuta)\";\"Vorgang\";\"Buchungstext\";\"Umsatz in EUR\";\n\
+            \"offen\";\"--\";\"Kartenverfügung\";\"Buchungstext: WOOPSIE//Berlin/DE\";\"-5,00\";\n\
+            \"07.03.2024\";\"07.03.2024\";\"Lastschrift / Belastung\";\"Empfänger: Stadtwerke Kto/IBAN: DE02120300000000202051 BLZ/BIC: BYLADEM1001 Buchungstext: Strom März Ref. 4711\";\"-1.025,88\";\n\
+            \"06.03.2024\";\"06.03.2024\";\"Kartenverfügung\";\"Buchungstext: REWE SAGT DANKE//Musterstadt/DE Ref. 0815\";\"-12,34\";\n\n\
+            \"Alter Kontostand\";\"2.272,78 EUR\";\n\n\
+            \"Umsätze Visa-Karte (Kreditkarte)\";\"Zeitraum: 30 Tage\";\n";
+
+        let records: Vec<Record> = ComdirectIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].iban, "DE02120300000000202051");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payee, "REWE SAGT DANKE");
+        assert_eq!(records[1].payment, Payment::DebitCard);
+    }
+}
@@ -0,0 +1,217 @@
+//! Transaction exports of the Commerzbank.
+//!
+//! Payee, purpose and references all come in one `Buchungstext`, as in
+//! `Stadtwerke  Strom End-to-End-Ref.: 4711 Mandatsref: M-1`. The labelled
+//! references are cut off, the end-to-end one becomes the info. Card
+//! payments name the merchant before a `//`, other payees end at the first
+//! double space. Without either, all of it is taken as the payee.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Buchungstag;Wertstellung;Umsatzart";
+
+/// Labels of the references following the purpose in a booking text.
+const LABELS: &[&str] = &[
+    "End-to-End-Ref.:",
+    "Kundenreferenz:",
+    "Mandatsref:",
+    "Gläubiger-ID:",
+    "SEPA-",
+];
+
+#[derive(Debug, Deserialize)]
+struct CommerzbankIR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Umsatzart")]
+    umsatzart: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+    #[serde(rename = "IBAN Auftraggeberkonto", default)]
+    iban: String,
+}
+
+/// Payee, memo and end-to-end reference told by a booking text.
+fn split(text: &str) -> (String, String, String) {
+    let end = LABELS
+        .iter()
+        .filter_map(|label| text.find(label))
+        .min()
+        .unwrap_or(text.len());
+    let reference = text
+        .split_once("End-to-End-Ref.:")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .filter(|r| !matches!(*r, "nicht" | "NOTPROVIDED"))
+        .unwrap_or_default();
+
+    let head = text[..end].trim();
+    let (payee, memo) = head
+        .split_once("//")
+        .or_else(|| head.split_once("  "))
+        .unwrap_or((head, ""));
+    (
+        payee.trim().to_string(),
+        memo.trim().to_string(),
+        reference.to_string(),
+    )
+}
+
+impl TryFrom<CommerzbankIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: CommerzbankIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        // Card payments come in as direct debits, only the merchant tells
+        let payment = match value.buchungstext.contains("//") {
+            true => Payment::DebitCard,
+            false => booking_payment(&value.umsatzart),
+        };
+        let (payee, memo, info) = split(&value.buchungstext);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungstag into datetime")?,
+            payment,
+            info,
+            payee,
+            memo,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, currency),
+            iban: value.iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct CommerzbankIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl CommerzbankIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, CommerzbankIR>(text.as_bytes(), b';', HEADER),
+                "commerzbank",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for CommerzbankIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\u{feff}Buchungstag;Wertstellung;Umsatzart;Buchungstext;Betrag;Währung;\
+        Auftraggeberkonto;Bankleitzahl Auftraggeberkonto;IBAN Auftraggeberkonto;Kategorie\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let (kind, text) = match row.kind {
+            Kind::Card => (
+                "Lastschrift",
+                format!(
+                    "{}//Musterstadt/DE {}T12:00:00 Karte Nr. 1",
+                    row.payee.to_uppercase(),
+                    row.date
+                ),
+            ),
+            Kind::DirectDebit => (
+                "Lastschrift",
+                format!(
+                    "{}  {} End-to-End-Ref.: {} SEPA-BASISLASTSCHRIFT wiederholend",
+                    row.payee, row.purpose, row.reference
+                ),
+            ),
+            Kind::Cash => ("Barauszahlung", format!("{}  {}", row.payee, row.purpose)),
+            Kind::StandingOrder => ("Dauerauftrag", format!("{}  {}", row.payee, row.purpose)),
+            Kind::Transfer | Kind::Salary => (
+                match row.amount.is_sign_negative() {
+                    true => "Überweisung",
+                    false => "Gutschrift",
+                },
+                format!(
+                    "{}  {} End-to-End-Ref.: {}",
+                    row.payee, row.purpose, row.reference
+                ),
+            ),
+        };
+        out.push_str(&format!(
+            "{};{};{};{};{};EUR;1234567;30040000;{};\n",
+            date,
+            date,
+            kind,
+            text,
+            row.amount_de(),
+            row.iban
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\u{feff}Buchungstag;Wertstellung;Umsatzart;Buchungstext;Betrag;Währung;Auftraggeberkonto;Bankleitzahl Auftraggeberkonto;IBAN Auftraggeberkonto;Kategorie\n\
+            07.03.2024;07.03.2024;Lastschrift;Stadtwerke Musterstadt  Strom März End-to-End-Ref.: 4711 Mandatsref: M-1 Gläubiger-ID: DE98ZZZ09999999999 SEPA-BASISLASTSCHRIFT wiederholend;-1.025,88;EUR;1234567;30040000;DE12300400000123456700;Wohnen\n\
+            06.03.2024;06.03.2024;Lastschrift;REWE SAGT DANKE 12345//Musterstadt/DE 2024-03-06T12:00:00 Karte Nr. 1;-12,34;EUR;1234567;30040000;DE12300400000123456700;\n\
+            05.03.2024;05.03.2024;Gutschrift;Erika Mustermann Geschenk End-to-End-Ref.: nicht angegeben;20,00;EUR;1234567;30040000;DE12300400000123456700;\n";
+
+        let records: Vec<Record> = CommerzbankIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke Musterstadt");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payee, "REWE SAGT DANKE 12345");
+        assert_eq!(records[1].payment, Payment::DebitCard);
+        assert_eq!(records[2].payee, "Erika Mustermann Geschenk");
+        assert_eq!(records[2].info, "");
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+    }
+}
@@ -0,0 +1,71 @@
+//! Compressed inputs, as in archived exports like `statements-2023.csv.gz`.
+//!
+//! The compression is told by the magic bytes, not the extension, so
+//! renamed files work just as well.
+
+use std::io::Read;
+
+use flate2::read::MultiGzDecoder;
+use miette::{Context, IntoDiagnostic, Result};
+
+const GZIP: &[u8] = &[0x1f, 0x8b];
+const XZ: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Extensions of compressed files, left out when looking at the extension
+/// of the contained file.
+const EXTENSIONS: &[&str] = &[".gz", ".xz"];
+
+/// The decompressed content, unchanged if it is not compressed.
+pub fn decompress(content: Vec<u8>) -> Result<Vec<u8>> {
+    if content.starts_with(GZIP) {
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(content.as_slice())
+            .read_to_end(&mut decompressed)
+            .into_diagnostic()
+            .wrap_err("Failed decompressing gzip input")?;
+        Ok(decompressed)
+    } else if content.starts_with(XZ) {
+        let mut decompressed = Vec::new();
+        lzma_rs::xz_decompress(&mut content.as_slice(), &mut decompressed)
+            .into_diagnostic()
+            .wrap_err("Failed decompressing xz input")?;
+        Ok(decompressed)
+    } else {
+        Ok(content)
+    }
+}
+
+/// The name of the contained file, as in `export.xlsx` for `export.xlsx.gz`.
+pub fn inner_name(name: &str) -> &str {
+    EXTENSIONS
+        .iter()
+        .find_map(|e| name.strip_suffix(e))
+        .unwrap_or(name)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_decompress() {
+        let content = b"Buchungstag;Wert;Umsatzart\n".to_vec();
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&content).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(decompress(gzip).unwrap(), content);
+
+        let mut xz = Vec::new();
+        lzma_rs::xz_compress(&mut content.as_slice(), &mut xz).unwrap();
+        assert_eq!(decompress(xz).unwrap(), content);
+
+        assert_eq!(decompress(content.clone()).unwrap(), content);
+        assert_eq!(inner_name("export.xlsx.gz"), "export.xlsx");
+    }
+}
@@ -0,0 +1,145 @@
+//! The csv export of the 1822direkt, the direct bank of the Frankfurter
+//! Sparkasse, in Windows-1252.
+//!
+//! Other than the CSV-CAMT export of the Sparkassen, the own account comes
+//! first as `Kontonummer`, the other party right after the dates and the
+//! booking text only before the purpose.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::{booking_payment, Purpose},
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+const HEADER: &str = "Kontonummer;Buchungstag;Wertstellung";
+
+#[derive(Debug, Deserialize)]
+struct Direkt1822IR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Auftraggeber/Empfänger")]
+    auftraggeber: String,
+    #[serde(rename = "IBAN")]
+    iban: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+}
+
+impl TryFrom<Direkt1822IR> for Record {
+    type Error = Report;
+
+    fn try_from(value: Direkt1822IR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        let purpose = Purpose::parse(&value.verwendungszweck);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungstag into datetime")?,
+            payment: booking_payment(&value.buchungstext),
+            info: purpose.end_to_end.unwrap_or_default(),
+            payee: value.auftraggeber,
+            memo: purpose.text,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, currency),
+            iban: value.iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct Direkt1822Iter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl Direkt1822Iter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, Direkt1822IR>(text.as_bytes(), b';', HEADER),
+                "1822direkt",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for Direkt1822Iter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Kontonummer\";\"Buchungstag\";\"Wertstellung\";\"Auftraggeber/Empfänger\";\"IBAN\";\
+        \"BIC\";\"Buchungstext\";\"Verwendungszweck\";\"Betrag\";\"Währung\"\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        out.push_str(&format!(
+            "\"1234567890\";\"{}\";\"{}\";\"{}\";\"{}\";\"\";\"{}\";\"EREF+{} SVWZ+{}\";\"{}\";\"EUR\"\n",
+            date,
+            date,
+            row.payee,
+            row.iban,
+            row.kind.booking_text(),
+            row.reference,
+            row.purpose,
+            row.amount_de()
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Kontonummer\";\"Buchungstag\";\"Wertstellung\";\"Auftraggeber/Empfänger\";\"IBAN\";\"BIC\";\"Buchungstext\";\"Verwendungszweck\";\"Betrag\";\"Währung\"\n\
+            \"1234567890\";\"07.03.2024\";\"07.03.2024\";\"Stadtwerke\";\"DE02120300000000202051\";\"BYLADEM1001\";\"Lastschrift\";\"EREF+4711 SVWZ+Strom März\";\"-1.025,88\";\"EUR\"\n";
+
+        let records: Vec<Record> = Direkt1822Iter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].iban, "DE02120300000000202051");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+    }
+}
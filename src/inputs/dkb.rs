@@ -0,0 +1,244 @@
+//! Girokonto exports of the Deutsche Kreditbank (DKB).
+//!
+//! Both layouts are read: the one of the banking introduced in 2023 with
+//! `Buchungsdatum`, `Status` and `Umsatztyp` columns, and the legacy one
+//! with `Buchungstag` and `Buchungstext`. Either starts with a preamble of
+//! account number, period and balance. Pending transactions are left out.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Buchungsdatum;Wertstellung;Status";
+const LEGACY_HEADER: &str = "Buchungstag;Wertstellung;Buchungstext";
+
+#[derive(Debug, Deserialize)]
+struct DkbIR {
+    #[serde(rename = "Buchungsdatum")]
+    buchungsdatum: String,
+    #[serde(rename = "Status")]
+    status: String,
+    #[serde(rename = "Zahlungspflichtige*r")]
+    zahlungspflichtiger: String,
+    #[serde(rename = "Zahlungsempfänger*in")]
+    zahlungsempfänger: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "IBAN")]
+    iban: String,
+    #[serde(rename = "Betrag (€)")]
+    betrag: String,
+    #[serde(rename = "Kundenreferenz", default)]
+    kundenreferenz: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LegacyIR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Auftraggeber / Begünstigter")]
+    auftraggeber: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "Kontonummer")]
+    kontonummer: String,
+    #[serde(rename = "Betrag (EUR)")]
+    betrag: String,
+    #[serde(rename = "Kundenreferenz", default)]
+    kundenreferenz: String,
+}
+
+fn record(
+    date: NaiveDate,
+    payment: Payment,
+    payee: String,
+    memo: String,
+    iban: String,
+    amount: &str,
+    reference: String,
+) -> Result<Record> {
+    Ok(Record {
+        date,
+        payment,
+        info: reference,
+        payee,
+        memo,
+        amount: Money::from_decimal(decimal_de(amount)?, EUR),
+        iban: iban.replace(' ', ""),
+        ..Default::default()
+    })
+}
+
+impl TryFrom<DkbIR> for Record {
+    type Error = miette::Report;
+
+    fn try_from(value: DkbIR) -> Result<Self> {
+        let date = NaiveDate::parse_from_str(&value.buchungsdatum, "%d.%m.%y")
+            .into_diagnostic()
+            .wrap_err("Failed converting buchungsdatum into datetime")?;
+        // Both parties are named, the other one depends on the direction
+        let payee = match value.betrag.trim_start().starts_with('-') {
+            true => value.zahlungsempfänger,
+            false => value.zahlungspflichtiger,
+        };
+        record(
+            date,
+            Payment::None,
+            payee,
+            value.verwendungszweck,
+            value.iban,
+            &value.betrag,
+            value.kundenreferenz,
+        )
+    }
+}
+
+impl TryFrom<LegacyIR> for Record {
+    type Error = miette::Report;
+
+    fn try_from(value: LegacyIR) -> Result<Self> {
+        let date = NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%Y")
+            .into_diagnostic()
+            .wrap_err("Failed converting buchungstag into datetime")?;
+        record(
+            date,
+            booking_payment(&value.buchungstext),
+            value.auftraggeber,
+            value.verwendungszweck,
+            value.kontonummer,
+            &value.betrag,
+            value.kundenreferenz,
+        )
+    }
+}
+
+pub struct DkbIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl DkbIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let text = match decode(rdr) {
+            Ok(text) => text,
+            Err(e) => return Self::from(vec![Err(e)]),
+        };
+
+        let records = match text.contains("Buchungsdatum") {
+            true => {
+                let mut rows = csv_rows::<_, DkbIR>(text.as_bytes(), b';', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.status == "Vorgemerkt"));
+                convert(rows, "dkb")
+            }
+            false => convert(
+                csv_rows::<_, LegacyIR>(text.as_bytes(), b';', LEGACY_HEADER),
+                "dkb",
+            ),
+        };
+        Self::from(records)
+    }
+}
+
+impl From<Vec<Result<Record>>> for DkbIter {
+    fn from(records: Vec<Result<Record>>) -> Self {
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for DkbIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in the layout of the current banking.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\u{feff}\"Girokonto\";\"DE89 3704 0044 0532 0130 00\"\n\"\"\n\
+        \"Kontostand vom 31.12.2023:\";\"1.000,00 €\"\n\"\"\n\
+        \"Buchungsdatum\";\"Wertstellung\";\"Status\";\"Zahlungspflichtige*r\";\
+        \"Zahlungsempfänger*in\";\"Verwendungszweck\";\"Umsatztyp\";\"IBAN\";\"Betrag (€)\";\
+        \"Gläubiger-ID\";\"Mandatsreferenz\";\"Kundenreferenz\"\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%y");
+        let (payer, payee, kind) = match row.amount.is_sign_negative() {
+            true => ("Max Mustermann", row.payee.as_str(), "Ausgang"),
+            false => (row.payee.as_str(), "Max Mustermann", "Eingang"),
+        };
+        out.push_str(&format!(
+            "\"{}\";\"{}\";\"Gebucht\";\"{}\";\"{}\";\"{}\";\"{}\";\"{}\";\"{}\";\"\";\"\";\"{}\"\n",
+            date,
+            date,
+            payer,
+            payee,
+            row.purpose,
+            kind,
+            row.iban,
+            row.amount_de(),
+            row.reference
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\u{feff}\"Girokonto\";\"DE12 1203 0000 1234 5678 90\"\n\"\"\n\
+            \"Kontostand vom 08.03.2024:\";\"1.234,56 €\"\n\"\"\n\
+            \"Buchungsdatum\";\"Wertstellung\";\"Status\";\"Zahlungspflichtige*r\";\"Zahlungsempfänger*in\";\"Verwendungszweck\";\"Umsatztyp\";\"IBAN\";\"Betrag (€)\";\"Gläubiger-ID\";\"Mandatsreferenz\";\"Kundenreferenz\"\n\
+            \"08.03.24\";\"08.03.24\";\"Vorgemerkt\";\"Max Mustermann\";\"Woopsie\";\"Pending\";\"Ausgang\";\"DE02120300000000202051\";\"-5\";\"\";\"\";\"\"\n\
+            \"07.03.24\";\"07.03.24\";\"Gebucht\";\"Max Mustermann\";\"Woopsie\";\"Doopsie\";\"Ausgang\";\"DE02 1203 0000 0000 2020 51\";\"-1.025,88 €\";\"\";\"\";\"4711\"\n";
+
+        let records: Vec<Record> = DkbIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Woopsie");
+        assert_eq!(records[0].iban, "DE02120300000000202051");
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[0].info, "4711");
+
+        let legacy = "\"Kontonummer:\";\"DE12120300001234567890 / Girokonto\";\n\n\
+            \"Von:\";\"01.03.2022\";\n\"Bis:\";\"31.03.2022\";\n\
+            \"Kontostand vom 31.03.2022:\";\"1.234,56 EUR\";\n\n\
+            \"Buchungstag\";\"Wertstellung\";\"Buchungstext\";\"Auftraggeber / Begünstigter\";\"Verwendungszweck\";\"Kontonummer\";\"BLZ\";\"Betrag (EUR)\";\"Gläubiger-ID\";\"Mandatsreferenz\";\"Kundenreferenz\";\n\
+            \"07.03.2022\";\"07.03.2022\";\"Lastschrift\";\"Stadtwerke\";\"Strom\";\"DE02120300000000202051\";\"BYLADEM1001\";\"-25,88\";\"\";\"\";\"\";\n";
+        let records: Vec<Record> = DkbIter::new(WINDOWS_1252.encode(legacy).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+    }
+}
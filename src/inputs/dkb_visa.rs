@@ -0,0 +1,201 @@
+//! Visa credit card exports of the Deutsche Kreditbank (DKB), in the
+//! layout of the current banking and the legacy one.
+//!
+//! The merchant becomes the payee. Purchases in foreign currencies keep
+//! their original amount in the memo, the amount is the one billed in euro.
+//! Payments onto the card and refunds come in positive. Pending
+//! transactions are left out.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Belegdatum;Wertstellung;Status";
+const LEGACY_HEADER: &str = "Umsatz abgerechnet";
+
+#[derive(Debug, Deserialize)]
+struct DkbVisaIR {
+    #[serde(rename = "Belegdatum")]
+    belegdatum: String,
+    #[serde(rename = "Status")]
+    status: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Betrag (€)")]
+    betrag: String,
+    #[serde(rename = "Fremdwährungsbetrag", default)]
+    fremdwährungsbetrag: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LegacyIR {
+    #[serde(rename = "Umsatz abgerechnet und nicht im Saldo enthalten")]
+    abgerechnet: String,
+    #[serde(rename = "Belegdatum")]
+    belegdatum: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Betrag (EUR)")]
+    betrag: String,
+    #[serde(rename = "Ursprünglicher Betrag", default)]
+    ursprünglicher_betrag: String,
+}
+
+fn record(date: NaiveDate, merchant: String, amount: &str, original: String) -> Result<Record> {
+    // Only foreign currencies tell something the amount does not
+    let memo = match original.is_empty() || original.ends_with("EUR") || original.ends_with('€') {
+        true => String::new(),
+        false => original,
+    };
+
+    Ok(Record {
+        date,
+        payment: Payment::CreditCard,
+        payee: merchant,
+        memo,
+        amount: Money::from_decimal(decimal_de(amount)?, EUR),
+        ..Default::default()
+    })
+}
+
+impl TryFrom<DkbVisaIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: DkbVisaIR) -> Result<Self> {
+        let date = NaiveDate::parse_from_str(&value.belegdatum, "%d.%m.%y")
+            .into_diagnostic()
+            .wrap_err("Failed converting belegdatum into datetime")?;
+        record(
+            date,
+            value.beschreibung,
+            &value.betrag,
+            value.fremdwährungsbetrag,
+        )
+    }
+}
+
+impl TryFrom<LegacyIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: LegacyIR) -> Result<Self> {
+        let date = NaiveDate::parse_from_str(&value.belegdatum, "%d.%m.%Y")
+            .into_diagnostic()
+            .wrap_err("Failed converting belegdatum into datetime")?;
+        record(
+            date,
+            value.beschreibung,
+            &value.betrag,
+            value.ursprünglicher_betrag,
+        )
+    }
+}
+
+pub struct DkbVisaIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl DkbVisaIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) if text.contains("Umsatz abgerechnet") => {
+                let mut rows = csv_rows::<_, LegacyIR>(text.as_bytes(), b';', LEGACY_HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.abgerechnet == "Nein"));
+                convert(rows, "dkb visa")
+            }
+            Ok(text) => {
+                let mut rows = csv_rows::<_, DkbVisaIR>(text.as_bytes(), b';', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.status == "Vorgemerkt"));
+                convert(rows, "dkb visa")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for DkbVisaIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in the layout of the current banking.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\u{feff}\"Karte\";\"Visa Kreditkarte\";\"4930 **** **** 1234\"\n\"\"\n\
+        \"Saldo vom 31.12.2023:\";\"-123,45 €\"\n\"\"\n\
+        \"Belegdatum\";\"Wertstellung\";\"Status\";\"Beschreibung\";\"Umsatztyp\";\
+        \"Betrag (€)\";\"Fremdwährungsbetrag\"\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%y");
+        out.push_str(&format!(
+            "\"{}\";\"{}\";\"Gebucht\";\"{}\";\"Im Geschäft\";\"{} €\";\"\"\n",
+            date,
+            date,
+            row.payee.to_uppercase(),
+            row.amount_de()
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\u{feff}\"Karte\";\"Visa Kreditkarte\";\"4930 **** **** 1234\"\n\"\"\n\
+            \"Saldo vom 08.03.2024:\";\"-123,45 €\"\n\"\"\n\
+            \"Belegdatum\";\"Wertstellung\";\"Status\";\"Beschreibung\";\"Umsatztyp\";\"Betrag (€)\";\"Fremdwährungsbetrag\"\n\
+            \"08.03.24\";\"09.03.24\";\"Vorgemerkt\";\"WOOPSIE\";\"Im Geschäft\";\"-5,00 €\";\"\"\n\
+            \"07.03.24\";\"08.03.24\";\"Gebucht\";\"APPLE.COM/BILL\";\"Online\";\"-9,31 €\";\"-10,00 USD\"\n\
+            \"06.03.24\";\"07.03.24\";\"Gebucht\";\"Ausgleich Kreditkarte\";\"Gutschrift\";\"100,00 €\";\"\"\n";
+
+        let records: Vec<Record> = DkbVisaIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "APPLE.COM/BILL");
+        assert_eq!(records[0].memo, "-10,00 USD");
+        assert_eq!(records[0].amount, Money::from_str("-9,31", EUR).unwrap());
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[1].amount, Money::from_str("100", EUR).unwrap());
+
+        let legacy = "\"Kreditkarte:\";\"4998********1234 Kreditkarte\";\n\n\
+            \"Von:\";\"01.03.2022\";\n\"Bis:\";\"31.03.2022\";\n\"Saldo:\";\"-123,45 EUR\";\n\n\
+            \"Umsatz abgerechnet und nicht im Saldo enthalten\";\"Wertstellung\";\"Belegdatum\";\"Beschreibung\";\"Betrag (EUR)\";\"Ursprünglicher Betrag\";\n\
+            \"Nein\";\"09.03.2022\";\"08.03.2022\";\"WOOPSIE\";\"-5,00\";\"\";\n\
+            \"Ja\";\"08.03.2022\";\"07.03.2022\";\"AMAZON.DE\";\"-25,88\";\"\";\n";
+        let records: Vec<Record> = DkbVisaIter::new(legacy.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].payee, "AMAZON.DE");
+        assert_eq!(records[0].memo, "");
+    }
+}
@@ -0,0 +1,213 @@
+//! The csv export of ELBA, the internet banking of Raiffeisen in Austria.
+//!
+//! The export has no header and a fixed set of columns: booking date,
+//! booking text, value date, amount, currency and a timestamp. It is `;`
+//! separated in Windows-1252 with decimal commas. The booking text combines
+//! the booking type with labelled fields like `Zahlungsreferenz:` or
+//! `IBAN Empfänger:`, which are split up into payee, IBAN, reference and
+//! purpose.
+
+use std::{collections::HashMap, io::Read, sync::LazyLock, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use regex::Regex;
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+/// The columns of the export, which it does not name itself.
+const HEADER: &str = "Buchungsdatum;Buchungstext;Valutadatum;Betrag;Währung;Zeitstempel";
+
+/// A row as the export writes them: two dates around the booking text,
+/// then amount and currency.
+static ROW: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^"?\d{2}\.\d{2}\.\d{4}"?;.*;"?\d{2}\.\d{2}\.\d{4}"?;"?[+-]?[\d.]*,\d{2}"?;"?[A-Z]{3}"?;"#)
+        .unwrap()
+});
+
+/// The labels of the fields in the booking text, longer ones first where
+/// they end alike.
+static FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?:IBAN|BIC) (?:Auftraggeber|Empfänger):|Auftraggeber:|Empfänger:|",
+        r"Zahlungsreferenz:|Verwendungszweck:|Mandat:|Creditor-ID:"
+    ))
+    .unwrap()
+});
+
+/// Whether the start of an input looks like an ELBA export.
+pub fn is_elba(head: &str) -> bool {
+    head.lines().next().is_some_and(|l| ROW.is_match(l))
+}
+
+#[derive(Debug, Deserialize)]
+struct ElbaIR {
+    #[serde(rename = "Buchungsdatum")]
+    buchungsdatum: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+}
+
+/// The booking type in front of the first label and the labelled fields.
+fn fields(text: &str) -> (String, HashMap<String, String>) {
+    let mut labels = FIELD.find_iter(text).peekable();
+    let lead = match labels.peek() {
+        Some(first) => &text[..first.start()],
+        None => text,
+    };
+    let mut fields = HashMap::new();
+    while let Some(label) = labels.next() {
+        let end = labels.peek().map_or(text.len(), |next| next.start());
+        let value = text[label.end()..end]
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        fields.insert(
+            label.as_str().trim_end_matches(':').to_string(),
+            value.join(" "),
+        );
+    }
+    (
+        lead.split_whitespace().collect::<Vec<_>>().join(" "),
+        fields,
+    )
+}
+
+impl TryFrom<ElbaIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: ElbaIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        let (lead, mut fields) = fields(&value.buchungstext);
+        let mut take = |labels: &[&str]| {
+            labels
+                .iter()
+                .find_map(|l| fields.remove(*l))
+                .unwrap_or_default()
+        };
+        let payee = take(&["Empfänger", "Auftraggeber"]);
+        let iban = take(&["IBAN Empfänger", "IBAN Auftraggeber"]);
+        let reference = take(&["Zahlungsreferenz"]);
+        let memo = [lead.clone(), take(&["Verwendungszweck"])]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungsdatum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: booking_payment(&lead),
+            info: reference,
+            payee,
+            memo,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, currency),
+            iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct ElbaIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl ElbaIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let text = format!("{}\n{}", HEADER, text);
+                convert(csv_rows::<_, ElbaIR>(text.as_bytes(), b';', HEADER), "elba")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for ElbaIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::new();
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let party = match row.amount.is_sign_negative() {
+            true => "Empfänger",
+            false => "Auftraggeber",
+        };
+        out.push_str(&format!(
+            "{};{} Zahlungsreferenz: {} Verwendungszweck: {} IBAN {}: {} {}: {};{};{};EUR;{} 10:00:00:000\n",
+            date,
+            row.kind.booking_text(),
+            row.reference,
+            row.purpose,
+            party,
+            row.iban,
+            party,
+            row.payee,
+            date,
+            row.amount_de(),
+            date
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "07.03.2024;Bezahlung Karte MC/000001234 BILLA DANKT 1140 WIEN;07.03.2024;-25,88;EUR;07.03.2024 18:30:12:000\n\
+            08.03.2024;Überweisung Zahlungsreferenz: DA-1 Verwendungszweck: Miete März IBAN Empfänger: AT611904300234573201 BIC Empfänger: BKAUATWW Empfänger: Wiener Wohnen;08.03.2024;-650,00;EUR;08.03.2024 06:00:00:000\n";
+        assert!(is_elba(input));
+
+        let records: Vec<Record> = ElbaIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(
+            records[0].memo,
+            "Bezahlung Karte MC/000001234 BILLA DANKT 1140 WIEN"
+        );
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+        assert_eq!(records[1].payee, "Wiener Wohnen");
+        assert_eq!(records[1].iban, "AT611904300234573201");
+        assert_eq!(records[1].info, "DA-1");
+        assert_eq!(records[1].memo, "Überweisung Miete März");
+    }
+}
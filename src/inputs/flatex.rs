@@ -0,0 +1,290 @@
+//! The cash account statements of flatex, `;` separated in Windows-1252,
+//! and of Degiro, the comma separated `Account.csv`.
+//!
+//! Both name the currency in a column beside the amount with an empty
+//! header, which gets a name before reading. Deposits, withdrawals,
+//! dividends, interest, fees and taxes are read. Securities trades, and at
+//! Degiro the currency exchanges paying for them, are left out unless asked
+//! for with `--include-trades`, in the category given by `--trade-category`.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{self, convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const FLATEX_HEADER: &str = "Buchtag;Valuta;BIC / BLZ";
+const DEGIRO_HEADER: &str = "Datum,Uhrzeit,Valutadatum";
+
+#[derive(Debug, Deserialize)]
+struct FlatexIR {
+    #[serde(rename = "Buchtag")]
+    buchtag: String,
+    #[serde(rename = "IBAN / Kontonummer", default)]
+    iban: String,
+    #[serde(rename = "Buchungsinformationen")]
+    buchungsinformationen: String,
+    #[serde(rename = "TA-Nr.", default)]
+    ta_nr: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+    #[serde(skip)]
+    trade_category: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DegiroIR {
+    #[serde(rename = "Datum")]
+    datum: String,
+    #[serde(rename = "Produkt", default)]
+    produkt: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Änderung")]
+    währung: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Order-ID", default)]
+    order_id: String,
+    #[serde(skip)]
+    trade_category: String,
+}
+
+/// Whether the booking text is one of a securities trade.
+fn is_trade(text: &str) -> bool {
+    text.starts_with("Währungswechsel")
+        || text
+            .split_whitespace()
+            .any(|w| matches!(w, "Kauf" | "Verkauf"))
+}
+
+/// The payment method of a booking text.
+fn payment(text: &str) -> Payment {
+    let text = text.to_lowercase();
+    if ["steuer", "gebühr", "entgelt"]
+        .iter()
+        .any(|w| text.contains(w))
+    {
+        Payment::FinancialInstitutionFee
+    } else if ["dividende", "ausschüttung", "zinsen"]
+        .iter()
+        .any(|w| text.contains(w))
+    {
+        Payment::Deposit
+    } else if ["einzahlung", "auszahlung", "überweisung"]
+        .iter()
+        .any(|w| text.contains(w))
+    {
+        Payment::BankTransfer
+    } else {
+        Payment::None
+    }
+}
+
+struct Booking<'a> {
+    date: &'a str,
+    format: &'a str,
+    text: String,
+    payee: String,
+    info: String,
+    amount: &'a str,
+    currency: &'a str,
+    category: String,
+    iban: String,
+}
+
+fn record(booking: Booking) -> Result<Record> {
+    let currency = iso::find(booking.currency)
+        .ok_or_else(|| miette!("Unknown currency '{}'", booking.currency))?;
+    Ok(Record {
+        date: NaiveDate::parse_from_str(booking.date, booking.format)
+            .into_diagnostic()
+            .wrap_err("Failed converting date into datetime")?,
+        payment: payment(&booking.text),
+        info: booking.info,
+        payee: booking.payee,
+        memo: booking.text,
+        amount: Money::from_decimal(decimal_de(booking.amount)?, currency),
+        category: booking.category,
+        iban: booking.iban,
+        ..Default::default()
+    })
+}
+
+impl TryFrom<FlatexIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: FlatexIR) -> Result<Self> {
+        record(Booking {
+            date: &value.buchtag,
+            format: "%d.%m.%Y",
+            payee: "flatex".to_string(),
+            text: value.buchungsinformationen,
+            info: value.ta_nr,
+            amount: &value.betrag,
+            currency: &value.währung,
+            category: value.trade_category,
+            iban: value.iban,
+        })
+    }
+}
+
+impl TryFrom<DegiroIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: DegiroIR) -> Result<Self> {
+        let payee = match value.produkt.is_empty() {
+            true => "Degiro".to_string(),
+            false => value.produkt,
+        };
+        record(Booking {
+            date: &value.datum,
+            format: "%d-%m-%Y",
+            payee,
+            text: value.beschreibung,
+            info: value.order_id,
+            amount: &value.betrag,
+            currency: &value.währung,
+            category: value.trade_category,
+            iban: String::new(),
+        })
+    }
+}
+
+pub struct FlatexIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl FlatexIter {
+    /// Reads the statement, leaving out securities trades unless `trades`
+    /// is the category to give them.
+    pub fn new<R: Read>(rdr: R, trades: Option<&str>) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) if text.contains(DEGIRO_HEADER) => {
+                let text = text.replacen("Änderung,,", "Änderung,Betrag,", 1).replacen(
+                    "Saldo,,",
+                    "Saldo,Saldobetrag,",
+                    1,
+                );
+                let mut rows = csv_rows::<_, DegiroIR>(text.as_bytes(), b',', DEGIRO_HEADER);
+                util::trades(
+                    &mut rows,
+                    trades,
+                    |ir| is_trade(&ir.beschreibung),
+                    |ir| &mut ir.trade_category,
+                );
+                convert(rows, "degiro")
+            }
+            Ok(text) => {
+                let text = text.replacen("Betrag;;", "Betrag;Währung;", 1);
+                let mut rows = csv_rows::<_, FlatexIR>(text.as_bytes(), b';', FLATEX_HEADER);
+                util::trades(
+                    &mut rows,
+                    trades,
+                    |ir| is_trade(&ir.buchungsinformationen),
+                    |ir| &mut ir.trade_category,
+                );
+                convert(rows, "flatex")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for FlatexIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample statement of `rows` in the layout of flatex, in Windows-1252
+/// like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Buchtag;Valuta;BIC / BLZ;IBAN / Kontonummer;Buchungsinformationen;TA-Nr.;Betrag;;\
+        Auftraggeberkonto;Konto-IBAN;\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let text = match row.amount.is_sign_negative() {
+            true => "Auszahlung",
+            false => "Einzahlung",
+        };
+        out.push_str(&format!(
+            "{};{};;{};{} {};{};{};EUR;1234567890;DE12345678901234567890;\n",
+            date,
+            date,
+            row.iban,
+            text,
+            row.payee,
+            row.reference,
+            row.amount_de()
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let flatex = "Buchtag;Valuta;BIC / BLZ;IBAN / Kontonummer;Buchungsinformationen;TA-Nr.;Betrag;;Auftraggeberkonto;Konto-IBAN;\n\
+            07.03.2024;07.03.2024;;DE02120300000000202051;Einzahlung Max Mustermann;1001;500,00;EUR;1234567890;DE12;\n\
+            08.03.2024;08.03.2024;;;WP-Abrechnung Kauf Apple;1002;-171,90;EUR;1234567890;DE12;\n\
+            15.03.2024;15.03.2024;;;Dividende Apple;1003;1,20;EUR;1234567890;DE12;\n\
+            31.03.2024;31.03.2024;;;Depotgebühr;1004;-2,50;EUR;1234567890;DE12;\n";
+        let records: Vec<Record> = FlatexIter::new(WINDOWS_1252.encode(flatex).0.as_ref(), None)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].info, "1001");
+        assert_eq!(records[0].amount, Money::from_str("500,00", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::Deposit);
+        assert_eq!(records[2].payment, Payment::FinancialInstitutionFee);
+
+        let degiro = "Datum,Uhrzeit,Valutadatum,Produkt,ISIN,Beschreibung,FX,Änderung,,Saldo,,Order-ID\n\
+            07-03-2024,10:00,07-03-2024,,,Einzahlung,,EUR,\"500,00\",EUR,\"500,00\",\n\
+            08-03-2024,09:00,08-03-2024,APPLE INC,US0378331005,Kauf 1 Apple@170 USD,,USD,\"-170,00\",USD,\"-170,00\",abc-1\n\
+            08-03-2024,09:00,08-03-2024,,,Währungswechsel (Ausbuchung),\"1,08\",EUR,\"-157,41\",EUR,\"342,59\",\n\
+            15-03-2024,09:00,15-03-2024,APPLE INC,US0378331005,Dividendensteuer,,USD,\"-0,18\",USD,\"-0,18\",\n";
+        let records: Vec<Record> = FlatexIter::new(degiro.as_bytes(), Some("Investment"))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].payee, "Degiro");
+        assert_eq!(records[1].payee, "APPLE INC");
+        assert_eq!(records[1].category, "Investment");
+        assert_eq!(records[2].category, "Investment");
+        assert_eq!(records[3].payment, Payment::FinancialInstitutionFee);
+        assert_eq!(records[3].category, "");
+    }
+}
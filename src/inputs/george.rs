@@ -0,0 +1,168 @@
+//! The csv export of George, the online banking of Erste Bank and the
+//! Austrian Sparkassen.
+//!
+//! Unlike the German Sparkasse exports the columns are named like the
+//! fields of the George JSON export, `Partnername` and `Referenz` among
+//! them, and dates are ISO. The delimiter follows the settings of the
+//! export, amounts use decimal commas or points accordingly.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+const HEADER: &str = "Buchungsdatum";
+
+#[derive(Debug, Deserialize)]
+struct GeorgeIR {
+    #[serde(rename = "Buchungsdatum")]
+    buchungsdatum: String,
+    #[serde(rename = "Partnername", default)]
+    partnername: String,
+    #[serde(rename = "Partner IBAN", default)]
+    partner_iban: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung", default)]
+    währung: String,
+    #[serde(rename = "Buchungs-Info", default)]
+    buchungs_info: String,
+    #[serde(rename = "Referenz", alias = "Zahlungsreferenz", default)]
+    referenz: String,
+    #[serde(rename = "Notiz", default)]
+    notiz: String,
+}
+
+impl TryFrom<GeorgeIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: GeorgeIR) -> Result<Self> {
+        let currency = match value.währung.is_empty() {
+            true => iso::EUR,
+            false => iso::find(&value.währung)
+                .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?,
+        };
+        let memo = [value.buchungs_info.as_str(), value.notiz.as_str()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let point = match value.betrag.contains(',') {
+            true => ',',
+            false => '.',
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungsdatum, "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(&value.buchungsdatum, "%d.%m.%Y"))
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: booking_payment(&value.buchungs_info),
+            info: value.referenz,
+            payee: value.partnername,
+            memo,
+            amount: Money::from_decimal(decimal(&value.betrag, point)?, currency),
+            iban: value.partner_iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct GeorgeIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl GeorgeIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let delimiter = match text.lines().next().unwrap_or_default().contains(';') {
+                    true => b';',
+                    false => b',',
+                };
+                convert(
+                    csv_rows::<_, GeorgeIR>(text.as_bytes(), delimiter, HEADER),
+                    "george",
+                )
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for GeorgeIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, with `;` and decimal commas.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Buchungsdatum\";\"Partnername\";\"Partner IBAN\";\"BIC/SWIFT\";\"Betrag\";\
+        \"Währung\";\"Buchungs-Info\";\"Referenz\";\"Notiz\";\"Valutadatum\"\n",
+    );
+    for row in rows {
+        let date = row.date.format("%Y-%m-%d");
+        out.push_str(&format!(
+            "\"{}\";\"{}\";\"{}\";\"\";\"{}\";\"EUR\";\"{} {}\";\"{}\";\"\";\"{}\"\n",
+            date,
+            row.payee,
+            row.iban,
+            row.amount_de(),
+            row.kind.booking_text(),
+            row.purpose,
+            row.reference,
+            date
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Buchungsdatum\";\"Partnername\";\"Partner IBAN\";\"BIC/SWIFT\";\"Betrag\";\"Währung\";\"Buchungs-Info\";\"Referenz\";\"Notiz\";\"Valutadatum\"\n\
+            \"2024-03-07\";\"BILLA DANKT\";\"\";\"\";\"-25,88\";\"EUR\";\"Bezahlung Karte MC/000001234\";\"POS 1234\";\"\";\"2024-03-07\"\n\
+            \"2024-03-08\";\"Wiener Wohnen\";\"AT611904300234573201\";\"BKAUATWW\";\"-650,00\";\"EUR\";\"Dauerauftrag Miete\";\"DA-1\";\"Miete März\";\"2024-03-08\"\n";
+
+        let records: Vec<Record> = GeorgeIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "BILLA DANKT");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].info, "POS 1234");
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::StandingOrder);
+        assert_eq!(records[1].iban, "AT611904300234573201");
+        assert_eq!(records[1].memo, "Dauerauftrag Miete Miete März");
+    }
+}
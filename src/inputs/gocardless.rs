@@ -0,0 +1,247 @@
+//! Transactions of the GoCardless Bank Account Data API, as saved by
+//! `hbconv fetch --provider gocardless`.
+//!
+//! Only booked transactions are read, pending ones change until booked.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{
+    iso::{self, Currency},
+    Money,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::trace;
+
+use super::{util::decimal, RecordIteratorRes};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+#[derive(Debug)]
+struct Gocardless {
+    buchungstag: NaiveDate,
+    betrag: Money<'static, Currency>,
+    referenz: String,
+    name: String,
+    iban: String,
+    verwendungszweck: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    transactions: Transactions,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transactions {
+    booked: Vec<GocardlessIR>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Account {
+    iban: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Amount {
+    amount: String,
+    currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GocardlessIR {
+    transaction_id: Option<String>,
+    booking_date: Option<String>,
+    value_date: Option<String>,
+    transaction_amount: Amount,
+    creditor_name: Option<String>,
+    #[serde(default)]
+    creditor_account: Account,
+    debtor_name: Option<String>,
+    #[serde(default)]
+    debtor_account: Account,
+    end_to_end_id: Option<String>,
+    remittance_information_unstructured: Option<String>,
+    #[serde(default)]
+    remittance_information_unstructured_array: Vec<String>,
+    remittance_information_structured: Option<String>,
+    additional_information: Option<String>,
+}
+
+impl TryFrom<GocardlessIR> for Gocardless {
+    type Error = Report;
+
+    fn try_from(value: GocardlessIR) -> Result<Self> {
+        let date = value
+            .booking_date
+            .or(value.value_date)
+            .ok_or_else(|| miette!("Transaction without booking date"))?;
+        let currency = iso::find(&value.transaction_amount.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.transaction_amount.currency))?;
+        let betrag = decimal(&value.transaction_amount.amount, '.')?;
+
+        // The other party is the creditor of outgoing payments
+        let (name, account) = match betrag.is_sign_negative() {
+            true => (value.creditor_name, value.creditor_account),
+            false => (value.debtor_name, value.debtor_account),
+        };
+        let verwendungszweck = value
+            .remittance_information_unstructured
+            .or_else(|| {
+                Some(value.remittance_information_unstructured_array.join(" "))
+                    .filter(|r| !r.is_empty())
+            })
+            .or(value.remittance_information_structured)
+            .or(value.additional_information)
+            .unwrap_or_default();
+
+        Ok(Self {
+            buchungstag: NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting booking date into datetime")?,
+            betrag: Money::from_decimal(betrag, currency),
+            referenz: value
+                .end_to_end_id
+                .filter(|r| r != "NOTPROVIDED")
+                .or(value.transaction_id)
+                .unwrap_or_default(),
+            name: name.unwrap_or_default(),
+            iban: account.iban,
+            verwendungszweck,
+        })
+    }
+}
+
+impl From<Gocardless> for Record {
+    fn from(val: Gocardless) -> Self {
+        Self {
+            date: val.buchungstag,
+            payment: Payment::None,
+            info: val.referenz,
+            payee: val.name,
+            memo: val.verwendungszweck,
+            amount: val.betrag,
+            iban: val.iban,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct GocardlessIter {
+    transactions: vec::IntoIter<Result<GocardlessIR>>,
+}
+
+impl GocardlessIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let transactions = match serde_json::from_reader::<_, Response>(rdr) {
+            Ok(response) => response.transactions.booked.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)
+                .into_diagnostic()
+                .wrap_err("Failed deserializing transactions")],
+        };
+
+        Self {
+            transactions: transactions.into_iter(),
+        }
+    }
+}
+
+impl Iterator for GocardlessIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ir = match self.transactions.next()? {
+            Ok(ir) => ir,
+            Err(e) => return Some(Err(e)),
+        };
+        trace!(?ir, "Read gocardless transaction");
+
+        Some(
+            Gocardless::try_from(ir)
+                .map(Record::from)
+                .wrap_err("Failed converting transaction"),
+        )
+    }
+}
+
+/// A sample response of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let booked: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            let party = match row.amount.is_sign_negative() {
+                true => "creditor",
+                false => "debtor",
+            };
+            let date = row.date.format("%Y-%m-%d").to_string();
+            json!({
+                "transactionId": row.reference,
+                "bookingDate": date,
+                "valueDate": date,
+                "transactionAmount": {"amount": row.amount.to_string(), "currency": "EUR"},
+                format!("{}Name", party): row.payee,
+                format!("{}Account", party): {"iban": row.iban},
+                "remittanceInformationUnstructured": row.purpose,
+                "additionalInformation": row.kind.booking_text(),
+            })
+        })
+        .collect();
+    let response = json!({"transactions": {"booked": booked, "pending": []}});
+    serde_json::to_vec_pretty(&response).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = r#"{"transactions": {
+            "booked": [
+                {
+                    "transactionId": "2024030701",
+                    "bookingDate": "2024-03-07",
+                    "valueDate": "2024-03-08",
+                    "transactionAmount": {"amount": "-25.88", "currency": "EUR"},
+                    "creditorName": "Woopsie",
+                    "creditorAccount": {"iban": "DE02120300000000202051"},
+                    "remittanceInformationUnstructuredArray": ["Doopsie", "March"]
+                },
+                {
+                    "valueDate": "2024-03-09",
+                    "transactionAmount": {"amount": "100.00", "currency": "EUR"},
+                    "debtorName": "Employer",
+                    "endToEndId": "4711",
+                    "remittanceInformationUnstructured": "Salary"
+                }
+            ],
+            "pending": []
+        }}"#;
+
+        let records: Vec<Record> = GocardlessIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].payee, "Woopsie");
+        assert_eq!(records[0].iban, "DE02120300000000202051");
+        assert_eq!(records[0].memo, "Doopsie March");
+        assert_eq!(records[0].info, "2024030701");
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(
+            records[1].date,
+            NaiveDate::from_ymd_opt(2024, 3, 9).unwrap()
+        );
+        assert_eq!(records[1].payee, "Employer");
+        assert_eq!(records[1].info, "4711");
+    }
+}
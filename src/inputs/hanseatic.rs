@@ -0,0 +1,154 @@
+//! The transaction export of the Hanseatic Bank GenialCard.
+//!
+//! Card payments show up as an authorization first, without a booking day,
+//! and get a row of their own once booked. Authorizations are read unless
+//! only booked transactions are asked for, `--booked-only`.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Buchungsdatum;Transaktionsdatum;Beschreibung";
+const AUTHORIZATION: &str = "Autorisierung";
+
+#[derive(Debug, Deserialize)]
+struct HanseaticIR {
+    #[serde(rename = "Transaktionsdatum")]
+    transaktionsdatum: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Status")]
+    status: String,
+}
+
+impl TryFrom<HanseaticIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: HanseaticIR) -> Result<Self> {
+        let amount = decimal_de(&value.betrag)?;
+        let text = value.beschreibung.to_lowercase();
+        let payment = match text.contains("zahlung") || text.contains("überweisung") {
+            // Payments onto the card
+            true if amount.is_sign_positive() => Payment::BankTransfer,
+            _ => Payment::CreditCard,
+        };
+        let info = match value.status == AUTHORIZATION {
+            true => value.status,
+            false => String::new(),
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.transaktionsdatum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting transaktionsdatum into datetime")?,
+            payment,
+            info,
+            payee: value.beschreibung,
+            amount: Money::from_decimal(amount, EUR),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct HanseaticIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl HanseaticIter {
+    /// Reads the export, leaving out authorizations unless `authorizations`.
+    pub fn new<R: Read>(rdr: R, authorizations: bool) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, HanseaticIR>(text.as_bytes(), b';', HEADER);
+                if !authorizations {
+                    rows.retain(|ir| !matches!(ir, Ok(ir) if ir.status == AUTHORIZATION));
+                }
+                convert(rows, "hanseatic")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for HanseaticIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, all of them booked.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out =
+        String::from("Buchungsdatum;Transaktionsdatum;Beschreibung;Karteninhaber;Betrag;Status\n");
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let text = match row.kind {
+            Kind::Salary | Kind::Transfer if row.amount.is_sign_positive() => {
+                "Überweisung Danke".to_string()
+            }
+            _ => row.payee.clone(),
+        };
+        out.push_str(&format!(
+            "{};{};{};Max Mustermann;{};Gebucht\n",
+            date,
+            date,
+            text,
+            row.amount_de()
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Buchungsdatum;Transaktionsdatum;Beschreibung;Karteninhaber;Betrag;Status\n\
+            ;08.03.2024;WOOPSIE;Max Mustermann;-5,00;Autorisierung\n\
+            07.03.2024;06.03.2024;REWE MARKT;Max Mustermann;-25,88;Gebucht\n\
+            05.03.2024;05.03.2024;Überweisung Danke;Max Mustermann;100,00;Gebucht\n";
+
+        let records: Vec<Record> = HanseaticIter::new(input.as_bytes(), true)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].info, "Autorisierung");
+
+        let records: Vec<Record> = HanseaticIter::new(input.as_bytes(), false)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 6).unwrap()
+        );
+        assert_eq!(records[0].payee, "REWE MARKT");
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+    }
+}
@@ -0,0 +1,196 @@
+//! The transaction download of HSBC UK.
+//!
+//! The download has no header, just date, description and amount, in newer
+//! ones followed by the balance. It is comma separated with `DD/MM/YYYY`
+//! dates and amounts in GBP with decimal points. Descriptions and amounts
+//! above a thousand are quoted when they contain commas. The description
+//! ends in a code of the payment type, like `VIS` or `DD`.
+
+use std::{io::Read, sync::LazyLock, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use regex::Regex;
+use rust_decimal::Decimal;
+use rusty_money::{iso::GBP, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+/// The columns of the download, which it does not name itself. The
+/// balance of newer ones is not read.
+const HEADER: &str = "Date,Description,Amount";
+
+/// A row as the download writes them: the date, a description that may be
+/// quoted and the amount, maybe followed by the balance.
+static ROW: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^"?(?:\d{2}/\d{2}/\d{4}|\d{1,2} [A-Za-z]{3} \d{4})"?,(?:"[^"]*"|[^",]*),"?-?[\d,]*\.\d{2}"?(?:,"?-?[\d,]*\.\d{2}"?)?\s*$"#)
+        .unwrap()
+});
+
+/// Whether the start of an input looks like an HSBC download.
+pub fn is_hsbc(head: &str) -> bool {
+    head.lines().next().is_some_and(|l| ROW.is_match(l))
+}
+
+#[derive(Debug, Deserialize)]
+struct HsbcIR {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Description")]
+    description: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+}
+
+/// The payment method of the type code the description ends or starts in.
+fn payment(description: &str) -> Payment {
+    let mut words = description.split_whitespace();
+    let codes = [words.next_back(), words.next()];
+    codes
+        .into_iter()
+        .flatten()
+        .find_map(|code| match code.to_uppercase().as_str() {
+            "VIS" | ")))" | "DEB" => Some(Payment::DebitCard),
+            "DD" => Some(Payment::DirectDebit),
+            "SO" => Some(Payment::StandingOrder),
+            "BP" | "CR" | "TFR" | "FPO" | "FPI" => Some(Payment::BankTransfer),
+            "ATM" => Some(Payment::Cash),
+            "CHQ" => Some(Payment::Check),
+            _ => None,
+        })
+        .unwrap_or(Payment::None)
+}
+
+impl TryFrom<HsbcIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: HsbcIR) -> Result<Self> {
+        let amount = decimal(&value.amount, '.')?;
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%d/%m/%Y")
+                .or_else(|_| NaiveDate::parse_from_str(&value.date, "%d %b %Y"))
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.description),
+            payee: value
+                .description
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+            amount: Money::from_decimal(amount, GBP),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct HsbcIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl HsbcIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let text = format!("{}\n{}", HEADER, text);
+                convert(csv_rows::<_, HsbcIR>(text.as_bytes(), b',', HEADER), "hsbc")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for HsbcIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// An amount with commas as thousands separators, quoted if it has any.
+fn amount_uk(amount: Decimal) -> String {
+    let amount = format!("{:.2}", amount);
+    let (sign, amount) = match amount.strip_prefix('-') {
+        Some(amount) => ("-", amount),
+        None => ("", amount.as_str()),
+    };
+    let (int, frac) = amount.split_once('.').unwrap_or((amount, "00"));
+    let mut out = String::new();
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    match int.len() > 3 {
+        true => format!("\"{}{}.{}\"", sign, out, frac),
+        false => format!("{}{}.{}", sign, out, frac),
+    }
+}
+
+/// A sample download of `rows` in GBP, without balance.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::new();
+    for row in rows {
+        let code = match row.kind {
+            Kind::Card => "VIS",
+            Kind::DirectDebit => "DD",
+            Kind::StandingOrder => "SO",
+            Kind::Cash => "ATM",
+            Kind::Transfer => "BP",
+            Kind::Salary => "CR",
+        };
+        out.push_str(&format!(
+            "{},\"{}, {} {}\",{}\n",
+            row.date.format("%d/%m/%Y"),
+            row.payee,
+            row.purpose,
+            code,
+            amount_uk(row.amount)
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "07/03/2024,\"TESCO STORES 1234, LONDON    VIS\",-25.88\n\
+            08/03/2024,THAMES WATER DD,-42.00,4932.12\n\
+            25/03/2024,\"EMPLOYER LTD, SALARY CR\",\"2,500.00\",\"7,432.12\"\n";
+
+        assert!(is_hsbc(input));
+        let records: Vec<Record> = HsbcIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "TESCO STORES 1234, LONDON VIS");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25.88", GBP).unwrap());
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+        assert_eq!(records[2].amount, Money::from_str("2500.00", GBP).unwrap());
+    }
+}
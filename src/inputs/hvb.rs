@@ -0,0 +1,144 @@
+//! The online banking csv of the HypoVereinsbank (UniCredit Germany).
+//!
+//! A block naming account and period may come before the header. The name
+//! of the other party comes in two columns, the older exports spell the
+//! columns without umlauts. The payment method is told by the purpose.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::{booking_payment, Purpose},
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+const HEADER: &str = "Kontonummer;Buchungsdatum;Valuta";
+
+#[derive(Debug, Deserialize)]
+struct HvbIR {
+    #[serde(rename = "Buchungsdatum")]
+    buchungsdatum: String,
+    #[serde(rename = "Empfänger 1", alias = "Empfaenger 1")]
+    empfänger_1: String,
+    #[serde(rename = "Empfänger 2", alias = "Empfaenger 2", default)]
+    empfänger_2: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung", alias = "Waehrung")]
+    währung: String,
+}
+
+impl TryFrom<HvbIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: HvbIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        let payee = [value.empfänger_1.as_str(), value.empfänger_2.as_str()]
+            .iter()
+            .filter(|name| !name.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let purpose = Purpose::parse(&value.verwendungszweck);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungsdatum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungsdatum into datetime")?,
+            payment: booking_payment(&value.verwendungszweck),
+            info: purpose.end_to_end.unwrap_or_default(),
+            payee,
+            memo: purpose.text,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, currency),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct HvbIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl HvbIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(csv_rows::<_, HvbIR>(text.as_bytes(), b';', HEADER), "hvb"),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for HvbIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Umsatzliste;Girokonto\nKontoinhaber;Max Mustermann\n\n\
+        Kontonummer;Buchungsdatum;Valuta;Empfänger 1;Empfänger 2;Verwendungszweck;Betrag;Währung\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        out.push_str(&format!(
+            "1234567890;{};{};{};;{} EREF+{} SVWZ+{};{};EUR\n",
+            date,
+            date,
+            row.payee,
+            row.kind.booking_text().to_uppercase(),
+            row.reference,
+            row.purpose,
+            row.amount_de()
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Kontonummer;Buchungsdatum;Valuta;Empfaenger 1;Empfaenger 2;Verwendungszweck;Betrag;Waehrung\n\
+            1234567890;07.03.2024;07.03.2024;Stadtwerke;Musterstadt GmbH;SEPA-LASTSCHRIFT EREF+4711 SVWZ+Strom März;-1.025,88;EUR\n\
+            1234567890;06.03.2024;06.03.2024;Erika Mustermann;;Geschenk;20,00;EUR\n";
+
+        let records: Vec<Record> = HvbIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke Musterstadt GmbH");
+        assert_eq!(records[0].memo, "SEPA-LASTSCHRIFT Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payee, "Erika Mustermann");
+        assert_eq!(records[1].payment, Payment::None);
+    }
+}
@@ -0,0 +1,193 @@
+//! The cash transactions report of an Interactive Brokers Flex Query, as
+//! csv.
+//!
+//! Deposits, withdrawals, dividends, withholding taxes, fees and interest
+//! keep the currency they were booked in. With `--base-currency` they are
+//! converted into the base currency of the account with the exchange rate
+//! of the report instead, noting the original amount in the memo.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "ClientAccountID";
+
+#[derive(Debug, Deserialize)]
+struct IbkrIR {
+    #[serde(rename = "CurrencyPrimary")]
+    currency: String,
+    #[serde(rename = "FXRateToBase", default)]
+    fx_rate: String,
+    #[serde(rename = "Symbol", default)]
+    symbol: String,
+    #[serde(rename = "Description")]
+    description: String,
+    #[serde(rename = "DateTime", alias = "Date/Time")]
+    date: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "TransactionID", default)]
+    transaction_id: String,
+    /// Currency to convert into, not set from the report
+    #[serde(skip)]
+    base: Option<String>,
+}
+
+impl TryFrom<IbkrIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: IbkrIR) -> Result<Self> {
+        let amount = decimal(&value.amount, '.')?;
+        let (amount, currency, memo) = match &value.base {
+            Some(base) if *base != value.currency => {
+                let rate = decimal(&value.fx_rate, '.')?;
+                let memo = format!("{} {} (rate {})", amount, value.currency, rate);
+                ((amount * rate).round_dp(2), base.as_str(), memo)
+            }
+            _ => (amount, value.currency.as_str(), String::new()),
+        };
+        let currency =
+            iso::find(currency).ok_or_else(|| miette!("Unknown currency '{}'", currency))?;
+        let payment = match value.kind.as_str() {
+            "Deposits/Withdrawals" => Payment::BankTransfer,
+            "Dividends" | "Payment In Lieu Of Dividends" | "Broker Interest Received" => {
+                Payment::Deposit
+            }
+            "Withholding Tax"
+            | "Other Fees"
+            | "Broker Interest Paid"
+            | "Commission Adjustments" => Payment::FinancialInstitutionFee,
+            _ => Payment::None,
+        };
+        // Dates are written `yyyyMMdd`, maybe followed by a time
+        let date: String = value
+            .date
+            .chars()
+            .filter(char::is_ascii_digit)
+            .take(8)
+            .collect();
+        let payee = match value.symbol.is_empty() {
+            true => "Interactive Brokers".to_string(),
+            false => value.symbol,
+        };
+        let memo = format!("{} {}", value.description, memo).trim().to_string();
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&date, "%Y%m%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            info: value.transaction_id,
+            payee,
+            memo,
+            amount: Money::from_decimal(amount, currency),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct IbkrIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl IbkrIter {
+    /// Reads the report, converting amounts into `base` if given.
+    pub fn new<R: Read>(rdr: R, base: Option<&str>) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, IbkrIR>(text.as_bytes(), b',', HEADER);
+                for ir in rows.iter_mut().flatten() {
+                    ir.base = base.map(str::to_uppercase);
+                }
+                convert(rows, "ibkr")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for IbkrIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample report of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"ClientAccountID\",\"CurrencyPrimary\",\"FXRateToBase\",\"AssetClass\",\"Symbol\",\
+        \"Description\",\"DateTime\",\"SettleDate\",\"Amount\",\"Type\",\"TransactionID\"\n",
+    );
+    for row in rows {
+        out.push_str(&format!(
+            "\"U1234567\",\"EUR\",\"1\",\"\",\"\",\"{}\",\"{};100000\",\"{}\",\"{}\",\"Deposits/Withdrawals\",\"{}\"\n",
+            row.payee,
+            row.date.format("%Y%m%d"),
+            row.date.format("%Y%m%d"),
+            row.amount,
+            row.reference
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::{EUR, USD};
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"ClientAccountID\",\"CurrencyPrimary\",\"FXRateToBase\",\"AssetClass\",\"Symbol\",\"Description\",\"DateTime\",\"SettleDate\",\"Amount\",\"Type\",\"TransactionID\"\n\
+            \"U1234567\",\"EUR\",\"1\",\"\",\"\",\"CASH RECEIPTS / ELECTRONIC FUND TRANSFERS\",\"20240307;101500\",\"20240307\",\"1000\",\"Deposits/Withdrawals\",\"111\"\n\
+            \"U1234567\",\"USD\",\"0.92\",\"STK\",\"AAPL\",\"AAPL CASH DIVIDEND USD 0.24 PER SHARE\",\"20240315\",\"20240315\",\"2.40\",\"Dividends\",\"222\"\n\
+            \"U1234567\",\"USD\",\"0.92\",\"STK\",\"AAPL\",\"AAPL US TAX\",\"20240315\",\"20240315\",\"-0.36\",\"Withholding Tax\",\"333\"\n";
+
+        let records: Vec<Record> = IbkrIter::new(input.as_bytes(), None)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].amount, Money::from_str("1000,00", EUR).unwrap());
+        assert_eq!(records[1].payee, "AAPL");
+        assert_eq!(records[1].payment, Payment::Deposit);
+        assert_eq!(records[1].amount, Money::from_str("2.40", USD).unwrap());
+        assert_eq!(records[2].payment, Payment::FinancialInstitutionFee);
+
+        let records: Vec<Record> = IbkrIter::new(input.as_bytes(), Some("eur"))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records[0].amount, Money::from_str("1000,00", EUR).unwrap());
+        assert_eq!(records[1].amount, Money::from_str("2,21", EUR).unwrap());
+        assert_eq!(
+            records[1].memo,
+            "AAPL CASH DIVIDEND USD 0.24 PER SHARE 2.40 USD (rate 0.92)"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! The "Umsatzanzeige" export of ING Germany (ING-DiBa), in ISO 8859-1.
+//!
+//! A preamble of account, period and balance comes first, its length
+//! changes with the export options. The booking text tells the payment
+//! method. Columns are read by name, so exports with the `Kategorie`
+//! column added in 2023 work as well.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+const HEADER: &str = "Buchung;";
+
+#[derive(Debug, Deserialize)]
+struct IngIR {
+    #[serde(rename = "Buchung")]
+    buchung: String,
+    #[serde(rename = "Auftraggeber/Empfänger")]
+    auftraggeber: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+}
+
+impl TryFrom<IngIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: IngIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchung, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchung into datetime")?,
+            payment: booking_payment(&value.buchungstext),
+            payee: value.auftraggeber,
+            memo: value.verwendungszweck,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, currency),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct IngIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl IngIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                // Balance and amount both have a `Währung` column, the first
+                // one is renamed so the second is the one of the amount
+                let text = text.replacen("Saldo;Währung", "Saldo;Saldowährung", 1);
+                convert(csv_rows::<_, IngIR>(text.as_bytes(), b';', HEADER), "ing")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for IngIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in ISO 8859-1 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let from = rows.first().map(|r| r.date).unwrap_or_default();
+    let to = rows.last().map(|r| r.date).unwrap_or_default();
+    let mut out = format!(
+        "Umsatzanzeige;Datei erstellt am: {} 10:00\n;Letztes Update: aktuell\n\n\
+        IBAN;DE89 3704 0044 0532 0130 00\nKontoname;Girokonto\nBank;ING\n\
+        Kunde;Max Mustermann\nZeitraum;{} - {}\nSaldo;1.000,00;EUR\n\n\
+        Sortierung;Datum aufsteigend\n\n\
+        In der CSV-Datei finden Sie alle bereits gebuchten Umsätze.\n\n\
+        Buchung;Valuta;Auftraggeber/Empfänger;Buchungstext;Verwendungszweck;Saldo;Währung;Betrag;Währung\n",
+        to.format("%d.%m.%Y"),
+        from.format("%d.%m.%Y"),
+        to.format("%d.%m.%Y")
+    );
+    let mut balance = Decimal::new(100000, 2);
+    for row in rows {
+        balance += row.amount;
+        let date = row.date.format("%d.%m.%Y");
+        out.push_str(&format!(
+            "{};{};{};{};{};{};EUR;{};EUR\n",
+            date,
+            date,
+            row.payee,
+            row.kind.booking_text().trim_start_matches("SEPA "),
+            row.purpose,
+            balance.to_string().replace('.', ","),
+            row.amount_de()
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Umsatzanzeige;Datei erstellt am: 08.03.2024 10:00\n;Letztes Update: aktuell\n\n\
+            IBAN;DE12 5001 0517 1234 5678 90\nKontoname;Girokonto\nBank;ING\nKunde;Max Mustermann\n\
+            Zeitraum;01.03.2024 - 08.03.2024\nSaldo;1.234,56;EUR\n\nSortierung;Datum absteigend\n\n\
+            In der CSV-Datei finden Sie alle bereits gebuchten Umsätze. Die vorgemerkten Umsätze werden nicht aufgenommen.\n\n\
+            Buchung;Valuta;Auftraggeber/Empfänger;Buchungstext;Verwendungszweck;Saldo;Währung;Betrag;Währung\n\
+            07.03.2024;07.03.2024;Stadtwerke;Lastschrift;Strom März;1.208,68;EUR;-1.025,88;EUR\n\
+            06.03.2024;06.03.2024;Arbeitgeber GmbH;Gehalt/Rente;Lohn;2.234,56;EUR;2.000,00;EUR\n";
+
+        let records: Vec<Record> = IngIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+    }
+}
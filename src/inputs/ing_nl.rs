@@ -0,0 +1,214 @@
+//! The csv export of ING in the Netherlands, with `YYYYMMDD` dates and
+//! unsigned amounts with decimal commas. Older exports are comma separated,
+//! newer ones `;` separated.
+//!
+//! Whether an amount is debited or credited is told by the `Af Bij`
+//! column. The notes carry labelled fields like `Omschrijving:`, `IBAN:`
+//! and `Kenmerk:`, which give memo, IBAN and info.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Datum";
+
+/// The labels of the fields in the notes.
+const LABELS: [&str; 5] = [
+    "Naam:",
+    "Omschrijving:",
+    "IBAN:",
+    "Kenmerk:",
+    "Machtiging ID:",
+];
+
+#[derive(Debug, Deserialize)]
+struct IngNlIR {
+    #[serde(rename = "Datum")]
+    datum: String,
+    #[serde(rename = "Naam / Omschrijving")]
+    naam: String,
+    #[serde(rename = "Tegenrekening", default)]
+    tegenrekening: String,
+    #[serde(rename = "Code", default)]
+    code: String,
+    #[serde(rename = "Af Bij")]
+    af_bij: String,
+    #[serde(rename = "Bedrag (EUR)")]
+    bedrag: String,
+    #[serde(rename = "Mededelingen", default)]
+    mededelingen: String,
+}
+
+/// The value of `label` in the notes, up to the next label.
+fn field(notes: &str, label: &str) -> Option<String> {
+    let start = notes.find(label)? + label.len();
+    let rest = &notes[start..];
+    let end = LABELS
+        .iter()
+        .filter_map(|l| rest.find(l))
+        .min()
+        .unwrap_or(rest.len());
+    Some(rest[..end].split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// The payment method of the two letter code.
+fn payment(code: &str) -> Payment {
+    match code.to_uppercase().as_str() {
+        "BA" => Payment::DebitCard,
+        "GM" | "PK" => Payment::Cash,
+        "IC" => Payment::DirectDebit,
+        "OV" | "GT" | "ID" | "VZ" => Payment::BankTransfer,
+        "ST" => Payment::Deposit,
+        "DV" => Payment::FinancialInstitutionFee,
+        _ => Payment::None,
+    }
+}
+
+impl TryFrom<IngNlIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: IngNlIR) -> Result<Self> {
+        let amount = decimal_de(&value.bedrag)?.abs();
+        let amount = match value.af_bij.to_lowercase().as_str() {
+            "af" => -amount,
+            "bij" => amount,
+            other => return Err(miette!("Unknown debit/credit indicator '{}'", other)),
+        };
+        let notes = &value.mededelingen;
+        let iban = match value.tegenrekening.is_empty() {
+            true => field(notes, "IBAN:").unwrap_or_default(),
+            false => value.tegenrekening,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.datum, "%Y%m%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.code),
+            info: field(notes, "Kenmerk:").unwrap_or_default(),
+            payee: value.naam,
+            memo: field(notes, "Omschrijving:").unwrap_or_else(|| notes.trim().to_string()),
+            amount: Money::from_decimal(amount, EUR),
+            iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct IngNlIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl IngNlIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let delimiter = match text.lines().next().is_some_and(|l| l.contains(';')) {
+                    true => b';',
+                    false => b',',
+                };
+                convert(
+                    csv_rows::<_, IngNlIR>(text.as_bytes(), delimiter, HEADER),
+                    "ing-nl",
+                )
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for IngNlIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` in the newer `;` separated layout.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Datum\";\"Naam / Omschrijving\";\"Rekening\";\"Tegenrekening\";\"Code\";\"Af Bij\";\
+        \"Bedrag (EUR)\";\"Mutatiesoort\";\"Mededelingen\";\"Saldo na mutatie\";\"Tag\"\n",
+    );
+    let mut saldo = Decimal::new(500000, 2);
+    for row in rows {
+        saldo += row.amount;
+        let (code, kind) = match row.kind {
+            Kind::Card => ("BA", "Betaalautomaat"),
+            Kind::Cash => ("GM", "Geldautomaat"),
+            Kind::DirectDebit => ("IC", "Incasso"),
+            Kind::StandingOrder | Kind::Transfer | Kind::Salary => ("OV", "Overschrijving"),
+        };
+        let af_bij = match row.amount.is_sign_negative() {
+            true => "Af",
+            false => "Bij",
+        };
+        out.push_str(&format!(
+            "\"{}\";\"{}\";\"NL69INGB0123456789\";\"{}\";\"{}\";\"{}\";\"{}\";\"{}\";\
+            \"Naam: {} Omschrijving: {} IBAN: {} Kenmerk: {}\";\"{}\";\"\"\n",
+            row.date.format("%Y%m%d"),
+            row.payee,
+            row.iban,
+            code,
+            af_bij,
+            row.amount_de().trim_start_matches('-'),
+            kind,
+            row.payee,
+            row.purpose,
+            row.iban,
+            row.reference,
+            saldo.to_string().replace('.', ",")
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Datum\",\"Naam / Omschrijving\",\"Rekening\",\"Tegenrekening\",\"Code\",\"Af Bij\",\"Bedrag (EUR)\",\"Mutatiesoort\",\"Mededelingen\"\n\
+            \"20240307\",\"Albert Heijn 1234\",\"NL69INGB0123456789\",\"\",\"BA\",\"Af\",\"25,88\",\"Betaalautomaat\",\"Pasvolgnr:001 07-03-2024 18:30 Transactie:123\"\n\
+            \"20240308\",\"Vattenfall\",\"NL69INGB0123456789\",\"NL20INGB0001234567\",\"IC\",\"Af\",\"42,00\",\"Incasso\",\"Naam: Vattenfall Omschrijving: Energie maart IBAN: NL20INGB0001234567 Kenmerk: REF-1 Machtiging ID: M-1\"\n\
+            \"20240325\",\"Werkgever BV\",\"NL69INGB0123456789\",\"NL02ABNA0123456789\",\"OV\",\"Bij\",\"2500,00\",\"Overschrijving\",\"Naam: Werkgever BV Omschrijving: Salaris maart IBAN: NL02ABNA0123456789\"\n";
+
+        let records: Vec<Record> = IngNlIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Albert Heijn 1234");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[1].memo, "Energie maart");
+        assert_eq!(records[1].info, "REF-1");
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+        assert_eq!(records[2].amount, Money::from_str("2500,00", EUR).unwrap());
+    }
+}
@@ -0,0 +1,155 @@
+//! The transaction export of the Klarna app, for "buy now pay later"
+//! purchases and their payments.
+//!
+//! Amounts are written without a sign, the type tells it. Purchases add
+//! to what is owed to Klarna and become negative, installments paid and
+//! refunds positive. Cancelled purchases are left out.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Datum,Händler,Typ";
+const CANCELLED: &str = "Storniert";
+
+#[derive(Debug, Deserialize)]
+struct KlarnaIR {
+    #[serde(rename = "Datum")]
+    datum: String,
+    #[serde(rename = "Händler")]
+    händler: String,
+    #[serde(rename = "Typ")]
+    typ: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+    #[serde(rename = "Bestellnummer", default)]
+    bestellnummer: String,
+    #[serde(rename = "Status", default)]
+    status: String,
+}
+
+impl TryFrom<KlarnaIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: KlarnaIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        let amount = decimal_de(&value.betrag)?.abs();
+        let (payment, amount) = match value.typ.as_str() {
+            "Kauf" => (Payment::ElectronicPayment, -amount),
+            "Rückerstattung" => (Payment::ElectronicPayment, amount),
+            "Ratenzahlung" | "Zahlung" => (Payment::BankTransfer, amount),
+            typ => return Err(miette!("Unknown klarna type '{}'", typ)),
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.datum, "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(&value.datum, "%d.%m.%Y"))
+                .into_diagnostic()
+                .wrap_err("Failed converting datum into datetime")?,
+            payment,
+            info: value.bestellnummer,
+            payee: value.händler,
+            memo: value.typ,
+            amount: Money::from_decimal(amount, currency),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct KlarnaIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl KlarnaIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, KlarnaIR>(text.as_bytes(), b',', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.status == CANCELLED));
+                convert(rows, "klarna")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for KlarnaIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from("Datum,Händler,Typ,Betrag,Währung,Bestellnummer,Status\n");
+    for row in rows {
+        let typ = match (row.kind, row.amount.is_sign_negative()) {
+            (_, true) => "Kauf",
+            (Kind::Salary | Kind::Transfer, false) => "Zahlung",
+            (_, false) => "Rückerstattung",
+        };
+        out.push_str(&format!(
+            "{},{},{},\"{}\",EUR,{},Abgeschlossen\n",
+            row.date.format("%Y-%m-%d"),
+            row.payee,
+            typ,
+            row.amount.abs().to_string().replace('.', ","),
+            row.reference
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Datum,Händler,Typ,Betrag,Währung,Bestellnummer,Status\n\
+            2024-03-07,Zalando,Kauf,\"89,90\",EUR,K-123,Abgeschlossen\n\
+            2024-03-08,Zalando,Kauf,\"19,90\",EUR,K-124,Storniert\n\
+            2024-03-14,Zalando,Rückerstattung,\"29,95\",EUR,K-123,Abgeschlossen\n\
+            2024-04-07,Zalando,Ratenzahlung,\"29,95\",EUR,K-123,Abgeschlossen\n";
+
+        let records: Vec<Record> = KlarnaIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Zalando");
+        assert_eq!(records[0].info, "K-123");
+        assert_eq!(records[0].amount, Money::from_str("-89,90", EUR).unwrap());
+        assert_eq!(records[1].amount, Money::from_str("29,95", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::ElectronicPayment);
+        assert_eq!(records[2].amount, Money::from_str("29,95", EUR).unwrap());
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+    }
+}
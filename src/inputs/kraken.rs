@@ -0,0 +1,218 @@
+//! The `ledgers.csv` export of Kraken, comma separated with decimal points.
+//!
+//! Every trade is written as one ledger entry per asset, sharing a
+//! reference id. They are paired into a single record, the fiat leg giving
+//! the amount and the crypto legs noted in the memo. Fiat deposits and
+//! withdrawals are read as they are, everything that does not move fiat
+//! money is left out. Fees are part of the amount.
+
+use std::{collections::HashMap, io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{
+    iso::{self, Currency},
+    Money,
+};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "txid,refid,time,type";
+
+#[derive(Debug, Deserialize)]
+struct KrakenIR {
+    txid: String,
+    refid: String,
+    time: String,
+    #[serde(rename = "type")]
+    kind: String,
+    asset: String,
+    amount: String,
+    #[serde(default)]
+    fee: String,
+}
+
+impl KrakenIR {
+    /// The fiat currency of the asset, like `ZEUR` or `EUR.HOLD`.
+    fn fiat(&self) -> Option<&'static Currency> {
+        let asset = self.asset.split('.').next().unwrap_or_default();
+        match asset.len() == 4 && asset.starts_with('Z') {
+            true => iso::find(&asset[1..]),
+            false => iso::find(asset),
+        }
+    }
+
+    /// The amount minus the fee, which is written positive.
+    fn net(&self) -> Result<Decimal> {
+        Ok(decimal(&self.amount, '.')? - decimal(&self.fee, '.')?)
+    }
+}
+
+/// The ledger entries of one reference id.
+#[derive(Debug)]
+struct KrakenTx(Vec<KrakenIR>);
+
+impl TryFrom<KrakenTx> for Record {
+    type Error = Report;
+
+    fn try_from(value: KrakenTx) -> Result<Self> {
+        let (fiat, crypto): (Vec<_>, Vec<_>) = value.0.iter().partition(|ir| ir.fiat().is_some());
+        let first = fiat
+            .first()
+            .ok_or_else(|| miette!("Ledger entries without a fiat leg"))?;
+        let currency = first.fiat().unwrap_or(iso::EUR);
+        let mut amount = Decimal::ZERO;
+        for ir in &fiat {
+            amount += ir.net()?;
+        }
+        let payment = match first.kind.as_str() {
+            "deposit" | "withdrawal" => Payment::BankTransfer,
+            "trade" | "spend" | "receive" => Payment::ElectronicPayment,
+            _ => Payment::None,
+        };
+        let mut legs = Vec::new();
+        for ir in &crypto {
+            // Crypto assets are prefixed with an `X` in four letters
+            let asset = match ir.asset.len() == 4 && ir.asset.starts_with('X') {
+                true => &ir.asset[1..],
+                false => &ir.asset,
+            };
+            legs.push(format!("{} {}", ir.net()?.normalize(), asset));
+        }
+        let memo = match legs.is_empty() {
+            true => String::new(),
+            false => format!("{} {}", first.kind, legs.join(", ")),
+        };
+        let date = first.time.get(..10).unwrap_or(&first.time);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            info: first.refid.clone(),
+            payee: "Kraken".to_string(),
+            memo,
+            amount: Money::from_decimal(amount, currency),
+            ..Default::default()
+        })
+    }
+}
+
+/// The entries grouped by their reference id in the order of the ledger,
+/// keeping the groups moving fiat money. Entries without a transaction id
+/// are pending duplicates of booked ones.
+fn pair(rows: Vec<Result<KrakenIR>>) -> Vec<Result<KrakenTx>> {
+    let mut txs: Vec<Result<KrakenTx>> = Vec::new();
+    let mut index = HashMap::new();
+    for ir in rows {
+        match ir {
+            Ok(ir) if ir.txid.is_empty() => {}
+            Ok(ir) => match index.get(&ir.refid) {
+                Some(&i) => {
+                    if let Some(Ok(KrakenTx(legs))) = txs.get_mut(i) {
+                        legs.push(ir);
+                    }
+                }
+                None => {
+                    index.insert(ir.refid.clone(), txs.len());
+                    txs.push(Ok(KrakenTx(vec![ir])));
+                }
+            },
+            Err(e) => txs.push(Err(e)),
+        }
+    }
+    txs.retain(|tx| !matches!(tx, Ok(tx) if tx.0.iter().all(|ir| ir.fiat().is_none())));
+    txs
+}
+
+pub struct KrakenIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl KrakenIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                pair(csv_rows::<_, KrakenIR>(text.as_bytes(), b',', HEADER)),
+                "kraken",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for KrakenIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample ledger of `rows` as fiat deposits and withdrawals.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"txid\",\"refid\",\"time\",\"type\",\"subtype\",\"aclass\",\"asset\",\"wallet\",\
+        \"amount\",\"fee\",\"balance\"\n",
+    );
+    for (i, row) in rows.iter().enumerate() {
+        let kind = match row.amount.is_sign_negative() {
+            true => "withdrawal",
+            false => "deposit",
+        };
+        out.push_str(&format!(
+            "\"L{:05}\",\"{}\",\"{} 10:00:00\",\"{}\",\"\",\"currency\",\"ZEUR\",\"spot / main\",{},0,0\n",
+            i, row.reference, row.date.format("%Y-%m-%d"), kind, row.amount
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"txid\",\"refid\",\"time\",\"type\",\"subtype\",\"aclass\",\"asset\",\"wallet\",\"amount\",\"fee\",\"balance\"\n\
+            \"\",\"Q1\",\"2024-03-07 10:00:00\",\"deposit\",\"\",\"currency\",\"ZEUR\",\"spot / main\",500.0000,0.0000,\"\"\n\
+            \"L1\",\"Q1\",\"2024-03-07 10:05:00\",\"deposit\",\"\",\"currency\",\"ZEUR\",\"spot / main\",500.0000,0.0000,500.0000\n\
+            \"L2\",\"T1\",\"2024-03-08 09:00:00\",\"trade\",\"\",\"currency\",\"ZEUR\",\"spot / main\",-150.0000,0.3900,349.6100\n\
+            \"L3\",\"T1\",\"2024-03-08 09:00:00\",\"trade\",\"\",\"currency\",\"XXBT\",\"spot / main\",0.0025000000,0.0000000000,0.0025000000\n\
+            \"L4\",\"S1\",\"2024-03-09 09:00:00\",\"staking\",\"\",\"currency\",\"ETH2\",\"spot / main\",0.0001,0,0.0001\n\
+            \"L5\",\"W1\",\"2024-03-10 09:00:00\",\"withdrawal\",\"\",\"currency\",\"ZEUR\",\"spot / main\",-100.0000,0.0900,249.5200\n";
+
+        let records: Vec<Record> = KrakenIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].amount, Money::from_str("500,00", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::ElectronicPayment);
+        assert_eq!(records[1].info, "T1");
+        assert_eq!(records[1].memo, "trade 0.0025 XBT");
+        assert_eq!(records[1].amount, Money::from_str("-150,39", EUR).unwrap());
+        assert_eq!(records[2].amount, Money::from_str("-100,09", EUR).unwrap());
+    }
+}
@@ -0,0 +1,165 @@
+//! The transaction export shared by the banks of Lloyds Banking Group,
+//! Lloyds, Halifax and Bank of Scotland. Comma separated with `DD/MM/YYYY`
+//! dates and amounts in GBP with decimal points.
+//!
+//! Debits and credits have columns of their own, one of which is empty, and
+//! the transaction type is a short code like `DEB` or `FPO`.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso::GBP, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Transaction Date,Transaction Type,Sort Code";
+
+#[derive(Debug, Deserialize)]
+struct LloydsIR {
+    #[serde(rename = "Transaction Date")]
+    date: String,
+    #[serde(rename = "Transaction Type", default)]
+    kind: String,
+    #[serde(rename = "Transaction Description", default)]
+    description: String,
+    #[serde(rename = "Debit Amount", default)]
+    debit: String,
+    #[serde(rename = "Credit Amount", default)]
+    credit: String,
+}
+
+/// The payment method of the transaction type code.
+fn payment(code: &str) -> Payment {
+    match code.trim().to_uppercase().as_str() {
+        "DEB" | "CSH" => Payment::DebitCard,
+        "DD" => Payment::DirectDebit,
+        "SO" => Payment::StandingOrder,
+        "FPO" | "FPI" | "BGC" | "BP" | "PAY" => Payment::BankTransfer,
+        "TFR" => Payment::InternalTransfer,
+        "CPT" => Payment::Cash,
+        "CHQ" => Payment::Check,
+        "DEP" => Payment::Deposit,
+        "CHG" | "FEE" => Payment::FinancialInstitutionFee,
+        _ => Payment::None,
+    }
+}
+
+impl TryFrom<LloydsIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: LloydsIR) -> Result<Self> {
+        let amount = decimal(&value.credit, '.')? - decimal(&value.debit, '.')?.abs();
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%d/%m/%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.kind),
+            payee: value.description,
+            amount: Money::from_decimal(amount, GBP),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct LloydsIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl LloydsIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, LloydsIR>(text.as_bytes(), b',', HEADER),
+                "lloyds",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for LloydsIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` in GBP.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Transaction Date,Transaction Type,Sort Code,Account Number,Transaction Description,\
+        Debit Amount,Credit Amount,Balance\n",
+    );
+    let mut balance = Decimal::new(500000, 2);
+    for row in rows {
+        balance += row.amount;
+        let kind = match row.kind {
+            Kind::Card => "DEB",
+            Kind::DirectDebit => "DD",
+            Kind::StandingOrder => "SO",
+            Kind::Cash => "CPT",
+            Kind::Transfer => "FPO",
+            Kind::Salary => "BGC",
+        };
+        let (debit, credit) = match row.amount.is_sign_negative() {
+            true => (row.amount.abs().to_string(), String::new()),
+            false => (String::new(), row.amount.to_string()),
+        };
+        out.push_str(&format!(
+            "{},{},'30-94-57,12345678,{},{},{},{}\n",
+            row.date.format("%d/%m/%Y"),
+            kind,
+            row.payee,
+            debit,
+            credit,
+            balance
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Transaction Date,Transaction Type,Sort Code,Account Number,Transaction Description,Debit Amount,Credit Amount,Balance\n\
+            07/03/2024,DEB,'30-94-57,12345678,TESCO STORES 1234,25.88,,4974.12\n\
+            08/03/2024,DD,'30-94-57,12345678,THAMES WATER,42.00,,4932.12\n\
+            25/03/2024,BGC,'30-94-57,12345678,EMPLOYER LTD,,2500.00,7432.12\n";
+
+        let records: Vec<Record> = LloydsIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "TESCO STORES 1234");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25.88", GBP).unwrap());
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+        assert_eq!(records[2].amount, Money::from_str("2500.00", GBP).unwrap());
+    }
+}
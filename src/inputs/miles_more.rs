@@ -0,0 +1,164 @@
+//! The export of the Miles & More credit card issued by the DKB, in
+//! Windows-1252.
+//!
+//! The award miles of a row become a tag like `miles-25`, purchases in
+//! foreign currencies note the original amount in the memo.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rust_decimal::prelude::ToPrimitive;
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Belegdatum;Buchungsdatum;Beschreibung";
+
+#[derive(Debug, Deserialize)]
+struct MilesMoreIR {
+    #[serde(rename = "Belegdatum")]
+    belegdatum: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Betrag (EUR)")]
+    betrag: String,
+    #[serde(rename = "Originalbetrag", default)]
+    originalbetrag: String,
+    #[serde(rename = "Originalwährung", default)]
+    originalwährung: String,
+    #[serde(rename = "Prämienmeilen", default)]
+    meilen: String,
+}
+
+impl TryFrom<MilesMoreIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: MilesMoreIR) -> Result<Self> {
+        let amount = decimal_de(&value.betrag)?;
+        let text = value.beschreibung.to_lowercase();
+        let payment = match text.contains("lastschrift") || text.contains("zahlung") {
+            // Settling the card from the account
+            true if amount.is_sign_positive() => Payment::BankTransfer,
+            _ => Payment::CreditCard,
+        };
+        let memo = match value.originalwährung.as_str() {
+            "" | "EUR" => String::new(),
+            currency => format!("{} {}", value.originalbetrag, currency),
+        };
+        let tags = match value.meilen.trim_start_matches('+') {
+            "" | "0" => Vec::new(),
+            miles => vec![format!("miles-{}", miles)],
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.belegdatum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting belegdatum into datetime")?,
+            payment,
+            payee: value.beschreibung,
+            memo,
+            amount: Money::from_decimal(amount, EUR),
+            tags,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct MilesMoreIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl MilesMoreIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, MilesMoreIR>(text.as_bytes(), b';', HEADER),
+                "miles & more",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for MilesMoreIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Miles & More Gold Credit Card;5310 XXXX XXXX 1234\n\n\
+        Belegdatum;Buchungsdatum;Beschreibung;Betrag (EUR);Originalbetrag;Originalwährung;Prämienmeilen\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let (text, miles) = match row.kind {
+            Kind::Salary | Kind::Transfer if row.amount.is_sign_positive() => {
+                ("Lastschrift".to_string(), 0)
+            }
+            // A mile for every two euros
+            _ => (
+                row.payee.clone(),
+                row.amount.abs().to_i64().unwrap_or_default() / 2,
+            ),
+        };
+        out.push_str(&format!(
+            "{};{};{};{};;;{}\n",
+            date,
+            date,
+            text,
+            row.amount_de(),
+            miles
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Miles & More Gold Credit Card;5310 XXXX XXXX 1234\n\n\
+            Belegdatum;Buchungsdatum;Beschreibung;Betrag (EUR);Originalbetrag;Originalwährung;Prämienmeilen\n\
+            06.03.2024;07.03.2024;APPLE.COM/BILL;-9,31;-10,00;USD;4\n\
+            05.03.2024;05.03.2024;Lastschrift;250,00;;;0\n";
+
+        let records: Vec<Record> = MilesMoreIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 6).unwrap()
+        );
+        assert_eq!(records[0].payee, "APPLE.COM/BILL");
+        assert_eq!(records[0].memo, "-10,00 USD");
+        assert_eq!(records[0].tags, vec!["miles-4"]);
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[0].amount, Money::from_str("-9,31", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+        assert!(records[1].tags.is_empty());
+    }
+}
@@ -1,3 +1,1021 @@
+pub mod abn_amro;
+pub mod advanzia;
+pub mod amazon_visa;
+pub mod amex;
+pub mod barclays;
+pub mod barclays_uk;
+pub mod binance;
+pub mod bitpanda;
+pub mod bunq;
+pub mod c24;
+pub mod camt;
+pub mod coinbase;
+pub mod comdirect;
+pub mod commerzbank;
+mod compressed;
+pub mod direkt1822;
+pub mod dkb;
+pub mod dkb_visa;
+pub mod elba;
+pub mod flatex;
+pub mod george;
+pub mod gocardless;
+pub mod hanseatic;
+pub mod hsbc;
+pub mod hvb;
+pub mod ibkr;
+pub mod ing;
+pub mod ing_nl;
+pub mod klarna;
+pub mod kraken;
+pub mod lloyds;
+pub mod miles_more;
+pub mod monzo;
+pub mod mt940;
+pub mod nationwide;
+pub mod natwest;
+pub mod neon;
+pub mod norisbank;
+pub mod paypal;
+pub mod paypal_api;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod plaid;
 pub mod postbank;
+pub mod postbank_visa;
+pub mod postfinance;
+pub mod psd;
+pub mod rabobank;
+pub mod revolut;
+pub mod santander;
+pub mod scalable;
+mod sepa;
 pub mod sparda;
+pub mod sparda_legacy;
+pub mod sparkasse;
+pub mod starling;
+pub mod tfbank;
+pub mod tomorrow;
+pub mod trade_republic;
+pub mod triodos;
+pub mod ubs;
+pub mod url;
 mod util;
+pub mod vivid;
+pub mod volksbank;
+pub mod wise;
+pub mod xlsx;
+pub mod zipped;
+pub mod zkb;
+
+use std::{
+    fs,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use abn_amro::AbnAmroIter;
+use advanzia::AdvanziaIter;
+use amazon_visa::AmazonVisaIter;
+use amex::AmexIter;
+use barclays::BarclaysIter;
+use barclays_uk::BarclaysUkIter;
+use binance::BinanceIter;
+use bitpanda::BitpandaIter;
+use bunq::BunqIter;
+use c24::C24Iter;
+use clap::ValueEnum;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{gpg, homebank::Record, sample::Row};
+use camt::CamtIter;
+use coinbase::CoinbaseIter;
+use comdirect::ComdirectIter;
+use commerzbank::CommerzbankIter;
+use direkt1822::Direkt1822Iter;
+use dkb::DkbIter;
+use dkb_visa::DkbVisaIter;
+use elba::ElbaIter;
+use flatex::FlatexIter;
+use george::GeorgeIter;
+use gocardless::GocardlessIter;
+use hanseatic::HanseaticIter;
+use hsbc::HsbcIter;
+use hvb::HvbIter;
+use ibkr::IbkrIter;
+use ing::IngIter;
+use ing_nl::IngNlIter;
+use klarna::KlarnaIter;
+use kraken::KrakenIter;
+use lloyds::LloydsIter;
+use miles_more::MilesMoreIter;
+use monzo::MonzoIter;
+use mt940::Mt940Iter;
+use nationwide::NationwideIter;
+use natwest::NatwestIter;
+use neon::NeonIter;
+use norisbank::NorisbankIter;
+use paypal::PaypalIter;
+use paypal_api::PaypalApiIter;
+use plaid::PlaidIter;
+use postbank::PostbankIter;
+use postbank_visa::PostbankVisaIter;
+use postfinance::PostfinanceIter;
+use psd::PsdIter;
+use rabobank::RabobankIter;
+use revolut::RevolutIter;
+use santander::SantanderIter;
+use scalable::ScalableIter;
+use sparda::TeoIter;
+use sparda_legacy::SpardaLegacyIter;
+use sparkasse::SparkasseIter;
+use starling::StarlingIter;
+use tfbank::TfBankIter;
+use tomorrow::TomorrowIter;
+use trade_republic::TradeRepublicIter;
+use triodos::TriodosIter;
+use ubs::UbsIter;
+use url::Download;
+use vivid::VividIter;
+use volksbank::VolksbankIter;
+use wise::WiseIter;
+use zkb::ZkbIter;
+
+#[derive(Debug, Clone, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Postbank Giro export, legacy and since the 2023 migration
+    Postbank,
+    Sparda,
+    /// Sparda online banking before TEO (SpardaSecureGo)
+    SpardaLegacy,
+    /// Deutsche Kreditbank Girokonto, current and legacy layout
+    Dkb,
+    /// Deutsche Kreditbank Visa credit card, current and legacy layout
+    DkbVisa,
+    /// ING Germany Umsatzanzeige CSV
+    Ing,
+    /// Sparkasse CSV-CAMT V8
+    Sparkasse,
+    /// Commerzbank transaction export
+    Commerzbank,
+    /// comdirect Girokonto and Verrechnungskonto export
+    Comdirect,
+    /// Volksbank, Raiffeisenbank and GLS Bank VR-NetWorld csv
+    Volksbank,
+    /// HypoVereinsbank (UniCredit Germany) online banking csv
+    Hvb,
+    /// norisbank Girokonto export
+    Norisbank,
+    /// Santander Consumer Bank Germany account and BestCard export
+    Santander,
+    /// 1822direkt (Frankfurter Sparkasse) export
+    #[value(name = "1822direkt")]
+    #[serde(rename = "1822direkt")]
+    Direkt1822,
+    /// PSD Bank transaction export
+    Psd,
+    /// Tomorrow csv export
+    Tomorrow,
+    /// C24 Smart account csv export
+    C24,
+    /// Vivid Money transaction export
+    Vivid,
+    /// Triodos Bank Germany csv export
+    Triodos,
+    /// Postbank Visa credit card export
+    PostbankVisa,
+    /// Amazon Visa credit card of LBB and Zinia
+    AmazonVisa,
+    /// Barclays Germany credit card export
+    Barclays,
+    /// Advanzia Gebührenfrei Mastercard statement export
+    Advanzia,
+    /// Hanseatic Bank GenialCard transaction export
+    Hanseatic,
+    /// Miles & More credit card export of the DKB
+    MilesMore,
+    /// American Express Germany activity export
+    Amex,
+    /// TF Bank Mastercard transaction export
+    TfBank,
+    /// PayPal activity export (Alle Transaktionen)
+    Paypal,
+    /// Klarna transaction export
+    Klarna,
+    /// Revolut statement export
+    Revolut,
+    /// Wise (TransferWise) balance statement export
+    Wise,
+    /// bunq csv export
+    Bunq,
+    /// Trade Republic transaction export
+    TradeRepublic,
+    /// Scalable Capital transaction export
+    Scalable,
+    /// flatex and Degiro cash account statement
+    Flatex,
+    /// Interactive Brokers Flex Query cash transactions report
+    Ibkr,
+    /// Coinbase transaction history
+    Coinbase,
+    /// Kraken ledgers
+    Kraken,
+    /// Binance transaction history
+    Binance,
+    /// Bitpanda transaction history
+    Bitpanda,
+    /// George of Erste Bank and the Austrian Sparkassen
+    George,
+    /// ELBA of Raiffeisen in Austria
+    Elba,
+    /// UBS account statements
+    Ubs,
+    /// PostFinance exports
+    Postfinance,
+    /// Zürcher Kantonalbank exports
+    Zkb,
+    /// neon statements
+    Neon,
+    /// Monzo exports
+    Monzo,
+    /// Starling Bank statements
+    Starling,
+    /// Barclays UK current account export
+    BarclaysUk,
+    /// Lloyds, Halifax and Bank of Scotland exports
+    Lloyds,
+    /// HSBC UK downloads
+    Hsbc,
+    /// Nationwide statement downloads
+    Nationwide,
+    /// NatWest, RBS and Ulster Bank exports
+    Natwest,
+    /// Rabobank exports
+    Rabobank,
+    /// ABN AMRO TAB downloads
+    AbnAmro,
+    /// ING Netherlands exports
+    IngNl,
+    /// SWIFT MT940 statements (.sta)
+    Mt940,
+    /// ISO 20022 camt.053 statements (.xml)
+    Camt,
+    /// Tables of text based pdf statements, experimental
+    #[cfg(feature = "pdf")]
+    Pdf,
+    /// Transactions of the GoCardless Bank Account Data API (.json)
+    Gocardless,
+    /// Transactions synced from Plaid (.json)
+    Plaid,
+    /// Transactions of the PayPal Reporting API (.json)
+    PaypalApi,
+}
+
+impl Format {
+    pub fn name(&self) -> String {
+        self.to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    /// The format of `content`, told by the signature of each export.
+    pub fn detect(content: &[u8]) -> Option<Format> {
+        // Enough for the headers, exports of German banks are Windows-1252
+        // but the signatures stick to ascii
+        let head = String::from_utf8_lossy(&content[..content.len().min(4096)]);
+        let json = head
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with('{');
+        let lower = head.to_lowercase();
+        // Some banks quote every field, others none
+        let plain = lower.replace('"', "");
+
+        #[cfg(feature = "pdf")]
+        if head.starts_with("%PDF") {
+            return Some(Format::Pdf);
+        }
+        let format = if head.contains("camt.053") {
+            Format::Camt
+        } else if head.lines().any(|l| l.starts_with(":20:")) {
+            Format::Mt940
+        } else if json && head.contains("\"transaction_details\"") {
+            Format::PaypalApi
+        } else if json && head.contains("\"booked\"") {
+            Format::Gocardless
+        } else if json && head.contains("\"added\"") {
+            Format::Plaid
+        } else if plain.contains("buchungstag;wert;umsatzart;")
+            && plain.contains(";verwendungszweck;iban;bic;")
+        {
+            // Checked before Postbank, as both headers start with
+            // `Buchungstag;Wert;Umsatzart`. Only Norisbank names its account
+            // column plain `IBAN`.
+            Format::Norisbank
+        } else if lower.contains("buchungstag;wert;umsatzart") {
+            Format::Postbank
+        } else if lower.contains("buchungstag;wertstellungstag;verwendungszweck") {
+            // Before TEO, whose header names the other party right away
+            Format::SpardaLegacy
+        } else if lower.contains("buchungstag;wertstellungstag") {
+            Format::Sparda
+        } else if plain.contains("buchungsdatum;wertstellung;status")
+            || plain.contains("buchungstag;wertstellung;buchungstext")
+        {
+            Format::Dkb
+        } else if plain.contains("belegdatum;wertstellung;status;beschreibung")
+            || plain.contains("umsatz abgerechnet und nicht im saldo enthalten")
+        {
+            Format::DkbVisa
+        } else if plain.contains("buchung;valuta;auftraggeber/empf")
+            || plain.contains("buchung;wertstellungsdatum;auftraggeber/empf")
+        {
+            Format::Ing
+        } else if plain.contains("auftragskonto;buchungstag;valutadatum;buchungstext") {
+            Format::Sparkasse
+        } else if plain.contains("buchungstag;wertstellung;umsatzart;buchungstext") {
+            Format::Commerzbank
+        } else if plain.contains("buchungstag;wertstellung (valuta);vorgang") {
+            Format::Comdirect
+        } else if plain.contains(";vwz1;") && plain.contains(";s/h") {
+            Format::Volksbank
+        } else if plain.contains("kontonummer;buchungsdatum;valuta;empf") {
+            Format::Hvb
+        } else if plain.contains("buchungsdatum;valuta;buchungstext;auftraggeber / empf")
+            || plain.contains("kartennummer;belegdatum;buchungsdatum;beschreibung")
+        {
+            Format::Santander
+        } else if plain.contains("kontonummer;buchungstag;wertstellung;auftraggeber/empf") {
+            Format::Direkt1822
+        } else if plain.contains(";verwendungszweck 1;") && plain.contains(";s/h") {
+            Format::Psd
+        } else if lower.contains("date,time,counterparty,iban") {
+            Format::Tomorrow
+        } else if plain.contains("transaktionstyp,buchungsdatum,zahlungsempf") {
+            Format::C24
+        } else if lower.contains("booking date;value date;type;counterparty") {
+            Format::Vivid
+        } else if plain.contains("buchungstag;valutadatum;gesch") {
+            Format::Triodos
+        } else if plain.contains("abrechnungszeitraum;belegdatum;buchungsdatum") {
+            Format::PostbankVisa
+        } else if plain.contains("umsatzdatum;buchungsdatum;beschreibung")
+            || plain.contains("transaktionsdatum;buchungsdatum;h")
+        {
+            Format::AmazonVisa
+        } else if plain.contains("referenznummer;transaktionsdatum;buchungsdatum") {
+            Format::Barclays
+        } else if plain.contains("datum;details;belastung;gutschrift") {
+            Format::Advanzia
+        } else if plain.contains("buchungsdatum;transaktionsdatum;beschreibung;karteninhaber") {
+            Format::Hanseatic
+        } else if plain.contains("belegdatum;buchungsdatum;beschreibung;")
+            && plain.contains("mienmeilen")
+        {
+            Format::MilesMore
+        } else if [
+            "datum,beschreibung,karteninhaber",
+            "date,description,card member",
+            "datum;beschreibung;karteninhaber",
+            "date;description;card member",
+        ]
+        .iter()
+        .any(|h| plain.contains(h))
+        {
+            Format::Amex
+        } else if plain.contains("transaktionsdatum;buchungsdatum;beschreibung;betrag") {
+            Format::TfBank
+        } else if plain.contains("datum,uhrzeit,zeitzone,name,typ,status") {
+            Format::Paypal
+        } else if plain.contains(",typ,betrag,") && plain.contains("bestellnummer") {
+            Format::Klarna
+        } else if lower.contains("type,product,started date,completed date") {
+            Format::Revolut
+        } else if lower.contains("transferwise id,date,amount") {
+            Format::Wise
+        } else if plain.contains("date;interest date;amount;account")
+            || plain.contains("date,interest date,amount,account")
+        {
+            Format::Bunq
+        } else if lower.contains("datetime,date,account_type,category,type") {
+            Format::TradeRepublic
+        } else if lower.contains("date;time;status;reference;description;assettype") {
+            Format::Scalable
+        } else if plain.contains("buchtag;valuta;bic / blz")
+            || lower.contains("datum,uhrzeit,valutadatum,produkt,isin")
+        {
+            Format::Flatex
+        } else if plain.contains("clientaccountid,") && plain.contains(",fxratetobase,") {
+            Format::Ibkr
+        } else if plain.contains("timestamp,transaction type,asset,quantity transacted") {
+            Format::Coinbase
+        } else if plain.contains("txid,refid,time,type") {
+            Format::Kraken
+        } else if plain.contains("user_id,utc_time,account,operation,coin,change") {
+            Format::Binance
+        } else if plain.contains("transaction id,timestamp,transaction type,in/out") {
+            Format::Bitpanda
+        } else if plain.contains("buchungsdatum")
+            && plain.contains("partnername")
+            && plain.contains("buchungs-info")
+        {
+            Format::George
+        } else if elba::is_elba(&head) {
+            Format::Elba
+        } else if plain.contains("abschlussdatum;abschlusszeit;buchungsdatum") {
+            Format::Ubs
+        } else if plain.contains("buchungsdatum;avisierungstext;gutschrift") {
+            Format::Postfinance
+        } else if plain.contains("datum;buchungstext;whg;betrag detail") {
+            Format::Zkb
+        } else if plain.contains("date;amount;original amount;original currency") {
+            Format::Neon
+        } else if plain.contains("transaction id,date,time,type,name") {
+            Format::Monzo
+        } else if plain.contains("date,counter party,reference,type") {
+            Format::Starling
+        } else if plain.contains("number,date,account,amount,subcategory,memo") {
+            Format::BarclaysUk
+        } else if plain.contains("transaction date,transaction type,sort code") {
+            Format::Lloyds
+        } else if hsbc::is_hsbc(&head) {
+            Format::Hsbc
+        } else if plain.contains("account name:") && plain.contains("date,transaction") {
+            Format::Nationwide
+        } else if plain.contains("date,type,description,value,balance") {
+            Format::Natwest
+        } else if plain.contains("iban/bban,munt,bic,volgnr,datum") {
+            Format::Rabobank
+        } else if abn_amro::is_abn_amro(&head) {
+            Format::AbnAmro
+        } else if plain.contains("datum,naam / omschrijving,rekening")
+            || plain.contains("datum;naam / omschrijving;rekening")
+        {
+            Format::IngNl
+        } else {
+            return None;
+        };
+        Some(format)
+    }
+
+    /// The layout of `content` for the formats reading several, to tell them
+    /// apart in the report.
+    pub fn layout(&self, content: &[u8]) -> Option<&'static str> {
+        let head = String::from_utf8_lossy(&content[..content.len().min(4096)]);
+        let plain = head.to_lowercase().replace('"', "");
+
+        let layout = match self {
+            Format::Postbank if plain.contains("buchungstag;wert;umsatzart;buchungsdetails") => {
+                "current"
+            }
+            Format::Postbank => "legacy",
+            Format::Dkb if plain.contains("buchungsdatum;wertstellung;status") => "current",
+            Format::Dkb => "legacy",
+            Format::DkbVisa if plain.contains("umsatz abgerechnet") => "legacy",
+            Format::DkbVisa => "current",
+            Format::AmazonVisa if plain.contains("transaktionsdatum;buchungsdatum;h") => "zinia",
+            Format::AmazonVisa => "lbb",
+            Format::Flatex if plain.contains("datum,uhrzeit,valutadatum") => "degiro",
+            Format::Flatex => "flatex",
+            Format::IngNl if plain.lines().next().is_some_and(|l| l.contains(';')) => "current",
+            Format::IngNl => "legacy",
+            _ => return None,
+        };
+        Some(layout)
+    }
+
+    /// Reads the records of an opened input with `options` for the formats
+    /// taking them.
+    pub fn read_with(&self, input: Box<dyn Read>, options: &InputOptions) -> RecordIterator {
+        match self {
+            Format::Hanseatic => {
+                let input = HanseaticIter::new(input, !options.booked_only);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::TradeRepublic => {
+                let input = TradeRepublicIter::new(input, options.trades());
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Scalable => {
+                let input = ScalableIter::new(input, options.trades());
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Flatex => {
+                let input = FlatexIter::new(input, options.trades());
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Binance => {
+                let input = BinanceIter::new(input, options.trades());
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Bitpanda => {
+                let input = BitpandaIter::new(input, options.trades());
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Neon => {
+                let input = NeonIter::new(input, options.category_map.as_deref());
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Ibkr => {
+                let input = IbkrIter::new(input, options.base_currency.as_deref());
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Postbank => {
+                let input = PostbankIter::new(input);
+                RecordIterator::new(Box::new(input.into_iter()))
+            }
+            Format::Sparda => {
+                let input = TeoIter::new(input);
+                RecordIterator::new(Box::new(input.into_iter()))
+            }
+            Format::Dkb => {
+                let input = DkbIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Mt940 => {
+                let input = Mt940Iter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Camt => {
+                let input = CamtIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Gocardless => {
+                let input = GocardlessIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Plaid => {
+                let input = PlaidIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::PaypalApi => {
+                let input = PaypalApiIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::DkbVisa => {
+                let input = DkbVisaIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Ing => {
+                let input = IngIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Sparkasse => {
+                let input = SparkasseIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Commerzbank => {
+                let input = CommerzbankIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Comdirect => {
+                let input = ComdirectIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Volksbank => {
+                let input = VolksbankIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Hvb => {
+                let input = HvbIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Norisbank => {
+                let input = NorisbankIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Santander => {
+                let input = SantanderIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Direkt1822 => {
+                let input = Direkt1822Iter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Psd => {
+                let input = PsdIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Tomorrow => {
+                let input = TomorrowIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::C24 => {
+                let input = C24Iter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Vivid => {
+                let input = VividIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Triodos => {
+                let input = TriodosIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::PostbankVisa => {
+                let input = PostbankVisaIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::SpardaLegacy => {
+                let input = SpardaLegacyIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::AmazonVisa => {
+                let input = AmazonVisaIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Barclays => {
+                let input = BarclaysIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Advanzia => {
+                let input = AdvanziaIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::MilesMore => {
+                let input = MilesMoreIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Amex => {
+                let input = AmexIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::TfBank => {
+                let input = TfBankIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Paypal => {
+                let input = PaypalIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Klarna => {
+                let input = KlarnaIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Revolut => {
+                let input = RevolutIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Wise => {
+                let input = WiseIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Bunq => {
+                let input = BunqIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Coinbase => {
+                let input = CoinbaseIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Kraken => {
+                let input = KrakenIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::George => {
+                let input = GeorgeIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Elba => {
+                let input = ElbaIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Ubs => {
+                let input = UbsIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Postfinance => {
+                let input = PostfinanceIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Zkb => {
+                let input = ZkbIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Monzo => {
+                let input = MonzoIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Starling => {
+                let input = StarlingIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::BarclaysUk => {
+                let input = BarclaysUkIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Lloyds => {
+                let input = LloydsIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Hsbc => {
+                let input = HsbcIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Nationwide => {
+                let input = NationwideIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Natwest => {
+                let input = NatwestIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::Rabobank => {
+                let input = RabobankIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::AbnAmro => {
+                let input = AbnAmroIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            Format::IngNl => {
+                let input = IngNlIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+            #[cfg(feature = "pdf")]
+            Format::Pdf => {
+                let input = pdf::PdfIter::new(input);
+                RecordIterator::new(Box::new(input))
+            }
+        }
+    }
+
+    /// Reads the records of an opened input.
+    pub fn read(&self, input: Box<dyn Read>) -> RecordIterator {
+        self.read_with(input, &InputOptions::default())
+    }
+
+    /// A synthetic export of `rows` in this format.
+    pub fn sample(&self, rows: &[Row]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Format::Postbank => postbank::sample(rows),
+            Format::Sparda => sparda::sample(rows),
+            Format::Dkb => dkb::sample(rows),
+            Format::Mt940 => mt940::sample(rows),
+            Format::Camt => camt::sample(rows),
+            Format::Gocardless => gocardless::sample(rows),
+            Format::Plaid => plaid::sample(rows),
+            Format::PaypalApi => paypal_api::sample(rows),
+            Format::DkbVisa => dkb_visa::sample(rows),
+            Format::Ing => ing::sample(rows),
+            Format::Sparkasse => sparkasse::sample(rows),
+            Format::Commerzbank => commerzbank::sample(rows),
+            Format::Comdirect => comdirect::sample(rows),
+            Format::Volksbank => volksbank::sample(rows),
+            Format::Hvb => hvb::sample(rows),
+            Format::Norisbank => norisbank::sample(rows),
+            Format::Santander => santander::sample(rows),
+            Format::Direkt1822 => direkt1822::sample(rows),
+            Format::Psd => psd::sample(rows),
+            Format::Tomorrow => tomorrow::sample(rows),
+            Format::C24 => c24::sample(rows),
+            Format::Vivid => vivid::sample(rows),
+            Format::Triodos => triodos::sample(rows),
+            Format::PostbankVisa => postbank_visa::sample(rows),
+            Format::SpardaLegacy => sparda_legacy::sample(rows),
+            Format::AmazonVisa => amazon_visa::sample(rows),
+            Format::Barclays => barclays::sample(rows),
+            Format::Advanzia => advanzia::sample(rows),
+            Format::Hanseatic => hanseatic::sample(rows),
+            Format::MilesMore => miles_more::sample(rows),
+            Format::Amex => amex::sample(rows),
+            Format::TfBank => tfbank::sample(rows),
+            Format::Paypal => paypal::sample(rows),
+            Format::Klarna => klarna::sample(rows),
+            Format::Revolut => revolut::sample(rows),
+            Format::Wise => wise::sample(rows),
+            Format::Bunq => bunq::sample(rows),
+            Format::TradeRepublic => trade_republic::sample(rows),
+            Format::Scalable => scalable::sample(rows),
+            Format::Flatex => flatex::sample(rows),
+            Format::Ibkr => ibkr::sample(rows),
+            Format::Coinbase => coinbase::sample(rows),
+            Format::Kraken => kraken::sample(rows),
+            Format::Binance => binance::sample(rows),
+            Format::Bitpanda => bitpanda::sample(rows),
+            Format::George => george::sample(rows),
+            Format::Elba => elba::sample(rows),
+            Format::Ubs => ubs::sample(rows),
+            Format::Postfinance => postfinance::sample(rows),
+            Format::Zkb => zkb::sample(rows),
+            Format::Neon => neon::sample(rows),
+            Format::Monzo => monzo::sample(rows),
+            Format::Starling => starling::sample(rows),
+            Format::BarclaysUk => barclays_uk::sample(rows),
+            Format::Lloyds => lloyds::sample(rows),
+            Format::Hsbc => hsbc::sample(rows),
+            Format::Nationwide => nationwide::sample(rows),
+            Format::Natwest => natwest::sample(rows),
+            Format::Rabobank => rabobank::sample(rows),
+            Format::AbnAmro => abn_amro::sample(rows),
+            Format::IngNl => ing_nl::sample(rows),
+            #[cfg(feature = "pdf")]
+            Format::Pdf => return Err(miette!("Samples of pdf statements are not supported")),
+        })
+    }
+}
+
+/// Options changing what some formats read.
+#[derive(Debug, Clone, Default, clap::Args, Deserialize, Serialize)]
+pub struct InputOptions {
+    /// Leave out card authorizations not booked yet, for inputs listing them
+    /// separately (hanseatic)
+    #[arg(long, env)]
+    pub booked_only: bool,
+    /// Read the securities trades of broker exports too (trade-republic,
+    /// scalable, flatex), the crypto trades of bitpanda or daily summaries
+    /// of them (binance)
+    #[arg(long, env)]
+    pub include_trades: bool,
+    /// Category of the securities trades read with `--include-trades`
+    #[arg(long, env, requires = "include_trades")]
+    pub trade_category: Option<String>,
+    /// Convert amounts into this base currency of the account with the
+    /// exchange rate the broker reports (ibkr)
+    #[arg(long, env)]
+    pub base_currency: Option<String>,
+    /// Toml file mapping the categories of the export to HomeBank ones, over
+    /// the built-in table (neon)
+    #[arg(long, env)]
+    pub category_map: Option<PathBuf>,
+}
+
+impl InputOptions {
+    /// The category of trades if they are read.
+    fn trades(&self) -> Option<&str> {
+        self.include_trades
+            .then(|| self.trade_category.as_deref().unwrap_or_default())
+    }
+}
+
+/// A file of the input, with the format it is read as.
+pub struct Input {
+    pub name: String,
+    pub format: Format,
+    /// Layout of the file for formats reading several
+    pub layout: Option<&'static str>,
+    pub records: RecordIterator,
+}
+
+impl Input {
+    /// The format of the file with its layout, like `postbank/legacy`.
+    pub fn format_name(&self) -> String {
+        match self.layout {
+            Some(layout) => format!("{}/{}", self.format.name(), layout),
+            None => self.format.name(),
+        }
+    }
+
+    /// The account of the file, its name without directory and extensions.
+    pub fn account(&self) -> String {
+        let inner = compressed::inner_name(gpg::inner_name(&self.name));
+        Path::new(inner)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// The content of the input as stored, downloading urls.
+pub fn read(path: &Path, downloads: &[Download]) -> Result<Vec<u8>> {
+    match url::url(path) {
+        Some(url) => url::download(url, downloads),
+        None => fs::read(path)
+            .into_diagnostic()
+            .wrap_err("Failed opening input file"),
+    }
+}
+
+/// Opens all files of the input `content` read from `path`, the files of zip
+/// archives matching `inner`. Encrypted and compressed files are decrypted
+/// and decompressed first. Without a `format` it is detected for each file.
+pub fn open(
+    path: &Path,
+    content: Vec<u8>,
+    format: Option<&Format>,
+    sheet: Option<&str>,
+    inner: Option<&str>,
+    decrypt: bool,
+    options: &InputOptions,
+) -> Result<Vec<Input>> {
+    let name = path.display().to_string();
+    let content = match decrypt {
+        true => gpg::decrypt(&name, content)?,
+        false => content,
+    };
+    let files = match zipped::is_zip(Path::new(gpg::inner_name(&name))) {
+        true => zipped::entries(content, inner)
+            .wrap_err_with(|| format!("Failed reading zip archive {}", name))?,
+        false => vec![(name, content)],
+    };
+
+    files
+        .into_iter()
+        .map(|(name, content)| {
+            let content = compressed::decompress(content)
+                .wrap_err_with(|| format!("Failed reading {}", name))?;
+            let inner_name = compressed::inner_name(gpg::inner_name(&name));
+            let content = match xlsx::is_spreadsheet(Path::new(inner_name)) {
+                true => xlsx::to_csv(content, &name, sheet)?,
+                false => content,
+            };
+            let format = match format {
+                Some(format) => format.clone(),
+                None => Format::detect(&content).ok_or_else(|| {
+                    miette!(
+                        help = "Pick the format with --format",
+                        "Failed detecting the format of {}",
+                        name
+                    )
+                })?,
+            };
+            let layout = format.layout(&content);
+            let records = format.read_with(Box::new(Cursor::new(content)), options);
+            Ok(Input {
+                name,
+                format,
+                layout,
+                records,
+            })
+        })
+        .collect()
+}
+
+pub type RecordIteratorRes = Result<Record>;
+
+pub struct RecordIterator {
+    inner: Box<dyn Iterator<Item = RecordIteratorRes>>,
+}
+
+impl RecordIterator {
+    pub fn new(inner: Box<dyn Iterator<Item = RecordIteratorRes>>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Iterator for RecordIterator {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let detected = |content: &str| Format::detect(content.as_bytes()).map(|f| f.name());
+
+        assert_eq!(
+            detected("Umsätze Girokonto;Zeitraum: 30 Tage\nBuchungstag;Wert;Umsatzart;Begünstigter / Auftraggeber\n"),
+            Some("postbank".to_string())
+        );
+        assert_eq!(
+            detected(":20:STARTUMS\n:25:12030000/1234567890\n"),
+            Some("mt940".to_string())
+        );
+        assert_eq!(
+            detected("{\"transactions\": {\"booked\": []}}"),
+            Some("gocardless".to_string())
+        );
+        assert_eq!(detected("Date,Amount\n"), None);
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = |format: Format, content: &str| format.layout(content.as_bytes());
+
+        assert_eq!(
+            layout(
+                Format::Postbank,
+                "Buchungstag;Wert;Umsatzart;Begünstigter / Auftraggeber\n"
+            ),
+            Some("legacy")
+        );
+        assert_eq!(
+            layout(
+                Format::Postbank,
+                "Buchungstag;Wert;Umsatzart;Buchungsdetails\n"
+            ),
+            Some("current")
+        );
+        assert_eq!(
+            layout(
+                Format::Dkb,
+                "\"Buchungsdatum\";\"Wertstellung\";\"Status\"\n"
+            ),
+            Some("current")
+        );
+        assert_eq!(
+            layout(Format::Sparda, "Buchungstag;Wertstellungstag\n"),
+            None
+        );
+    }
+}
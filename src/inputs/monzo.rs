@@ -0,0 +1,189 @@
+//! The csv export of Monzo, comma separated with `DD/MM/YYYY` dates and
+//! decimal points.
+//!
+//! Monzo's own categories are kept as they are, notes make the memo and
+//! the `#tags` in them become tags. The transaction id goes into the info,
+//! which tells apart otherwise identical transactions when deduplicating.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Transaction ID,Date,Time,Type,Name";
+
+#[derive(Debug, Deserialize)]
+struct MonzoIR {
+    #[serde(rename = "Transaction ID")]
+    id: String,
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Category", default)]
+    category: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Currency")]
+    currency: String,
+    #[serde(rename = "Local amount", default)]
+    local_amount: String,
+    #[serde(rename = "Local currency", default)]
+    local_currency: String,
+    #[serde(rename = "Notes and #tags", default)]
+    notes: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+}
+
+impl TryFrom<MonzoIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: MonzoIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        let amount = decimal(&value.amount, '.')?;
+        let payment = match value.kind.to_lowercase().as_str() {
+            "card payment" => Payment::DebitCard,
+            "direct debit" => Payment::DirectDebit,
+            "standing order" => Payment::StandingOrder,
+            "pot transfer" => Payment::InternalTransfer,
+            "faster payment" | "bacs (direct credit)" | "monzo-to-monzo" => Payment::BankTransfer,
+            "atm" => Payment::Cash,
+            _ => Payment::None,
+        };
+        let (tags, words): (Vec<_>, Vec<_>) = value
+            .notes
+            .split_whitespace()
+            .partition(|w| w.len() > 1 && w.starts_with('#'));
+        let mut memo = match words.is_empty() {
+            true => value.description,
+            false => words.join(" "),
+        };
+        // Payments abroad note what they were in the other currency
+        if !value.local_currency.is_empty() && value.local_currency != value.currency {
+            memo = format!("{} {} {}", memo, value.local_amount, value.local_currency)
+                .trim()
+                .to_string();
+        }
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%d/%m/%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            info: value.id,
+            payee: value.name,
+            memo,
+            amount: Money::from_decimal(amount, currency),
+            category: value.category,
+            tags: tags
+                .into_iter()
+                .map(|t| t.trim_start_matches('#').to_lowercase())
+                .collect(),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct MonzoIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl MonzoIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, MonzoIR>(text.as_bytes(), b',', HEADER),
+                "monzo",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for MonzoIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` in GBP.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Transaction ID,Date,Time,Type,Name,Emoji,Category,Amount,Currency,Local amount,\
+        Local currency,Notes and #tags,Address,Receipt,Description,Category split,Money Out,\
+        Money In\n",
+    );
+    for (i, row) in rows.iter().enumerate() {
+        let kind = match row.kind {
+            Kind::Card => "Card payment",
+            Kind::DirectDebit => "Direct Debit",
+            Kind::StandingOrder => "Standing order",
+            _ => "Faster payment",
+        };
+        out.push_str(&format!(
+            "tx_{:016},{},10:00:00,{},{},,General,{},GBP,{},GBP,,,,{},,,\n",
+            i,
+            row.date.format("%d/%m/%Y"),
+            kind,
+            row.payee,
+            row.amount,
+            row.amount,
+            row.purpose
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::GBP;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Transaction ID,Date,Time,Type,Name,Emoji,Category,Amount,Currency,Local amount,Local currency,Notes and #tags,Address,Receipt,Description,Category split,Money Out,Money In\n\
+            tx_0001,07/03/2024,18:30:12,Card payment,Tesco,🛒,Groceries,-25.88,GBP,-25.88,GBP,Weekly shop #family,1 High St,,TESCO STORES 1234,,-25.88,\n\
+            tx_0002,08/03/2024,09:00:00,Card payment,Café Paris,☕,Eating out,-8.60,GBP,-10.00,EUR,,,,CAFE PARIS,,-8.60,\n\
+            tx_0003,25/03/2024,06:00:00,Faster payment,Employer Ltd,,Income,2500.00,GBP,2500.00,GBP,,,,SALARY MARCH,,,2500.00\n";
+
+        let records: Vec<Record> = MonzoIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].info, "tx_0001");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].category, "Groceries");
+        assert_eq!(records[0].memo, "Weekly shop");
+        assert_eq!(records[0].tags, vec!["family"]);
+        assert_eq!(records[0].amount, Money::from_str("-25.88", GBP).unwrap());
+        assert_eq!(records[1].memo, "CAFE PARIS -10.00 EUR");
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+    }
+}
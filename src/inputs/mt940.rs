@@ -0,0 +1,323 @@
+//! SWIFT MT940 statements, the `.sta` files of German banks and banking
+//! programs.
+//!
+//! Every `:61:` statement line becomes a record, completed by the `:86:`
+//! details following it. The currency is taken from the opening balance.
+
+use std::{
+    io::{BufRead, BufReader, Lines, Read},
+    iter::Peekable,
+    sync::OnceLock,
+};
+
+use chrono::{Datelike, NaiveDate};
+use encoding_rs::WINDOWS_1252;
+use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use regex::Regex;
+use rust_decimal::Decimal;
+use rusty_money::{
+    iso::{self, Currency, EUR},
+    Money,
+};
+use tracing::trace;
+
+use super::{
+    sepa::{Details, Purpose},
+    util::decimal_de,
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::Record,
+    sample::{Kind, Row},
+};
+
+#[derive(Debug)]
+struct Mt940 {
+    _valuta: NaiveDate,
+    buchungstag: NaiveDate,
+    betrag: Money<'static, Currency>,
+    kundenreferenz: String,
+    details: Details,
+}
+
+#[derive(Debug)]
+struct Mt940IR {
+    statement_line: String,
+    details: Option<String>,
+    currency: &'static Currency,
+}
+
+fn statement_line() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        // value date, entry date, mark, funds code, amount, type, reference
+        Regex::new(r"^(\d{6})(\d{4})?(R?[CD])[A-Z]?(\d+,\d*)[NFS][A-Z0-9]{3}([^/\n]*)").unwrap()
+    })
+}
+
+impl TryFrom<Mt940IR> for Mt940 {
+    type Error = Report;
+
+    fn try_from(value: Mt940IR) -> Result<Self> {
+        let caps = statement_line()
+            .captures(&value.statement_line)
+            .ok_or_else(|| miette!("Invalid statement line '{}'", value.statement_line))?;
+
+        let valuta = NaiveDate::parse_from_str(&caps[1], "%y%m%d")
+            .into_diagnostic()
+            .wrap_err("Failed converting value date into datetime")?;
+        let buchungstag = match caps.get(2) {
+            Some(entry) => {
+                let (month, day) = entry.as_str().split_at(2);
+                let (month, day): (u32, u32) = (month.parse().unwrap(), day.parse().unwrap());
+                // Booked around the turn of the year
+                let year = match (valuta.month(), month) {
+                    (1, 12) => valuta.year() - 1,
+                    (12, 1) => valuta.year() + 1,
+                    _ => valuta.year(),
+                };
+                NaiveDate::from_ymd_opt(year, month, day)
+                    .ok_or_else(|| miette!("Invalid entry date {}", entry.as_str()))?
+            }
+            None => valuta,
+        };
+
+        let mut betrag = decimal_de(&caps[4])?;
+        // Debits and reversed credits take money from the account
+        if matches!(&caps[3], "D" | "RC") {
+            betrag = -betrag;
+        }
+
+        Ok(Self {
+            _valuta: valuta,
+            buchungstag,
+            betrag: Money::from_decimal(betrag, value.currency),
+            kundenreferenz: caps[5].trim().to_string(),
+            details: value
+                .details
+                .as_deref()
+                .map(Details::parse)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl From<Mt940> for Record {
+    fn from(val: Mt940) -> Self {
+        let purpose = Purpose::parse(&val.details.purpose);
+        let info = match purpose.end_to_end {
+            Some(reference) => reference,
+            None if val.kundenreferenz != "NONREF" => val.kundenreferenz,
+            None => String::new(),
+        };
+        let memo = match purpose.text.is_empty() {
+            true => val.details.booking_text.clone(),
+            false => purpose.text,
+        };
+
+        Self {
+            date: val.buchungstag,
+            payment: val.details.payment(),
+            info,
+            payee: val.details.name.clone(),
+            memo,
+            amount: val.betrag,
+            iban: val.details.iban,
+            ..Default::default()
+        }
+    }
+}
+
+type DecodedLines<R> = Peekable<Lines<BufReader<DecodeReaderBytes<R, Vec<u8>>>>>;
+
+pub struct Mt940Iter<R: Read> {
+    lines: DecodedLines<R>,
+    /// A field read ahead while looking for `:86:`
+    peeked: Option<(String, String)>,
+    currency: &'static Currency,
+}
+
+impl<R: Read> Mt940Iter<R> {
+    pub fn new(rdr: R) -> Self {
+        // Most banks write ISO 8859-1, a BOM still switches to UTF-8
+        let decoder = DecodeReaderBytesBuilder::new()
+            .encoding(Some(WINDOWS_1252))
+            .build(rdr);
+
+        Self {
+            lines: BufReader::new(decoder).lines().peekable(),
+            peeked: None,
+            currency: EUR,
+        }
+    }
+
+    /// The next `:tag:` with its content, continuation lines included.
+    fn field(&mut self) -> Option<Result<(String, String)>> {
+        if let Some(field) = self.peeked.take() {
+            return Some(Ok(field));
+        }
+
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e).into_diagnostic().wrap_err("Failed reading line")),
+            };
+            let Some((tag, content)) = line.strip_prefix(':').and_then(|rest| rest.split_once(':'))
+            else {
+                // Headers, trailers and the `-` ending a statement
+                continue;
+            };
+
+            let (tag, mut content) = (tag.to_string(), content.trim_end().to_string());
+            while let Some(Ok(next)) = self.lines.peek() {
+                if next.starts_with(':') || next.trim() == "-" {
+                    break;
+                }
+                content.push('\n');
+                content.push_str(next.trim_end());
+                self.lines.next();
+            }
+            return Some(Ok((tag, content)));
+        }
+    }
+}
+
+impl<R: Read> Iterator for Mt940Iter<R> {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (tag, content) = match self.field()? {
+                Ok(field) => field,
+                Err(e) => return Some(Err(e)),
+            };
+
+            match tag.as_str() {
+                "60F" | "60M" => {
+                    let code = content.get(7..10).unwrap_or_default();
+                    match iso::find(code) {
+                        Some(currency) => self.currency = currency,
+                        None => return Some(Err(miette!("Unknown currency '{}'", code))),
+                    }
+                }
+                "61" => {
+                    let details = match self.field() {
+                        Some(Ok((tag, content))) if tag == "86" => Some(content),
+                        Some(Ok(field)) => {
+                            self.peeked = Some(field);
+                            None
+                        }
+                        Some(Err(e)) => return Some(Err(e)),
+                        None => None,
+                    };
+                    let ir = Mt940IR {
+                        statement_line: content,
+                        details,
+                        currency: self.currency,
+                    };
+                    trace!(?ir, "Read mt940 statement line");
+
+                    return Some(
+                        Mt940::try_from(ir)
+                            .map(Record::from)
+                            .wrap_err("Failed converting statement line"),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A sample statement of `rows`, in ISO 8859-1 like most banks write.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let from = rows.first().map(|r| r.date).unwrap_or_default();
+    let to = rows.last().map(|r| r.date).unwrap_or_default();
+    let opening = Decimal::new(100000, 2);
+    let closing = opening + rows.iter().map(|r| r.amount).sum::<Decimal>();
+    let balance = |tag: &str, date: NaiveDate, amount: Decimal| {
+        let mark = if amount.is_sign_negative() { 'D' } else { 'C' };
+        format!(
+            ":{}:{}{}EUR{}\r\n",
+            tag,
+            mark,
+            date.format("%y%m%d"),
+            amount.abs().to_string().replace('.', ",")
+        )
+    };
+
+    let mut out = String::from(":20:STARTUMSE\r\n:25:37040044/0532013000\r\n:28C:00001/001\r\n");
+    out.push_str(&balance("60F", from, opening));
+    for row in rows {
+        let (mark, code) = match (row.amount.is_sign_negative(), row.kind) {
+            (_, Kind::DirectDebit) => ('D', "NDDT"),
+            (true, _) => ('D', "NTRF"),
+            (false, _) => ('C', "NTRF"),
+        };
+        out.push_str(&format!(
+            ":61:{}{}{}{}{}NONREF\r\n",
+            row.date.format("%y%m%d"),
+            row.date.format("%m%d"),
+            mark,
+            row.amount.abs().to_string().replace('.', ","),
+            code
+        ));
+        out.push_str(&format!(
+            ":86:{}?00{}?20EREF+{}\r\n?21SVWZ+{}",
+            row.kind.gvc(),
+            row.kind.booking_text(),
+            row.reference,
+            row.purpose
+        ));
+        if !row.iban.is_empty() {
+            out.push_str(&format!("\r\n?30COBADEFFXXX?31{}", row.iban));
+        }
+        out.push_str(&format!("\r\n?32{}\r\n", row.payee));
+    }
+    out.push_str(&balance("62F", to, closing));
+    out.push_str("-\r\n");
+    WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = ":20:STARTUMSE\r\n:25:12030000/1234567890\r\n:28C:00001/001\r\n:60F:C231229EUR1000,00\r\n\
+            :61:2401021229D25,88NDDTNONREF\r\n\
+            :86:105?00SEPA-BASISLASTSCHRIFT?20EREF+4711?21SVWZ+Strom Dezem\r\nber?31DE02120300000000202051?32Stadtwerke\r\n\
+            :61:240103C100,NTRFABC123//XYZ\r\n\
+            :86:Bargeld\r\n:62F:C240103EUR1074,12\r\n-\r\n";
+
+        let records: Vec<Record> = Mt940Iter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2023, 12, 29).unwrap()
+        );
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom Dezember");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].iban, "DE02120300000000202051");
+
+        assert_eq!(
+            records[1].date,
+            NaiveDate::from_ymd_opt(2024, 1, 3).unwrap()
+        );
+        assert_eq!(records[1].amount, Money::from_str("100", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::None);
+        assert_eq!(records[1].memo, "Bargeld");
+        assert_eq!(records[1].info, "ABC123");
+    }
+}
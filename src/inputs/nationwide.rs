@@ -0,0 +1,180 @@
+//! The statement download of Nationwide Building Society, comma separated
+//! and quoted with dates like `07 Mar 2024`.
+//!
+//! A few lines of account name and balances come before the header. Paid
+//! out and paid in have columns of their own, with amounts like `£1,234.56`
+//! whose pound sign is dropped. Older downloads are in Windows-1252.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso::GBP, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Date,Transaction";
+
+#[derive(Debug, Deserialize)]
+struct NationwideIR {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Transaction type", alias = "Transactions", default)]
+    kind: String,
+    #[serde(rename = "Description", alias = "Location", default)]
+    description: String,
+    #[serde(rename = "Paid out", default)]
+    paid_out: String,
+    #[serde(rename = "Paid in", default)]
+    paid_in: String,
+}
+
+fn payment(kind: &str) -> Payment {
+    let lower = kind.to_lowercase();
+    if lower.contains("direct debit") {
+        Payment::DirectDebit
+    } else if lower.contains("standing order") {
+        Payment::StandingOrder
+    } else if lower.contains("contactless") || lower.contains("visa") || lower.contains("card") {
+        Payment::DebitCard
+    } else if lower.contains("cash") || lower.contains("atm") {
+        Payment::Cash
+    } else if lower.contains("payment to")
+        || lower.contains("transfer")
+        || lower.contains("bank credit")
+    {
+        Payment::BankTransfer
+    } else if lower.contains("interest") || lower.contains("fee") {
+        Payment::FinancialInstitutionFee
+    } else {
+        Payment::None
+    }
+}
+
+impl TryFrom<NationwideIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: NationwideIR) -> Result<Self> {
+        let amount = decimal(&value.paid_in, '.')? - decimal(&value.paid_out, '.')?.abs();
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%d %b %Y")
+                .or_else(|_| NaiveDate::parse_from_str(&value.date, "%d/%m/%Y"))
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.kind),
+            payee: value.description,
+            memo: value.kind,
+            amount: Money::from_decimal(amount, GBP),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct NationwideIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl NationwideIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, NationwideIR>(text.as_bytes(), b',', HEADER),
+                "nationwide",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for NationwideIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample download of `rows`, in Windows-1252 like the older ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Account Name:\",\"FlexAccount ****12345\"\n\
+        \"Account Balance:\",\"£5,000.00\"\n\
+        \"Available Balance: \",\"£5,000.00\"\n\
+        \n\
+        \"Date\",\"Transaction type\",\"Description\",\"Paid out\",\"Paid in\",\"Balance\"\n",
+    );
+    let mut balance = Decimal::new(500000, 2);
+    for row in rows {
+        balance += row.amount;
+        let kind = match row.kind {
+            Kind::Card => "Contactless Payment",
+            Kind::DirectDebit => "Direct debit",
+            Kind::StandingOrder => "Standing order",
+            Kind::Cash => "Cash withdrawal",
+            Kind::Transfer => "Payment to",
+            Kind::Salary => "Bank credit",
+        };
+        let (paid_out, paid_in) = match row.amount.is_sign_negative() {
+            true => (format!("£{}", row.amount.abs()), String::new()),
+            false => (String::new(), format!("£{}", row.amount)),
+        };
+        out.push_str(&format!(
+            "\"{}\",\"{}\",\"{}\",\"{}\",\"{}\",\"£{}\"\n",
+            row.date.format("%d %b %Y"),
+            kind,
+            row.payee,
+            paid_out,
+            paid_in,
+            balance
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Account Name:\",\"FlexAccount ****12345\"\n\
+            \"Account Balance:\",\"£7,432.12\"\n\
+            \"Available Balance: \",\"£7,432.12\"\n\
+            \n\
+            \"Date\",\"Transaction type\",\"Description\",\"Paid out\",\"Paid in\",\"Balance\"\n\
+            \"07 Mar 2024\",\"Contactless Payment\",\"TESCO STORES 1234\",\"£25.88\",\"\",\"£4,974.12\"\n\
+            \"08 Mar 2024\",\"Direct debit\",\"THAMES WATER\",\"£42.00\",\"\",\"£4,932.12\"\n\
+            \"25 Mar 2024\",\"Bank credit\",\"EMPLOYER LTD\",\"\",\"£2,500.00\",\"£7,432.12\"\n";
+
+        let records: Vec<Record> = NationwideIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "TESCO STORES 1234");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25.88", GBP).unwrap());
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+        assert_eq!(records[2].amount, Money::from_str("2500.00", GBP).unwrap());
+    }
+}
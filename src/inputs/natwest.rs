@@ -0,0 +1,167 @@
+//! The transaction export shared by NatWest, Royal Bank of Scotland and
+//! Ulster Bank. Comma separated with dates like `07 Mar 2024` and signed
+//! amounts in GBP with decimal points.
+//!
+//! The type is a short code like `POS`, `DD` or `BAC`. Descriptions may
+//! start with an apostrophe, which is dropped, and are quoted when they
+//! contain commas.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso::GBP, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Date,Type,Description,Value";
+
+#[derive(Debug, Deserialize)]
+struct NatwestIR {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Type", default)]
+    kind: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+    #[serde(rename = "Value")]
+    value: String,
+}
+
+/// The payment method of the type code.
+fn payment(code: &str) -> Payment {
+    match code.trim().to_uppercase().as_str() {
+        "POS" | "DEB" => Payment::DebitCard,
+        "DD" | "D/D" => Payment::DirectDebit,
+        "SO" | "S/O" => Payment::StandingOrder,
+        "BAC" | "DPC" | "OTR" | "ITR" => Payment::BankTransfer,
+        "TFR" => Payment::InternalTransfer,
+        "C/L" | "CSH" => Payment::Cash,
+        "CHQ" => Payment::Check,
+        "CDM" => Payment::Deposit,
+        "CHG" | "INT" => Payment::FinancialInstitutionFee,
+        _ => Payment::None,
+    }
+}
+
+impl TryFrom<NatwestIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: NatwestIR) -> Result<Self> {
+        let amount = decimal(&value.value, '.')?;
+        // The description often reads `'PAYEE , REFERENCE`
+        let description = value.description.trim_start_matches('\'');
+        let (payee, memo) = match description.split_once(" , ") {
+            Some((payee, memo)) => (payee.trim(), memo.trim()),
+            None => (description.trim(), ""),
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%d %b %Y")
+                .or_else(|_| NaiveDate::parse_from_str(&value.date, "%d/%m/%Y"))
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.kind),
+            payee: payee.to_string(),
+            memo: memo.to_string(),
+            amount: Money::from_decimal(amount, GBP),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct NatwestIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl NatwestIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, NatwestIR>(text.as_bytes(), b',', HEADER),
+                "natwest",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for NatwestIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` in GBP.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from("Date,Type,Description,Value,Balance,Account Name,Account Number\n");
+    let mut balance = Decimal::new(500000, 2);
+    for row in rows {
+        balance += row.amount;
+        let code = match row.kind {
+            Kind::Card => "POS",
+            Kind::DirectDebit => "D/D",
+            Kind::StandingOrder => "S/O",
+            Kind::Cash => "C/L",
+            Kind::Transfer => "DPC",
+            Kind::Salary => "BAC",
+        };
+        out.push_str(&format!(
+            "{},{},\"'{} , {}\",{},{},\"'SMITH J\",'600000-12345678\n",
+            row.date.format("%d %b %Y"),
+            code,
+            row.payee,
+            row.purpose,
+            row.amount,
+            balance
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Date,Type,Description,Value,Balance,Account Name,Account Number\n\
+            07 Mar 2024,POS,\"'1234 06MAR24 TESCO STORES 1234 , LONDON GB\",-25.88,4974.12,'SMITH J,'600000-12345678\n\
+            08 Mar 2024,D/D,'THAMES WATER,-42.00,4932.12,'SMITH J,'600000-12345678\n\
+            25 Mar 2024,BAC,\"'EMPLOYER LTD , SALARY MARCH\",2500.00,7432.12,'SMITH J,'600000-12345678\n";
+
+        let records: Vec<Record> = NatwestIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "1234 06MAR24 TESCO STORES 1234");
+        assert_eq!(records[0].memo, "LONDON GB");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25.88", GBP).unwrap());
+        assert_eq!(records[1].payee, "THAMES WATER");
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+        assert_eq!(records[2].amount, Money::from_str("2500.00", GBP).unwrap());
+    }
+}
@@ -0,0 +1,217 @@
+//! The statement export of neon, `;` separated with ISO dates and decimal
+//! points.
+//!
+//! Every row carries the category neon sorted it into. It is mapped to a
+//! HomeBank category by a built-in table, which a toml file given with
+//! `--category-map` extends or overrides:
+//!
+//! ```toml
+//! groceries = "Food:Supermarket"
+//! sport = "Leisure:Sport"
+//! ```
+//!
+//! Categories mapped to an empty string are left empty, ones missing from
+//! both are passed through as they are.
+
+use std::{collections::BTreeMap, io::Read, path::Path, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::CHF, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    config,
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Date;Amount;Original amount";
+
+/// The categories of neon with the HomeBank categories they are mapped to
+/// by default.
+const CATEGORIES: [(&str, &str); 16] = [
+    ("groceries", "Food:Groceries"),
+    ("restaurants", "Food:Restaurants"),
+    ("shopping", "Shopping"),
+    ("transport", "Transportation"),
+    ("travel", "Vacation"),
+    ("leisure", "Leisure"),
+    ("health", "Health"),
+    ("household", "Home"),
+    ("utilities", "Bills:Utilities"),
+    ("insurance", "Insurance"),
+    ("education", "Education"),
+    ("finances", "Bank Charges"),
+    ("cash", "Cash"),
+    ("income", "Income"),
+    ("other", ""),
+    ("uncategorized", ""),
+];
+
+#[derive(Debug, Deserialize)]
+struct NeonIR {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Original amount", default)]
+    original_amount: String,
+    #[serde(rename = "Original currency", default)]
+    original_currency: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+    #[serde(rename = "Subject", default)]
+    subject: String,
+    #[serde(rename = "Category", default)]
+    category: String,
+}
+
+impl TryFrom<NeonIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: NeonIR) -> Result<Self> {
+        let amount = decimal(&value.amount, '.')?;
+        // Payments abroad note what they were in the other currency
+        let memo = match value.original_currency.as_str() {
+            "" | "CHF" => value.subject,
+            currency => format!("{} {} {}", value.subject, value.original_amount, currency)
+                .trim()
+                .to_string(),
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: Payment::None,
+            payee: value.description,
+            memo,
+            amount: Money::from_decimal(amount, CHF),
+            category: value.category,
+            ..Default::default()
+        })
+    }
+}
+
+/// The built-in category table, extended by the toml file at `path`.
+fn categories(path: Option<&Path>) -> Result<BTreeMap<String, String>> {
+    let mut categories: BTreeMap<String, String> = CATEGORIES
+        .iter()
+        .map(|(neon, homebank)| (neon.to_string(), homebank.to_string()))
+        .collect();
+    if let Some(path) = path {
+        let file: BTreeMap<String, String> = config::read_toml(path)?;
+        categories.extend(file.into_iter().map(|(k, v)| (k.to_lowercase(), v)));
+    }
+    Ok(categories)
+}
+
+pub struct NeonIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl NeonIter {
+    /// Reads the statement, mapping categories with the built-in table and
+    /// the file at `category_map`.
+    pub fn new<R: Read>(rdr: R, category_map: Option<&Path>) -> Self {
+        let records = match (decode(rdr), categories(category_map)) {
+            (Ok(text), Ok(categories)) => {
+                let mut rows = csv_rows::<_, NeonIR>(text.as_bytes(), b';', HEADER);
+                for ir in rows.iter_mut().flatten() {
+                    if let Some(category) = categories.get(&ir.category.to_lowercase()) {
+                        ir.category.clone_from(category);
+                    }
+                }
+                convert(rows, "neon")
+            }
+            (Err(e), _) | (_, Err(e)) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for NeonIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample statement of `rows` in CHF.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Date\";\"Amount\";\"Original amount\";\"Original currency\";\"Exchange rate\";\
+        \"Description\";\"Subject\";\"Category\";\"Tags\";\"Wise\";\"Spaces\"\n",
+    );
+    for row in rows {
+        let category = match row.amount.is_sign_negative() {
+            true => "shopping",
+            false => "income",
+        };
+        out.push_str(&format!(
+            "\"{}\";\"{}\";\"\";\"\";\"\";\"{}\";\"{}\";\"{}\";\"\";\"no\";\"no\"\n",
+            row.date.format("%Y-%m-%d"),
+            row.amount,
+            row.payee,
+            row.purpose,
+            category
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Date\";\"Amount\";\"Original amount\";\"Original currency\";\"Exchange rate\";\"Description\";\"Subject\";\"Category\";\"Tags\";\"Wise\";\"Spaces\"\n\
+            \"2024-03-07\";\"-25.80\";\"\";\"\";\"\";\"Coop\";\"\";\"groceries\";\"\";\"no\";\"no\"\n\
+            \"2024-03-08\";\"-43.12\";\"-45.00\";\"EUR\";\"0.958\";\"Ristorante Roma\";\"\";\"restaurants\";\"\";\"no\";\"no\"\n\
+            \"2024-03-25\";\"6500.00\";\"\";\"\";\"\";\"Arbeitgeber AG\";\"Lohn März\";\"income\";\"\";\"no\";\"no\"\n\
+            \"2024-03-26\";\"-12.00\";\"\";\"\";\"\";\"Kino Arena\";\"\";\"cinema\";\"\";\"no\";\"no\"\n";
+
+        let records: Vec<Record> = NeonIter::new(input.as_bytes(), None)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Coop");
+        assert_eq!(records[0].category, "Food:Groceries");
+        assert_eq!(records[0].amount, Money::from_str("-25.80", CHF).unwrap());
+        assert_eq!(records[1].memo, "-45.00 EUR");
+        assert_eq!(records[2].category, "Income");
+        assert_eq!(records[3].category, "cinema");
+
+        let path = std::env::temp_dir().join(format!("hbconv-neon-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "Groceries = \"Food:Supermarket\"\ncinema = \"Leisure:Cinema\"\n",
+        )
+        .unwrap();
+        let records: Vec<Record> = NeonIter::new(input.as_bytes(), Some(&path))
+            .collect::<Result<_>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(records[0].category, "Food:Supermarket");
+        assert_eq!(records[1].category, "Food:Restaurants");
+        assert_eq!(records[3].category, "Leisure:Cinema");
+    }
+}
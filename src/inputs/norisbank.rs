@@ -0,0 +1,177 @@
+//! Girokonto exports of the norisbank, in Windows-1252.
+//!
+//! The layout is close to the Deutsche Bank one read as `postbank`, but
+//! with an `IBAN` column and separate `Soll` and `Haben` columns instead of
+//! a signed amount. The closing `Kontostand` line is left out.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+const HEADER: &str = "Buchungstag;Wert;Umsatzart";
+
+#[derive(Debug, Deserialize)]
+struct NorisbankIR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Umsatzart")]
+    umsatzart: String,
+    #[serde(rename = "Begünstigter / Auftraggeber")]
+    begünstigter: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "IBAN")]
+    iban: String,
+    #[serde(rename = "Kundenreferenz")]
+    kundenreferenz: String,
+    #[serde(rename = "Soll")]
+    soll: String,
+    #[serde(rename = "Haben")]
+    haben: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+}
+
+impl TryFrom<NorisbankIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: NorisbankIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        let amount = match value.soll.is_empty() {
+            true => decimal_de(&value.haben)?,
+            // Debits are mostly written negative, but not always
+            false => -decimal_de(&value.soll)?.abs(),
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungstag into datetime")?,
+            payment: booking_payment(&value.umsatzart),
+            info: value.kundenreferenz,
+            payee: value.begünstigter,
+            memo: value.verwendungszweck,
+            amount: Money::from_decimal(amount, currency),
+            iban: value.iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct NorisbankIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl NorisbankIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                // The closing balance has too few columns to be read as a row
+                let text: String = text
+                    .split_inclusive('\n')
+                    .filter(|line| !line.starts_with("Kontostand;"))
+                    .collect();
+                convert(
+                    csv_rows::<_, NorisbankIR>(text.as_bytes(), b';', HEADER),
+                    "norisbank",
+                )
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for NorisbankIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let from = rows.first().map(|r| r.date).unwrap_or_default();
+    let to = rows.last().map(|r| r.date).unwrap_or_default();
+    let mut out = format!(
+        "Umsätze Girokonto;Zeitraum: {} - {};\nNeuester Kontostand;;;;1.000,00;EUR\n\
+        Buchungstag;Wert;Umsatzart;Begünstigter / Auftraggeber;Verwendungszweck;IBAN;BIC;\
+        Kundenreferenz;Mandatsreferenz;Gläubiger ID;Soll;Haben;Währung\n",
+        from.format("%d.%m.%Y"),
+        to.format("%d.%m.%Y")
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let (soll, haben) = match row.amount.is_sign_negative() {
+            true => (row.amount_de(), String::new()),
+            false => (String::new(), row.amount_de()),
+        };
+        out.push_str(&format!(
+            "{};{};{};{};{};{};NORSDE51XXX;{};;;{};{};EUR\n",
+            date,
+            date,
+            row.kind.booking_text(),
+            row.payee,
+            row.purpose,
+            row.iban,
+            row.reference,
+            soll,
+            haben
+        ));
+    }
+    out.push_str(&format!(
+        "Kontostand;{};;;1.000,00;EUR\n",
+        to.format("%d.%m.%Y")
+    ));
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Umsätze Girokonto;Zeitraum: 01.03.2024 - 08.03.2024;\nNeuester Kontostand;;;;1.234,56;EUR\n\
+            Buchungstag;Wert;Umsatzart;Begünstigter / Auftraggeber;Verwendungszweck;IBAN;BIC;Kundenreferenz;Mandatsreferenz;Gläubiger ID;Soll;Haben;Währung\n\
+            07.03.2024;07.03.2024;SEPA-Lastschrift;Stadtwerke;Strom März;DE02120300000000202051;BYLADEM1001;4711;M-1;DE98ZZZ09999999999;-1.025,88;;EUR\n\
+            06.03.2024;06.03.2024;SEPA-Überweisung;Erika Mustermann;Geschenk;DE02120300000000202051;BYLADEM1001;;;;;20,00;EUR\n\
+            Kontostand;08.03.2024;;;1.234,56;EUR\n";
+
+        let records: Vec<Record> = NorisbankIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+        assert_eq!(records[1].amount, Money::from_str("20", EUR).unwrap());
+    }
+}
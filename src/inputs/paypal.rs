@@ -0,0 +1,245 @@
+//! The "Alle Transaktionen" activity export of PayPal, comma separated with
+//! German headers and amounts.
+//!
+//! Like with the Reporting API, payments in another currency take the
+//! amount of their conversion leg in the account currency, the legs
+//! referring to them by `Zugehöriger Transaktionscode` are left out. Fees
+//! become a split line of their payment. Only completed rows changing the
+//! balance are read, leaving out pending payments and holds.
+
+use std::{collections::HashMap, io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use rust_decimal::Decimal;
+use rusty_money::{
+    iso::{self, Currency},
+    Money,
+};
+use serde::Deserialize;
+use tracing::trace;
+
+use super::{
+    util::{csv_rows, decimal_de, decode, with_fee},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Datum,Uhrzeit,Zeitzone";
+const COMPLETED: &str = "Abgeschlossen";
+/// Rows of holds and pending authorizations, not changing the balance
+const NO_IMPACT: &str = "Memo";
+
+#[derive(Debug, Deserialize)]
+struct PaypalIR {
+    #[serde(rename = "Datum")]
+    datum: String,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Typ")]
+    typ: String,
+    #[serde(rename = "Status")]
+    status: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+    #[serde(rename = "Brutto")]
+    brutto: String,
+    #[serde(rename = "Gebühr", default)]
+    gebühr: String,
+    #[serde(rename = "Absender E-Mail-Adresse", default)]
+    absender: String,
+    #[serde(rename = "Empfänger E-Mail-Adresse", default)]
+    empfänger: String,
+    #[serde(rename = "Transaktionscode")]
+    transaktionscode: String,
+    #[serde(rename = "Artikelbezeichnung", default)]
+    artikelbezeichnung: String,
+    #[serde(rename = "Zugehöriger Transaktionscode", default)]
+    zugehöriger_transaktionscode: String,
+    #[serde(rename = "Betreff", default)]
+    betreff: String,
+    #[serde(rename = "Hinweis", default)]
+    hinweis: String,
+    #[serde(rename = "Auswirkung auf Guthaben", default)]
+    auswirkung: String,
+}
+
+fn money(value: &str, currency: &str) -> Result<Money<'static, Currency>> {
+    let currency = iso::find(currency).ok_or_else(|| miette!("Unknown currency '{}'", currency))?;
+    let value = match value.is_empty() {
+        true => Decimal::ZERO,
+        false => decimal_de(value)?,
+    };
+    Ok(Money::from_decimal(value, currency))
+}
+
+impl PaypalIR {
+    fn is_conversion(&self) -> bool {
+        self.typ.contains("Währungsumrechnung")
+    }
+
+    /// The record, `conversion` being the leg in the other currency.
+    fn record(self, conversion: Option<&PaypalIR>) -> Result<Record> {
+        let gross = money(&self.brutto, &self.währung)?;
+        let memo = [&self.artikelbezeichnung, &self.betreff, &self.hinweis]
+            .into_iter()
+            .find(|m| !m.is_empty())
+            .cloned()
+            .unwrap_or_default();
+        let email = match gross.is_negative() {
+            true => self.empfänger,
+            false => self.absender,
+        };
+        let payee = match self.name.is_empty() {
+            true => email,
+            false => self.name,
+        };
+
+        let fee = money(&self.gebühr, &self.währung)?;
+        let (amount, splits, memo) = match conversion {
+            Some(leg) => {
+                let memo = format!("{} ({})", memo, gross).trim().to_string();
+                (money(&leg.brutto, &leg.währung)?, Vec::new(), memo)
+            }
+            None if !fee.is_zero() => {
+                let (total, splits) = with_fee(gross, fee, &memo, "PayPal");
+                (total, splits, memo)
+            }
+            None => (gross, Vec::new(), memo),
+        };
+
+        Ok(Record {
+            date: NaiveDate::parse_from_str(&self.datum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting datum into datetime")?,
+            payment: Payment::None,
+            info: self.transaktionscode,
+            payee,
+            memo,
+            amount,
+            splits,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct PaypalIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl PaypalIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let rows = match decode(rdr) {
+            Ok(text) => csv_rows::<_, PaypalIR>(text.as_bytes(), b',', HEADER),
+            Err(e) => vec![Err(e)],
+        };
+
+        let mut legs: HashMap<String, Vec<PaypalIR>> = HashMap::new();
+        let mut payments = Vec::new();
+        for row in rows {
+            match row {
+                Ok(ir) if ir.status != COMPLETED || ir.auswirkung == NO_IMPACT => {}
+                Ok(ir) if ir.is_conversion() => {
+                    legs.entry(ir.zugehöriger_transaktionscode.clone())
+                        .or_default()
+                        .push(ir);
+                }
+                row => payments.push(row),
+            }
+        }
+
+        let records = payments
+            .into_iter()
+            .map(|payment| {
+                let payment = payment?;
+                trace!(ir = ?payment, bank = "paypal", "Read row");
+                let leg = legs
+                    .get(&payment.transaktionscode)
+                    .and_then(|legs| legs.iter().find(|l| l.währung != payment.währung));
+                payment.record(leg).wrap_err("Failed converting record")
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for PaypalIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\u{feff}\"Datum\",\"Uhrzeit\",\"Zeitzone\",\"Name\",\"Typ\",\"Status\",\"Währung\",\
+        \"Brutto\",\"Gebühr\",\"Netto\",\"Absender E-Mail-Adresse\",\"Empfänger E-Mail-Adresse\",\
+        \"Transaktionscode\",\"Artikelbezeichnung\",\"Zugehöriger Transaktionscode\",\
+        \"Guthaben\",\"Betreff\",\"Hinweis\",\"Auswirkung auf Guthaben\"\n",
+    );
+    for row in rows {
+        let (typ, impact) = match row.amount.is_sign_negative() {
+            true => ("Handyzahlung", "Soll"),
+            false => ("Zahlung erhalten", "Haben"),
+        };
+        out.push_str(&format!(
+            "\"{}\",\"10:00:00\",\"Europe/Berlin\",\"{}\",\"{}\",\"Abgeschlossen\",\"EUR\",\
+            \"{}\",\"0,00\",\"{}\",\"\",\"\",\"{}\",\"{}\",\"\",\"0,00\",\"\",\"\",\"{}\"\n",
+            row.date.format("%d.%m.%Y"),
+            row.payee,
+            typ,
+            row.amount_de(),
+            row.amount_de(),
+            row.reference,
+            row.purpose,
+            impact
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Datum\",\"Uhrzeit\",\"Zeitzone\",\"Name\",\"Typ\",\"Status\",\"Währung\",\"Brutto\",\"Gebühr\",\"Netto\",\"Absender E-Mail-Adresse\",\"Empfänger E-Mail-Adresse\",\"Transaktionscode\",\"Artikelbezeichnung\",\"Zugehöriger Transaktionscode\",\"Guthaben\",\"Betreff\",\"Hinweis\",\"Auswirkung auf Guthaben\"\n\
+            \"07.03.2024\",\"10:22:33\",\"Europe/Berlin\",\"Max Mustermann\",\"Zahlung erhalten\",\"Abgeschlossen\",\"EUR\",\"50,00\",\"-1,60\",\"48,40\",\"max@example.com\",\"me@example.com\",\"1AB\",\"Old bike\",\"\",\"48,40\",\"\",\"\",\"Haben\"\n\
+            \"08.03.2024\",\"08:00:00\",\"Europe/Berlin\",\"Woopsie Inc\",\"Express-Zahlung\",\"Abgeschlossen\",\"USD\",\"-10,00\",\"0,00\",\"-10,00\",\"me@example.com\",\"shop@example.com\",\"2CD\",\"Ebook\",\"\",\"-10,00\",\"\",\"\",\"Soll\"\n\
+            \"08.03.2024\",\"08:00:00\",\"Europe/Berlin\",\"\",\"Allgemeine Währungsumrechnung\",\"Abgeschlossen\",\"EUR\",\"-9,31\",\"0,00\",\"-9,31\",\"me@example.com\",\"\",\"3EF\",\"\",\"2CD\",\"39,09\",\"\",\"\",\"Soll\"\n\
+            \"08.03.2024\",\"08:00:00\",\"Europe/Berlin\",\"\",\"Allgemeine Währungsumrechnung\",\"Abgeschlossen\",\"USD\",\"10,00\",\"0,00\",\"10,00\",\"\",\"me@example.com\",\"4GH\",\"\",\"2CD\",\"0,00\",\"\",\"\",\"Haben\"\n\
+            \"09.03.2024\",\"08:00:00\",\"Europe/Berlin\",\"Hotel\",\"Kontoeinbehalt für offene Autorisierung\",\"Abgeschlossen\",\"EUR\",\"-20,00\",\"0,00\",\"-20,00\",\"\",\"\",\"5IJ\",\"\",\"\",\"39,09\",\"\",\"\",\"Memo\"\n\
+            \"09.03.2024\",\"08:00:00\",\"Europe/Berlin\",\"Woopsie\",\"Handyzahlung\",\"Ausstehend\",\"EUR\",\"-5,00\",\"0,00\",\"-5,00\",\"\",\"\",\"6KL\",\"\",\"\",\"39,09\",\"\",\"\",\"Soll\"\n";
+
+        let records: Vec<Record> = PaypalIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Max Mustermann");
+        assert_eq!(records[0].info, "1AB");
+        assert_eq!(records[0].amount, Money::from_str("48,40", EUR).unwrap());
+        assert_eq!(records[0].splits.len(), 2);
+        assert_eq!(records[0].splits[1].category, "Fees:PayPal");
+
+        assert_eq!(records[1].payee, "Woopsie Inc");
+        assert_eq!(records[1].amount, Money::from_str("-9,31", EUR).unwrap());
+        assert_eq!(records[1].memo, "Ebook (-$10.00)");
+    }
+}
@@ -0,0 +1,303 @@
+//! Transactions of the PayPal Reporting API, as saved by `hbconv fetch
+//! --provider paypal`.
+//!
+//! Payments in another currency come with conversion legs referring to
+//! them, the payment then gets the amount of the leg in the other currency
+//! and the legs are left out. Fees become a split line of their payment.
+//! Only completed transactions are read.
+
+use std::{collections::HashMap, io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use rusty_money::{
+    iso::{self, Currency},
+    Money,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::trace;
+
+use super::{
+    util::{decimal, with_fee},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+#[derive(Debug, Deserialize)]
+struct Report {
+    transaction_details: Vec<PaypalIR>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Amount {
+    currency_code: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Info {
+    transaction_id: String,
+    paypal_reference_id: Option<String>,
+    transaction_event_code: String,
+    transaction_initiation_date: String,
+    transaction_amount: Amount,
+    fee_amount: Option<Amount>,
+    transaction_status: String,
+    transaction_subject: Option<String>,
+    transaction_note: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Name {
+    alternate_full_name: Option<String>,
+    given_name: Option<String>,
+    surname: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Payer {
+    email_address: Option<String>,
+    payer_name: Name,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaypalIR {
+    transaction_info: Info,
+    #[serde(default)]
+    payer_info: Payer,
+}
+
+fn money(amount: &Amount) -> Result<Money<'static, Currency>> {
+    let currency = iso::find(&amount.currency_code)
+        .ok_or_else(|| miette!("Unknown currency '{}'", amount.currency_code))?;
+    Ok(Money::from_decimal(decimal(&amount.value, '.')?, currency))
+}
+
+impl PaypalIR {
+    fn is_conversion(&self) -> bool {
+        self.transaction_info
+            .transaction_event_code
+            .starts_with("T02")
+    }
+
+    /// The record, `conversion` being the leg in the other currency.
+    fn record(self, conversion: Option<&Amount>) -> Result<Record> {
+        let info = self.transaction_info;
+        let date = info
+            .transaction_initiation_date
+            .get(..10)
+            .unwrap_or(&info.transaction_initiation_date);
+        let gross = money(&info.transaction_amount)?;
+        let memo = info
+            .transaction_subject
+            .or(info.transaction_note)
+            .unwrap_or_default();
+
+        let name = self.payer_info.payer_name;
+        let full_name = match (name.given_name, name.surname) {
+            (Some(given), Some(surname)) => Some(format!("{} {}", given, surname)),
+            (given, surname) => given.or(surname),
+        };
+        let payee = name
+            .alternate_full_name
+            .or(full_name)
+            .or(self.payer_info.email_address)
+            .unwrap_or_default();
+
+        let fee = info.fee_amount.as_ref().map(money).transpose()?;
+        let (amount, splits, memo) = match (conversion, fee) {
+            (Some(leg), _) => {
+                let memo = format!("{} ({})", memo, gross).trim().to_string();
+                (money(leg)?, Vec::new(), memo)
+            }
+            (None, Some(fee)) if !fee.is_zero() => {
+                let (total, splits) = with_fee(gross, fee, &memo, "PayPal");
+                (total, splits, memo)
+            }
+            (None, _) => (gross, Vec::new(), memo),
+        };
+
+        Ok(Record {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: Payment::None,
+            info: info.transaction_id,
+            payee,
+            memo,
+            amount,
+            splits,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct PaypalApiIter {
+    transactions: vec::IntoIter<Result<(PaypalIR, Option<Amount>)>>,
+}
+
+impl PaypalApiIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let report = match serde_json::from_reader::<_, Report>(rdr) {
+            Ok(report) => report,
+            Err(e) => {
+                let e = Err(e)
+                    .into_diagnostic()
+                    .wrap_err("Failed deserializing transactions");
+                return Self {
+                    transactions: vec![e].into_iter(),
+                };
+            }
+        };
+
+        let (conversions, payments): (Vec<_>, Vec<_>) = report
+            .transaction_details
+            .into_iter()
+            .filter(|t| t.transaction_info.transaction_status == "S")
+            .partition(PaypalIR::is_conversion);
+
+        let mut legs: HashMap<String, Vec<Amount>> = HashMap::new();
+        for conversion in conversions {
+            if let Some(reference) = conversion.transaction_info.paypal_reference_id {
+                legs.entry(reference)
+                    .or_default()
+                    .push(conversion.transaction_info.transaction_amount);
+            }
+        }
+
+        let transactions = payments
+            .into_iter()
+            .map(|payment| {
+                let info = &payment.transaction_info;
+                let leg = legs.get(&info.transaction_id).and_then(|legs| {
+                    legs.iter()
+                        .find(|l| l.currency_code != info.transaction_amount.currency_code)
+                        .cloned()
+                });
+                Ok((payment, leg))
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            transactions: transactions.into_iter(),
+        }
+    }
+}
+
+impl Iterator for PaypalApiIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (ir, conversion) = match self.transactions.next()? {
+            Ok(transaction) => transaction,
+            Err(e) => return Some(Err(e)),
+        };
+        trace!(?ir, "Read paypal transaction");
+
+        Some(
+            ir.record(conversion.as_ref())
+                .wrap_err("Failed converting transaction"),
+        )
+    }
+}
+
+/// A sample report of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let details: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "transaction_info": {
+                    "transaction_id": row.reference,
+                    "transaction_event_code": "T0006",
+                    "transaction_initiation_date": row.date.format("%Y-%m-%dT10:00:00+0000").to_string(),
+                    "transaction_amount": {"currency_code": "EUR", "value": row.amount.to_string()},
+                    "transaction_status": "S",
+                    "transaction_subject": row.purpose,
+                },
+                "payer_info": {"payer_name": {"alternate_full_name": row.payee}},
+            })
+        })
+        .collect();
+    let report = json!({"transaction_details": details, "total_pages": 1, "page": 1});
+    serde_json::to_vec_pretty(&report).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = r#"{"transaction_details": [
+            {
+                "transaction_info": {
+                    "transaction_id": "1AB", "transaction_event_code": "T0006",
+                    "transaction_initiation_date": "2024-03-07T10:22:33+0000",
+                    "transaction_amount": {"currency_code": "EUR", "value": "50.00"},
+                    "fee_amount": {"currency_code": "EUR", "value": "-1.60"},
+                    "transaction_status": "S", "transaction_subject": "Old bike"
+                },
+                "payer_info": {"payer_name": {"given_name": "Max", "surname": "Mustermann"}}
+            },
+            {
+                "transaction_info": {
+                    "transaction_id": "2CD", "transaction_event_code": "T0006",
+                    "transaction_initiation_date": "2024-03-08T08:00:00+0000",
+                    "transaction_amount": {"currency_code": "USD", "value": "-10.00"},
+                    "transaction_status": "S", "transaction_subject": "Ebook"
+                },
+                "payer_info": {"payer_name": {"alternate_full_name": "Woopsie Inc"}}
+            },
+            {
+                "transaction_info": {
+                    "transaction_id": "3EF", "paypal_reference_id": "2CD",
+                    "transaction_event_code": "T0200",
+                    "transaction_initiation_date": "2024-03-08T08:00:00+0000",
+                    "transaction_amount": {"currency_code": "USD", "value": "10.00"},
+                    "transaction_status": "S"
+                }
+            },
+            {
+                "transaction_info": {
+                    "transaction_id": "4GH", "paypal_reference_id": "2CD",
+                    "transaction_event_code": "T0200",
+                    "transaction_initiation_date": "2024-03-08T08:00:00+0000",
+                    "transaction_amount": {"currency_code": "EUR", "value": "-9.31"},
+                    "transaction_status": "S"
+                }
+            },
+            {
+                "transaction_info": {
+                    "transaction_id": "5IJ", "transaction_event_code": "T0006",
+                    "transaction_initiation_date": "2024-03-09T08:00:00+0000",
+                    "transaction_amount": {"currency_code": "EUR", "value": "-5.00"},
+                    "transaction_status": "P"
+                }
+            }
+        ]}"#;
+
+        let records: Vec<Record> = PaypalApiIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].payee, "Max Mustermann");
+        assert_eq!(records[0].amount, Money::from_str("48,40", EUR).unwrap());
+        assert_eq!(records[0].splits.len(), 2);
+        assert_eq!(records[0].splits[1].category, "Fees:PayPal");
+
+        assert_eq!(records[1].payee, "Woopsie Inc");
+        assert_eq!(records[1].amount, Money::from_str("-9,31", EUR).unwrap());
+        assert_eq!(records[1].memo, "Ebook (-$10.00)");
+    }
+}
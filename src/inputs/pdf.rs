@@ -0,0 +1,178 @@
+//! Transactions from the table of text based pdf statements, as credit card
+//! providers like Advanzia send them. Experimental, behind the `pdf` feature.
+//!
+//! Every line starting with a date and ending in an amount is a
+//! transaction, an optional second date being the booking date. Amounts
+//! without sign are charges, credits carry a `+` or `H`.
+
+use std::{io::Read, sync::OnceLock, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use regex::Regex;
+use rusty_money::{
+    iso::{Currency, EUR},
+    Money,
+};
+use tracing::trace;
+
+use super::{util::decimal_de, RecordIteratorRes};
+use crate::homebank::{Payment, Record};
+
+#[derive(Debug)]
+struct Pdf {
+    datum: NaiveDate,
+    haendler: String,
+    betrag: Money<'static, Currency>,
+}
+
+#[derive(Debug)]
+struct PdfIR {
+    datum: String,
+    haendler: String,
+    betrag: String,
+    vorzeichen: String,
+}
+
+fn table_row() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"^\s*(\d{2}\.\d{2}\.\d{2,4})\s+(?:\d{2}\.\d{2}\.\d{2,4}\s+)?(.+?)\s+([+-]?)\s?(\d{1,3}(?:\.\d{3})*,\d{2})\s?([+-]|S|H)?\s*$",
+        )
+        .unwrap()
+    })
+}
+
+impl TryFrom<PdfIR> for Pdf {
+    type Error = Report;
+
+    fn try_from(value: PdfIR) -> Result<Self> {
+        let format = match value.datum.len() {
+            10 => "%d.%m.%Y",
+            _ => "%d.%m.%y",
+        };
+        let mut betrag = decimal_de(&value.betrag)?;
+        if !matches!(value.vorzeichen.as_str(), "+" | "H") {
+            betrag = -betrag;
+        }
+
+        Ok(Self {
+            datum: NaiveDate::parse_from_str(&value.datum, format)
+                .into_diagnostic()
+                .wrap_err("Failed converting datum into datetime")?,
+            haendler: value.haendler,
+            betrag: Money::from_decimal(betrag, EUR),
+        })
+    }
+}
+
+impl From<Pdf> for Record {
+    fn from(val: Pdf) -> Self {
+        Self {
+            date: val.datum,
+            // The statement does not tell, the profile fills in a default
+            payment: Payment::None,
+            payee: val.haendler,
+            amount: val.betrag,
+            ..Default::default()
+        }
+    }
+}
+
+fn rows(text: &str) -> Vec<PdfIR> {
+    text.lines()
+        .filter_map(|line| table_row().captures(line))
+        .map(|caps| {
+            let leading = &caps[3];
+            let trailing = caps.get(5).map_or("", |m| m.as_str());
+            PdfIR {
+                datum: caps[1].to_string(),
+                haendler: caps[2].trim().to_string(),
+                betrag: caps[4].to_string(),
+                vorzeichen: match leading.is_empty() {
+                    true => trailing.to_string(),
+                    false => leading.to_string(),
+                },
+            }
+        })
+        .collect()
+}
+
+pub struct PdfIter {
+    rows: vec::IntoIter<Result<PdfIR>>,
+}
+
+impl PdfIter {
+    /// Reads the whole statement, pdf text can't be extracted page by page
+    /// from a stream.
+    pub fn new<R: Read>(mut rdr: R) -> Self {
+        let mut buffer = Vec::new();
+        let text = rdr
+            .read_to_end(&mut buffer)
+            .into_diagnostic()
+            .wrap_err("Failed reading pdf")
+            .and_then(|_| {
+                pdf_extract::extract_text_from_mem(&buffer)
+                    .map_err(|e| miette!("Failed extracting pdf text: {}", e))
+            });
+
+        let rows = match text {
+            Ok(text) => rows(&text).into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        };
+        Self {
+            rows: rows.into_iter(),
+        }
+    }
+}
+
+impl Iterator for PdfIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ir = match self.rows.next()? {
+            Ok(ir) => ir,
+            Err(e) => return Some(Err(e)),
+        };
+        trace!(?ir, "Read pdf row");
+
+        Some(
+            Pdf::try_from(ir)
+                .map(Record::from)
+                .wrap_err("Failed converting pdf row"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_rows() {
+        let text = "Kreditkartenabrechnung\n\
+            Datum Buchung Händler Betrag\n\
+            07.03.2024 08.03.2024 AMAZON.DE*AB12C 25,88\n\
+            09.03.24   Zahlung, danke 1.000,00 +\n\
+            Saldo neu 974,12\n";
+
+        let records: Vec<Record> = rows(text)
+            .into_iter()
+            .map(|ir| Pdf::try_from(ir).map(Record::from))
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "AMAZON.DE*AB12C");
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payee, "Zahlung, danke");
+        assert_eq!(records[1].amount, Money::from_str("1000", EUR).unwrap());
+    }
+}
@@ -0,0 +1,211 @@
+//! Transactions synced from Plaid, as saved by `hbconv fetch --provider
+//! plaid`.
+//!
+//! Plaid counts money leaving the account as positive, the sign is flipped.
+//! Pending transactions are left out, they are added again once posted.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{
+    iso::{self, Currency},
+    Money,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::trace;
+
+use super::RecordIteratorRes;
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+#[derive(Debug)]
+struct Plaid {
+    date: NaiveDate,
+    amount: Money<'static, Currency>,
+    transaction_id: String,
+    payee: String,
+    name: String,
+    category: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sync {
+    added: Vec<PlaidIR>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Category {
+    primary: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaidIR {
+    transaction_id: String,
+    date: String,
+    amount: Decimal,
+    iso_currency_code: Option<String>,
+    unofficial_currency_code: Option<String>,
+    name: Option<String>,
+    merchant_name: Option<String>,
+    #[serde(default)]
+    pending: bool,
+    personal_finance_category: Option<Category>,
+}
+
+impl TryFrom<PlaidIR> for Plaid {
+    type Error = Report;
+
+    fn try_from(value: PlaidIR) -> Result<Self> {
+        let code = value
+            .iso_currency_code
+            .or(value.unofficial_currency_code)
+            .unwrap_or_default();
+        let currency = iso::find(&code).ok_or_else(|| miette!("Unknown currency '{}'", code))?;
+        let name = value.name.unwrap_or_default();
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            amount: Money::from_decimal(-value.amount, currency),
+            transaction_id: value.transaction_id,
+            payee: value.merchant_name.unwrap_or_else(|| name.clone()),
+            name,
+            category: value
+                .personal_finance_category
+                .map(|c| c.primary)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl From<Plaid> for Record {
+    fn from(val: Plaid) -> Self {
+        Self {
+            date: val.date,
+            payment: Payment::None,
+            info: val.transaction_id,
+            payee: val.payee,
+            memo: val.name,
+            amount: val.amount,
+            // Plaid's category, as in `FOOD_AND_DRINK`, is a tag so rules
+            // can pick it up without clashing with HomeBank categories
+            tags: match val.category.is_empty() {
+                true => Vec::new(),
+                false => vec![val.category.to_lowercase()],
+            },
+            ..Default::default()
+        }
+    }
+}
+
+pub struct PlaidIter {
+    transactions: vec::IntoIter<Result<PlaidIR>>,
+}
+
+impl PlaidIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let transactions = match serde_json::from_reader::<_, Sync>(rdr) {
+            Ok(sync) => sync
+                .added
+                .into_iter()
+                .filter(|t| !t.pending)
+                .map(Ok)
+                .collect(),
+            Err(e) => vec![Err(e)
+                .into_diagnostic()
+                .wrap_err("Failed deserializing transactions")],
+        };
+
+        Self {
+            transactions: transactions.into_iter(),
+        }
+    }
+}
+
+impl Iterator for PlaidIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ir = match self.transactions.next()? {
+            Ok(ir) => ir,
+            Err(e) => return Some(Err(e)),
+        };
+        trace!(?ir, "Read plaid transaction");
+
+        Some(
+            Plaid::try_from(ir)
+                .map(Record::from)
+                .wrap_err("Failed converting transaction"),
+        )
+    }
+}
+
+/// A sample sync of `rows`, in US dollars.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let added: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "transaction_id": row.reference,
+                "account_id": "BxBXxLj1m4HMXBm9WZZmCWVbPjX16EHwv99vp",
+                "date": row.date.format("%Y-%m-%d").to_string(),
+                // Plaid counts money leaving the account as positive
+                "amount": f64::try_from(-row.amount).unwrap_or_default(),
+                "iso_currency_code": "USD",
+                "name": row.purpose,
+                "merchant_name": row.payee,
+                "pending": false,
+            })
+        })
+        .collect();
+    let sync = json!({"added": added, "modified": [], "removed": [], "has_more": false});
+    serde_json::to_vec_pretty(&sync).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::USD;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = r#"{"added": [
+            {
+                "transaction_id": "lPNjeW1nR6CDn5okmGQ6hEpMo4lLNoSrzqDje",
+                "account_id": "BxBXxLj1m4HMXBm9WZZmCWVbPjX16EHwv99vp",
+                "date": "2024-03-07",
+                "amount": 25.88,
+                "iso_currency_code": "USD",
+                "name": "SQ *WOOPSIE COFFEE",
+                "merchant_name": "Woopsie Coffee",
+                "pending": false,
+                "personal_finance_category": {"primary": "FOOD_AND_DRINK", "detailed": "FOOD_AND_DRINK_COFFEE"}
+            },
+            {
+                "transaction_id": "pending",
+                "date": "2024-03-08",
+                "amount": 10,
+                "iso_currency_code": "USD",
+                "pending": true
+            }
+        ]}"#;
+
+        let records: Vec<Record> = PlaidIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].amount, Money::from_str("-25.88", USD).unwrap());
+        assert_eq!(records[0].payee, "Woopsie Coffee");
+        assert_eq!(records[0].memo, "SQ *WOOPSIE COFFEE");
+        assert_eq!(records[0].tags, vec!["food_and_drink".to_string()]);
+    }
+}
@@ -1,20 +1,29 @@
 use chrono::NaiveDate;
 use csv::{DeserializeRecordsIntoIter, ReaderBuilder};
 use miette::{Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
 use rusty_money::{
     iso::{Currency, EUR},
     Money,
 };
 use serde::Deserialize;
-use std::{io::Read, iter::Skip};
+use std::{io::Read, iter::Skip, vec};
+use tracing::trace;
 
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
 use crate::{
     homebank::{Payment, Record},
-    RecordIteratorRes,
+    sample::Row,
 };
 
 use super::util::{SkipLast, SkipLastIterator};
 
+const CURRENT_HEADER: &str = "Buchungstag;Wert;Umsatzart;Buchungsdetails";
+
 #[derive(Debug)]
 pub struct Postbank {
     buchungstag: NaiveDate,
@@ -22,7 +31,7 @@ pub struct Postbank {
     _umsatzart: String,
     auftraggeber: String,
     verwendungszweck: String,
-    _iban: String,
+    iban: String,
     _bic: String,
     kundenreferenz: String,
     _mandatsreferenz: String,
@@ -59,12 +68,83 @@ struct PostbankIR {
     _währung: String,
 }
 
-pub struct PostbankIter<R: Read> {
+/// A row of the layout since the 2023 migration.
+#[derive(Debug, Deserialize)]
+struct CurrentIR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Umsatzart")]
+    umsatzart: String,
+    #[serde(rename = "Buchungsdetails")]
+    buchungsdetails: String,
+    #[serde(rename = "Auftraggeber")]
+    auftraggeber: String,
+    #[serde(rename = "Empfänger")]
+    empfänger: String,
+    #[serde(rename = "Betrag (€)")]
+    betrag: String,
+}
+
+impl TryFrom<CurrentIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: CurrentIR) -> Result<Self> {
+        // Both parties are named, the other one depends on the direction
+        let payee = match value.betrag.trim_start().starts_with('-') {
+            true => value.empfänger,
+            false => value.auftraggeber,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungstag into datetime")?,
+            payment: booking_payment(&value.umsatzart),
+            payee,
+            memo: value.buchungsdetails,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, EUR),
+            ..Default::default()
+        })
+    }
+}
+
+/// Reads the legacy layout and the one since the 2023 migration, told
+/// apart by their header row.
+pub struct PostbankIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl PostbankIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) if text.contains(CURRENT_HEADER) => convert(
+                csv_rows::<_, CurrentIR>(text.as_bytes(), b';', CURRENT_HEADER),
+                "postbank",
+            ),
+            Ok(text) => LegacyIter::new(text.as_bytes()).collect(),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for PostbankIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+struct LegacyIter<R: Read> {
     deser: SkipLastIterator<Skip<DeserializeRecordsIntoIter<R, PostbankIR>>>,
 }
 
-impl<R: Read> PostbankIter<R> {
-    pub fn new(rdr: R) -> Self {
+impl<R: Read> LegacyIter<R> {
+    fn new(rdr: R) -> Self {
         let rdr = ReaderBuilder::new()
             .delimiter(b';')
             .has_headers(false)
@@ -83,13 +163,14 @@ impl<R: Read> PostbankIter<R> {
     }
 }
 
-impl<R: Read> Iterator for PostbankIter<R> {
+impl<R: Read> Iterator for LegacyIter<R> {
     type Item = RecordIteratorRes;
 
     fn next(&mut self) -> Option<Self::Item> {
         let next = self
             .deser
             .next()?
+            .inspect(|ir| trace!(?ir, "Read postbank row"))
             .map(Postbank::try_from)
             .into_diagnostic()
             .wrap_err("Failed deserializing record");
@@ -115,7 +196,7 @@ impl TryFrom<PostbankIR> for Postbank {
             _umsatzart: value._umsatzart,
             auftraggeber: value.auftraggeber,
             verwendungszweck: value.verwendungszweck,
-            _iban: value._iban,
+            iban: value._iban,
             _bic: value._bic,
             kundenreferenz: value.kundenreferenz,
             _mandatsreferenz: value._mandatsreferenz,
@@ -138,17 +219,55 @@ impl From<Postbank> for Record {
     fn from(val: Postbank) -> Self {
         Self {
             date: val.buchungstag,
-            payment: Payment::ElectronicPayment,
+            payment: Payment::None,
             info: val.kundenreferenz,
             payee: val.auftraggeber,
             memo: val.verwendungszweck,
             amount: val.betrag,
-            category: String::new(),
-            tags: Vec::new(),
+            iban: val.iban,
+            ..Default::default()
         }
     }
 }
 
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let balance: Decimal = Decimal::new(100000, 2) + rows.iter().map(|r| r.amount).sum::<Decimal>();
+    let mut out = String::from(
+        "Umsätze Girokonto;Zeitraum: 90 Tage\nKontoinhaber;Max Mustermann\n\
+        Kontonummer;1234567890\nIBAN;DE89370400440532013000\n\nKontostand;1000,00 €\n\
+        Vorgemerkte und noch nicht gebuchte Umsätze sind nicht Bestandteil dieser Übersicht.\n\
+        Buchungstag;Wert;Umsatzart;Begünstigter / Auftraggeber;Verwendungszweck;\
+        IBAN / Kontonummer;BIC;Kundenreferenz;Mandatsreferenz ;Gläubiger ID;Fremde Gebühren;\
+        Betrag;Abweichender Empfänger;Anzahl der Aufträge;Anzahl der Schecks;Soll;Haben;Währung\n",
+    );
+    for row in rows {
+        let date = row.date.format("%-d.%-m.%Y");
+        let (soll, haben) = match row.amount.is_sign_negative() {
+            true => (row.amount_de(), String::new()),
+            false => (String::new(), row.amount_de()),
+        };
+        out.push_str(&format!(
+            "{};{};{};{};{};{};;{};;;;{};;;;{};{};EUR\n",
+            date,
+            date,
+            row.kind.booking_text(),
+            row.payee,
+            row.purpose,
+            row.iban,
+            row.reference,
+            row.amount_de(),
+            soll,
+            haben
+        ));
+    }
+    out.push_str(&format!(
+        ";Kontostand;{} €\n",
+        balance.to_string().replace('.', ",")
+    ));
+    out.into_bytes()
+}
+
 #[cfg(test)]
 mod test {
     use miette::Result;
@@ -157,12 +276,26 @@ mod test {
 
     #[test]
     fn test_to_iter() {
-        let input = b"\n\n\n\n\n\n\n\n7.3.2024;7.3.2024;SEPA Lastschrift;Woopsie;Doopsie;DE123;;ABCD;EFG;DE123;;-25,88;;;;-25,88;;EUR\n";
+        let input = "Umsätze Girokonto;Zeitraum: 30 Tage\nKontoinhaber;Max Mustermann\nKontonummer;1234567890\nIBAN;DE123\n\nKontostand;1.000,00 €\nVorgemerkte und noch nicht gebuchte Umsätze sind nicht Bestandteil dieser Übersicht.\nBuchungstag;Wert;Umsatzart;Begünstigter / Auftraggeber;Verwendungszweck;IBAN / Kontonummer;BIC;Kundenreferenz;Mandatsreferenz ;Gläubiger ID;Fremde Gebühren;Betrag;Abweichender Empfänger;Anzahl der Aufträge;Anzahl der Schecks;Soll;Haben;Währung\n7.3.2024;7.3.2024;SEPA Lastschrift;Woopsie;Doopsie;DE123;;ABCD;EFG;DE123;;-25,88;;;;-25,88;;EUR\n;Kontostand;974,12 €\n";
 
-        let postbank_iter = PostbankIter::new(&input[..]);
+        let postbank_iter = PostbankIter::new(input.as_bytes());
         let element: Vec<Result<Record>> = postbank_iter.collect();
 
         assert_eq!(element.len(), 1);
         assert!(element[0].is_ok());
+
+        let current = "Umsätze;Girokonto;\nKontonummer;DE89 3704 0044 0532 0130 00;\nZeitraum;01.03.2024 - 08.03.2024;\n\n\
+            Buchungstag;Wert;Umsatzart;Buchungsdetails;Auftraggeber;Empfänger;Betrag (€);Saldo (€)\n\
+            07.03.2024;07.03.2024;Lastschrift;Strom März;Max Mustermann;Stadtwerke;-25,88 €;974,12 €\n\
+            06.03.2024;06.03.2024;Gutschrift;Geschenk;Erika Mustermann;Max Mustermann;20,00 €;1.000,00 €\n";
+        let records: Vec<Record> = PostbankIter::new(current.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payee, "Erika Mustermann");
     }
 }
@@ -0,0 +1,145 @@
+//! Visa credit card exports of the Postbank, in Windows-1252.
+//!
+//! Nothing like the Giro export: each row names its billing period, the
+//! merchant and, for purchases abroad, the foreign amount and the exchange
+//! rate, which go into the memo. Charges are written positive and payments
+//! onto the card negative, so the signs are flipped.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Abrechnungszeitraum;Belegdatum;Buchungsdatum";
+
+#[derive(Debug, Deserialize)]
+struct PostbankVisaIR {
+    #[serde(rename = "Abrechnungszeitraum")]
+    abrechnungszeitraum: String,
+    #[serde(rename = "Belegdatum")]
+    belegdatum: String,
+    #[serde(rename = "Händler")]
+    händler: String,
+    #[serde(rename = "Betrag in Fremdwährung", default)]
+    fremdbetrag: String,
+    #[serde(rename = "Fremdwährung", default)]
+    fremdwährung: String,
+    #[serde(rename = "Kurs", default)]
+    kurs: String,
+    #[serde(rename = "Betrag in EUR")]
+    betrag: String,
+}
+
+impl TryFrom<PostbankVisaIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: PostbankVisaIR) -> Result<Self> {
+        let memo = match value.fremdwährung.as_str() {
+            "" | "EUR" => String::new(),
+            currency => format!("{} {} (Kurs {})", value.fremdbetrag, currency, value.kurs),
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.belegdatum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting belegdatum into datetime")?,
+            payment: Payment::CreditCard,
+            info: value.abrechnungszeitraum,
+            payee: value.händler,
+            memo,
+            amount: Money::from_decimal(-decimal_de(&value.betrag)?, EUR),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct PostbankVisaIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl PostbankVisaIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, PostbankVisaIR>(text.as_bytes(), b';', HEADER),
+                "postbank visa",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for PostbankVisaIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Kreditkartenumsätze;Postbank Visa Card\nKartennummer;4546 **** **** 1234\n\n\
+        Abrechnungszeitraum;Belegdatum;Buchungsdatum;Händler;Betrag in Fremdwährung;\
+        Fremdwährung;Kurs;Betrag in EUR\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        out.push_str(&format!(
+            "{};{};{};{};;;;{}\n",
+            row.date.format("%m/%Y"),
+            date,
+            date,
+            row.payee.to_uppercase(),
+            (-row.amount).to_string().replace('.', ",")
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Kreditkartenumsätze;Postbank Visa Card\nKartennummer;4546 **** **** 1234\n\n\
+            Abrechnungszeitraum;Belegdatum;Buchungsdatum;Händler;Betrag in Fremdwährung;Fremdwährung;Kurs;Betrag in EUR\n\
+            03/2024;07.03.2024;08.03.2024;APPLE.COM/BILL;10,00;USD;1,0742;9,31\n\
+            03/2024;01.03.2024;01.03.2024;Zahlung Danke;;;;-100,00\n";
+
+        let records: Vec<Record> = PostbankVisaIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "APPLE.COM/BILL");
+        assert_eq!(records[0].memo, "10,00 USD (Kurs 1,0742)");
+        assert_eq!(records[0].info, "03/2024");
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[0].amount, Money::from_str("-9,31", EUR).unwrap());
+        assert_eq!(records[1].amount, Money::from_str("100", EUR).unwrap());
+    }
+}
@@ -0,0 +1,246 @@
+//! The csv export of PostFinance, `;` separated in ISO-8859-1 with decimal
+//! points.
+//!
+//! A block of metadata, the currency of the account among it, comes before
+//! the header, whose amount columns are named after that currency, and a
+//! disclaimer after the bookings. The running balance is checked against
+//! the amounts, warning about rows that are missing from the export.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+use tracing::warn;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Buchungsdatum;Avisierungstext;Gutschrift";
+
+#[derive(Debug, Deserialize)]
+struct PostfinanceIR {
+    #[serde(rename = "Buchungsdatum")]
+    buchungsdatum: String,
+    #[serde(rename = "Avisierungstext")]
+    avisierungstext: String,
+    #[serde(rename = "Gutschrift", default)]
+    gutschrift: String,
+    #[serde(rename = "Lastschrift", default)]
+    lastschrift: String,
+    #[serde(rename = "Saldo", default)]
+    saldo: String,
+    /// Currency of the account, not set from the row
+    #[serde(skip)]
+    currency: String,
+}
+
+impl PostfinanceIR {
+    fn amount(&self) -> Result<Decimal> {
+        Ok(decimal(&self.gutschrift, '.')? - decimal(&self.lastschrift, '.')?.abs())
+    }
+}
+
+/// The payment method of the notification text, which starts with the
+/// booking type in capitals.
+fn payment(text: &str) -> Payment {
+    let lower = text.to_lowercase();
+    match booking_payment(text) {
+        Payment::None if lower.starts_with("kauf/dienstleistung") => Payment::DebitCard,
+        Payment::None if lower.starts_with("giro") => Payment::BankTransfer,
+        Payment::None if lower.contains("preis für") || lower.starts_with("jahrespreis") => {
+            Payment::FinancialInstitutionFee
+        }
+        payment => payment,
+    }
+}
+
+impl TryFrom<PostfinanceIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: PostfinanceIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        // Card payments name the merchant after the card number
+        let payee = value
+            .avisierungstext
+            .split_once("KARTEN NR. ")
+            .and_then(|(_, rest)| rest.split_once(' '))
+            .map(|(_, merchant)| merchant.to_string())
+            .unwrap_or_default();
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungsdatum, "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(&value.buchungsdatum, "%d.%m.%Y"))
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.avisierungstext),
+            payee,
+            amount: Money::from_decimal(value.amount()?, currency),
+            memo: value.avisierungstext,
+            ..Default::default()
+        })
+    }
+}
+
+/// Warns about each balance that does not follow from the one before and
+/// the amounts in between, whether the export is sorted ascending or
+/// descending. Rows may leave the balance empty.
+fn verify(rows: &[Result<PostfinanceIR>]) {
+    let mut last: Option<(Decimal, Decimal)> = None;
+    let mut between = Decimal::ZERO;
+    for ir in rows.iter().flatten() {
+        let (Ok(amount), Ok(saldo)) = (ir.amount(), decimal(&ir.saldo, '.')) else {
+            continue;
+        };
+        if ir.saldo.is_empty() {
+            between += amount;
+            continue;
+        }
+        if let Some((previous, previous_amount)) = last {
+            let ascending = previous + between + amount == saldo;
+            let descending = saldo + previous_amount + between == previous;
+            if !ascending && !descending {
+                warn!(date = %ir.buchungsdatum, %saldo, "Balance does not add up, rows may be missing");
+            }
+        }
+        last = Some((saldo, amount));
+        between = Decimal::ZERO;
+    }
+}
+
+pub struct PostfinanceIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl PostfinanceIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut currency = "CHF";
+                let mut lines = Vec::new();
+                let mut header = false;
+                for line in text.lines() {
+                    if let Some(value) = line.strip_prefix("Währung:;") {
+                        currency = value.trim_matches(|c| c == ';' || c == '"');
+                    }
+                    // The disclaimer follows the bookings after a blank line
+                    if header && line.trim().is_empty() {
+                        break;
+                    }
+                    match line.replace('"', "").starts_with(HEADER) {
+                        true => {
+                            header = true;
+                            lines.push(line.replace(&format!(" in {}", currency), ""));
+                        }
+                        false => lines.push(line.to_string()),
+                    }
+                }
+                let text = lines.join("\n");
+                let mut rows = csv_rows::<_, PostfinanceIR>(text.as_bytes(), b';', HEADER);
+                for ir in rows.iter_mut().flatten() {
+                    ir.currency = currency.to_string();
+                }
+                verify(&rows);
+                convert(rows, "postfinance")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for PostfinanceIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` in CHF, in ISO-8859-1 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let from = rows.first().map(|r| r.date).unwrap_or_default();
+    let to = rows.last().map(|r| r.date).unwrap_or_default();
+    let mut out = format!(
+        "Datum von:;{}\nDatum bis:;{}\nBuchungsart:;Alle Buchungen\n\
+        Konto:;CH5209000000123456789\nWährung:;CHF\n\
+        Buchungsdatum;Avisierungstext;Gutschrift in CHF;Lastschrift in CHF;Valuta;Saldo in CHF\n",
+        from.format("%Y-%m-%d"),
+        to.format("%Y-%m-%d")
+    );
+    let mut saldo = Decimal::new(500000, 2);
+    for row in rows {
+        let date = row.date.format("%Y-%m-%d");
+        saldo += row.amount;
+        let (credit, debit) = match row.amount.is_sign_negative() {
+            true => (String::new(), row.amount.to_string()),
+            false => (row.amount.to_string(), String::new()),
+        };
+        out.push_str(&format!(
+            "{};\"{} {} MITTEILUNGEN: {}\";{};{};{};{}\n",
+            date,
+            row.kind.booking_text().to_uppercase(),
+            row.payee,
+            row.purpose,
+            credit,
+            debit,
+            date,
+            saldo
+        ));
+    }
+    out.push_str("\nDisclaimer:\nDies ist kein durch PostFinance AG erstelltes Dokument.\n");
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::CHF;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Datum von:;2024-03-01\n\
+            Datum bis:;2024-03-31\n\
+            Buchungsart:;Alle Buchungen\n\
+            Konto:;CH5209000000123456789\n\
+            Währung:;CHF\n\
+            Buchungsdatum;Avisierungstext;Gutschrift in CHF;Lastschrift in CHF;Valuta;Saldo in CHF\n\
+            2024-03-25;\"GIRO BANK Arbeitgeber AG MITTEILUNGEN: Lohn März\";6500.00;;2024-03-25;9824.20\n\
+            2024-03-08;\"LASTSCHRIFT Krankenkasse Zürich\";;-650.00;2024-03-08;3324.20\n\
+            2024-03-07;\"KAUF/DIENSTLEISTUNG VOM 06.03.2024 KARTEN NR. XXXX1234 COOP-1234 BERN\";;-25.80;2024-03-07;3974.20\n\
+            \n\
+            Disclaimer:\n\
+            Dies ist kein durch PostFinance AG erstelltes Dokument.\n";
+
+        let records: Vec<Record> = PostfinanceIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 25).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].amount, Money::from_str("6500.00", CHF).unwrap());
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[2].payment, Payment::DebitCard);
+        assert_eq!(records[2].payee, "COOP-1234 BERN");
+        assert_eq!(records[2].amount, Money::from_str("-25.80", CHF).unwrap());
+    }
+}
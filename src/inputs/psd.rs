@@ -0,0 +1,120 @@
+//! Transaction exports of the PSD Banken, in Windows-1252.
+//!
+//! Close to the VR-NetWorld csv and read the same way, but below a
+//! preamble naming the bank and with the purpose in up to seven lines
+//! `Verwendungszweck 1` to `Verwendungszweck 7`.
+
+use std::{io::Read, vec};
+
+use miette::{Report, Result};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decode},
+    volksbank::VolksbankIR,
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+const HEADER: &str = "Buchungstag;Valuta;Empfänger/Auftraggeber";
+
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+struct PsdIR(VolksbankIR);
+
+impl TryFrom<PsdIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: PsdIR) -> Result<Self> {
+        value
+            .0
+            .record("Empfänger/Auftraggeber", "Verwendungszweck ")
+    }
+}
+
+pub struct PsdIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl PsdIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(csv_rows::<_, PsdIR>(text.as_bytes(), b';', HEADER), "psd"),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for PsdIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let lines: Vec<String> = (1..=7).map(|n| format!("Verwendungszweck {}", n)).collect();
+    let mut out = format!(
+        "PSD Bank Musterstadt eG\nUmsätze;Girokonto;1234567890\n\n\
+        Buchungstag;Valuta;Empfänger/Auftraggeber;IBAN;BIC;Buchungstext;{};Betrag;Währung;S/H\n",
+        lines.join(";")
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let sign = match row.amount.is_sign_negative() {
+            true => "S",
+            false => "H",
+        };
+        out.push_str(&format!(
+            "{};{};{};{};GENODEF1P00;{};EREF+{};SVWZ+{};;;;;;{};EUR;{}\n",
+            date,
+            date,
+            row.payee,
+            row.iban,
+            row.kind.booking_text(),
+            row.reference,
+            row.purpose,
+            row.amount.abs().to_string().replace('.', ","),
+            sign
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "PSD Bank Nord eG\nUmsätze;Girokonto;1234567890\n\n\
+            Buchungstag;Valuta;Empfänger/Auftraggeber;IBAN;BIC;Buchungstext;Verwendungszweck 1;Verwendungszweck 2;Betrag;Währung;S/H\n\
+            07.03.2024;07.03.2024;Stadtwerke;DE02120300000000202051;BYLADEM1001;Lastschrift;EREF+4711;SVWZ+Strom März;1.025,88;EUR;S\n";
+
+        let records: Vec<Record> = PsdIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+    }
+}
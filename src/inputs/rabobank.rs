@@ -0,0 +1,220 @@
+//! The transaction export of Rabobank in the Netherlands, comma separated
+//! and quoted with ISO dates and signed amounts with decimal commas.
+//!
+//! Besides the other party the export names the ultimate and initiating
+//! party, of which the first one filled is the payee. The three description
+//! columns make the memo. The payment reference, or the transaction
+//! reference, goes into the info. The transaction code tells the payment
+//! method.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "IBAN/BBAN,Munt,BIC,Volgnr,Datum";
+
+#[derive(Debug, Deserialize)]
+struct RabobankIR {
+    #[serde(rename = "Munt")]
+    munt: String,
+    #[serde(rename = "Datum")]
+    datum: String,
+    #[serde(rename = "Bedrag")]
+    bedrag: String,
+    #[serde(rename = "Tegenrekening IBAN/BBAN", default)]
+    tegenrekening: String,
+    #[serde(rename = "Naam tegenpartij", default)]
+    naam_tegenpartij: String,
+    #[serde(rename = "Naam uiteindelijke partij", default)]
+    naam_uiteindelijke_partij: String,
+    #[serde(rename = "Naam initiërende partij", default)]
+    naam_initierende_partij: String,
+    #[serde(rename = "Code", default)]
+    code: String,
+    #[serde(rename = "Transactiereferentie", default)]
+    transactiereferentie: String,
+    #[serde(rename = "Betalingskenmerk", default)]
+    betalingskenmerk: String,
+    #[serde(rename = "Omschrijving-1", default)]
+    omschrijving1: String,
+    #[serde(rename = "Omschrijving-2", default)]
+    omschrijving2: String,
+    #[serde(rename = "Omschrijving-3", default)]
+    omschrijving3: String,
+}
+
+/// The payment method of the two letter transaction code.
+fn payment(code: &str) -> Payment {
+    match code.to_lowercase().as_str() {
+        "bc" | "ba" => Payment::DebitCard,
+        "ga" | "gb" => Payment::Cash,
+        "ei" | "ac" => Payment::DirectDebit,
+        "tb" | "cb" | "bg" | "ov" | "id" | "sb" => Payment::BankTransfer,
+        "db" => Payment::FinancialInstitutionFee,
+        _ => Payment::None,
+    }
+}
+
+impl TryFrom<RabobankIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: RabobankIR) -> Result<Self> {
+        let currency =
+            iso::find(&value.munt).ok_or_else(|| miette!("Unknown currency '{}'", value.munt))?;
+        let payee = [
+            &value.naam_tegenpartij,
+            &value.naam_uiteindelijke_partij,
+            &value.naam_initierende_partij,
+        ]
+        .into_iter()
+        .find(|s| !s.is_empty())
+        .cloned()
+        .unwrap_or_default();
+        let memo = [
+            value.omschrijving1,
+            value.omschrijving2,
+            value.omschrijving3,
+        ]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+        let info = match value.betalingskenmerk.is_empty() {
+            true => value.transactiereferentie,
+            false => value.betalingskenmerk,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.datum, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.code),
+            info,
+            payee,
+            memo,
+            amount: Money::from_decimal(decimal_de(&value.bedrag)?, currency),
+            iban: value.tegenrekening,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct RabobankIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl RabobankIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, RabobankIR>(text.as_bytes(), b',', HEADER),
+                "rabobank",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for RabobankIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` in EUR.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"IBAN/BBAN\",\"Munt\",\"BIC\",\"Volgnr\",\"Datum\",\"Rentedatum\",\"Bedrag\",\
+        \"Saldo na trn\",\"Tegenrekening IBAN/BBAN\",\"Naam tegenpartij\",\
+        \"Naam uiteindelijke partij\",\"Naam initiërende partij\",\"BIC tegenpartij\",\"Code\",\
+        \"Batch ID\",\"Transactiereferentie\",\"Machtigingskenmerk\",\"Incassant ID\",\
+        \"Betalingskenmerk\",\"Omschrijving-1\",\"Omschrijving-2\",\"Omschrijving-3\",\
+        \"Reden retour\",\"Oorspr bedrag\",\"Oorspr munt\",\"Koers\"\n",
+    );
+    for (i, row) in rows.iter().enumerate() {
+        let date = row.date.format("%Y-%m-%d");
+        let code = match row.kind {
+            Kind::Card => "bc",
+            Kind::DirectDebit => "ei",
+            Kind::Cash => "ga",
+            Kind::StandingOrder | Kind::Transfer => "tb",
+            Kind::Salary => "cb",
+        };
+        let amount = match row.amount.is_sign_negative() {
+            true => row.amount_de(),
+            false => format!("+{}", row.amount_de()),
+        };
+        out.push_str(&format!(
+            "\"NL44RABO0123456789\",\"EUR\",\"RABONL2U\",\"{:018}\",\"{}\",\"{}\",\"{}\",\"\",\
+            \"{}\",\"{}\",\"\",\"\",\"\",\"{}\",\"\",\"{}\",\"\",\"\",\"\",\"{}\",\"\",\"\",\
+            \"\",\"\",\"\",\"\"\n",
+            i + 1,
+            date,
+            date,
+            amount,
+            row.iban,
+            row.payee,
+            code,
+            row.reference,
+            row.purpose
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let header = "\"IBAN/BBAN\",\"Munt\",\"BIC\",\"Volgnr\",\"Datum\",\"Rentedatum\",\"Bedrag\",\"Saldo na trn\",\"Tegenrekening IBAN/BBAN\",\"Naam tegenpartij\",\"Naam uiteindelijke partij\",\"Naam initiërende partij\",\"BIC tegenpartij\",\"Code\",\"Batch ID\",\"Transactiereferentie\",\"Machtigingskenmerk\",\"Incassant ID\",\"Betalingskenmerk\",\"Omschrijving-1\",\"Omschrijving-2\",\"Omschrijving-3\",\"Reden retour\",\"Oorspr bedrag\",\"Oorspr munt\",\"Koers\"\n";
+        let input = format!(
+            "{}{}{}{}",
+            header,
+            "\"NL44RABO0123456789\",\"EUR\",\"RABONL2U\",\"000000000000001\",\"2024-03-07\",\"2024-03-07\",\"-25,88\",\"+4974,12\",\"\",\"Albert Heijn 1234\",\"\",\"\",\"\",\"bc\",\"\",\"\",\"\",\"\",\"\",\"Betaalautomaat 18:30 pasnr. 123\",\"\",\"\",\"\",\"\",\"\",\"\"\n",
+            "\"NL44RABO0123456789\",\"EUR\",\"RABONL2U\",\"000000000000002\",\"2024-03-08\",\"2024-03-08\",\"-42,00\",\"+4932,12\",\"NL20INGB0001234567\",\"Vattenfall\",\"\",\"\",\"INGBNL2A\",\"ei\",\"\",\"REF-1\",\"M-1\",\"NL12ZZZ123\",\"\",\"Energie maart\",\"klantnr 42\",\"\",\"\",\"\",\"\",\"\"\n",
+            "\"NL44RABO0123456789\",\"EUR\",\"RABONL2U\",\"000000000000003\",\"2024-03-25\",\"2024-03-25\",\"+2500,00\",\"+7432,12\",\"NL02ABNA0123456789\",\"\",\"Werkgever BV\",\"\",\"ABNANL2A\",\"cb\",\"\",\"REF-2\",\"\",\"\",\"1234 5678\",\"Salaris maart\",\"\",\"\",\"\",\"\",\"\",\"\"\n"
+        );
+
+        let records: Vec<Record> = RabobankIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Albert Heijn 1234");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[1].memo, "Energie maart klantnr 42");
+        assert_eq!(records[1].info, "REF-1");
+        assert_eq!(records[1].iban, "NL20INGB0001234567");
+        assert_eq!(records[2].payee, "Werkgever BV");
+        assert_eq!(records[2].info, "1234 5678");
+        assert_eq!(records[2].amount, Money::from_str("2500,00", EUR).unwrap());
+    }
+}
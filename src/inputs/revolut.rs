@@ -0,0 +1,173 @@
+//! The statement export of Revolut, comma separated with decimal points.
+//!
+//! Every row keeps the currency of its pocket, exchanges between pockets
+//! show up as a leg in each and become internal transfers. Products other
+//! than the current account, like savings vaults, become a tag. Fees are
+//! written positive and become a split line of their payment. Reverted
+//! transactions are left out.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode, with_fee},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Type,Product,Started Date";
+/// The product of the account itself, which needs no tag.
+const MAIN_PRODUCT: &str = "Current";
+const REVERTED: &str = "REVERTED";
+
+#[derive(Debug, Deserialize)]
+struct RevolutIR {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "Product")]
+    product: String,
+    #[serde(rename = "Started Date")]
+    started_date: String,
+    #[serde(rename = "Description")]
+    description: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Fee", default)]
+    fee: String,
+    #[serde(rename = "Currency")]
+    currency: String,
+    #[serde(rename = "State")]
+    state: String,
+}
+
+impl TryFrom<RevolutIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: RevolutIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        let gross = Money::from_decimal(decimal(&value.amount, '.')?, currency);
+        let fee = Money::from_decimal(-decimal(&value.fee, '.')?, currency);
+        let (amount, splits) = match fee.is_zero() {
+            true => (gross, Vec::new()),
+            false => with_fee(gross, fee, &value.description, "Revolut"),
+        };
+        let payment = match value.kind.as_str() {
+            "CARD_PAYMENT" | "CARD_REFUND" => Payment::DebitCard,
+            "TRANSFER" => Payment::BankTransfer,
+            "EXCHANGE" => Payment::InternalTransfer,
+            "TOPUP" => Payment::Deposit,
+            "ATM" => Payment::Cash,
+            "FEE" => Payment::FinancialInstitutionFee,
+            _ => Payment::None,
+        };
+        let tags = match value.product.as_str() {
+            "" | MAIN_PRODUCT => Vec::new(),
+            product => vec![product.to_lowercase().replace(' ', "-")],
+        };
+        let date = value.started_date.get(..10).unwrap_or(&value.started_date);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting started date into datetime")?,
+            payment,
+            payee: value.description,
+            amount,
+            tags,
+            splits,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct RevolutIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl RevolutIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, RevolutIR>(text.as_bytes(), b',', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.state == REVERTED));
+                convert(rows, "revolut")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for RevolutIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Type,Product,Started Date,Completed Date,Description,Amount,Fee,Currency,State,Balance\n",
+    );
+    for row in rows {
+        let kind = match row.kind {
+            Kind::Card => "CARD_PAYMENT",
+            Kind::Cash => "ATM",
+            Kind::Salary | Kind::Transfer | Kind::StandingOrder | Kind::DirectDebit => "TRANSFER",
+        };
+        let date = row.date.format("%Y-%m-%d 10:00:00");
+        out.push_str(&format!(
+            "{},Current,{},{},{},{},0.00,EUR,COMPLETED,\n",
+            kind, date, date, row.payee, row.amount
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::{EUR, USD};
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Type,Product,Started Date,Completed Date,Description,Amount,Fee,Currency,State,Balance\n\
+            CARD_PAYMENT,Current,2024-03-07 10:22:33,2024-03-08 04:11:00,Rewe,-25.88,0.00,EUR,COMPLETED,974.12\n\
+            CARD_PAYMENT,Current,2024-03-07 11:00:00,,Woopsie,-5.00,0.00,EUR,REVERTED,\n\
+            EXCHANGE,Current,2024-03-08 09:00:00,2024-03-08 09:00:00,Exchanged to USD,-100.00,0.50,EUR,COMPLETED,873.62\n\
+            EXCHANGE,Current,2024-03-08 09:00:00,2024-03-08 09:00:00,Exchanged from EUR,108.20,0.00,USD,COMPLETED,108.20\n\
+            TRANSFER,Savings,2024-03-09 09:00:00,2024-03-09 09:00:00,To Holiday,50.00,0.00,EUR,COMPLETED,50.00\n";
+
+        let records: Vec<Record> = RevolutIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Rewe");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::InternalTransfer);
+        assert_eq!(records[1].amount, Money::from_str("-100,50", EUR).unwrap());
+        assert_eq!(records[1].splits.len(), 2);
+        assert_eq!(records[2].amount, Money::from_str("108.20", USD).unwrap());
+        assert_eq!(records[3].tags, vec!["savings"]);
+    }
+}
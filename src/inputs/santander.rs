@@ -0,0 +1,207 @@
+//! Account and BestCard credit card exports of the Santander Consumer Bank
+//! Germany, in Windows-1252.
+//!
+//! Either starts with a preamble of account or card and period. The card
+//! export names the merchant in `Beschreibung`, purchases and payments onto
+//! the card both come in with their sign.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Buchungsdatum;Valuta;Buchungstext";
+const CARD_HEADER: &str = "Kartennummer;Belegdatum;Buchungsdatum";
+
+#[derive(Debug, Deserialize)]
+struct SantanderIR {
+    #[serde(rename = "Buchungsdatum")]
+    buchungsdatum: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Auftraggeber / Empfänger")]
+    auftraggeber: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CardIR {
+    #[serde(rename = "Belegdatum")]
+    belegdatum: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+}
+
+fn record(
+    date: &str,
+    payment: Payment,
+    payee: String,
+    memo: String,
+    amount: &str,
+    currency: &str,
+) -> Result<Record> {
+    let currency = iso::find(currency).ok_or_else(|| miette!("Unknown currency '{}'", currency))?;
+
+    Ok(Record {
+        date: NaiveDate::parse_from_str(date, "%d.%m.%Y")
+            .into_diagnostic()
+            .wrap_err("Failed converting date into datetime")?,
+        payment,
+        payee,
+        memo,
+        amount: Money::from_decimal(decimal_de(amount)?, currency),
+        ..Default::default()
+    })
+}
+
+impl TryFrom<SantanderIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: SantanderIR) -> Result<Self> {
+        record(
+            &value.buchungsdatum,
+            booking_payment(&value.buchungstext),
+            value.auftraggeber,
+            value.verwendungszweck,
+            &value.betrag,
+            &value.währung,
+        )
+    }
+}
+
+impl TryFrom<CardIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: CardIR) -> Result<Self> {
+        record(
+            &value.belegdatum,
+            Payment::CreditCard,
+            value.beschreibung,
+            String::new(),
+            &value.betrag,
+            &value.währung,
+        )
+    }
+}
+
+pub struct SantanderIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl SantanderIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) if text.contains(CARD_HEADER) => convert(
+                csv_rows::<_, CardIR>(text.as_bytes(), b';', CARD_HEADER),
+                "santander",
+            ),
+            Ok(text) => convert(
+                csv_rows::<_, SantanderIR>(text.as_bytes(), b';', HEADER),
+                "santander",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for SantanderIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample account export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let from = rows.first().map(|r| r.date).unwrap_or_default();
+    let to = rows.last().map(|r| r.date).unwrap_or_default();
+    let mut out = format!(
+        "Kontoumsätze;Girokonto\nKontonummer;1234567890\nZeitraum;{} - {}\n\n\
+        Buchungsdatum;Valuta;Buchungstext;Auftraggeber / Empfänger;Verwendungszweck;Betrag;Währung\n",
+        from.format("%d.%m.%Y"),
+        to.format("%d.%m.%Y")
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        out.push_str(&format!(
+            "{};{};{};{};{};{};EUR\n",
+            date,
+            date,
+            row.kind.booking_text(),
+            row.payee,
+            row.purpose,
+            row.amount_de()
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Kontoumsätze;Girokonto\nKontonummer;1234567890\nZeitraum;01.03.2024 - 08.03.2024\n\n\
+            Buchungsdatum;Valuta;Buchungstext;Auftraggeber / Empfänger;Verwendungszweck;Betrag;Währung\n\
+            07.03.2024;07.03.2024;SEPA-Lastschrift;Stadtwerke;Strom März;-1.025,88;EUR\n";
+        let records: Vec<Record> = SantanderIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+
+        let card = "Kreditkartenumsätze;BestCard\nZeitraum;01.03.2024 - 08.03.2024\n\n\
+            Kartennummer;Belegdatum;Buchungsdatum;Beschreibung;Betrag;Währung\n\
+            4543 **** **** 1234;06.03.2024;07.03.2024;AMAZON.DE;-25,88;EUR\n\
+            4543 **** **** 1234;05.03.2024;05.03.2024;Zahlung Danke;100,00;EUR\n";
+        let records: Vec<Record> = SantanderIter::new(WINDOWS_1252.encode(card).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].payee, "AMAZON.DE");
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 6).unwrap()
+        );
+        assert_eq!(records[1].amount, Money::from_str("100", EUR).unwrap());
+    }
+}
@@ -0,0 +1,183 @@
+//! The transaction export of Scalable Capital, `;` separated with ISO dates
+//! and decimal commas.
+//!
+//! Deposits, withdrawals, distributions, interest, fees and taxes move the
+//! cash account and are always read. Buys, sells and savings plan
+//! executions are left out unless asked for with `--include-trades`, in the
+//! category given by `--trade-category`. Only executed rows are read.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{self, convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "date;time;status;reference";
+const EXECUTED: &str = "Executed";
+/// The types of securities trades.
+const TRADES: [&str; 3] = ["Buy", "Sell", "Savings plan"];
+
+#[derive(Debug, Deserialize)]
+struct ScalableIR {
+    date: String,
+    status: String,
+    reference: String,
+    description: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    shares: String,
+    amount: String,
+    #[serde(default)]
+    fee: String,
+    #[serde(default)]
+    tax: String,
+    currency: String,
+    #[serde(skip)]
+    trade_category: String,
+}
+
+impl ScalableIR {
+    fn is_trade(&self) -> bool {
+        TRADES.contains(&self.kind.as_str())
+    }
+}
+
+impl TryFrom<ScalableIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: ScalableIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        // Fees and taxes are written as negative amounts of their own
+        let amount =
+            decimal(&value.amount, ',')? + decimal(&value.fee, ',')? + decimal(&value.tax, ',')?;
+        let payment = match value.kind.as_str() {
+            "Deposit" | "Withdrawal" => Payment::BankTransfer,
+            "Distribution" | "Interest" => Payment::Deposit,
+            "Fee" | "Taxes" => Payment::FinancialInstitutionFee,
+            _ => Payment::None,
+        };
+        let memo = match value.is_trade() && !value.shares.is_empty() {
+            true => format!("{} {} shares", value.kind, value.shares),
+            false => value.kind,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            info: value.reference,
+            payee: value.description,
+            memo,
+            amount: Money::from_decimal(amount, currency),
+            category: value.trade_category,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct ScalableIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl ScalableIter {
+    /// Reads the export, leaving out securities trades unless `trades` is
+    /// the category to give them.
+    pub fn new<R: Read>(rdr: R, trades: Option<&str>) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, ScalableIR>(text.as_bytes(), b';', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.status != EXECUTED));
+                util::trades(&mut rows, trades, ScalableIR::is_trade, |ir| {
+                    &mut ir.trade_category
+                });
+                convert(rows, "scalable")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for ScalableIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, all of them moving the cash account.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "date;time;status;reference;description;assetType;type;isin;shares;price;amount;fee;tax;currency\n",
+    );
+    for row in rows {
+        let kind = match row.amount.is_sign_negative() {
+            true => "Withdrawal",
+            false => "Deposit",
+        };
+        out.push_str(&format!(
+            "{};10:00:00;Executed;{};{};Cash;{};;;;{};;;EUR\n",
+            row.date.format("%Y-%m-%d"),
+            row.reference,
+            row.payee,
+            kind,
+            row.amount_de()
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "date;time;status;reference;description;assetType;type;isin;shares;price;amount;fee;tax;currency\n\
+            2024-03-07;10:22:33;Executed;SCAL-1;Einzahlung;Cash;Deposit;;;;500,00;;;EUR\n\
+            2024-03-08;09:00:00;Executed;SCAL-2;iShares Core MSCI World;Security;Savings plan;IE00B4L5Y983;2,5;90,00;-225,00;-0,99;;EUR\n\
+            2024-03-09;09:00:00;Cancelled;SCAL-3;Apple;Security;Buy;US0378331005;1;170,00;-170,00;;;EUR\n\
+            2024-03-15;09:00:00;Executed;SCAL-4;iShares Core MSCI World;Security;Distribution;IE00B4L5Y983;;;3,20;;-0,84;EUR\n";
+
+        let records: Vec<Record> = ScalableIter::new(input.as_bytes(), None)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].amount, Money::from_str("500,00", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::Deposit);
+        assert_eq!(records[1].amount, Money::from_str("2,36", EUR).unwrap());
+
+        let records: Vec<Record> = ScalableIter::new(input.as_bytes(), Some("Investment"))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].amount, Money::from_str("-225,99", EUR).unwrap());
+        assert_eq!(records[1].category, "Investment");
+        assert_eq!(records[1].memo, "Savings plan 2,5 shares");
+    }
+}
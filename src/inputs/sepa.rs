@@ -0,0 +1,204 @@
+//! The structured transaction details German banks put into MT940 `:86:`
+//! fields, and the SEPA keywords inside the remittance information.
+//!
+//! `:86:` starts with a three digit business transaction code (GVC),
+//! followed by `?NN` subfields: `?00` the booking text, `?20` to `?29` and
+//! `?60` to `?63` the purpose, `?30`/`?31` BIC and IBAN and `?32`/`?33` the
+//! name of the other party.
+
+use crate::homebank::Payment;
+
+/// Keywords structuring a SEPA purpose, as in `EREF+123 SVWZ+Rent`.
+const KEYWORDS: &[&str] = &[
+    "EREF", "KREF", "MREF", "CRED", "DEBT", "COAM", "OAMT", "SVWZ", "ABWA", "ABWE", "IBAN", "BIC",
+];
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Details {
+    /// Business transaction code
+    pub gvc: String,
+    pub booking_text: String,
+    pub purpose: String,
+    pub bic: String,
+    pub iban: String,
+    pub name: String,
+}
+
+/// The remittance information split into its SEPA keywords.
+#[derive(Debug, Default, PartialEq)]
+pub struct Purpose {
+    pub end_to_end: Option<String>,
+    pub mandate: Option<String>,
+    pub creditor_id: Option<String>,
+    /// The free text, without the keywords
+    pub text: String,
+}
+
+impl Details {
+    /// Parses a `:86:` field, taking unstructured ones as just a purpose.
+    pub fn parse(field: &str) -> Self {
+        let structured = field.len() > 3
+            && field.is_char_boundary(3)
+            && field[..3].bytes().all(|b| b.is_ascii_digit());
+        if !structured {
+            return Self {
+                purpose: field.replace('\n', " "),
+                ..Self::default()
+            };
+        }
+
+        // Lines are wrapped at a fixed width, subfields included
+        let field = field.replace('\n', "");
+        let separator = field[3..].chars().next().unwrap_or('?');
+        let mut details = Self {
+            gvc: field[..3].to_string(),
+            ..Self::default()
+        };
+        for subfield in field[3..].split(separator).skip(1) {
+            if subfield.len() < 2 || !subfield.is_char_boundary(2) {
+                continue;
+            }
+            let (code, value) = subfield.split_at(2);
+            match code.parse::<u8>() {
+                Ok(0) => details.booking_text.push_str(value),
+                Ok(20..=29 | 60..=63) => details.purpose.push_str(value),
+                Ok(30) => details.bic.push_str(value),
+                Ok(31) => details.iban.push_str(value),
+                Ok(32 | 33) => details.name.push_str(value),
+                _ => {}
+            }
+        }
+        details
+    }
+
+    /// The payment method matching the business transaction code.
+    pub fn payment(&self) -> Payment {
+        payment(&self.gvc)
+    }
+}
+
+/// The payment method of a business transaction code, which camt exports
+/// carry in their proprietary bank transaction code.
+pub fn payment(gvc: &str) -> Payment {
+    match gvc {
+        "104" | "105" | "107" | "108" | "171" | "174" => Payment::DirectDebit,
+        "106" => Payment::DebitCard,
+        "152" => Payment::StandingOrder,
+        "116" | "117" | "118" | "119" | "153" | "166" | "169" => Payment::BankTransfer,
+        "082" | "083" => Payment::Cash,
+        "805" | "808" | "809" => Payment::FinancialInstitutionFee,
+        _ => Payment::None,
+    }
+}
+
+/// The payment method told by a German booking text, as in `SEPA-Lastschrift`
+/// or `Kartenzahlung girocard`, for exports without a GVC.
+pub fn booking_payment(text: &str) -> Payment {
+    let text = text.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| text.contains(w));
+    if has(&["dauerauftrag"]) {
+        Payment::StandingOrder
+    } else if has(&["lastschrift"]) {
+        Payment::DirectDebit
+    } else if has(&["karte", "girocard", "maestro", "kartenzahlung"]) {
+        Payment::DebitCard
+    } else if has(&["bargeld", "geldautomat", "auszahlung", "einzahlung"]) {
+        Payment::Cash
+    } else if has(&[
+        "überweisung",
+        "ueberweisung",
+        "gutschrift",
+        "gehalt",
+        "lohn",
+        "rente",
+    ]) {
+        Payment::BankTransfer
+    } else if has(&["entgelt", "gebühr", "gebuehr", "abschluss", "zinsen"]) {
+        Payment::FinancialInstitutionFee
+    } else {
+        Payment::None
+    }
+}
+
+impl Purpose {
+    pub fn parse(purpose: &str) -> Self {
+        let mut starts: Vec<(usize, &str)> = KEYWORDS
+            .iter()
+            .flat_map(|k| {
+                purpose
+                    .match_indices(&format!("{}+", k))
+                    .map(|(idx, _)| (idx, *k))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        starts.sort();
+
+        let Some((first, _)) = starts.first() else {
+            return Self {
+                text: purpose.trim().to_string(),
+                ..Self::default()
+            };
+        };
+
+        let mut parsed = Self::default();
+        let mut text = vec![purpose[..*first].trim()];
+        for (idx, (start, keyword)) in starts.iter().enumerate() {
+            let end = starts.get(idx + 1).map_or(purpose.len(), |(end, _)| *end);
+            let value = purpose[start + keyword.len() + 1..end].trim();
+            match *keyword {
+                "EREF" if value != "NOTPROVIDED" => parsed.end_to_end = Some(value.to_string()),
+                "MREF" => parsed.mandate = Some(value.to_string()),
+                "CRED" => parsed.creditor_id = Some(value.to_string()),
+                "SVWZ" => text.push(value),
+                _ => {}
+            }
+        }
+        text.retain(|t| !t.is_empty());
+        parsed.text = text.join(" ");
+        parsed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_details() {
+        let field = "105?00SEPA-BASISLASTSCHRIFT?100599?20EREF+4711?21MREF+M-1?22CRED+DE98ZZZ0999999\n9999?23SVWZ+Strom Maerz?30BYLADEM1001?31DE02120300000000202051?32Stadtwerke\n?33 Musterstadt";
+        let details = Details::parse(field);
+        assert_eq!(
+            details,
+            Details {
+                gvc: "105".to_string(),
+                booking_text: "SEPA-BASISLASTSCHRIFT".to_string(),
+                purpose: "EREF+4711MREF+M-1CRED+DE98ZZZ09999999999SVWZ+Strom Maerz".to_string(),
+                bic: "BYLADEM1001".to_string(),
+                iban: "DE02120300000000202051".to_string(),
+                name: "Stadtwerke Musterstadt".to_string(),
+            }
+        );
+        assert_eq!(details.payment(), Payment::DirectDebit);
+
+        assert_eq!(
+            Purpose::parse(&details.purpose),
+            Purpose {
+                end_to_end: Some("4711".to_string()),
+                mandate: Some("M-1".to_string()),
+                creditor_id: Some("DE98ZZZ09999999999".to_string()),
+                text: "Strom Maerz".to_string(),
+            }
+        );
+        assert_eq!(Purpose::parse("Rent\nMarch").text, "Rent\nMarch");
+        assert_eq!(
+            booking_payment("SEPA-Basislastschrift"),
+            Payment::DirectDebit
+        );
+        assert_eq!(
+            booking_payment("Kartenzahlung girocard"),
+            Payment::DebitCard
+        );
+    }
+}
@@ -2,15 +2,20 @@ use std::{io::Read, iter::Skip};
 
 use chrono::NaiveDate;
 use csv::{DeserializeRecordsIntoIter, ReaderBuilder};
-use rusty_money::{iso::{Currency, EUR}, Money};
 use encoding_rs::WINDOWS_1252;
 use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
 use miette::{Context, IntoDiagnostic, Report};
+use rusty_money::{
+    iso::{Currency, EUR},
+    Money,
+};
 use serde::Deserialize;
+use tracing::trace;
 
+use super::RecordIteratorRes;
 use crate::{
     homebank::{Payment, Record},
-    RecordIteratorRes,
+    sample::Row,
 };
 
 struct Sparda {
@@ -38,7 +43,6 @@ impl TryFrom<SpardaIR> for Sparda {
     type Error = Report;
 
     fn try_from(value: SpardaIR) -> Result<Self, Self::Error> {
-
         Ok(Self {
             buchungstag: NaiveDate::parse_from_str(&value.buchungstag, "%Y-%m-%d")
                 .into_diagnostic()
@@ -49,8 +53,7 @@ impl TryFrom<SpardaIR> for Sparda {
             gegeniban: value.gegeniban,
             name_gegenkonto: value.name_gegenkonto,
             verwendungszweck: value.verwendungszweck,
-            umsatz: Money::from_str(
-                value.umsatz.trim_matches('"'), EUR)
+            umsatz: Money::from_str(value.umsatz.trim_matches('"'), EUR)
                 .into_diagnostic()
                 .wrap_err("Failed converting currency")?,
             _währung: value.währung,
@@ -62,13 +65,13 @@ impl From<Sparda> for Record {
     fn from(val: Sparda) -> Self {
         Self {
             date: val.buchungstag,
-            payment: Payment::ElectronicPayment,
-            info: val.gegeniban,
+            payment: Payment::None,
+            info: val.gegeniban.clone(),
             payee: val.name_gegenkonto,
             memo: val.verwendungszweck,
             amount: val.umsatz,
-            category: String::new(),
-            tags: Vec::new(),
+            iban: val.gegeniban,
+            ..Default::default()
         }
     }
 }
@@ -109,6 +112,7 @@ impl<R: Read> Iterator for TeoIter<R> {
         let next = self
             .deser
             .next()?
+            .inspect(|ir| trace!(?ir, "Read sparda row"))
             .map(Sparda::try_from)
             .into_diagnostic()
             .wrap_err("Failed deserializing record");
@@ -120,3 +124,30 @@ impl<R: Read> Iterator for TeoIter<R> {
         }
     }
 }
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let from = rows.first().map(|r| r.date).unwrap_or_default();
+    let to = rows.last().map(|r| r.date).unwrap_or_default();
+    let mut out = format!(
+        "Umsatzanzeige;\nBLZ:;50090500;\nKonto:;1234567890;\nIBAN:;DE89370400440532013000;\n\
+        Kontoinhaber:;Max Mustermann;\nZeitraum:;{};{};\nKontostand:;1000,00;EUR\n\
+        Umsatzart:;Alle;\nSuchbegriff:;;\n\
+        Buchungstag;Wertstellungstag;GegenIBAN;Name Gegenkonto;Verwendungszweck;Umsatz;Währung\n",
+        from.format("%d.%m.%Y"),
+        to.format("%d.%m.%Y")
+    );
+    for row in rows {
+        let date = row.date.format("%Y-%m-%d");
+        out.push_str(&format!(
+            "{};{};{};{};{};{};EUR\n",
+            date,
+            date,
+            row.iban,
+            row.payee,
+            row.purpose,
+            row.amount_de()
+        ));
+    }
+    WINDOWS_1252.encode(&out).0.into_owned()
+}
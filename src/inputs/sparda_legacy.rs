@@ -0,0 +1,157 @@
+//! Exports of the Sparda online banking before TEO, in Windows-1252, for
+//! converting historic statements.
+//!
+//! Dates are written `DD.MM.YYYY` and the preamble is shorter. There is no
+//! column for the other party, the `Verwendungszweck` starts with its name,
+//! separated from the purpose by a double space or the first SEPA keyword.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::Purpose,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Buchungstag;Wertstellungstag;Verwendungszweck";
+
+#[derive(Debug, Deserialize)]
+struct SpardaLegacyIR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "Umsatz")]
+    umsatz: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+}
+
+/// The other party and the rest of a combined purpose.
+fn split(text: &str) -> (&str, &str) {
+    let end = text
+        .find("  ")
+        .into_iter()
+        .chain(text.find("EREF+"))
+        .chain(text.find("SVWZ+"))
+        .min()
+        .unwrap_or(text.len());
+    (text[..end].trim(), text[end..].trim())
+}
+
+impl TryFrom<SpardaLegacyIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: SpardaLegacyIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        let (payee, purpose) = split(&value.verwendungszweck);
+        let purpose = Purpose::parse(purpose);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungstag into datetime")?,
+            payment: Payment::None,
+            info: purpose.end_to_end.unwrap_or_default(),
+            payee: payee.to_string(),
+            memo: purpose.text,
+            amount: Money::from_decimal(decimal_de(&value.umsatz)?, currency),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct SpardaLegacyIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl SpardaLegacyIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, SpardaLegacyIR>(text.as_bytes(), b';', HEADER),
+                "sparda legacy",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for SpardaLegacyIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let from = rows.first().map(|r| r.date).unwrap_or_default();
+    let to = rows.last().map(|r| r.date).unwrap_or_default();
+    let mut out = format!(
+        "Umsatzanzeige;\nBLZ:;50090500;\nKonto:;1234567890;\nZeitraum:;{};{};\n\n\
+        Buchungstag;Wertstellungstag;Verwendungszweck;Umsatz;Währung\n",
+        from.format("%d.%m.%Y"),
+        to.format("%d.%m.%Y")
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        out.push_str(&format!(
+            "{};{};{}  EREF+{} SVWZ+{};{};EUR\n",
+            date,
+            date,
+            row.payee,
+            row.reference,
+            row.purpose,
+            row.amount_de()
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Umsatzanzeige;\nBLZ:;50090500;\nKonto:;1234567890;\nZeitraum:;01.03.2019;31.03.2019;\n\n\
+            Buchungstag;Wertstellungstag;Verwendungszweck;Umsatz;Währung\n\
+            07.03.2019;07.03.2019;Stadtwerke Musterstadt EREF+4711 SVWZ+Strom März;-1.025,88;EUR\n\
+            06.03.2019;06.03.2019;Erika Mustermann  Geschenk;20,00;EUR\n";
+
+        let records: Vec<Record> = SpardaLegacyIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2019, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke Musterstadt");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payee, "Erika Mustermann");
+        assert_eq!(records[1].memo, "Geschenk");
+    }
+}
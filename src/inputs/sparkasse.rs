@@ -0,0 +1,175 @@
+//! The "CSV-CAMT V8" export of the Sparkassen, the default download of
+//! every Sparkasse in Germany.
+//!
+//! There is no preamble, each row names the own account. The booking text
+//! tells the payment method, the end-to-end reference becomes the info.
+//! Pending transactions are left out.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::Record,
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Auftragskonto;Buchungstag;Valutadatum";
+
+#[derive(Debug, Deserialize)]
+struct SparkasseIR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "Kundenreferenz (End-to-End)", default)]
+    kundenreferenz: String,
+    #[serde(rename = "Beguenstigter/Zahlungspflichtiger")]
+    beguenstigter: String,
+    #[serde(rename = "Kontonummer/IBAN")]
+    iban: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Waehrung")]
+    waehrung: String,
+    #[serde(rename = "Info", default)]
+    info: String,
+}
+
+impl TryFrom<SparkasseIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: SparkasseIR) -> Result<Self> {
+        let currency = iso::find(&value.waehrung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.waehrung))?;
+        let info = match value.kundenreferenz.as_str() {
+            "NOTPROVIDED" => String::new(),
+            _ => value.kundenreferenz,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungstag into datetime")?,
+            payment: booking_payment(&value.buchungstext),
+            info,
+            payee: value.beguenstigter,
+            memo: value.verwendungszweck,
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, currency),
+            iban: value.iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct SparkasseIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl SparkasseIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, SparkasseIR>(text.as_bytes(), b';', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if ir.info == "Umsatz vorgemerkt"));
+                convert(rows, "sparkasse")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for SparkasseIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// The booking text of a Sparkasse for `kind`.
+fn booking_text(kind: Kind) -> &'static str {
+    match kind {
+        Kind::DirectDebit => "FOLGELASTSCHRIFT",
+        Kind::Card => "KARTENZAHLUNG",
+        Kind::StandingOrder => "DAUERAUFTRAG",
+        Kind::Transfer => "ONLINE-UEBERWEISUNG",
+        Kind::Salary => "LOHN GEHALT",
+        Kind::Cash => "BARGELDAUSZAHLUNG",
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Auftragskonto\";\"Buchungstag\";\"Valutadatum\";\"Buchungstext\";\
+        \"Verwendungszweck\";\"Glaeubiger ID\";\"Mandatsreferenz\";\
+        \"Kundenreferenz (End-to-End)\";\"Sammlerreferenz\";\"Lastschrift Ursprungsbetrag\";\
+        \"Auslagenersatz Ruecklastschrift\";\"Beguenstigter/Zahlungspflichtiger\";\
+        \"Kontonummer/IBAN\";\"BIC (SWIFT-Code)\";\"Betrag\";\"Waehrung\";\"Info\"\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%y");
+        out.push_str(&format!(
+            "\"DE89370400440532013000\";\"{}\";\"{}\";\"{}\";\"{}\";\"\";\"\";\"{}\";\"\";\"\";\"\";\
+            \"{}\";\"{}\";\"\";\"{}\";\"EUR\";\"Umsatz gebucht\"\n",
+            date,
+            date,
+            booking_text(row.kind),
+            row.purpose,
+            row.reference,
+            row.payee,
+            row.iban,
+            row.amount_de()
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Auftragskonto\";\"Buchungstag\";\"Valutadatum\";\"Buchungstext\";\"Verwendungszweck\";\"Glaeubiger ID\";\"Mandatsreferenz\";\"Kundenreferenz (End-to-End)\";\"Sammlerreferenz\";\"Lastschrift Ursprungsbetrag\";\"Auslagenersatz Ruecklastschrift\";\"Beguenstigter/Zahlungspflichtiger\";\"Kontonummer/IBAN\";\"BIC (SWIFT-Code)\";\"Betrag\";\"Waehrung\";\"Info\"\n\
+            \"DE12300500001234567890\";\"08.03.24\";\"08.03.24\";\"KARTENZAHLUNG\";\"Bäckerei\";\"\";\"\";\"\";\"\";\"\";\"\";\"Woopsie\";\"DE02120300000000202051\";\"\";\"-5,00\";\"EUR\";\"Umsatz vorgemerkt\"\n\
+            \"DE12300500001234567890\";\"07.03.24\";\"07.03.24\";\"FOLGELASTSCHRIFT\";\"Strom März\";\"DE98ZZZ09999999999\";\"M-1\";\"4711\";\"\";\"\";\"\";\"Stadtwerke\";\"DE02120300000000202051\";\"BYLADEM1001\";\"-1.025,88\";\"EUR\";\"Umsatz gebucht\"\n\
+            \"DE12300500001234567890\";\"06.03.24\";\"06.03.24\";\"GUTSCHR. UEBERWEISUNG\";\"Geschenk\";\"\";\"\";\"NOTPROVIDED\";\"\";\"\";\"\";\"Erika Mustermann\";\"DE02120300000000202051\";\"\";\"20,00\";\"EUR\";\"Umsatz gebucht\"\n";
+
+        let records: Vec<Record> = SparkasseIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+        assert_eq!(records[1].info, "");
+    }
+}
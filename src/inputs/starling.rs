@@ -0,0 +1,157 @@
+//! The statement csv of Starling Bank, comma separated with `DD/MM/YYYY`
+//! dates and amounts in GBP with decimal points.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso::GBP, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Date,Counter Party,Reference,Type";
+
+#[derive(Debug, Deserialize)]
+struct StarlingIR {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Counter Party")]
+    counter_party: String,
+    #[serde(rename = "Reference", default)]
+    reference: String,
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "Amount (GBP)")]
+    amount: String,
+    #[serde(rename = "Notes", default)]
+    notes: String,
+}
+
+/// The payment method of the type column, which is in capitals.
+fn payment(kind: &str) -> Payment {
+    match kind.to_uppercase().as_str() {
+        "FASTER PAYMENT" | "ONLINE PAYMENT" | "BACS" | "CHAPS" => Payment::BankTransfer,
+        "DIRECT DEBIT" => Payment::DirectDebit,
+        "STANDING ORDER" => Payment::StandingOrder,
+        "CARD" | "CONTACTLESS" | "APPLE PAY" | "GOOGLE PAY" | "MASTER CARD" => Payment::DebitCard,
+        "CASH" | "ATM" => Payment::Cash,
+        "INTERNAL TRANSFER" => Payment::InternalTransfer,
+        _ => Payment::None,
+    }
+}
+
+impl TryFrom<StarlingIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: StarlingIR) -> Result<Self> {
+        let amount = decimal(&value.amount, '.')?;
+        let mut memo = vec![value.reference, value.notes];
+        memo.retain(|s| !s.is_empty());
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%d/%m/%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.kind),
+            payee: value.counter_party,
+            memo: memo.join(" "),
+            amount: Money::from_decimal(amount, GBP),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct StarlingIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl StarlingIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, StarlingIR>(text.as_bytes(), b',', HEADER),
+                "starling",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for StarlingIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample statement of `rows` in GBP.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Date,Counter Party,Reference,Type,Amount (GBP),Balance (GBP),Spending Category,Notes\n",
+    );
+    let mut balance = Decimal::new(500000, 2);
+    for row in rows {
+        balance += row.amount;
+        let kind = match row.kind {
+            Kind::Card => "CARD",
+            Kind::DirectDebit => "DIRECT DEBIT",
+            Kind::StandingOrder => "STANDING ORDER",
+            Kind::Cash => "CASH",
+            Kind::Transfer | Kind::Salary => "FASTER PAYMENT",
+        };
+        out.push_str(&format!(
+            "{},{},{},{},{},{},GENERAL,\n",
+            row.date.format("%d/%m/%Y"),
+            row.payee,
+            row.purpose,
+            kind,
+            row.amount,
+            balance
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Date,Counter Party,Reference,Type,Amount (GBP),Balance (GBP),Spending Category,Notes\n\
+            07/03/2024,Tesco,TESCO STORES 1234,CARD,-25.88,4974.12,GROCERIES,\n\
+            08/03/2024,Thames Water,TW123456,DIRECT DEBIT,-42.00,4932.12,BILLS_AND_SERVICES,\n\
+            25/03/2024,Employer Ltd,SALARY MARCH,FASTER PAYMENT,2500.00,7432.12,INCOME,March\n";
+
+        let records: Vec<Record> = StarlingIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Tesco");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25.88", GBP).unwrap());
+        assert_eq!(records[1].payment, Payment::DirectDebit);
+        assert_eq!(records[2].payment, Payment::BankTransfer);
+        assert_eq!(records[2].memo, "SALARY MARCH March");
+    }
+}
@@ -0,0 +1,148 @@
+//! The transaction export of the TF Bank Mastercard Gold.
+//!
+//! Dates are written `YYYY/MM/DD` and amounts with a decimal point, a
+//! thousands space and a trailing sign, as in `1 234.56-`. Both used to be
+//! fixed by hand before the import.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso::EUR, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Transaktionsdatum;Buchungsdatum;Beschreibung;Betrag";
+
+#[derive(Debug, Deserialize)]
+struct TfBankIR {
+    #[serde(rename = "Transaktionsdatum")]
+    transaktionsdatum: String,
+    #[serde(rename = "Beschreibung")]
+    beschreibung: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+}
+
+impl TryFrom<TfBankIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: TfBankIR) -> Result<Self> {
+        // The export writes the sign trailing, spreadsheets rendered by the
+        // Excel input the usual decimal comma
+        let amount = match value.betrag.contains(',') {
+            true => decimal_de(&value.betrag)?,
+            false => decimal(&value.betrag, '.')?,
+        };
+        let text = value.beschreibung.to_lowercase();
+        let payment = match text.contains("zahlung") || text.contains("überweisung") {
+            // Payments onto the card
+            true if amount.is_sign_positive() => Payment::BankTransfer,
+            _ => Payment::CreditCard,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.transaktionsdatum, "%Y/%m/%d")
+                .or_else(|_| NaiveDate::parse_from_str(&value.transaktionsdatum, "%d.%m.%Y"))
+                .into_diagnostic()
+                .wrap_err("Failed converting transaktionsdatum into datetime")?,
+            payment,
+            payee: value.beschreibung,
+            amount: Money::from_decimal(amount, EUR),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct TfBankIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl TfBankIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, TfBankIR>(text.as_bytes(), b';', HEADER),
+                "tf bank",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for TfBankIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from("Transaktionsdatum;Buchungsdatum;Beschreibung;Betrag;Währung\n");
+    for row in rows {
+        let date = row.date.format("%Y/%m/%d");
+        let text = match row.kind {
+            Kind::Salary | Kind::Transfer if row.amount.is_sign_positive() => {
+                "Zahlung - Danke".to_string()
+            }
+            _ => row.payee.clone(),
+        };
+        let sign = match row.amount.is_sign_negative() {
+            true => "-",
+            false => "",
+        };
+        out.push_str(&format!(
+            "{};{};{};{}{};EUR\n",
+            date,
+            date,
+            text,
+            row.amount.abs(),
+            sign
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Transaktionsdatum;Buchungsdatum;Beschreibung;Betrag;Währung\n\
+            2024/03/07;2024/03/08;HOTEL BERLIN;1 025.88-;EUR\n\
+            2024/03/05;2024/03/05;Zahlung - Danke;250.00;EUR\n\
+            06.03.2024;06.03.2024;REWE MARKT;-12,50;EUR\n";
+
+        let records: Vec<Record> = TfBankIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "HOTEL BERLIN");
+        assert_eq!(records[0].payment, Payment::CreditCard);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+        assert_eq!(records[1].amount, Money::from_str("250,00", EUR).unwrap());
+        assert_eq!(records[2].amount, Money::from_str("-12,50", EUR).unwrap());
+    }
+}
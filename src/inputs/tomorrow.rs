@@ -0,0 +1,178 @@
+//! The csv export of Tomorrow, a bank on the accounts of the Solarisbank.
+//!
+//! UTF-8 and comma separated, with ISO dates and decimal points. The
+//! category Tomorrow assigns is mapped onto a HomeBank category by
+//! [`CATEGORIES`], others are left to the rules and the profile.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Date,Time,Counterparty";
+
+/// Categories of Tomorrow and the HomeBank category they become.
+const CATEGORIES: &[(&str, &str)] = &[
+    ("Groceries", "Food:Groceries"),
+    ("Restaurants & Cafes", "Food:Dining Out"),
+    ("Housing", "Home:Rent"),
+    ("Utilities", "Home:Utilities"),
+    ("Mobility", "Transportation"),
+    ("Travel", "Leisure:Travel"),
+    ("Leisure", "Leisure"),
+    ("Shopping", "Shopping"),
+    ("Health", "Health"),
+    ("Insurance", "Insurance"),
+    ("Subscriptions", "Bills:Subscriptions"),
+    ("Cash", "Cash"),
+    ("Salary", "Income:Salary"),
+    ("Income", "Income"),
+    ("Savings", "Savings"),
+    ("Fees", "Bank Charges"),
+];
+
+#[derive(Debug, Deserialize)]
+struct TomorrowIR {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Counterparty")]
+    counterparty: String,
+    #[serde(rename = "IBAN", default)]
+    iban: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Currency")]
+    currency: String,
+    #[serde(rename = "Category", default)]
+    category: String,
+    #[serde(rename = "Type", default)]
+    kind: String,
+}
+
+impl TryFrom<TomorrowIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: TomorrowIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        let amount = decimal(&value.amount, '.')?;
+        let category = CATEGORIES
+            .iter()
+            .find(|(tomorrow, _)| *tomorrow == value.category)
+            .map(|(_, homebank)| homebank.to_string())
+            .unwrap_or_default();
+        let payment = match value.kind.as_str() {
+            "Card payment" => Payment::DebitCard,
+            "Direct debit" => Payment::DirectDebit,
+            "Standing order" => Payment::StandingOrder,
+            "Transfer" => Payment::BankTransfer,
+            "ATM withdrawal" => Payment::Cash,
+            _ => Payment::None,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            payee: value.counterparty,
+            memo: value.description,
+            amount: Money::from_decimal(amount, currency),
+            category,
+            iban: value.iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct TomorrowIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl TomorrowIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, TomorrowIR>(text.as_bytes(), b',', HEADER),
+                "tomorrow",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for TomorrowIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out =
+        String::from("Date,Time,Counterparty,IBAN,Description,Amount,Currency,Category,Type\n");
+    for row in rows {
+        let (category, kind) = match row.kind {
+            Kind::DirectDebit => ("Utilities", "Direct debit"),
+            Kind::Card => ("Groceries", "Card payment"),
+            Kind::StandingOrder => ("Housing", "Standing order"),
+            Kind::Transfer => ("Transfers", "Transfer"),
+            Kind::Salary => ("Salary", "Transfer"),
+            Kind::Cash => ("Cash", "ATM withdrawal"),
+        };
+        out.push_str(&format!(
+            "{},12:00:00,{},{},{},{},EUR,{},{}\n",
+            row.date, row.payee, row.iban, row.purpose, row.amount, category, kind
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Date,Time,Counterparty,IBAN,Description,Amount,Currency,Category,Type\n\
+            2024-03-07,12:30:00,REWE Markt GmbH,,Einkauf,-25.88,EUR,Groceries,Card payment\n\
+            2024-03-06,08:00:00,Erika Mustermann,DE02120300000000202051,Geschenk,20.00,EUR,Gifts,Transfer\n";
+
+        let records: Vec<Record> = TomorrowIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "REWE Markt GmbH");
+        assert_eq!(records[0].category, "Food:Groceries");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].category, "");
+        assert_eq!(records[1].iban, "DE02120300000000202051");
+    }
+}
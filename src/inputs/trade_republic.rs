@@ -0,0 +1,203 @@
+//! The transaction export of Trade Republic, comma separated with ISO
+//! dates and decimal points.
+//!
+//! Card payments, interest, dividends, transfers and taxes move the cash
+//! account and are always read. Securities trades, saveback included as it
+//! buys shares without touching the cash, are left out unless asked for
+//! with `--include-trades`, in the category given by `--trade-category`.
+//! Fees and taxes of a row are part of its amount.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{self, convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "datetime,date,account_type,category,type";
+/// The category of securities trades.
+const TRADING: &str = "TRADING";
+
+#[derive(Debug, Deserialize)]
+struct TradeRepublicIR {
+    date: String,
+    category: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    shares: String,
+    amount: String,
+    #[serde(default)]
+    fee: String,
+    #[serde(default)]
+    tax: String,
+    currency: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    transaction_id: String,
+    #[serde(default)]
+    counterparty_name: String,
+    #[serde(default)]
+    counterparty_iban: String,
+    #[serde(skip)]
+    trade_category: String,
+}
+
+impl TryFrom<TradeRepublicIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: TradeRepublicIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        let amount =
+            decimal(&value.amount, '.')? + decimal(&value.fee, '.')? + decimal(&value.tax, '.')?;
+        let payment = match value.kind.as_str() {
+            "CARD_TRANSACTION" | "CARD_REFUND" => Payment::DebitCard,
+            "INTEREST_PAYMENT" | "DIVIDEND" | "SAVEBACK" => Payment::Deposit,
+            "CUSTOMER_INBOUND" | "CUSTOMER_OUTBOUND_REQUEST" => Payment::BankTransfer,
+            "FEE" | "TAX_OPTIMIZATION" => Payment::FinancialInstitutionFee,
+            _ => Payment::None,
+        };
+        let payee = match value.counterparty_name.is_empty() {
+            true => value.name,
+            false => value.counterparty_name,
+        };
+        let memo = match (value.category.as_str(), value.shares.is_empty()) {
+            (TRADING, false) => format!("{} {} shares", value.kind, value.shares),
+            _ => value.description,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            info: value.transaction_id,
+            payee,
+            memo,
+            amount: Money::from_decimal(amount, currency),
+            category: value.trade_category,
+            iban: value.counterparty_iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct TradeRepublicIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl TradeRepublicIter {
+    /// Reads the export, leaving out securities trades unless `trades` is
+    /// the category to give them.
+    pub fn new<R: Read>(rdr: R, trades: Option<&str>) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                let mut rows = csv_rows::<_, TradeRepublicIR>(text.as_bytes(), b',', HEADER);
+                util::trades(
+                    &mut rows,
+                    trades,
+                    |ir| ir.category == TRADING,
+                    |ir| &mut ir.trade_category,
+                );
+                convert(rows, "trade republic")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for TradeRepublicIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, all of them moving the cash account.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "datetime,date,account_type,category,type,asset_class,name,symbol,shares,price,amount,\
+        fee,tax,currency,original_amount,original_currency,fx_rate,description,transaction_id,\
+        counterparty_name,counterparty_iban,payment_reference,mcc_code\n",
+    );
+    for row in rows {
+        let (category, kind) = match (row.kind, row.amount.is_sign_negative()) {
+            (Kind::Card, _) => ("CARD", "CARD_TRANSACTION"),
+            (_, true) => ("CASH", "CUSTOMER_OUTBOUND_REQUEST"),
+            (_, false) => ("CASH", "CUSTOMER_INBOUND"),
+        };
+        out.push_str(&format!(
+            "{},{},DEFAULT,{},{},,,,,,{},,,EUR,,,,{},{},{},{},,\n",
+            row.date.format("%Y-%m-%dT10:00:00.000Z"),
+            row.date.format("%Y-%m-%d"),
+            category,
+            kind,
+            row.amount,
+            row.purpose,
+            row.reference,
+            row.payee,
+            row.iban
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "datetime,date,account_type,category,type,asset_class,name,symbol,shares,price,amount,fee,tax,currency,original_amount,original_currency,fx_rate,description,transaction_id,counterparty_name,counterparty_iban,payment_reference,mcc_code\n\
+            2024-03-07T10:22:33.000Z,2024-03-07,DEFAULT,CARD,CARD_TRANSACTION,,,,,,-25.88,,,EUR,,,,REWE,t-1,Rewe,,,5411\n\
+            2024-03-08T10:00:00.000Z,2024-03-08,DEFAULT,TRADING,BUY,STOCK,Apple Inc,US0378331005,2,170.00,-340.00,-1.00,,EUR,,,,,t-2,,,,\n\
+            2024-03-09T10:00:00.000Z,2024-03-09,DEFAULT,TRADING,SAVEBACK,FUND,MSCI World,IE00B4L5Y983,0.25,100.00,25.00,,,EUR,,,,,t-3,,,,\n\
+            2024-03-10T10:00:00.000Z,2024-03-10,DEFAULT,CASH,DIVIDEND,STOCK,Apple Inc,US0378331005,,,1.20,,-0.32,EUR,,,,,t-4,,,,\n\
+            2024-04-01T10:00:00.000Z,2024-04-01,DEFAULT,CASH,INTEREST_PAYMENT,,,,,,12.50,,,EUR,,,,Zinsen,t-5,,,,\n";
+
+        let records: Vec<Record> = TradeRepublicIter::new(input.as_bytes(), None)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Rewe");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::Deposit);
+        assert_eq!(records[1].amount, Money::from_str("0,88", EUR).unwrap());
+        assert_eq!(records[2].payment, Payment::Deposit);
+
+        let records: Vec<Record> = TradeRepublicIter::new(input.as_bytes(), Some("Investment"))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[1].amount, Money::from_str("-341,00", EUR).unwrap());
+        assert_eq!(records[1].memo, "BUY 2 shares");
+        assert_eq!(records[1].category, "Investment");
+        assert_eq!(records[0].category, "");
+    }
+}
@@ -0,0 +1,153 @@
+//! The csv export of the Triodos Bank Germany.
+//!
+//! Its columns follow the fields of MT940: a business transaction code
+//! (GVC) telling the payment method, the booking text and the purpose with
+//! its SEPA keywords, which are read like those of `:86:` fields.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::{payment, Purpose},
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+const HEADER: &str = "Buchungstag;Valutadatum;Geschäftsvorfallcode";
+
+#[derive(Debug, Deserialize)]
+struct TriodosIR {
+    #[serde(rename = "Buchungstag")]
+    buchungstag: String,
+    #[serde(rename = "Geschäftsvorfallcode")]
+    gvc: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Verwendungszweck")]
+    verwendungszweck: String,
+    #[serde(rename = "Name Zahlungsbeteiligter")]
+    name: String,
+    #[serde(rename = "IBAN Zahlungsbeteiligter")]
+    iban: String,
+    #[serde(rename = "Betrag")]
+    betrag: String,
+    #[serde(rename = "Währung")]
+    währung: String,
+}
+
+impl TryFrom<TriodosIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: TriodosIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        let purpose = Purpose::parse(&value.verwendungszweck);
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungstag, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting buchungstag into datetime")?,
+            payment: payment(&value.gvc),
+            info: purpose.end_to_end.unwrap_or_default(),
+            payee: value.name,
+            memo: match purpose.text.is_empty() {
+                true => value.buchungstext,
+                false => purpose.text,
+            },
+            amount: Money::from_decimal(decimal_de(&value.betrag)?, currency),
+            iban: value.iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct TriodosIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl TriodosIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, TriodosIR>(text.as_bytes(), b';', HEADER),
+                "triodos",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for TriodosIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Triodos Bank N.V. Deutschland\nKonto;DE89370400440532013000\n\n\
+        Buchungstag;Valutadatum;Geschäftsvorfallcode;Buchungstext;Verwendungszweck;\
+        Name Zahlungsbeteiligter;IBAN Zahlungsbeteiligter;BIC Zahlungsbeteiligter;Betrag;Währung\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        out.push_str(&format!(
+            "{};{};{};{};EREF+{} SVWZ+{};{};{};;{};EUR\n",
+            date,
+            date,
+            row.kind.gvc(),
+            row.kind.booking_text(),
+            row.reference,
+            row.purpose,
+            row.payee,
+            row.iban,
+            row.amount_de()
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Triodos Bank N.V. Deutschland\nKonto;DE12500310001234567800\n\n\
+            Buchungstag;Valutadatum;Geschäftsvorfallcode;Buchungstext;Verwendungszweck;Name Zahlungsbeteiligter;IBAN Zahlungsbeteiligter;BIC Zahlungsbeteiligter;Betrag;Währung\n\
+            07.03.2024;07.03.2024;105;SEPA-BASISLASTSCHRIFT;EREF+4711 MREF+M-1 SVWZ+Strom März;Stadtwerke;DE02120300000000202051;BYLADEM1001;-1.025,88;EUR\n\
+            06.03.2024;06.03.2024;805;ABSCHLUSS;;;;;-5,00;EUR\n";
+
+        let records: Vec<Record> = TriodosIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].memo, "ABSCHLUSS");
+        assert_eq!(records[1].payment, Payment::FinancialInstitutionFee);
+    }
+}
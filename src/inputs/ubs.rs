@@ -0,0 +1,234 @@
+//! The account statement csv of UBS, `;` separated with ISO dates and
+//! amounts like `1'234.50`.
+//!
+//! A block of account details comes before the header and footnote rows
+//! after the bookings, neither of which is read. Debits and credits have
+//! columns of their own, the currency of the account is passed through.
+//! The third description holds labelled fields like the IBAN of the other
+//! party and the reason for the payment.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Abschlussdatum;Abschlusszeit;Buchungsdatum";
+
+#[derive(Debug, Deserialize)]
+struct UbsIR {
+    #[serde(rename = "Buchungsdatum", default)]
+    buchungsdatum: String,
+    #[serde(rename = "Währung", default)]
+    währung: String,
+    #[serde(rename = "Belastung", default)]
+    belastung: String,
+    #[serde(rename = "Gutschrift", default)]
+    gutschrift: String,
+    #[serde(rename = "Transaktions-Nr.", default)]
+    transaktions_nr: String,
+    #[serde(rename = "Beschreibung1", default)]
+    beschreibung1: String,
+    #[serde(rename = "Beschreibung2", default)]
+    beschreibung2: String,
+    #[serde(rename = "Beschreibung3", default)]
+    beschreibung3: String,
+}
+
+impl UbsIR {
+    /// Balance rows between the bookings fill neither date nor amount.
+    fn is_booking(&self) -> bool {
+        let amount = !self.belastung.is_empty() || !self.gutschrift.is_empty();
+        !self.buchungsdatum.is_empty() && amount
+    }
+}
+
+/// The payment method of the booking type, which is German with Swiss
+/// terms beside the ones of German banks.
+fn payment(text: &str) -> Payment {
+    let lower = text.to_lowercase();
+    match booking_payment(text) {
+        Payment::None if lower.contains("bancomat") => Payment::Cash,
+        Payment::None if lower.contains("lsv") => Payment::DirectDebit,
+        Payment::None if lower.contains("e-banking") || lower.contains("zahlung") => {
+            Payment::BankTransfer
+        }
+        payment => payment,
+    }
+}
+
+impl TryFrom<UbsIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: UbsIR) -> Result<Self> {
+        let currency = iso::find(&value.währung)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.währung))?;
+        // Debits may or may not carry their sign
+        let amount = decimal(&value.gutschrift, '.')? - decimal(&value.belastung, '.')?.abs();
+        let mut iban = String::new();
+        let mut memo = vec![value.beschreibung2.clone()];
+        for field in value.beschreibung3.split(';') {
+            match field.split_once(':') {
+                Some((label, v)) if label.contains("IBAN") => iban = v.trim().to_string(),
+                Some((label, v)) if label.contains("Zahlungsgrund") => {
+                    memo.push(v.trim().to_string())
+                }
+                _ => {}
+            }
+        }
+        memo.retain(|s| !s.is_empty());
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.buchungsdatum, "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(&value.buchungsdatum, "%d.%m.%Y"))
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.beschreibung2),
+            info: value.transaktions_nr,
+            payee: value.beschreibung1,
+            memo: memo.join(" "),
+            amount: Money::from_decimal(amount, currency),
+            iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct UbsIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl UbsIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => {
+                // The footnotes follow the bookings after a blank line
+                let mut lines = Vec::new();
+                let mut header = false;
+                for line in text.lines() {
+                    if header && line.trim().is_empty() {
+                        break;
+                    }
+                    header |= line.starts_with(HEADER);
+                    lines.push(line);
+                }
+                let text = lines.join("\n");
+                let mut rows = csv_rows::<_, UbsIR>(text.as_bytes(), b';', HEADER);
+                rows.retain(|ir| !matches!(ir, Ok(ir) if !ir.is_booking()));
+                convert(rows, "ubs")
+            }
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for UbsIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// An amount like `1'234.50`.
+fn amount_ch(amount: Decimal) -> String {
+    let amount = format!("{:.2}", amount.abs());
+    let (int, frac) = amount.split_once('.').unwrap_or((&amount, "00"));
+    let mut out = String::new();
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            out.push('\'');
+        }
+        out.push(c);
+    }
+    format!("{}.{}", out, frac)
+}
+
+/// A sample statement of `rows` in CHF, with the account details and
+/// footnotes of the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Kontonummer:;0235 00123456.01;\n\
+        IBAN:;CH93 0076 2011 6238 5295 7;\n\
+        Bewertet in:;CHF;\n\
+        \n\
+        Abschlussdatum;Abschlusszeit;Buchungsdatum;Valutadatum;Währung;Belastung;Gutschrift;\
+        Einzelbetrag;Saldo;Transaktions-Nr.;Beschreibung1;Beschreibung2;Beschreibung3;Fussnoten\n",
+    );
+    for row in rows {
+        let date = row.date.format("%Y-%m-%d");
+        let (debit, credit) = match row.amount.is_sign_negative() {
+            true => (format!("-{}", amount_ch(row.amount)), String::new()),
+            false => (String::new(), amount_ch(row.amount)),
+        };
+        out.push_str(&format!(
+            "{};10:00:00;{};{};CHF;{};{};;;{};{};{};\"Zahlungsgrund: {}; Konto-Nr. IBAN: {}\";\n",
+            date,
+            date,
+            date,
+            debit,
+            credit,
+            row.reference,
+            row.payee,
+            row.kind.booking_text(),
+            row.purpose,
+            row.iban
+        ));
+    }
+    out.push_str("\nAlle Angaben ohne Gewähr;\n");
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::CHF;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "Kontonummer:;0235 00123456.01;\n\
+            Bewertet in:;CHF;\n\
+            \n\
+            Abschlussdatum;Abschlusszeit;Buchungsdatum;Valutadatum;Währung;Belastung;Gutschrift;Einzelbetrag;Saldo;Transaktions-Nr.;Beschreibung1;Beschreibung2;Beschreibung3;Fussnoten\n\
+            2024-03-07;18:30:12;2024-03-07;2024-03-07;CHF;-25.80;;;4'974.20;9930123AB;Coop-1234 Zürich;Debitkarten-Zahlung;;\n\
+            2024-03-08;06:00:00;2024-03-08;2024-03-08;CHF;-1'650.00;;;3'324.20;9930124AB;Immo AG;e-banking-Auftrag;\"Zahlungsgrund: Miete März; Konto-Nr. IBAN: CH5604835012345678009\";\n\
+            2024-03-25;06:00:00;2024-03-25;2024-03-25;CHF;;6'500.00;;9'824.20;9930125AB;Arbeitgeber AG;Gutschrift;\"Zahlungsgrund: Lohn März\";\n\
+            \n\
+            Alle Angaben ohne Gewähr;\n";
+
+        let records: Vec<Record> = UbsIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Coop-1234 Zürich");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25.80", CHF).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+        assert_eq!(records[1].iban, "CH5604835012345678009");
+        assert_eq!(records[1].memo, "e-banking-Auftrag Miete März");
+        assert_eq!(records[1].amount, Money::from_str("-1650.00", CHF).unwrap());
+        assert_eq!(records[2].amount, Money::from_str("6500.00", CHF).unwrap());
+    }
+}
@@ -0,0 +1,119 @@
+//! Inputs downloaded from `https://` urls, as of exports on a NAS or
+//! WebDAV share.
+//!
+//! Credentials and headers come from the `downloads` of the config file,
+//! the first entry the url starts with applies:
+//!
+//! ```toml
+//! [[downloads]]
+//! url = "https://nas.example.com/webdav/"
+//! user = "max"
+//! password_env = "NAS_PASSWORD"
+//! headers = { "X-Requested-By" = "hbconv" }
+//! ```
+
+use std::{collections::BTreeMap, env, io::Read, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Deserialize;
+use tracing::info;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Download {
+    /// Prefix of the urls these settings apply to
+    pub url: String,
+    /// User for basic auth
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// Environment variable holding the password, to keep it out of the file
+    pub password_env: Option<String>,
+    /// Additional request headers
+    pub headers: BTreeMap<String, String>,
+}
+
+/// The url of an input given as one.
+pub fn url(path: &Path) -> Option<&str> {
+    path.to_str()
+        .filter(|p| p.starts_with("https://") || p.starts_with("http://"))
+}
+
+/// The content at `url`.
+pub fn download(url: &str, downloads: &[Download]) -> Result<Vec<u8>> {
+    let request = request(url, downloads)?;
+    let mut content = Vec::new();
+    request
+        .call()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed downloading {}", url))?
+        .into_reader()
+        .read_to_end(&mut content)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed downloading {}", url))?;
+
+    info!(url, bytes = content.len(), "Downloaded input");
+    Ok(content)
+}
+
+fn request(url: &str, downloads: &[Download]) -> Result<ureq::Request> {
+    if !url.starts_with("https://") {
+        return Err(miette!(
+            help = "Download it by hand if the server has no https",
+            "Only https urls are downloaded, not {}",
+            url
+        ));
+    }
+
+    let mut request = ureq::get(url);
+    let Some(download) = downloads.iter().find(|d| url.starts_with(&d.url)) else {
+        return Ok(request);
+    };
+
+    if let Some(user) = &download.user {
+        let password = match &download.password_env {
+            Some(var) => env::var(var)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed reading the password from ${}", var))?,
+            None => download.password.clone().unwrap_or_default(),
+        };
+        let credentials = STANDARD.encode(format!("{}:{}", user, password));
+        request = request.set("Authorization", &format!("Basic {}", credentials));
+    }
+    for (name, value) in &download.headers {
+        request = request.set(name, value);
+    }
+    Ok(request)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_request() {
+        let downloads = [Download {
+            url: "https://nas.example.com/webdav/".to_string(),
+            user: Some("max".to_string()),
+            password: Some("secret".to_string()),
+            headers: BTreeMap::from([("X-Requested-By".to_string(), "hbconv".to_string())]),
+            ..Download::default()
+        }];
+
+        let nas = request("https://nas.example.com/webdav/giro.csv", &downloads).unwrap();
+        assert_eq!(nas.header("Authorization"), Some("Basic bWF4OnNlY3JldA=="));
+        assert_eq!(nas.header("X-Requested-By"), Some("hbconv"));
+
+        let bank = request("https://bank.example.com/export.csv", &downloads).unwrap();
+        assert_eq!(bank.header("Authorization"), None);
+
+        assert!(request("http://nas.example.com/webdav/giro.csv", &downloads).is_err());
+        assert_eq!(
+            url(Path::new("https://nas.example.com/giro.csv")),
+            Some("https://nas.example.com/giro.csv")
+        );
+        assert_eq!(url(Path::new("giro.csv")), None);
+    }
+}
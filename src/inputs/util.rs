@@ -1,4 +1,15 @@
-use std::iter::Peekable;
+use std::{fmt::Debug, io::Read, iter::Peekable, str::FromStr};
+
+use csv::{ReaderBuilder, Trim};
+use encoding_rs::WINDOWS_1252;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use tracing::trace;
+
+use rusty_money::{iso::Currency, Money};
+
+use crate::homebank::{Record, SplitLine};
 
 pub struct SkipLastIterator<I: Iterator>(Peekable<I>);
 
@@ -17,3 +28,166 @@ pub trait SkipLast: Iterator + Sized {
 }
 
 impl<I: Iterator> SkipLast for I {}
+
+/// The text of an export, UTF-8 or else Windows-1252 as most German banks
+/// write it. A BOM is left out.
+pub fn decode<R: Read>(mut rdr: R) -> Result<String> {
+    let mut content = Vec::new();
+    rdr.read_to_end(&mut content)
+        .into_diagnostic()
+        .wrap_err("Failed reading input")?;
+    let text = match String::from_utf8(content) {
+        Ok(text) => text,
+        Err(e) => WINDOWS_1252.decode(e.as_bytes()).0.into_owned(),
+    };
+    Ok(text.trim_start_matches('\u{feff}').to_string())
+}
+
+/// The rows of a csv export below its header line, the first one starting
+/// with `header` ignoring quotes and case. Preambles of any length before
+/// it are left out. Not finding it gives a single error.
+pub fn csv_rows<R: Read, IR: DeserializeOwned>(
+    rdr: R,
+    delimiter: u8,
+    header: &str,
+) -> Vec<Result<IR>> {
+    let text = match decode(rdr) {
+        Ok(text) => text,
+        Err(e) => return vec![Err(e)],
+    };
+    let lower = header.to_lowercase();
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        if line.replace('"', "").to_lowercase().starts_with(&lower) {
+            break;
+        }
+        start += line.len();
+    }
+    if start == text.len() {
+        return vec![Err(miette!("No header line starting with '{}'", header))];
+    }
+
+    ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(&text.as_bytes()[start..])
+        .into_deserialize()
+        .map(|row| {
+            row.into_diagnostic()
+                .wrap_err("Failed deserializing record")
+        })
+        .collect()
+}
+
+/// An amount with `point` as its decimal separator, as in `£1,234.56` or
+/// `1'234.56 CHF`. Thousands separators, currency symbols and whatever else
+/// is not a digit or sign are left out. A trailing sign, as in `25.88-`, is
+/// taken like a leading one. Empty amounts are zero.
+pub fn decimal(value: &str, point: char) -> Result<Decimal> {
+    if value.trim().is_empty() {
+        return Ok(Decimal::ZERO);
+    }
+    let digits: String = value
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '-' | '+') || *c == point)
+        .map(|c| match c == point {
+            true => '.',
+            false => c,
+        })
+        .collect();
+    let digits = match digits.strip_suffix(['-', '+']) {
+        Some(rest) if !rest.starts_with(['-', '+']) => format!("{}{}", &digits[rest.len()..], rest),
+        _ => digits,
+    };
+    Decimal::from_str(&digits)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed converting amount '{}'", value))
+}
+
+/// An amount as German banks write it, as in `-1.234,56 €`.
+pub fn decimal_de(value: &str) -> Result<Decimal> {
+    match value.trim().is_empty() {
+        true => Err(miette!("Failed converting empty amount")),
+        false => decimal(value, ','),
+    }
+}
+
+/// The total of a payment with a fee of `provider`, split into the payment
+/// and the fee, which gets the category `Fees:<provider>`.
+pub fn with_fee(
+    gross: Money<'static, Currency>,
+    fee: Money<'static, Currency>,
+    memo: &str,
+    provider: &str,
+) -> (Money<'static, Currency>, Vec<SplitLine>) {
+    let total = Money::from_decimal(gross.amount() + fee.amount(), gross.currency());
+    let splits = vec![
+        SplitLine {
+            amount: gross,
+            category: String::new(),
+            memo: memo.to_string(),
+        },
+        SplitLine {
+            amount: fee,
+            category: format!("Fees:{}", provider),
+            memo: format!("{} fee", provider),
+        },
+    ];
+    (total, splits)
+}
+
+/// Leaves out the rows of securities trades, told apart by `is_trade`,
+/// unless `trades` is the category to give them. `category` points to the
+/// field of the row keeping it, which the exports have no column for and
+/// is skipped when deserializing.
+pub fn trades<IR>(
+    rows: &mut Vec<Result<IR>>,
+    trades: Option<&str>,
+    is_trade: impl Fn(&IR) -> bool,
+    category: impl Fn(&mut IR) -> &mut String,
+) {
+    match trades {
+        Some(trades) => {
+            for ir in rows.iter_mut().flatten().filter(|ir| is_trade(ir)) {
+                *category(ir) = trades.to_string();
+            }
+        }
+        None => rows.retain(|ir| !matches!(ir, Ok(ir) if is_trade(ir))),
+    }
+}
+
+/// The records of the rows read as `IR`, tracing each row of the `bank`.
+pub fn convert<IR>(rows: Vec<Result<IR>>, bank: &str) -> Vec<Result<Record>>
+where
+    IR: Debug,
+    Record: TryFrom<IR, Error = Report>,
+{
+    rows.into_iter()
+        .map(|ir| {
+            let ir = ir?;
+            trace!(?ir, bank, "Read row");
+            Record::try_from(ir).wrap_err("Failed converting record")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_decimal() {
+        let amount = |value| decimal(value, '.').unwrap();
+        assert_eq!(amount("-£1,234.56"), Decimal::new(-123456, 2));
+        assert_eq!(amount("1'234.56 CHF"), Decimal::new(123456, 2));
+        assert_eq!(amount(" "), Decimal::ZERO);
+        assert_eq!(amount("25.88-"), Decimal::new(-2588, 2));
+        assert_eq!(decimal_de("1.025,88+").unwrap(), Decimal::new(102588, 2));
+        assert_eq!(decimal_de("-1.234,56 €").unwrap(), Decimal::new(-123456, 2));
+        assert!(decimal("n/a", '.').is_err());
+        assert!(decimal_de("").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! The transaction export of Vivid Money, UTF-8 with decimal points.
+//!
+//! Cashback Vivid pays out becomes a deposit, moving money between the main
+//! account and its pockets an internal transfer. The pocket goes into the
+//! memo of those transfers, so either side can be told apart.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "Booking date;Value date;Type";
+
+#[derive(Debug, Deserialize)]
+struct VividIR {
+    #[serde(rename = "Booking date")]
+    booking_date: String,
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "Counterparty", default)]
+    counterparty: String,
+    #[serde(rename = "IBAN", default)]
+    iban: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Currency")]
+    currency: String,
+    #[serde(rename = "Pocket", default)]
+    pocket: String,
+}
+
+impl TryFrom<VividIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: VividIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        let amount = decimal(&value.amount, '.')?;
+        let payment = match value.kind.as_str() {
+            "Card payment" => Payment::DebitCard,
+            "Direct debit" => Payment::DirectDebit,
+            "Incoming transfer" | "Outgoing transfer" => Payment::BankTransfer,
+            "Standing order" => Payment::StandingOrder,
+            "ATM withdrawal" => Payment::Cash,
+            "Cashback" => Payment::Deposit,
+            "Pocket transfer" => Payment::InternalTransfer,
+            "Fee" => Payment::FinancialInstitutionFee,
+            _ => Payment::None,
+        };
+        let memo = match (payment, value.description.is_empty()) {
+            (Payment::InternalTransfer, true) => format!("Pocket {}", value.pocket),
+            (Payment::InternalTransfer, false) => {
+                format!("{} (Pocket {})", value.description, value.pocket)
+            }
+            _ => value.description,
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.booking_date, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting booking date into datetime")?,
+            payment,
+            payee: value.counterparty,
+            memo,
+            amount: Money::from_decimal(amount, currency),
+            iban: value.iban,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct VividIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl VividIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, VividIR>(text.as_bytes(), b';', HEADER),
+                "vivid",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for VividIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, the standing orders filling a pocket.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "Booking date;Value date;Type;Counterparty;IBAN;Description;Amount;Currency;Pocket\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let (kind, pocket) = match row.kind {
+            Kind::DirectDebit => ("Direct debit", ""),
+            Kind::Card => ("Card payment", ""),
+            Kind::StandingOrder => ("Pocket transfer", "Savings"),
+            Kind::Transfer => ("Outgoing transfer", ""),
+            Kind::Salary => ("Incoming transfer", ""),
+            Kind::Cash => ("ATM withdrawal", ""),
+        };
+        out.push_str(&format!(
+            "{};{};{};{};{};{};{};EUR;{}\n",
+            date, date, kind, row.payee, row.iban, row.purpose, row.amount, pocket
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input =
+            "Booking date;Value date;Type;Counterparty;IBAN;Description;Amount;Currency;Pocket\n\
+            07.03.2024;07.03.2024;Card payment;REWE Markt GmbH;;Einkauf;-25.88;EUR;\n\
+            06.03.2024;06.03.2024;Cashback;Vivid Money;;Cashback Februar;1.20;EUR;\n\
+            05.03.2024;05.03.2024;Pocket transfer;;;;-100.00;EUR;Urlaub\n";
+
+        let records: Vec<Record> = VividIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "REWE Markt GmbH");
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(records[1].payment, Payment::Deposit);
+        assert_eq!(records[2].payment, Payment::InternalTransfer);
+        assert_eq!(records[2].memo, "Pocket Urlaub");
+    }
+}
@@ -0,0 +1,170 @@
+//! The "Umsätze" csv of VR-NetWorld, used by the Volksbanken,
+//! Raiffeisenbanken and the GLS Bank.
+//!
+//! The purpose comes in up to fourteen lines `VWZ1` to `VWZ14`, joined into
+//! the memo with the SEPA keywords left out. Amounts are unsigned, the
+//! `S/H` column tells debit (Soll) from credit (Haben).
+
+use std::{collections::HashMap, io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::{booking_payment, Purpose},
+    util::{convert, csv_rows, decimal_de, decode},
+    RecordIteratorRes,
+};
+use crate::{homebank::Record, sample::Row};
+
+const HEADER: &str = "Buchungstag;Valuta;";
+
+/// A row by column name, the number of purpose lines differs between
+/// exports.
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub(super) struct VolksbankIR(HashMap<String, String>);
+
+impl VolksbankIR {
+    fn get(&self, column: &str) -> &str {
+        self.0.get(column).map_or("", String::as_str)
+    }
+
+    /// The record of the row, naming the other party in the `payee` column
+    /// and the purpose lines `purpose` followed by their number.
+    pub(super) fn record(&self, payee: &str, purpose: &str) -> Result<Record> {
+        let date = NaiveDate::parse_from_str(self.get("Buchungstag"), "%d.%m.%Y")
+            .into_diagnostic()
+            .wrap_err("Failed converting buchungstag into datetime")?;
+        let currency = iso::find(self.get("Währung"))
+            .ok_or_else(|| miette!("Unknown currency '{}'", self.get("Währung")))?;
+        let mut amount = decimal_de(self.get("Betrag"))?;
+        if self.get("S/H") == "S" {
+            amount = -amount;
+        }
+
+        let lines: Vec<&str> = (1..=14)
+            .map(|n| self.get(&format!("{}{}", purpose, n)))
+            .filter(|line| !line.is_empty())
+            .collect();
+        let purpose = Purpose::parse(&lines.join(" "));
+
+        Ok(Record {
+            date,
+            payment: booking_payment(self.get("Buchungstext")),
+            info: purpose.end_to_end.unwrap_or_default(),
+            payee: self.get(payee).to_string(),
+            memo: purpose.text,
+            amount: Money::from_decimal(amount, currency),
+            iban: self.get("IBAN").to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+impl TryFrom<VolksbankIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: VolksbankIR) -> Result<Self> {
+        value.record("Auftraggeber/Zahlungsempfänger", "VWZ")
+    }
+}
+
+pub struct VolksbankIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl VolksbankIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                csv_rows::<_, VolksbankIR>(text.as_bytes(), b';', HEADER),
+                "volksbank",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for VolksbankIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`, in Windows-1252 like the real ones.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let vwz: Vec<String> = (1..=14).map(|n| format!("\"VWZ{}\"", n)).collect();
+    let mut out = format!(
+        "\"Buchungstag\";\"Valuta\";\"Auftraggeber/Zahlungsempfänger\";\"IBAN\";\"BIC\";\
+        \"Buchungstext\";{};\"Betrag\";\"Währung\";\"S/H\"\n",
+        vwz.join(";")
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let mut lines = vec![
+            format!("EREF+{}", row.reference),
+            format!("SVWZ+{}", row.purpose),
+        ];
+        lines.resize(14, String::new());
+        let lines: Vec<String> = lines.iter().map(|l| format!("\"{}\"", l)).collect();
+        let sign = match row.amount.is_sign_negative() {
+            true => "S",
+            false => "H",
+        };
+        out.push_str(&format!(
+            "\"{}\";\"{}\";\"{}\";\"{}\";\"GENODEF1XXX\";\"{}\";{};\"{}\";\"EUR\";\"{}\"\n",
+            date,
+            date,
+            row.payee,
+            row.iban,
+            row.kind.booking_text(),
+            lines.join(";"),
+            row.amount.abs().to_string().replace('.', ","),
+            sign
+        ));
+    }
+    encoding_rs::WINDOWS_1252.encode(&out).0.into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Umsatzanzeige\"\n\"BLZ:\";\"43060967\"\n\"Konto:\";\"1234567800\"\n\n\
+            \"Buchungstag\";\"Valuta\";\"Auftraggeber/Zahlungsempfänger\";\"IBAN\";\"BIC\";\"Buchungstext\";\"VWZ1\";\"VWZ2\";\"VWZ3\";\"Betrag\";\"Währung\";\"S/H\"\n\
+            \"07.03.2024\";\"07.03.2024\";\"Stadtwerke\";\"DE02120300000000202051\";\"BYLADEM1001\";\"Basislastschrift\";\"EREF+4711 MREF+M-1\";\"SVWZ+Strom\";\"März\";\"1.025,88\";\"EUR\";\"S\"\n\
+            \"06.03.2024\";\"06.03.2024\";\"Arbeitgeber GmbH\";\"DE02120300000000202051\";\"BYLADEM1001\";\"Gehalt/Rente\";\"Lohn\";\"\";\"\";\"2.000,00\";\"EUR\";\"H\"\n";
+
+        let records: Vec<Record> = VolksbankIter::new(WINDOWS_1252.encode(input).0.as_ref())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payee, "Stadtwerke");
+        assert_eq!(records[0].memo, "Strom März");
+        assert_eq!(records[0].info, "4711");
+        assert_eq!(records[0].payment, Payment::DirectDebit);
+        assert_eq!(records[0].amount, Money::from_str("-1025,88", EUR).unwrap());
+        assert_eq!(records[1].memo, "Lohn");
+        assert_eq!(records[1].amount, Money::from_str("2000", EUR).unwrap());
+    }
+}
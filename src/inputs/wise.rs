@@ -0,0 +1,212 @@
+//! The statement export of a Wise (formerly TransferWise) balance, comma
+//! separated with `DD-MM-YYYY` dates and decimal points.
+//!
+//! The amount is what left or reached the balance, fees included, which
+//! become a split line. Cross-currency transfers note the source and target
+//! amounts in the memo. The `TRANSFER-…` id goes into the info.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    util::{convert, csv_rows, decimal, decode, with_fee},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::{Kind, Row},
+};
+
+const HEADER: &str = "TransferWise ID,Date,Amount";
+
+#[derive(Debug, Deserialize)]
+struct WiseIR {
+    #[serde(rename = "TransferWise ID")]
+    id: String,
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Amount")]
+    amount: String,
+    #[serde(rename = "Currency")]
+    currency: String,
+    #[serde(rename = "Description")]
+    description: String,
+    #[serde(rename = "Payment Reference", default)]
+    reference: String,
+    #[serde(rename = "Exchange From", default)]
+    exchange_from: String,
+    #[serde(rename = "Exchange To", default)]
+    exchange_to: String,
+    #[serde(rename = "Exchange Rate", default)]
+    exchange_rate: String,
+    #[serde(rename = "Exchange To Amount", default)]
+    exchange_to_amount: String,
+    #[serde(rename = "Payer Name", default)]
+    payer_name: String,
+    #[serde(rename = "Payee Name", default)]
+    payee_name: String,
+    #[serde(rename = "Merchant", default)]
+    merchant: String,
+    #[serde(rename = "Total fees", default)]
+    total_fees: String,
+}
+
+impl TryFrom<WiseIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: WiseIR) -> Result<Self> {
+        let currency = iso::find(&value.currency)
+            .ok_or_else(|| miette!("Unknown currency '{}'", value.currency))?;
+        let amount = decimal(&value.amount, '.')?;
+        let fees = decimal(&value.total_fees, '.')?.abs();
+        let payment = match value.id.split_once('-').map(|(kind, _)| kind) {
+            Some("CARD") => Payment::DebitCard,
+            Some("TRANSFER") => Payment::BankTransfer,
+            Some("BALANCE") => Payment::InternalTransfer,
+            Some("DIRECT_DEBIT") => Payment::DirectDebit,
+            _ => Payment::None,
+        };
+
+        let memo = match value.exchange_to.is_empty() || value.exchange_from == value.exchange_to {
+            true => value.reference,
+            false => {
+                let exchange = format!(
+                    "{} {} → {} {} (rate {})",
+                    amount.abs() - fees,
+                    value.exchange_from,
+                    value.exchange_to_amount,
+                    value.exchange_to,
+                    value.exchange_rate
+                );
+                format!("{} {}", value.reference, exchange)
+                    .trim()
+                    .to_string()
+            }
+        };
+        let payee = [value.payee_name, value.payer_name, value.merchant]
+            .into_iter()
+            .find(|p| !p.is_empty())
+            .unwrap_or(value.description);
+
+        let total = Money::from_decimal(amount, currency);
+        let (amount, splits) = match fees.is_zero() {
+            true => (total, Vec::new()),
+            // The fee is part of the amount, the payment itself is the rest
+            false => with_fee(
+                Money::from_decimal(amount + fees, currency),
+                Money::from_decimal(-fees, currency),
+                &memo,
+                "Wise",
+            ),
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.date, "%d-%m-%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment,
+            info: value.id,
+            payee,
+            memo,
+            amount,
+            splits,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct WiseIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl WiseIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(csv_rows::<_, WiseIR>(text.as_bytes(), b',', HEADER), "wise"),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for WiseIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows`.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "TransferWise ID,Date,Amount,Currency,Description,Payment Reference,Running Balance,\
+        Exchange From,Exchange To,Exchange Rate,Payer Name,Payee Name,Payee Account Number,\
+        Merchant,Card Last Four Digits,Card Holder Full Name,Attachment,Note,Total fees,\
+        Exchange To Amount\n",
+    );
+    for row in rows {
+        let (id, payer, payee, merchant) = match (row.kind, row.amount.is_sign_negative()) {
+            (Kind::Card, _) => ("CARD", "", "", row.payee.as_str()),
+            (_, true) => ("TRANSFER", "", row.payee.as_str(), ""),
+            (_, false) => ("TRANSFER", row.payee.as_str(), "", ""),
+        };
+        out.push_str(&format!(
+            "{}-{},{},{},EUR,{},,,,,,{},{},,{},,,,,0.00,\n",
+            id,
+            row.reference,
+            row.date.format("%d-%m-%Y"),
+            row.amount,
+            row.purpose,
+            payer,
+            payee,
+            merchant
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "TransferWise ID,Date,Amount,Currency,Description,Payment Reference,Running Balance,Exchange From,Exchange To,Exchange Rate,Payer Name,Payee Name,Payee Account Number,Merchant,Card Last Four Digits,Card Holder Full Name,Attachment,Note,Total fees,Exchange To Amount\n\
+            TRANSFER-123456,07-03-2024,-101.20,EUR,Sent money to Jane Doe,Rent,898.80,EUR,USD,1.082,,Jane Doe,12345678,,,,,,1.20,108.20\n\
+            CARD-98765,08-03-2024,-25.88,EUR,Card transaction,,872.92,,,,,,,Rewe,1234,Max Mustermann,,,0.00,\n";
+
+        let records: Vec<Record> = WiseIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].info, "TRANSFER-123456");
+        assert_eq!(records[0].payee, "Jane Doe");
+        assert_eq!(records[0].payment, Payment::BankTransfer);
+        assert_eq!(records[0].memo, "Rent 100.00 EUR → 108.20 USD (rate 1.082)");
+        assert_eq!(records[0].amount, Money::from_str("-101,20", EUR).unwrap());
+        assert_eq!(records[0].splits.len(), 2);
+        assert_eq!(
+            records[0].splits[0].amount,
+            Money::from_str("-100,00", EUR).unwrap()
+        );
+
+        assert_eq!(records[1].payee, "Rewe");
+        assert_eq!(records[1].payment, Payment::DebitCard);
+        assert!(records[1].splits.is_empty());
+    }
+}
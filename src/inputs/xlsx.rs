@@ -0,0 +1,120 @@
+//! Excel exports, read as the csv file the bank would have exported.
+//!
+//! The sheet is rendered to a `;` separated csv, which the parser of the
+//! chosen format reads as usual. Dates become `31.12.2024` and numbers get
+//! a decimal comma, like in the csv exports of German banks.
+
+use std::{io::Cursor, path::Path};
+
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+use csv::{QuoteStyle, WriterBuilder};
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+/// Whether the input is a spreadsheet instead of a csv file.
+pub fn is_spreadsheet(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    matches!(extension.as_deref(), Some("xlsx" | "xlsm" | "xls" | "ods"))
+}
+
+/// The rows of `sheet`, or of the first sheet, of the spreadsheet `name`
+/// as csv.
+pub fn to_csv(content: Vec<u8>, name: &str, sheet: Option<&str>) -> Result<Vec<u8>> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(content))
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed opening spreadsheet {}", name))?;
+
+    let names = workbook.sheet_names();
+    let sheet = match sheet {
+        Some(sheet) if names.iter().any(|n| n == sheet) => sheet.to_string(),
+        Some(sheet) => {
+            return Err(miette!(
+                help = format!("Sheets in the file: {}", names.join(", ")),
+                "No sheet named '{}' in {}",
+                sheet,
+                name
+            ))
+        }
+        None => names
+            .first()
+            .cloned()
+            .ok_or_else(|| miette!("{} has no sheets", name))?,
+    };
+    let range = workbook
+        .worksheet_range(&sheet)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed reading sheet {}", sheet))?;
+
+    // Starting with a BOM, so parsers expecting Windows-1252 read UTF-8
+    let mut csv = "\u{feff}".as_bytes().to_vec();
+    let mut writer = WriterBuilder::new()
+        .delimiter(b';')
+        .quote_style(QuoteStyle::Necessary)
+        .flexible(true)
+        .from_writer(&mut csv);
+    for row in range.rows() {
+        writer
+            .write_record(row.iter().map(cell))
+            .into_diagnostic()
+            .wrap_err("Failed converting spreadsheet row")?;
+    }
+    writer
+        .flush()
+        .into_diagnostic()
+        .wrap_err("Failed converting spreadsheet")?;
+    drop(writer);
+
+    Ok(csv)
+}
+
+fn cell(data: &Data) -> String {
+    match data {
+        Data::Float(f) => f.to_string().replace('.', ","),
+        Data::DateTime(d) => d
+            .as_datetime()
+            .map(|d| d.format("%d.%m.%Y").to_string())
+            .unwrap_or_default(),
+        Data::Empty => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rust_xlsxwriter::{Format, Workbook};
+
+    use super::*;
+
+    #[test]
+    fn test_to_csv() {
+        let dir = std::env::temp_dir().join(format!("hbconv-xlsx-in-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("export.xlsx");
+
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("Info").unwrap();
+        let sheet = workbook.add_worksheet().set_name("Umsätze").unwrap();
+        let date = Format::new().set_num_format("dd.mm.yyyy");
+        sheet.write(0, 0, "Buchungstag").unwrap();
+        sheet.write(0, 1, "Betrag").unwrap();
+        sheet
+            .write_with_format(1, 0, &NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(), &date)
+            .unwrap();
+        sheet.write(1, 1, -25.88).unwrap();
+        sheet.write(1, 2, "Woopsie; Doopsie").unwrap();
+        workbook.save(&path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let csv = to_csv(content.clone(), "export.xlsx", Some("Umsätze")).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "\u{feff}Buchungstag;Betrag;\n07.03.2024;-25,88;\"Woopsie; Doopsie\"\n"
+        );
+        assert!(to_csv(content, "export.xlsx", Some("Missing")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+//! Zip archives, as some banks deliver one csv per account in a single
+//! download. Every file of the archive is converted on its own.
+
+use std::{
+    io::{Cursor, Read, Seek},
+    path::Path,
+};
+
+use glob::Pattern;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use zip::ZipArchive;
+
+/// Whether the input is a zip archive.
+pub fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+}
+
+/// Names and contents of the files in the archive, only those matching
+/// `inner` if given.
+pub fn entries(content: Vec<u8>, inner: Option<&str>) -> Result<Vec<(String, Vec<u8>)>> {
+    read(Cursor::new(content), inner)
+}
+
+fn read<R: Read + Seek>(rdr: R, inner: Option<&str>) -> Result<Vec<(String, Vec<u8>)>> {
+    let pattern = inner
+        .map(Pattern::new)
+        .transpose()
+        .into_diagnostic()
+        .wrap_err("Invalid glob for files in the archive")?;
+    let mut archive = ZipArchive::new(rdr).into_diagnostic()?;
+
+    let mut entries = Vec::new();
+    let mut names = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).into_diagnostic()?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().into_diagnostic()?.into_owned();
+        if pattern.as_ref().is_some_and(|p| !p.matches(&name)) {
+            names.push(name);
+            continue;
+        }
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed extracting {}", name))?;
+        entries.push((name, content));
+    }
+
+    if entries.is_empty() {
+        return Err(miette!(
+            help = format!("Files in the archive: {}", names.join(", ")),
+            "No files to convert in the archive"
+        ));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use pretty_assertions::assert_eq;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    #[test]
+    fn test_entries() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for name in ["giro/1234.csv", "giro/5678.csv", "Readme.txt"] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(name.as_bytes()).unwrap();
+        }
+        let content = writer.finish().unwrap().into_inner();
+
+        let entries = read(Cursor::new(content.clone()), Some("giro/*.csv")).unwrap();
+        let names: Vec<&str> = entries.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["giro/1234.csv", "giro/5678.csv"]);
+        assert_eq!(entries[0].1, b"giro/1234.csv");
+
+        assert_eq!(read(Cursor::new(content.clone()), None).unwrap().len(), 3);
+        assert!(read(Cursor::new(content), Some("*.sta")).is_err());
+    }
+}
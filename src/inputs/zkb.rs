@@ -0,0 +1,224 @@
+//! The transaction export of Zürcher Kantonalbank, `;` separated with
+//! decimal points.
+//!
+//! Collective bookings, like several payments of one eBanking order, come
+//! as a booking followed by a detail row per payment without date and
+//! with an amount of its own. The details are merged into the memo of the
+//! booking they belong to.
+
+use std::{io::Read, vec};
+
+use chrono::NaiveDate;
+use miette::{miette, Context, IntoDiagnostic, Report, Result};
+use rusty_money::{iso, Money};
+use serde::Deserialize;
+
+use super::{
+    sepa::booking_payment,
+    util::{convert, csv_rows, decimal, decode},
+    RecordIteratorRes,
+};
+use crate::{
+    homebank::{Payment, Record},
+    sample::Row,
+};
+
+const HEADER: &str = "Datum;Buchungstext;Whg";
+
+#[derive(Debug, Deserialize)]
+struct ZkbIR {
+    #[serde(rename = "Datum")]
+    datum: String,
+    #[serde(rename = "Buchungstext")]
+    buchungstext: String,
+    #[serde(rename = "Whg", default)]
+    whg: String,
+    #[serde(rename = "Betrag Detail", default)]
+    betrag_detail: String,
+    #[serde(rename = "ZKB-Referenz", default)]
+    zkb_referenz: String,
+    #[serde(
+        rename = "Belastung CHF",
+        alias = "Belastung EUR",
+        alias = "Belastung USD",
+        default
+    )]
+    belastung: String,
+    #[serde(
+        rename = "Gutschrift CHF",
+        alias = "Gutschrift EUR",
+        alias = "Gutschrift USD",
+        default
+    )]
+    gutschrift: String,
+    #[serde(rename = "Zahlungszweck", default)]
+    zahlungszweck: String,
+    /// The detail rows following the booking, not set from the row itself
+    #[serde(skip)]
+    details: Vec<String>,
+}
+
+fn payment(text: &str) -> Payment {
+    let lower = text.to_lowercase();
+    match booking_payment(text) {
+        Payment::None if lower.contains("card") => Payment::DebitCard,
+        Payment::None if lower.contains("bezug") => Payment::Cash,
+        Payment::None if lower.contains("lsv") || lower.contains("ebill") => Payment::DirectDebit,
+        Payment::None if lower.contains("auftrag") => Payment::BankTransfer,
+        Payment::None if lower.contains("preis") => Payment::FinancialInstitutionFee,
+        payment => payment,
+    }
+}
+
+impl TryFrom<ZkbIR> for Record {
+    type Error = Report;
+
+    fn try_from(value: ZkbIR) -> Result<Self> {
+        // Accounts in francs leave the currency of their bookings empty
+        let currency = match value.whg.is_empty() {
+            true => iso::CHF,
+            false => {
+                iso::find(&value.whg).ok_or_else(|| miette!("Unknown currency '{}'", value.whg))?
+            }
+        };
+        let amount = decimal(&value.gutschrift, '.')? - decimal(&value.belastung, '.')?.abs();
+        // Orders name the other party after a colon
+        let payee = value
+            .buchungstext
+            .split_once(": ")
+            .map(|(_, rest)| rest.split(',').next().unwrap_or(rest).trim().to_string())
+            .unwrap_or_default();
+        let mut memo = vec![value.buchungstext.clone(), value.zahlungszweck];
+        memo.extend(value.details);
+        memo.retain(|s| !s.is_empty());
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(&value.datum, "%d.%m.%Y")
+                .into_diagnostic()
+                .wrap_err("Failed converting date into datetime")?,
+            payment: payment(&value.buchungstext),
+            info: value.zkb_referenz,
+            payee,
+            memo: memo.join("; "),
+            amount: Money::from_decimal(amount, currency),
+            ..Default::default()
+        })
+    }
+}
+
+/// Merges the detail rows, which have no date, into the booking before
+/// them.
+fn merge(rows: Vec<Result<ZkbIR>>) -> Vec<Result<ZkbIR>> {
+    let mut out: Vec<Result<ZkbIR>> = Vec::new();
+    for ir in rows {
+        match (ir, out.last_mut()) {
+            (Ok(detail), Some(Ok(booking))) if detail.datum.is_empty() => {
+                let text = [
+                    detail.buchungstext,
+                    detail.zahlungszweck,
+                    detail.betrag_detail,
+                ]
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+                booking.details.push(text);
+            }
+            (ir, _) => out.push(ir),
+        }
+    }
+    out
+}
+
+pub struct ZkbIter {
+    records: vec::IntoIter<Result<Record>>,
+}
+
+impl ZkbIter {
+    pub fn new<R: Read>(rdr: R) -> Self {
+        let records = match decode(rdr) {
+            Ok(text) => convert(
+                merge(csv_rows::<_, ZkbIR>(text.as_bytes(), b';', HEADER)),
+                "zkb",
+            ),
+            Err(e) => vec![Err(e)],
+        };
+
+        Self {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for ZkbIter {
+    type Item = RecordIteratorRes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+/// A sample export of `rows` in CHF.
+pub fn sample(rows: &[Row]) -> Vec<u8> {
+    let mut out = String::from(
+        "\"Datum\";\"Buchungstext\";\"Whg\";\"Betrag Detail\";\"ZKB-Referenz\";\"Referenznummer\";\
+        \"Belastung CHF\";\"Gutschrift CHF\";\"Valuta\";\"Saldo CHF\";\"Zahlungszweck\";\"Details\"\n",
+    );
+    for row in rows {
+        let date = row.date.format("%d.%m.%Y");
+        let (debit, credit) = match row.amount.is_sign_negative() {
+            true => (row.amount.abs().to_string(), String::new()),
+            false => (String::new(), row.amount.to_string()),
+        };
+        out.push_str(&format!(
+            "\"{}\";\"{}: {}\";\"\";\"\";\"{}\";\"\";\"{}\";\"{}\";\"{}\";\"\";\"{}\";\"\"\n",
+            date,
+            row.kind.booking_text(),
+            row.payee,
+            row.reference,
+            debit,
+            credit,
+            date,
+            row.purpose
+        ));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::CHF;
+
+    use super::*;
+
+    #[test]
+    fn test_to_iter() {
+        let input = "\"Datum\";\"Buchungstext\";\"Whg\";\"Betrag Detail\";\"ZKB-Referenz\";\"Referenznummer\";\"Belastung CHF\";\"Gutschrift CHF\";\"Valuta\";\"Saldo CHF\";\"Zahlungszweck\";\"Details\"\n\
+            \"07.03.2024\";\"Einkauf ZKB Visa Debit card Nr. xxxx 1234, Coop Zürich\";\"\";\"\";\"Z1\";\"\";\"25.80\";\"\";\"07.03.2024\";\"4974.20\";\"\";\"\"\n\
+            \"08.03.2024\";\"Sammelauftrag eBanking\";\"\";\"\";\"Z2\";\"\";\"1739.00\";\"\";\"08.03.2024\";\"3235.20\";\"\";\"\"\n\
+            \"\";\"Auftrag eBanking: Immo AG, Zürich\";\"CHF\";\"1650.00\";\"\";\"\";\"\";\"\";\"\";\"\";\"Miete März\";\"\"\n\
+            \"\";\"Auftrag eBanking: Swisscom, Bern\";\"CHF\";\"89.00\";\"\";\"\";\"\";\"\";\"\";\"\";\"\";\"\"\n\
+            \"25.03.2024\";\"Gutschrift: Arbeitgeber AG, Zürich\";\"\";\"\";\"Z3\";\"\";\"\";\"6500.00\";\"25.03.2024\";\"9735.20\";\"Lohn März\";\"\"\n";
+
+        let records: Vec<Record> = ZkbIter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+        assert_eq!(records[0].payment, Payment::DebitCard);
+        assert_eq!(records[0].amount, Money::from_str("-25.80", CHF).unwrap());
+        assert_eq!(records[1].payment, Payment::BankTransfer);
+        assert_eq!(
+            records[1].memo,
+            "Sammelauftrag eBanking; Auftrag eBanking: Immo AG, Zürich Miete März 1650.00; \
+            Auftrag eBanking: Swisscom, Bern 89.00"
+        );
+        assert_eq!(records[1].amount, Money::from_str("-1739.00", CHF).unwrap());
+        assert_eq!(records[2].payee, "Arbeitgeber AG");
+        assert_eq!(records[2].amount, Money::from_str("6500.00", CHF).unwrap());
+    }
+}
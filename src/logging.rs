@@ -0,0 +1,36 @@
+//! Log setup, everything is written to stderr.
+
+use clap::ValueEnum;
+use miette::{miette, Report, Result};
+use serde::Serialize;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Installs the global subscriber. `filter` takes either a plain level like
+/// `debug` or per module directives like `warn,hbconv::inputs::postbank=trace`.
+pub fn init(filter: &str, format: &LogFormat) -> Result<()> {
+    let filter = EnvFilter::try_new(filter).map_err(|err| miette!("Invalid log level: {}", err))?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    let res = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    };
+    res.map_err(|err| miette!("Failed setting up logging: {}", err))
+}
+
+/// Renders a report with all its causes on a single line.
+pub fn chain(err: &Report) -> String {
+    err.chain()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(": ")
+}
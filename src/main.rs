@@ -1,99 +1,119 @@
+mod anonymize;
+mod archive;
+mod config;
+mod convert;
+mod enrich;
+mod fetch;
+mod gpg;
 mod homebank;
 mod inputs;
-
-use std::{
-    fs::File,
-    path::{Path, PathBuf},
-};
-
-use clap::{Parser, ValueEnum};
-use homebank::Record;
-use inputs::{postbank::PostbankIter, sparda::TeoIter};
-use miette::{Context, IntoDiagnostic, Result};
+mod logging;
+mod outputs;
+mod pipeline;
+mod profile;
+mod rates;
+mod repair;
+mod report;
+mod review;
+mod rules;
+mod sample;
+mod split;
+mod watch;
+
+use std::io;
+
+use anonymize::Anonymize;
+use archive::Reapply;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use convert::Args;
+use fetch::Fetch;
+use logging::LogFormat;
+use miette::Result;
+use sample::Sample;
+use watch::Watch;
 
 /// A conversion tool to produce homebank compatible csv files
 #[derive(Parser)]
-struct Args {
-    #[arg(short, long, env)]
-    output: PathBuf,
-    input: PathBuf,
-    #[arg(short, long, env, value_enum)]
-    format: Format,
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    args: Option<Args>,
+    /// Log level, either global (`debug`) or per module (`warn,hbconv::inputs=trace`)
+    #[arg(long, env, global = true, default_value = "warn")]
+    log_level: String,
+    #[arg(long, env, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
-enum Format {
-    Postbank,
-    Sparda,
+#[derive(Subcommand)]
+enum Command {
+    /// Print a shell completion script, regenerate it after upgrading to pick
+    /// up newly supported formats
+    Completions { shell: Shell },
+    /// Convert all archived exports again with the current rules and config
+    Reapply(Reapply),
+    /// Fetch a statement from the bank and convert it
+    Fetch(Box<Fetch>),
+    /// Watch a directory and convert new exports as they arrive
+    Watch(Box<Watch>),
+    /// Replace the personal data of an export with fakes, to share it in an
+    /// issue
+    Anonymize(Anonymize),
+    /// Print a synthetic export of a format, to try rules and outputs with
+    Sample(Sample),
 }
 
-impl Format {
-    fn open_input(&self, input: &Path) -> Result<RecordIterator> {
-        let input = File::open(input)
-            .into_diagnostic()
-            .wrap_err("Failed opening input file")?;
-        match self {
-            Format::Postbank => {
-                let input = PostbankIter::new(input);
-                Ok(RecordIterator::new(Box::new(input.into_iter())))
-            }
-            Format::Sparda => {
-                let input = TeoIter::new(input);
-                Ok(RecordIterator::new(Box::new(input.into_iter())))
-            }
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    logging::init(&cli.log_level, &cli.log_format)?;
+
+    match (cli.command, cli.args) {
+        (Some(Command::Completions { shell }), _) => {
+            clap_complete::generate(shell, &mut Cli::command(), "hbconv", &mut io::stdout());
+            Ok(())
         }
+        (Some(Command::Reapply(reapply)), _) => reapply.run(),
+        (Some(Command::Fetch(fetch)), _) => fetch.run(),
+        (Some(Command::Watch(watch)), _) => watch.run(),
+        (Some(Command::Anonymize(anonymize)), _) => anonymize.run(),
+        (Some(Command::Sample(sample)), _) => sample.run(),
+        (None, Some(args)) => convert::run(&args),
+        // clap rejects a missing subcommand together with missing arguments
+        (None, None) => unreachable!(),
     }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-
-    // Open I/O
-    let input = args.format.open_input(&args.input)?;
-    let output = File::create(args.output)
-        .into_diagnostic()
-        .wrap_err("Failed opening output file")?;
-    let mut output = Record::writer(output);
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    for record in input {
-        let hb_record = match record {
-            Ok(r) => r,
-            Err(err) => {
-                eprintln!("{:?}", err);
-                continue;
-            }
-        };
-        hb_record.write(&mut output)?;
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
     }
 
-    output
-        .flush()
-        .into_diagnostic()
-        .wrap_err("Failed flushing output")?;
+    #[test]
+    fn test_parse_convert() {
+        let cli = Cli::parse_from(["hbconv", "-f", "postbank", "-o", "out.csv", "in.csv"]);
+        assert!(cli.command.is_none());
+        assert!(cli.args.is_some());
 
-    Ok(())
-}
+        let cli = Cli::parse_from(["hbconv", "reapply", "--archive", "archive"]);
+        assert!(matches!(cli.command, Some(Command::Reapply(_))));
 
-type RecordIteratorRes = Result<Record>;
+        let cli = Cli::parse_from(["hbconv", "fetch", "-o", "out.csv", "statement.sta"]);
+        assert!(matches!(cli.command, Some(Command::Fetch(_))));
 
-struct RecordIterator {
-    inner: Box<dyn Iterator<Item = RecordIteratorRes>>,
-}
-
-impl RecordIterator {
-    fn new(inner: Box<dyn Iterator<Item = RecordIteratorRes>>) -> Self {
-        Self { inner }
-    }
-}
+        let cli = Cli::parse_from(["hbconv", "watch", "Downloads", "--rules", "watch.toml"]);
+        assert!(matches!(cli.command, Some(Command::Watch(_))));
 
-impl Iterator for RecordIterator {
-    type Item = RecordIteratorRes;
+        let cli = Cli::parse_from(["hbconv", "anonymize", "-o", "sample.csv", "in.csv"]);
+        assert!(matches!(cli.command, Some(Command::Anonymize(_))));
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next()
+        let cli = Cli::parse_from(["hbconv", "sample", "--format", "sparda", "--rows", "50"]);
+        assert!(matches!(cli.command, Some(Command::Sample(_))));
     }
 }
-
-trait IntoRecord {
-    fn into_record(self) -> Record;
-}
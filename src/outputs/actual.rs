@@ -0,0 +1,229 @@
+//! Pushing records to an Actual Budget server.
+//!
+//! Actual itself only offers a node library, so this talks to the
+//! `actual-http-api` REST wrapper running next to the server. All records
+//! are imported into `--actual-account` in one request. The fingerprint of
+//! a record, numbered among identical ones, is its `imported_id`, which
+//! Actual uses to skip transactions imported before. The lines of split
+//! records become subtransactions.
+
+use std::collections::HashMap;
+
+use miette::{miette, Context, IntoDiagnostic, Result};
+use rusty_money::{iso::Currency, Money};
+use serde::Serialize;
+use serde_json::json;
+use tracing::info;
+
+use super::{Output, OutputOptions};
+use crate::homebank::Record;
+
+/// Output name selecting Actual Budget.
+pub const API_OUTPUT: &str = "actual";
+
+pub struct ActualOutput {
+    url: String,
+    api_key: String,
+    account: String,
+    dry_run: bool,
+    transactions: Vec<Transaction>,
+    seen: HashMap<String, usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Transaction {
+    account: String,
+    date: String,
+    /// In minor units, cents for EUR
+    amount: i64,
+    payee_name: String,
+    imported_payee: String,
+    notes: String,
+    imported_id: String,
+    cleared: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    subtransactions: Vec<Subtransaction>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Subtransaction {
+    amount: i64,
+    notes: String,
+}
+
+impl ActualOutput {
+    pub fn new(options: &OutputOptions) -> Result<Self> {
+        let missing = |name| miette!("The actual output needs --{}", name);
+        let url = options
+            .actual_url
+            .as_deref()
+            .ok_or_else(|| missing("actual-url"))?;
+        let budget = options
+            .actual_budget
+            .as_deref()
+            .ok_or_else(|| missing("actual-budget"))?;
+        let account = options
+            .actual_account
+            .clone()
+            .ok_or_else(|| missing("actual-account"))?;
+        let api_key = match (&options.actual_api_key, options.dry_run) {
+            (Some(key), _) => key.clone(),
+            (None, true) => String::new(),
+            (None, false) => return Err(missing("actual-api-key")),
+        };
+
+        Ok(Self {
+            url: format!(
+                "{}/v1/budgets/{}/accounts/{}/transactions/import",
+                url.trim_end_matches('/'),
+                budget,
+                account
+            ),
+            api_key,
+            account,
+            dry_run: options.dry_run,
+            transactions: Vec::new(),
+            seen: HashMap::new(),
+        })
+    }
+}
+
+impl Output for ActualOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let imported_id = record.occurrence_id(&mut self.seen);
+        self.transactions
+            .push(transaction(record, &self.account, imported_id));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let body = json!({ "transactions": self.transactions });
+        if self.dry_run {
+            println!("{}", body);
+            return Ok(());
+        }
+
+        let response: serde_json::Value = ureq::post(&self.url)
+            .set("x-api-key", &self.api_key)
+            .send_json(&body)
+            .into_diagnostic()
+            .wrap_err("Failed importing transactions into Actual Budget")?
+            .into_json()
+            .into_diagnostic()
+            .wrap_err("Failed reading Actual Budget response")?;
+
+        let count = |key: &str| {
+            response["data"][key]
+                .as_array()
+                .map(Vec::len)
+                .unwrap_or_default()
+        };
+        info!(
+            added = count("added"),
+            updated = count("updated"),
+            "Imported transactions into Actual Budget"
+        );
+        Ok(())
+    }
+}
+
+fn transaction(record: &Record, account: &str, imported_id: String) -> Transaction {
+    Transaction {
+        account: account.to_string(),
+        date: record.date.format("%Y-%m-%d").to_string(),
+        amount: minor_units(&record.amount),
+        payee_name: record.payee.clone(),
+        imported_payee: record.payee.clone(),
+        notes: record.memo.clone(),
+        imported_id,
+        cleared: true,
+        subtransactions: record
+            .splits
+            .iter()
+            .map(|line| Subtransaction {
+                amount: minor_units(&line.amount),
+                notes: line.memo.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn minor_units(money: &Money<'_, Currency>) -> i64 {
+    let mut amount = *money.amount();
+    amount.rescale(money.currency().exponent);
+    amount.mantissa() as i64
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::{Payment, SplitLine};
+
+    #[test]
+    fn test_transaction() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-1.025,8", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        let mut output = ActualOutput {
+            url: String::new(),
+            api_key: String::new(),
+            account: "acc".to_string(),
+            dry_run: true,
+            transactions: Vec::new(),
+            seen: HashMap::new(),
+        };
+        output.write(&record).unwrap();
+        output.write(&record).unwrap();
+
+        let transaction = &output.transactions[0];
+        assert_eq!(transaction.amount, -102580);
+        assert_eq!(transaction.date, "2024-03-07");
+        let fingerprint = &record.fingerprint()[..32];
+        assert_eq!(transaction.imported_id, format!("{}-0", fingerprint));
+        assert_eq!(
+            output.transactions[1].imported_id,
+            format!("{}-1", fingerprint)
+        );
+        assert!(transaction.subtransactions.is_empty());
+
+        let split = Record {
+            splits: vec![
+                SplitLine {
+                    amount: Money::from_str("-1.025,00", EUR).unwrap(),
+                    category: String::new(),
+                    memo: "Doopsie".to_string(),
+                },
+                SplitLine {
+                    amount: Money::from_str("-0,80", EUR).unwrap(),
+                    category: "Fees".to_string(),
+                    memo: "Fee".to_string(),
+                },
+            ],
+            ..record
+        };
+        output.write(&split).unwrap();
+        assert_eq!(
+            output.transactions[2].subtransactions,
+            vec![
+                Subtransaction {
+                    amount: -102500,
+                    notes: "Doopsie".to_string()
+                },
+                Subtransaction {
+                    amount: -80,
+                    notes: "Fee".to_string()
+                }
+            ]
+        );
+    }
+}
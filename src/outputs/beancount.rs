@@ -0,0 +1,199 @@
+//! Beancount transaction directives.
+//!
+//! Like text produced by beancount importers this only contains the
+//! transactions, the accounts are expected to be opened in the ledger
+//! including the file. Split records get one balancing posting per line.
+
+use std::{
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use miette::{Context, IntoDiagnostic, Result};
+
+use super::{
+    ledger::{balancing, ledger_amount},
+    OutFile, Output, OutputOptions,
+};
+use crate::homebank::Record;
+
+pub struct BeancountOutput {
+    writer: BufWriter<OutFile>,
+    options: OutputOptions,
+}
+
+impl BeancountOutput {
+    pub fn create(path: &Path, options: &OutputOptions) -> Result<Self> {
+        let file = OutFile::open(path, options)?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            options: options.clone(),
+        })
+    }
+}
+
+impl Output for BeancountOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        write_transaction(&mut self.writer, record, &self.options)
+            .into_diagnostic()
+            .wrap_err("Failed writing beancount transaction")
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")
+    }
+}
+
+fn write_transaction<W: Write>(
+    w: &mut W,
+    record: &Record,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    write!(
+        w,
+        "{} * \"{}\" \"{}\"",
+        record.date.format("%Y-%m-%d"),
+        quote(&record.payee),
+        quote(&record.memo)
+    )?;
+    for tag in &record.tags {
+        write!(w, " #{}", tag_name(tag))?;
+    }
+    writeln!(w)?;
+
+    if !record.info.is_empty() {
+        writeln!(w, "  ref: \"{}\"", quote(&record.info))?;
+    }
+    writeln!(
+        w,
+        "  {:<36}  {}",
+        account_name(&options.account),
+        ledger_amount(&record.amount)
+    )?;
+    if record.splits.is_empty() {
+        writeln!(w, "  {}", account_name(&options.counter_account(record)))?;
+    }
+    for line in &record.splits {
+        write!(
+            w,
+            "  {:<36}  {}",
+            account_name(&options.category_account(&line.category)),
+            ledger_amount(&balancing(line))
+        )?;
+        if !line.memo.is_empty() {
+            write!(w, "  ; {}", line.memo)?;
+        }
+        writeln!(w)?;
+    }
+    writeln!(w)
+}
+
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Beancount account components have to start with a capital letter or a
+/// digit and may only contain letters, digits and dashes.
+fn account_name(account: &str) -> String {
+    account
+        .split(':')
+        .map(|component| {
+            let mut name = String::new();
+            for c in component.trim().chars() {
+                if c.is_alphanumeric() {
+                    if name.is_empty() {
+                        name.extend(c.to_uppercase());
+                    } else {
+                        name.push(c);
+                    }
+                } else if !name.is_empty() && !name.ends_with('-') {
+                    name.push('-');
+                }
+            }
+            let name = name.trim_end_matches('-');
+            if name.is_empty() {
+                "Unknown".to_string()
+            } else {
+                name.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_/.".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::{Payment, SplitLine};
+
+    #[test]
+    fn test_transaction() {
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            info: "ABCD".to_string(),
+            payee: "Woopsie \"Inc\"".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-40,00", EUR).unwrap(),
+            category: "Bill:Withdrawal of cash".to_string(),
+            tags: vec!["my tag".to_string()],
+            ..Default::default()
+        };
+        let options = OutputOptions::default();
+
+        let mut out = Vec::new();
+        write_transaction(&mut out, &record, &options).unwrap();
+
+        let expected = "\
+2024-03-07 * \"Woopsie \\\"Inc\\\"\" \"Doopsie\" #my-tag
+  ref: \"ABCD\"
+  Assets:Checking                       -40.00 EUR
+  Expenses:Bill:Withdrawal-of-cash
+
+";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        record.splits = vec![
+            SplitLine {
+                amount: Money::from_str("-38,00", EUR).unwrap(),
+                category: "Bill".to_string(),
+                memo: String::new(),
+            },
+            SplitLine {
+                amount: Money::from_str("-2,00", EUR).unwrap(),
+                category: "Fees".to_string(),
+                memo: "ATM fee".to_string(),
+            },
+        ];
+        let mut out = Vec::new();
+        write_transaction(&mut out, &record, &options).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with(
+            "\
+  Assets:Checking                       -40.00 EUR
+  Expenses:Bill                         38.00 EUR
+  Expenses:Fees                         2.00 EUR  ; ATM fee
+
+"
+        ));
+    }
+}
@@ -0,0 +1,403 @@
+//! CSV file for the Firefly III data importer.
+//!
+//! Next to `<name>.firefly.csv` an import configuration `<name>.firefly.json`
+//! is written, telling the importer the role of every column, the date
+//! format and the delimiter. Both can be uploaded as is. The fingerprint of
+//! every record, numbered among identical ones, goes into the external id,
+//! which the importer uses to skip records imported before. Split records
+//! become one transaction per line, the line numbered after the external id.
+//!
+//! The `firefly3` output skips the file round trip and pushes every record
+//! to the REST API of the instance at `--firefly-url`. Transactions Firefly
+//! already knows are recognized by their duplicate hash and skipped.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use csv::Writer;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{debug, info};
+
+use super::{OutFile, Output, OutputOptions};
+use crate::homebank::Record;
+
+/// Output name selecting the API instead of a file.
+pub const API_OUTPUT: &str = "firefly3";
+
+/// The columns written, with their role in the importer.
+const COLUMNS: [(&str, &str); 9] = [
+    ("date", "date_transaction"),
+    ("description", "description"),
+    ("payee", "opposing-name"),
+    ("iban", "opposing-iban"),
+    ("amount", "amount"),
+    ("currency", "currency-code"),
+    ("category", "category-name"),
+    ("tags", "tags-space"),
+    ("external_id", "external-id"),
+];
+
+pub struct FireflyOutput {
+    writer: Writer<OutFile>,
+    config: PathBuf,
+    seen: HashMap<String, usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct FireflyRow<'a> {
+    date: String,
+    description: &'a str,
+    payee: &'a str,
+    iban: &'a str,
+    amount: String,
+    currency: &'a str,
+    category: &'a str,
+    tags: String,
+    external_id: String,
+}
+
+impl FireflyOutput {
+    pub fn create(path: &Path, encrypt_to: &[String]) -> Result<Self> {
+        let writer = Writer::from_writer(OutFile::create(path, encrypt_to)?);
+
+        Ok(Self {
+            writer,
+            config: path.with_extension("json"),
+            seen: HashMap::new(),
+        })
+    }
+}
+
+impl Output for FireflyOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let external_id = record.occurrence_id(&mut self.seen);
+        for (line, external_id) in lines(record, external_id) {
+            self.writer
+                .serialize(FireflyRow::new(&line, external_id))
+                .into_diagnostic()
+                .wrap_err("Failed writing Firefly III row")?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")?;
+
+        let config = serde_json::to_string_pretty(&import_config()).into_diagnostic()?;
+        fs::write(&self.config, config + "\n")
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed writing {}", self.config.display()))
+    }
+}
+
+impl<'a> FireflyRow<'a> {
+    fn new(record: &'a Record, external_id: String) -> Self {
+        let exponent = record.amount.currency().exponent as usize;
+
+        Self {
+            date: record.date.format("%Y-%m-%d").to_string(),
+            // The importer refuses transactions without a description
+            description: if record.memo.is_empty() {
+                &record.payee
+            } else {
+                &record.memo
+            },
+            payee: &record.payee,
+            iban: &record.iban,
+            amount: format!("{:.*}", exponent, record.amount.amount()),
+            currency: record.amount.currency().iso_alpha_code,
+            category: &record.category,
+            tags: record.tags.join(" "),
+            external_id,
+        }
+    }
+}
+
+pub struct FireflyApiOutput {
+    url: String,
+    token: String,
+    account: String,
+    dry_run: bool,
+    created: usize,
+    duplicates: usize,
+    seen: HashMap<String, usize>,
+}
+
+impl FireflyApiOutput {
+    pub fn new(options: &OutputOptions) -> Result<Self> {
+        let url = options
+            .firefly_url
+            .as_deref()
+            .ok_or_else(|| miette!("The firefly3 output needs --firefly-url"))?;
+        let token = match (&options.firefly_token, options.dry_run) {
+            (Some(token), _) => token.clone(),
+            (None, true) => String::new(),
+            (None, false) => return Err(miette!("The firefly3 output needs --firefly-token")),
+        };
+
+        Ok(Self {
+            url: format!("{}/api/v1/transactions", url.trim_end_matches('/')),
+            token,
+            account: options.firefly_account.clone(),
+            dry_run: options.dry_run,
+            created: 0,
+            duplicates: 0,
+            seen: HashMap::new(),
+        })
+    }
+
+    /// Sends one transaction, counting it as created or as a duplicate.
+    fn send(&mut self, record: &Record, external_id: String) -> Result<()> {
+        let body = transaction(record, &self.account, external_id);
+        if self.dry_run {
+            println!("{}", body);
+            return Ok(());
+        }
+
+        let response = ureq::post(&self.url)
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .set("Accept", "application/vnd.api+json")
+            .send_json(&body);
+
+        match response {
+            Ok(_) => self.created += 1,
+            Err(ureq::Error::Status(422, response)) => {
+                let error = response.into_string().unwrap_or_default();
+                if !is_duplicate(&error) {
+                    return Err(miette!("Firefly III rejected the transaction: {}", error));
+                }
+                debug!(payee = %record.payee, date = %record.date, "Skipping duplicate transaction");
+                self.duplicates += 1;
+            }
+            Err(err) => {
+                return Err(err)
+                    .into_diagnostic()
+                    .wrap_err("Failed sending transaction to Firefly III")
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Output for FireflyApiOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let external_id = record.occurrence_id(&mut self.seen);
+        for (line, external_id) in lines(record, external_id) {
+            self.send(&line, external_id)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if !self.dry_run {
+            info!(
+                created = self.created,
+                duplicates = self.duplicates,
+                "Pushed transactions to Firefly III"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The records written for `record`, one per split line with the line
+/// numbered after `external_id`.
+fn lines(record: &Record, external_id: String) -> Vec<(Record, String)> {
+    if record.splits.is_empty() {
+        return vec![(record.clone(), external_id)];
+    }
+    record
+        .lines()
+        .into_iter()
+        .enumerate()
+        .map(|(idx, line)| (line, format!("{}/{}", external_id, idx)))
+        .collect()
+}
+
+/// Request body creating a single transaction.
+fn transaction(record: &Record, account: &str, external_id: String) -> serde_json::Value {
+    let exponent = record.amount.currency().exponent as usize;
+    let row = FireflyRow::new(record, external_id);
+    // Firefly refuses an empty name, without one it books against the cash
+    // account
+    let payee = (!row.payee.is_empty()).then_some(row.payee);
+    let (kind, source, destination) = if record.amount.is_negative() {
+        ("withdrawal", Some(account), payee)
+    } else {
+        ("deposit", payee, Some(account))
+    };
+
+    json!({
+        "error_if_duplicate_hash": true,
+        "apply_rules": true,
+        "transactions": [{
+            "type": kind,
+            "date": row.date,
+            "amount": format!("{:.*}", exponent, record.amount.amount().abs()),
+            "currency_code": row.currency,
+            "description": row.description,
+            "source_name": source,
+            "destination_name": destination,
+            "category_name": (!row.category.is_empty()).then_some(row.category),
+            "tags": record.tags,
+            "external_id": row.external_id,
+            "notes": (!record.info.is_empty()).then_some(&record.info),
+        }],
+    })
+}
+
+/// Whether a validation error only complains about a known transaction.
+fn is_duplicate(error: &str) -> bool {
+    error.contains("Duplicate of transaction")
+}
+
+/// Import configuration in the format of the data importer (version 3).
+fn import_config() -> serde_json::Value {
+    json!({
+        "version": 3,
+        "source": format!("hbconv-{}", env!("CARGO_PKG_VERSION")),
+        "flow": "file",
+        "content_type": "csv",
+        "date": "Y-m-d",
+        "delimiter": "comma",
+        "headers": true,
+        "rules": true,
+        "add_import_tag": true,
+        "roles": COLUMNS.iter().map(|(_, role)| *role).collect::<Vec<_>>(),
+        "do_mapping": COLUMNS.iter().map(|_| false).collect::<Vec<_>>(),
+        "mapping": {},
+        "duplicate_detection_method": "cell",
+        "unique_column_index": COLUMNS.iter().position(|(name, _)| *name == "external_id"),
+        "unique_column_type": "external-id",
+        "ignore_duplicate_lines": true,
+        "ignore_duplicate_transactions": true,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::{Payment, SplitLine};
+
+    #[test]
+    fn test_row() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("-1.025,88", EUR).unwrap(),
+            category: "Food".to_string(),
+            tags: vec!["a".to_string(), "b".to_string()],
+            iban: "DE123".to_string(),
+            ..Default::default()
+        };
+
+        let mut seen = HashMap::new();
+        let mut writer = Writer::from_writer(Vec::new());
+        for _ in 0..2 {
+            let external_id = record.occurrence_id(&mut seen);
+            writer
+                .serialize(FireflyRow::new(&record, external_id))
+                .unwrap();
+        }
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let mut lines = written.lines();
+
+        let header: Vec<_> = COLUMNS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(lines.next(), Some(header.join(",").as_str()));
+        for n in 0..2 {
+            assert_eq!(
+                lines.next(),
+                Some(
+                    format!(
+                        "2024-03-07,Woopsie,Woopsie,DE123,-1025.88,EUR,Food,a b,{}-{}",
+                        &record.fingerprint()[..32],
+                        n
+                    )
+                    .as_str()
+                )
+            );
+        }
+
+        let config = import_config();
+        assert_eq!(config["roles"].as_array().unwrap().len(), COLUMNS.len());
+        assert_eq!(config["unique_column_index"], 8);
+    }
+
+    #[test]
+    fn test_transaction() {
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        let body = transaction(&record, "Checking", "a-0".to_string());
+        let split = &body["transactions"][0];
+        assert_eq!(split["type"], "withdrawal");
+        assert_eq!(split["amount"], "25.88");
+        assert_eq!(split["source_name"], "Checking");
+        assert_eq!(split["destination_name"], "Woopsie");
+        assert!(split["category_name"].is_null());
+
+        record.amount = Money::from_str("10", EUR).unwrap();
+        let body = transaction(&record, "Checking", "a-1".to_string());
+        assert_eq!(body["transactions"][0]["type"], "deposit");
+        assert_eq!(body["transactions"][0]["external_id"], "a-1");
+        assert_eq!(body["transactions"][0]["destination_name"], "Checking");
+
+        let mut anonymous = record.clone();
+        anonymous.payee = String::new();
+        let body = transaction(&anonymous, "Checking", "a-1".to_string());
+        assert!(body["transactions"][0]["source_name"].is_null());
+        assert_eq!(body["transactions"][0]["destination_name"], "Checking");
+
+        record.splits = vec![
+            SplitLine {
+                amount: Money::from_str("10,35", EUR).unwrap(),
+                category: String::new(),
+                memo: String::new(),
+            },
+            SplitLine {
+                amount: Money::from_str("-0,35", EUR).unwrap(),
+                category: "Fees:PayPal".to_string(),
+                memo: "PayPal fee".to_string(),
+            },
+        ];
+        let lines = lines(&record, "a-2".to_string());
+        let bodies: Vec<_> = lines
+            .into_iter()
+            .map(|(line, id)| transaction(&line, "Checking", id))
+            .map(|body| body["transactions"][0].clone())
+            .collect();
+        assert_eq!(bodies[0]["type"], "deposit");
+        assert_eq!(bodies[0]["amount"], "10.35");
+        assert_eq!(bodies[0]["description"], "Doopsie");
+        assert_eq!(bodies[0]["external_id"], "a-2/0");
+        assert_eq!(bodies[1]["type"], "withdrawal");
+        assert_eq!(bodies[1]["amount"], "0.35");
+        assert_eq!(bodies[1]["category_name"], "Fees:PayPal");
+        assert_eq!(bodies[1]["description"], "PayPal fee");
+        assert_eq!(bodies[1]["external_id"], "a-2/1");
+
+        assert!(is_duplicate(
+            r#"{"message":"Duplicate of transaction #12.","errors":{}}"#
+        ));
+    }
+}
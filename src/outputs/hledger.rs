@@ -0,0 +1,259 @@
+//! hledger flavoured journal output.
+//!
+//! On top of the plain ledger output this marks every transaction as
+//! cleared, as bank exports only contain booked transactions, writes tags
+//! the way hledger parses them and maps records to accounts through an
+//! optional account mapping file:
+//!
+//! ```toml
+//! [[accounts]]
+//! payee = "(?i)rewe|edeka"
+//! account = "expenses:food:groceries"
+//!
+//! [[accounts]]
+//! category = "^Car"
+//! account = "expenses:car"
+//! ```
+//!
+//! The first mapping whose patterns all match wins, unmatched records fall
+//! back to the category or the expense placeholder. Each line of a split
+//! record is mapped by its own category and gets a posting of its own.
+
+use std::{
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use miette::{Context, IntoDiagnostic, Result};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::{
+    ledger::{balancing, ledger_amount},
+    OutFile, Output, OutputOptions,
+};
+use crate::{config, homebank::Record};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccountMapFile {
+    accounts: Vec<AccountMapping>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccountMapping {
+    payee: Option<String>,
+    category: Option<String>,
+    account: String,
+}
+
+struct CompiledMapping {
+    payee: Option<Regex>,
+    category: Option<Regex>,
+    account: String,
+}
+
+pub struct HledgerOutput {
+    writer: BufWriter<OutFile>,
+    options: OutputOptions,
+    mappings: Vec<CompiledMapping>,
+}
+
+impl HledgerOutput {
+    pub fn create(path: &Path, options: &OutputOptions) -> Result<Self> {
+        let mappings = match &options.account_map {
+            Some(path) => {
+                let file: AccountMapFile = config::read_toml(path)?;
+                compile(file.accounts)
+                    .wrap_err_with(|| format!("Invalid account mapping in {}", path.display()))?
+            }
+            None => Vec::new(),
+        };
+
+        let file = OutFile::open(path, options)?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            options: options.clone(),
+            mappings,
+        })
+    }
+
+    fn account(&self, record: &Record) -> String {
+        self.mappings
+            .iter()
+            .find(|m| {
+                m.payee.as_ref().is_none_or(|p| p.is_match(&record.payee))
+                    && m.category
+                        .as_ref()
+                        .is_none_or(|c| c.is_match(&record.category))
+            })
+            .map(|m| m.account.clone())
+            .unwrap_or_else(|| self.options.counter_account(record))
+    }
+}
+
+fn compile(mappings: Vec<AccountMapping>) -> Result<Vec<CompiledMapping>> {
+    let compile = |pattern: &Option<String>| {
+        pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .into_diagnostic()
+            .wrap_err("Invalid account mapping pattern")
+    };
+
+    mappings
+        .into_iter()
+        .map(|m| {
+            Ok(CompiledMapping {
+                payee: compile(&m.payee)?,
+                category: compile(&m.category)?,
+                account: m.account,
+            })
+        })
+        .collect()
+}
+
+impl Output for HledgerOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let accounts: Vec<_> = record.lines().iter().map(|l| self.account(l)).collect();
+        write_transaction(&mut self.writer, record, &self.options.account, &accounts)
+            .into_diagnostic()
+            .wrap_err("Failed writing hledger transaction")
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")
+    }
+}
+
+/// Writes `record` balanced by `counter_accounts`, one for each split line
+/// or the only one of a record without lines.
+fn write_transaction<W: Write>(
+    w: &mut W,
+    record: &Record,
+    account: &str,
+    counter_accounts: &[String],
+) -> io::Result<()> {
+    write!(w, "{} *", record.date.format("%Y-%m-%d"))?;
+    if !record.info.is_empty() {
+        write!(w, " ({})", record.info)?;
+    }
+    write!(w, " {}", record.payee)?;
+    if !record.memo.is_empty() {
+        write!(w, " | {}", record.memo)?;
+    }
+    if !record.tags.is_empty() {
+        let tags: Vec<_> = record.tags.iter().map(|t| format!("{}:", t)).collect();
+        write!(w, "  ; {}", tags.join(", "))?;
+    }
+    writeln!(w)?;
+
+    writeln!(w, "    {:<36}  {}", account, ledger_amount(&record.amount))?;
+    if record.splits.is_empty() {
+        for counter_account in counter_accounts {
+            writeln!(w, "    {}", counter_account)?;
+        }
+    }
+    for (line, counter_account) in record.splits.iter().zip(counter_accounts) {
+        write!(
+            w,
+            "    {:<36}  {}",
+            counter_account,
+            ledger_amount(&balancing(line))
+        )?;
+        if !line.memo.is_empty() {
+            write!(w, "  ; {}", line.memo)?;
+        }
+        writeln!(w)?;
+    }
+    writeln!(w)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::{Payment, SplitLine};
+
+    #[test]
+    fn test_transaction() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            info: "ABCD".to_string(),
+            payee: "REWE Markt".to_string(),
+            memo: "Einkauf".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            tags: vec!["weekly".to_string(), "food".to_string()],
+            ..Default::default()
+        };
+        let mappings: AccountMapFile = toml::from_str(
+            r#"
+            [[accounts]]
+            payee = "(?i)edeka"
+            account = "expenses:food:other"
+
+            [[accounts]]
+            payee = "(?i)rewe"
+            account = "expenses:food:groceries"
+            "#,
+        )
+        .unwrap();
+        let mappings = compile(mappings.accounts).unwrap();
+        let account = mappings
+            .iter()
+            .find(|m| m.payee.as_ref().is_some_and(|p| p.is_match(&record.payee)))
+            .map(|m| m.account.clone())
+            .unwrap();
+
+        let mut out = Vec::new();
+        write_transaction(&mut out, &record, "assets:checking", &[account]).unwrap();
+
+        let expected = "\
+2024-03-07 * (ABCD) REWE Markt | Einkauf  ; weekly:, food:
+    assets:checking                       -25.88 EUR
+    expenses:food:groceries
+
+";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        let record = Record {
+            splits: vec![
+                SplitLine {
+                    amount: Money::from_str("-25,00", EUR).unwrap(),
+                    category: String::new(),
+                    memo: String::new(),
+                },
+                SplitLine {
+                    amount: Money::from_str("-0,88", EUR).unwrap(),
+                    category: "Fees".to_string(),
+                    memo: "Fee".to_string(),
+                },
+            ],
+            ..record
+        };
+        let accounts = [
+            "expenses:food:groceries".to_string(),
+            "expenses:fees".to_string(),
+        ];
+        let mut out = Vec::new();
+        write_transaction(&mut out, &record, "assets:checking", &accounts).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with(
+            "\
+    assets:checking                       -25.88 EUR
+    expenses:food:groceries               25.00 EUR
+    expenses:fees                         0.88 EUR  ; Fee
+
+"
+        ));
+    }
+}
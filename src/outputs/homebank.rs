@@ -0,0 +1,59 @@
+use std::{io::Write, path::Path};
+
+use csv::Writer;
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+use super::{OutFile, Output, OutputOptions};
+use crate::homebank::{CsvStyle, Record};
+
+/// Writes records as a Homebank importable csv file.
+pub struct HomebankOutput {
+    writer: Writer<OutFile>,
+}
+
+impl HomebankOutput {
+    pub fn create(path: &Path, options: &OutputOptions) -> Result<Self> {
+        let mut style = options.csv_style()?;
+        // Appended records continue the file, header and BOM are there already
+        let continued = options.append && path.metadata().is_ok_and(|m| m.len() > 0);
+        style.header &= !continued;
+        let mut file = OutFile::open(path, options)?;
+        if options.bom && !continued {
+            file.write_all(b"\xEF\xBB\xBF")
+                .into_diagnostic()
+                .wrap_err("Failed writing byte order mark")?;
+        }
+
+        Ok(Self {
+            writer: Record::writer(file, &style),
+        })
+    }
+}
+
+impl Output for HomebankOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        record.write(&mut self.writer)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")
+    }
+}
+
+impl OutputOptions {
+    pub fn csv_style(&self) -> Result<CsvStyle> {
+        let delimiter = u8::try_from(self.out_delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .ok_or_else(|| miette!("The output delimiter must be an ascii character"))?;
+
+        Ok(CsvStyle {
+            delimiter,
+            header: self.out_header,
+            crlf: self.crlf,
+        })
+    }
+}
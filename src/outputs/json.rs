@@ -0,0 +1,198 @@
+//! JSON and JSON Lines, for scripting with jq or loading into data
+//! pipelines.
+//!
+//! Amounts are decimal strings, so no precision is lost on the way. Split
+//! records carry their lines in `splits`.
+
+use std::{
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Result};
+use serde::Serialize;
+
+use super::{OutFile, Output};
+use crate::homebank::{Payment, Record, SplitLine};
+
+pub struct JsonOutput {
+    writer: BufWriter<OutFile>,
+    /// One object per line instead of a single array
+    lines: bool,
+    written: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRecord<'a> {
+    date: NaiveDate,
+    paymode: Payment,
+    info: &'a str,
+    payee: &'a str,
+    memo: &'a str,
+    amount: String,
+    currency: &'a str,
+    category: &'a str,
+    tags: &'a [String],
+    iban: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    splits: Vec<JsonSplit<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonSplit<'a> {
+    amount: String,
+    category: &'a str,
+    memo: &'a str,
+}
+
+impl JsonOutput {
+    pub fn create(path: &Path, lines: bool, encrypt_to: &[String]) -> Result<Self> {
+        let file = OutFile::create(path, encrypt_to)?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            lines,
+            written: 0,
+        })
+    }
+}
+
+impl Output for JsonOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let separator = match (self.lines, self.written) {
+            (true, _) => "",
+            (false, 0) => "[\n",
+            (false, _) => ",\n",
+        };
+        let json = serde_json::to_string(&JsonRecord::from(record)).into_diagnostic()?;
+        write!(self.writer, "{}{}", separator, json)
+            .and_then(|_| match self.lines {
+                true => writeln!(self.writer),
+                false => Ok(()),
+            })
+            .into_diagnostic()
+            .wrap_err("Failed writing json record")?;
+
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let end = match (self.lines, self.written) {
+            (true, _) => "",
+            (false, 0) => "[]\n",
+            (false, _) => "\n]\n",
+        };
+        write!(self.writer, "{}", end)
+            .and_then(|_| self.writer.flush())
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")
+    }
+}
+
+impl<'a> From<&'a Record> for JsonRecord<'a> {
+    fn from(record: &'a Record) -> Self {
+        let exponent = record.amount.currency().exponent as usize;
+
+        Self {
+            date: record.date,
+            paymode: record.payment,
+            info: &record.info,
+            payee: &record.payee,
+            memo: &record.memo,
+            amount: format!("{:.*}", exponent, record.amount.amount()),
+            currency: record.amount.currency().iso_alpha_code,
+            category: &record.category,
+            tags: &record.tags,
+            iban: &record.iban,
+            splits: record.splits.iter().map(JsonSplit::from).collect(),
+        }
+    }
+}
+
+impl<'a> From<&'a SplitLine> for JsonSplit<'a> {
+    fn from(line: &'a SplitLine) -> Self {
+        let exponent = line.amount.currency().exponent as usize;
+
+        Self {
+            amount: format!("{:.*}", exponent, line.amount.amount()),
+            category: &line.category,
+            memo: &line.memo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+
+    #[test]
+    fn test_json_and_lines() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-1.025,8", EUR).unwrap(),
+            category: "Food".to_string(),
+            tags: vec!["fun".to_string()],
+            ..Default::default()
+        };
+        let expected = r#"{"date":"2024-03-07","paymode":"ElectronicPayment","info":"","payee":"Woopsie","memo":"Doopsie","amount":"-1025.80","currency":"EUR","category":"Food","tags":["fun"],"iban":""}"#;
+
+        let dir = std::env::temp_dir().join(format!("hbconv-json-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (lines, name) in [(false, "out.json"), (true, "out.jsonl")] {
+            let path = dir.join(name);
+            let mut output = JsonOutput::create(&path, lines, &[]).unwrap();
+            output.write(&record).unwrap();
+            output.write(&record).unwrap();
+            output.finish().unwrap();
+
+            let written = fs::read_to_string(&path).unwrap();
+            if lines {
+                assert_eq!(written, format!("{expected}\n{expected}\n"));
+            } else {
+                assert_eq!(written, format!("[\n{expected},\n{expected}\n]\n"));
+                serde_json::from_str::<serde_json::Value>(&written).unwrap();
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            amount: Money::from_str("-10,35", EUR).unwrap(),
+            splits: vec![
+                SplitLine {
+                    amount: Money::from_str("-10,00", EUR).unwrap(),
+                    category: String::new(),
+                    memo: "Order".to_string(),
+                },
+                SplitLine {
+                    amount: Money::from_str("-0,35", EUR).unwrap(),
+                    category: "Fees:PayPal".to_string(),
+                    memo: "PayPal fee".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(JsonRecord::from(&record)).unwrap();
+        assert_eq!(
+            json["splits"],
+            serde_json::json!([
+                {"amount": "-10.00", "category": "", "memo": "Order"},
+                {"amount": "-0.35", "category": "Fees:PayPal", "memo": "PayPal fee"}
+            ])
+        );
+    }
+}
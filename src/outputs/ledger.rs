@@ -0,0 +1,180 @@
+//! Plain text accounting journal for ledger-cli.
+//!
+//! Every record becomes a transaction with two postings: the amount on the
+//! account the export belongs to and the balancing posting on an expense
+//! account. That account is derived from the category if there is one and
+//! the expense placeholder otherwise. Split records get one balancing
+//! posting per line instead.
+
+use std::{
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use miette::{Context, IntoDiagnostic, Result};
+use rusty_money::{iso::Currency, Money};
+
+use super::{OutFile, Output, OutputOptions};
+use crate::homebank::{Record, SplitLine};
+
+pub struct LedgerOutput {
+    writer: BufWriter<OutFile>,
+    options: OutputOptions,
+}
+
+impl LedgerOutput {
+    pub fn create(path: &Path, options: &OutputOptions) -> Result<Self> {
+        let file = OutFile::open(path, options)?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            options: options.clone(),
+        })
+    }
+}
+
+impl Output for LedgerOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        write_transaction(&mut self.writer, record, &self.options)
+            .into_diagnostic()
+            .wrap_err("Failed writing ledger transaction")
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")
+    }
+}
+
+fn write_transaction<W: Write>(
+    w: &mut W,
+    record: &Record,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    write!(w, "{}", record.date.format("%Y/%m/%d"))?;
+    if !record.info.is_empty() {
+        write!(w, " ({})", record.info)?;
+    }
+    writeln!(w, " {}", record.payee)?;
+
+    if !record.memo.is_empty() {
+        writeln!(w, "    ; {}", record.memo)?;
+    }
+    if !record.tags.is_empty() {
+        writeln!(w, "    ; :{}:", record.tags.join(":"))?;
+    }
+
+    writeln!(
+        w,
+        "    {:<36}  {}",
+        options.account,
+        ledger_amount(&record.amount)
+    )?;
+    if record.splits.is_empty() {
+        writeln!(w, "    {}", options.counter_account(record))?;
+    }
+    for line in &record.splits {
+        write!(
+            w,
+            "    {:<36}  {}",
+            options.category_account(&line.category),
+            ledger_amount(&balancing(line))
+        )?;
+        if !line.memo.is_empty() {
+            write!(w, "  ; {}", line.memo)?;
+        }
+        writeln!(w)?;
+    }
+    writeln!(w)
+}
+
+/// Formats the amount with a '.' decimal separator followed by the
+/// currency code, which ledger treats as commodity.
+pub(super) fn ledger_amount(amount: &Money<'_, Currency>) -> String {
+    let exponent = amount.currency().exponent as usize;
+    format!(
+        "{:.*} {}",
+        exponent,
+        amount.amount(),
+        amount.currency().iso_alpha_code
+    )
+}
+
+/// The amount of the posting balancing a split line.
+pub(super) fn balancing(line: &SplitLine) -> Money<'static, Currency> {
+    Money::from_decimal(-*line.amount.amount(), line.amount.currency())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_transaction() {
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            info: "ABCD".to_string(),
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-1.025,88", EUR).unwrap(),
+            tags: vec!["fun".to_string()],
+            ..Default::default()
+        };
+        let options = OutputOptions::default();
+
+        let mut out = Vec::new();
+        write_transaction(&mut out, &record, &options).unwrap();
+        record.category = "Food:Groceries".to_string();
+        write_transaction(&mut out, &record, &options).unwrap();
+
+        let expected = "\
+2024/03/07 (ABCD) Woopsie
+    ; Doopsie
+    ; :fun:
+    Assets:Checking                       -1025.88 EUR
+    Expenses:Unknown
+
+2024/03/07 (ABCD) Woopsie
+    ; Doopsie
+    ; :fun:
+    Assets:Checking                       -1025.88 EUR
+    Expenses:Food:Groceries
+
+";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        record.splits = vec![
+            SplitLine {
+                amount: Money::from_str("-1.025,00", EUR).unwrap(),
+                category: String::new(),
+                memo: String::new(),
+            },
+            SplitLine {
+                amount: Money::from_str("-0,88", EUR).unwrap(),
+                category: "Fees:PayPal".to_string(),
+                memo: "PayPal fee".to_string(),
+            },
+        ];
+        let mut out = Vec::new();
+        write_transaction(&mut out, &record, &options).unwrap();
+
+        let expected = "\
+2024/03/07 (ABCD) Woopsie
+    ; Doopsie
+    ; :fun:
+    Assets:Checking                       -1025.88 EUR
+    Expenses:Unknown                      1025.00 EUR
+    Expenses:Fees:PayPal                  0.88 EUR  ; PayPal fee
+
+";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+}
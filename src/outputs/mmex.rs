@@ -0,0 +1,134 @@
+//! CSV file for the import of Money Manager EX.
+//!
+//! MMEX has no payment methods, it tells withdrawals, deposits and
+//! transfers apart and keeps the amount positive. Categories have a single
+//! level of subcategories, so `Food:Groceries:Bio` becomes the subcategory
+//! `Groceries:Bio` of `Food`. Split records become one row per line.
+
+use std::path::Path;
+
+use csv::Writer;
+use miette::{Context, IntoDiagnostic, Result};
+use serde::Serialize;
+
+use super::{OutFile, Output, OutputOptions};
+use crate::homebank::{Payment, Record};
+
+pub struct MmexOutput {
+    writer: Writer<OutFile>,
+    account: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct MmexRow<'a> {
+    date: String,
+    status: &'a str,
+    #[serde(rename = "Type")]
+    kind: &'a str,
+    account: &'a str,
+    payee: &'a str,
+    category: &'a str,
+    sub_category: &'a str,
+    amount: String,
+    currency: &'a str,
+    number: &'a str,
+    notes: &'a str,
+    tags: String,
+}
+
+impl MmexOutput {
+    pub fn create(path: &Path, options: &OutputOptions) -> Result<Self> {
+        let writer = Writer::from_writer(OutFile::create(path, &options.encrypt_to)?);
+
+        Ok(Self {
+            writer,
+            account: options.account.clone(),
+        })
+    }
+}
+
+impl Output for MmexOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        for line in record.lines() {
+            self.writer
+                .serialize(row(&line, &self.account))
+                .into_diagnostic()
+                .wrap_err("Failed writing MMEX row")?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")
+    }
+}
+
+fn row<'a>(record: &'a Record, account: &'a str) -> MmexRow<'a> {
+    let kind = match record.payment {
+        Payment::InternalTransfer => "Transfer",
+        _ if record.amount.is_negative() => "Withdrawal",
+        _ => "Deposit",
+    };
+    let (category, sub_category) = record
+        .category
+        .split_once(':')
+        .unwrap_or((&record.category, ""));
+    let exponent = record.amount.currency().exponent as usize;
+
+    MmexRow {
+        date: record.date.format("%Y-%m-%d").to_string(),
+        status: "",
+        kind,
+        account,
+        payee: &record.payee,
+        category,
+        sub_category,
+        amount: format!("{:.*}", exponent, record.amount.amount().abs()),
+        currency: record.amount.currency().iso_alpha_code,
+        number: &record.info,
+        notes: &record.memo,
+        tags: record.tags.join(" "),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+
+    #[test]
+    fn test_rows() {
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            info: "ABCD".to_string(),
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            category: "Food:Groceries:Bio".to_string(),
+            tags: vec!["fun".to_string()],
+            ..Default::default()
+        };
+
+        let mut writer = Writer::from_writer(Vec::new());
+        writer.serialize(row(&record, "Giro")).unwrap();
+        record.payment = Payment::InternalTransfer;
+        record.category = String::new();
+        writer.serialize(row(&record, "Giro")).unwrap();
+
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            written,
+            "Date,Status,Type,Account,Payee,Category,SubCategory,Amount,Currency,Number,Notes,Tags\n\
+            2024-03-07,,Withdrawal,Giro,Woopsie,Food,Groceries:Bio,25.88,EUR,ABCD,Doopsie,fun\n\
+            2024-03-07,,Transfer,Giro,Woopsie,,,25.88,EUR,ABCD,Doopsie,fun\n"
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! Output backends the converted records can be written to.
+
+pub mod actual;
+pub mod beancount;
+pub mod firefly;
+pub mod hledger;
+pub mod homebank;
+pub mod json;
+pub mod ledger;
+pub mod mmex;
+pub mod ofx;
+pub mod parquet;
+pub mod partition;
+pub mod sqlite;
+pub mod xhb;
+pub mod xlsx;
+pub mod ynab;
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gpg::{self, Encrypted},
+    homebank::Record,
+};
+
+/// A destination for converted records.
+pub trait Output {
+    fn write(&mut self, record: &Record) -> Result<()>;
+
+    /// Called once after the last record, flushing anything still buffered.
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// Settings shared by the output backends.
+#[derive(Debug, Clone, clap::Args, Deserialize, Serialize)]
+pub struct OutputOptions {
+    /// Write all outputs in this format instead of picking it by extension
+    #[arg(long, env, value_enum)]
+    pub output_format: Option<OutputFormat>,
+    /// Field delimiter of the homebank csv output
+    #[arg(long, env, default_value_t = ';')]
+    pub out_delimiter: char,
+    /// Start the homebank csv output with a header line
+    #[arg(long, env)]
+    pub out_header: bool,
+    /// End the lines of the homebank csv output with CRLF
+    #[arg(long, env)]
+    pub crlf: bool,
+    /// Start the homebank csv output with a UTF-8 byte order mark, for Excel
+    #[arg(long, env)]
+    pub bom: bool,
+    /// Account the converted records belong to
+    #[arg(long, env, default_value = "Assets:Checking")]
+    pub account: String,
+    /// Account balancing uncategorized records
+    #[arg(long, env, default_value = "Expenses:Unknown")]
+    pub expense_account: String,
+    /// Toml file mapping payees and categories to accounts (hledger)
+    #[arg(long, env)]
+    pub account_map: Option<PathBuf>,
+    /// Base url of the Firefly III instance the `firefly3` output pushes to
+    #[arg(long, env)]
+    pub firefly_url: Option<String>,
+    /// Personal access token for the Firefly III API
+    #[arg(long, env, hide_env_values = true)]
+    #[serde(skip)]
+    pub firefly_token: Option<String>,
+    /// Firefly III asset account the records belong to
+    #[arg(long, env, default_value = "Checking Account")]
+    pub firefly_account: String,
+    /// Account of the HomeBank file the records are appended to
+    #[arg(long, env)]
+    pub xhb_account: Option<String>,
+    /// Base url of the actual-http-api server the `actual` output pushes to
+    #[arg(long, env)]
+    pub actual_url: Option<String>,
+    /// API key of the actual-http-api server
+    #[arg(long, env, hide_env_values = true)]
+    #[serde(skip)]
+    pub actual_api_key: Option<String>,
+    /// Sync id of the Actual budget
+    #[arg(long, env)]
+    pub actual_budget: Option<String>,
+    /// Id of the Actual account the records are imported into
+    #[arg(long, env)]
+    pub actual_account: Option<String>,
+    /// Append to existing output files instead of replacing them (homebank
+    /// csv, ledger, hledger and beancount)
+    #[arg(long, env)]
+    #[serde(default)]
+    pub append: bool,
+    /// Encrypt the output files with gpg to this key, may be given several times
+    #[arg(long, env, value_delimiter = ',')]
+    #[serde(default)]
+    pub encrypt_to: Vec<String>,
+    /// Print what the `firefly3` and `actual` outputs would send instead of
+    /// sending it
+    #[arg(long)]
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Update the records the sqlite and xhb outputs already have instead
+    /// of leaving them, for reapplying changed rules
+    #[arg(skip)]
+    #[serde(skip)]
+    pub replace: bool,
+}
+
+/// The defaults of the command line.
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            output_format: None,
+            out_delimiter: ';',
+            out_header: false,
+            crlf: false,
+            bom: false,
+            account: "Assets:Checking".to_string(),
+            expense_account: "Expenses:Unknown".to_string(),
+            account_map: None,
+            firefly_url: None,
+            firefly_token: None,
+            firefly_account: "Checking Account".to_string(),
+            xhb_account: None,
+            actual_url: None,
+            actual_api_key: None,
+            actual_budget: None,
+            actual_account: None,
+            append: false,
+            encrypt_to: Vec::new(),
+            dry_run: false,
+            replace: false,
+        }
+    }
+}
+
+impl OutputOptions {
+    /// The account balancing a record, derived from its category if set.
+    pub fn counter_account(&self, record: &Record) -> String {
+        self.category_account(&record.category)
+    }
+
+    /// The account of `category`, the expense placeholder if empty.
+    pub fn category_account(&self, category: &str) -> String {
+        if category.is_empty() {
+            return self.expense_account.clone();
+        }
+
+        let root = self.expense_account.split(':').next().unwrap_or("Expenses");
+        format!("{}:{}", root, category)
+    }
+
+    /// The format written to `path`, `--output-format` or the one matching
+    /// its extension.
+    pub fn format(&self, path: &Path) -> OutputFormat {
+        self.output_format
+            .unwrap_or_else(|| OutputFormat::detect(path))
+    }
+}
+
+/// The file of an output, piped through gpg for `--encrypt-to`.
+pub enum OutFile {
+    Plain(File),
+    Encrypted(Encrypted),
+}
+
+impl OutFile {
+    pub fn create(path: &Path, encrypt_to: &[String]) -> Result<Self> {
+        let file = match encrypt_to.is_empty() {
+            true => File::create(path).into_diagnostic().map(Self::Plain),
+            false => Encrypted::create(path, encrypt_to).map(Self::Encrypted),
+        };
+        file.wrap_err_with(|| format!("Failed opening output file {}", path.display()))
+    }
+
+    /// Opens the file of a line based output, appending with `--append`.
+    pub fn open(path: &Path, options: &OutputOptions) -> Result<Self> {
+        if !options.append {
+            return Self::create(path, &options.encrypt_to);
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map(Self::Plain)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed opening output file {}", path.display()))
+    }
+}
+
+impl Write for OutFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(file) => file.write(buf),
+            Self::Encrypted(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(file) => file.flush(),
+            Self::Encrypted(file) => file.flush(),
+        }
+    }
+}
+
+/// The output backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Homebank,
+    Ofx,
+    Ledger,
+    Hledger,
+    Beancount,
+    Ynab,
+    Mmex,
+    Firefly,
+    /// The Firefly III API
+    Firefly3,
+    Json,
+    Jsonl,
+    Xlsx,
+    Sqlite,
+    Parquet,
+    /// Appending to an existing HomeBank file
+    Xhb,
+    /// Actual Budget, through actual-http-api
+    Actual,
+}
+
+impl OutputFormat {
+    /// Whether records are sent to a server instead of written to a file.
+    pub fn is_api(self) -> bool {
+        matches!(self, Self::Firefly3 | Self::Actual)
+    }
+
+    /// Whether the written file is text, which a diff can show.
+    pub fn is_text(self) -> bool {
+        !matches!(self, Self::Xlsx | Self::Sqlite | Self::Parquet)
+    }
+
+    /// Picks the format from the extension of the given path, falling back to
+    /// the homebank csv format. Other csv flavours are told apart by a second
+    /// extension, as in `budget.ynab.csv`.
+    pub fn detect(path: &Path) -> Self {
+        // `out.ledger.gpg` is a ledger file, only encrypted
+        let inner = path.to_string_lossy();
+        let path = Path::new(gpg::inner_name(&inner));
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        if name == firefly::API_OUTPUT {
+            return Self::Firefly3;
+        }
+        if name == actual::API_OUTPUT {
+            return Self::Actual;
+        }
+        if name.ends_with(".ynab.csv") {
+            return Self::Ynab;
+        }
+        if name.ends_with(".mmex.csv") {
+            return Self::Mmex;
+        }
+        if name.ends_with(".firefly.csv") {
+            return Self::Firefly;
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+
+        match extension.as_deref() {
+            Some("ofx" | "qfx") => Self::Ofx,
+            Some("ledger" | "journal") => Self::Ledger,
+            Some("beancount" | "bean") => Self::Beancount,
+            Some("hledger") => Self::Hledger,
+            Some("json") => Self::Json,
+            Some("jsonl" | "ndjson") => Self::Jsonl,
+            Some("xlsx") => Self::Xlsx,
+            Some("sqlite" | "sqlite3" | "db") => Self::Sqlite,
+            Some("parquet") => Self::Parquet,
+            Some("xhb") => Self::Xhb,
+            _ => Self::Homebank,
+        }
+    }
+}
+
+/// Opens the output backend for the given path, in `--output-format` or the
+/// format matching its extension.
+pub fn open(path: &Path, options: &OutputOptions) -> Result<Box<dyn Output>> {
+    let format = options.format(path);
+    if !options.encrypt_to.is_empty() && matches!(format, OutputFormat::Sqlite | OutputFormat::Xhb)
+    {
+        return Err(miette!(
+            "The sqlite and xhb outputs are not written as a stream and can't be encrypted"
+        ));
+    }
+
+    let appendable = matches!(
+        format,
+        OutputFormat::Homebank
+            | OutputFormat::Ledger
+            | OutputFormat::Hledger
+            | OutputFormat::Beancount
+    );
+    if options.append && !appendable {
+        return Err(miette!(
+            help = "Append to homebank csv, ledger, hledger or beancount files",
+            "Can't append to {}",
+            path.display()
+        ));
+    }
+    if options.append && !options.encrypt_to.is_empty() {
+        return Err(miette!("Encrypted outputs can't be appended to"));
+    }
+
+    Ok(match format {
+        OutputFormat::Homebank => Box::new(homebank::HomebankOutput::create(path, options)?),
+        OutputFormat::Ofx => Box::new(ofx::OfxOutput::create(path, &options.encrypt_to)?),
+        OutputFormat::Ledger => Box::new(ledger::LedgerOutput::create(path, options)?),
+        OutputFormat::Hledger => Box::new(hledger::HledgerOutput::create(path, options)?),
+        OutputFormat::Beancount => Box::new(beancount::BeancountOutput::create(path, options)?),
+        OutputFormat::Ynab => Box::new(ynab::YnabOutput::create(path, &options.encrypt_to)?),
+        OutputFormat::Mmex => Box::new(mmex::MmexOutput::create(path, options)?),
+        OutputFormat::Firefly => {
+            Box::new(firefly::FireflyOutput::create(path, &options.encrypt_to)?)
+        }
+        OutputFormat::Firefly3 => Box::new(firefly::FireflyApiOutput::new(options)?),
+        OutputFormat::Json => Box::new(json::JsonOutput::create(path, false, &options.encrypt_to)?),
+        OutputFormat::Jsonl => Box::new(json::JsonOutput::create(path, true, &options.encrypt_to)?),
+        OutputFormat::Xlsx => Box::new(xlsx::XlsxOutput::create(path, &options.encrypt_to)?),
+        OutputFormat::Sqlite => Box::new(sqlite::SqliteOutput::create(path, options.replace)?),
+        OutputFormat::Parquet => {
+            Box::new(parquet::ParquetOutput::create(path, &options.encrypt_to)?)
+        }
+        OutputFormat::Xhb => Box::new(xhb::XhbOutput::create(path, options)?),
+        OutputFormat::Actual => Box::new(actual::ActualOutput::new(options)?),
+    })
+}
+
+/// Writes the same record stream to several outputs in a single pass.
+pub struct FanOut {
+    outputs: Vec<Box<dyn Output>>,
+}
+
+impl FanOut {
+    pub fn open<P: AsRef<Path>>(paths: &[P], options: &OutputOptions) -> Result<Self> {
+        let outputs = paths
+            .iter()
+            .map(|path| open(path.as_ref(), options))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { outputs })
+    }
+}
+
+impl Output for FanOut {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        for output in &mut self.outputs {
+            output.write(record)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        for output in &mut self.outputs {
+            output.finish()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_fan_out_writes_all() {
+        let dir = std::env::temp_dir().join(format!("hbconv-fanout-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let paths = [dir.join("a.csv"), dir.join("b.csv")];
+
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        let options = OutputOptions::default();
+        let mut output = FanOut::open(&paths, &options).unwrap();
+        output.write(&record).unwrap();
+        output.finish().unwrap();
+
+        for path in &paths {
+            let written = fs::read_to_string(path).unwrap();
+            assert_eq!(written, "2024-03-07;8;;Woopsie;Doopsie;-25,88;;\n");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_default_options() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            options: OutputOptions,
+        }
+
+        let parsed = <Cli as clap::Parser>::parse_from(["hbconv"]).options;
+        assert_eq!(
+            format!("{:?}", parsed),
+            format!("{:?}", OutputOptions::default())
+        );
+
+        // Archives written before --dry-run have no field for it
+        let mut json = serde_json::to_value(OutputOptions::default()).unwrap();
+        json.as_object_mut().unwrap().remove("dry_run");
+        let options: OutputOptions = serde_json::from_value(json).unwrap();
+        assert!(!options.dry_run);
+    }
+}
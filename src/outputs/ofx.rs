@@ -0,0 +1,249 @@
+//! OFX 2.2 (xml) bank statement output.
+//!
+//! The records are collected and written as a single statement once the
+//! conversion is done, as the statement header needs the covered date range.
+//! Every transaction gets a FITID derived from its fingerprint, so importing
+//! the same transactions twice lets the importing side detect duplicates.
+//!
+//! A statement has a single currency, records in several have to be
+//! converted into one with `--convert-to` first. No input tells the balance
+//! of the account, so the ledger balance is the net change of the written
+//! transactions.
+
+use std::{
+    collections::HashMap,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use chrono::{NaiveDate, Utc};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use rust_decimal::Decimal;
+
+use super::{OutFile, Output};
+use crate::homebank::{Payment, Record};
+
+pub struct OfxOutput {
+    writer: BufWriter<OutFile>,
+    records: Vec<Record>,
+}
+
+impl OfxOutput {
+    pub fn create(path: &Path, encrypt_to: &[String]) -> Result<Self> {
+        let file = OutFile::create(path, encrypt_to)?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            records: Vec::new(),
+        })
+    }
+}
+
+impl Output for OfxOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let currency = currency(&self.records)?;
+        write_statement(&mut self.writer, &self.records, currency)
+            .into_diagnostic()
+            .wrap_err("Failed writing ofx statement")?;
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")
+    }
+}
+
+/// The currency of all `records`, OFX has one per statement.
+fn currency(records: &[Record]) -> Result<&'static str> {
+    let mut codes = records.iter().map(|r| r.amount.currency().iso_alpha_code);
+    let first = codes.next().unwrap_or("EUR");
+    match codes.find(|c| *c != first) {
+        Some(other) => Err(miette!(
+            help = "Convert them into one currency with --convert-to",
+            "The ofx output can't mix {} and {} records",
+            first,
+            other
+        )),
+        None => Ok(first),
+    }
+}
+
+fn write_statement<W: Write>(w: &mut W, records: &[Record], currency: &str) -> std::io::Result<()> {
+    let now = Utc::now().format("%Y%m%d%H%M%S");
+    let start = records.iter().map(|r| r.date).min();
+    let end = records.iter().map(|r| r.date).max();
+
+    writeln!(
+        w,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#
+    )?;
+    writeln!(
+        w,
+        r#"<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE" OLDFILEUID="NONE" NEWFILEUID="NONE"?>"#
+    )?;
+    writeln!(w, "<OFX>")?;
+    writeln!(w, "<SIGNONMSGSRSV1><SONRS>")?;
+    writeln!(
+        w,
+        "<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>"
+    )?;
+    writeln!(w, "<DTSERVER>{}</DTSERVER><LANGUAGE>ENG</LANGUAGE>", now)?;
+    writeln!(w, "</SONRS></SIGNONMSGSRSV1>")?;
+    writeln!(w, "<BANKMSGSRSV1><STMTTRNRS>")?;
+    writeln!(
+        w,
+        "<TRNUID>0</TRNUID><STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>"
+    )?;
+    writeln!(w, "<STMTRS>")?;
+    writeln!(w, "<CURDEF>{}</CURDEF>", currency)?;
+    // The input formats do not tell which account they belong to
+    writeln!(
+        w,
+        "<BANKACCTFROM><BANKID>0</BANKID><ACCTID>0</ACCTID><ACCTTYPE>CHECKING</ACCTTYPE></BANKACCTFROM>"
+    )?;
+    writeln!(w, "<BANKTRANLIST>")?;
+    if let (Some(start), Some(end)) = (start, end) {
+        writeln!(
+            w,
+            "<DTSTART>{}</DTSTART><DTEND>{}</DTEND>",
+            ofx_date(start),
+            ofx_date(end)
+        )?;
+    }
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for record in records {
+        let fitid = record.occurrence_id(&mut seen);
+
+        let exponent = record.amount.currency().exponent as usize;
+        writeln!(w, "<STMTTRN>")?;
+        writeln!(w, "<TRNTYPE>{}</TRNTYPE>", trntype(record))?;
+        writeln!(w, "<DTPOSTED>{}</DTPOSTED>", ofx_date(record.date))?;
+        writeln!(
+            w,
+            "<TRNAMT>{:.*}</TRNAMT>",
+            exponent,
+            record.amount.amount()
+        )?;
+        writeln!(w, "<FITID>{}</FITID>", fitid)?;
+        if !record.info.is_empty() {
+            writeln!(
+                w,
+                "<REFNUM>{}</REFNUM>",
+                escape(&truncate(&record.info, 32))
+            )?;
+        }
+        // OFX limits NAME to 32 and MEMO to 255 characters
+        writeln!(w, "<NAME>{}</NAME>", escape(&truncate(&record.payee, 32)))?;
+        if !record.memo.is_empty() {
+            writeln!(w, "<MEMO>{}</MEMO>", escape(&truncate(&record.memo, 255)))?;
+        }
+        writeln!(w, "</STMTTRN>")?;
+    }
+
+    writeln!(w, "</BANKTRANLIST>")?;
+    // The net change, the balance before the first transaction is unknown
+    let change: Decimal = records.iter().map(|r| *r.amount.amount()).sum();
+    let exponent = records
+        .first()
+        .map(|r| r.amount.currency().exponent as usize)
+        .unwrap_or(2);
+    writeln!(
+        w,
+        "<LEDGERBAL><BALAMT>{:.*}</BALAMT><DTASOF>{}</DTASOF></LEDGERBAL>",
+        exponent, change, now
+    )?;
+    writeln!(w, "</STMTRS>")?;
+    writeln!(w, "</STMTTRNRS></BANKMSGSRSV1>")?;
+    writeln!(w, "</OFX>")?;
+
+    Ok(())
+}
+
+fn trntype(record: &Record) -> &'static str {
+    match record.payment {
+        Payment::Check => "CHECK",
+        Payment::Cash => "CASH",
+        Payment::BankTransfer | Payment::InternalTransfer => "XFER",
+        Payment::CreditCard | Payment::DebitCard => "POS",
+        Payment::StandingOrder => "REPEATPMT",
+        Payment::Deposit => "DEP",
+        Payment::FinancialInstitutionFee => "FEE",
+        Payment::DirectDebit => "DIRECTDEBIT",
+        Payment::None | Payment::ElectronicPayment => {
+            if record.amount.is_negative() {
+                "DEBIT"
+            } else {
+                "CREDIT"
+            }
+        }
+    }
+}
+
+fn ofx_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod test {
+    use rusty_money::{
+        iso::{EUR, USD},
+        Money,
+    };
+
+    use super::*;
+
+    fn record(payee: &str) -> Record {
+        Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::DirectDebit,
+            payee: payee.to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_statement() {
+        let records = vec![record("Woop & Sie"), record("Woop & Sie")];
+        let mut out = Vec::new();
+        write_statement(&mut out, &records, currency(&records).unwrap()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("<DTSTART>20240307</DTSTART><DTEND>20240307</DTEND>"));
+        assert!(out.contains("<TRNTYPE>DIRECTDEBIT</TRNTYPE>"));
+        assert!(out.contains("<TRNAMT>-25.88</TRNAMT>"));
+        assert!(out.contains("<NAME>Woop &amp; Sie</NAME>"));
+
+        let fitids: Vec<_> = out.lines().filter(|l| l.starts_with("<FITID>")).collect();
+        assert_eq!(fitids.len(), 2);
+        assert_ne!(fitids[0], fitids[1]);
+        assert!(fitids[0].ends_with("-0</FITID>"));
+        assert!(out.contains("<CURDEF>EUR</CURDEF>"));
+        assert!(out.contains("<LEDGERBAL><BALAMT>-51.76</BALAMT>"));
+    }
+
+    #[test]
+    fn test_mixed_currencies() {
+        let dollars = Record {
+            amount: Money::from_str("-10.00", USD).unwrap(),
+            ..record("Woopsie")
+        };
+        assert!(currency(&[record("Woopsie"), dollars]).is_err());
+    }
+}
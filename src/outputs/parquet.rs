@@ -0,0 +1,270 @@
+//! Parquet file for analyzing the history with DuckDB, Polars and friends.
+//!
+//! Dates are written as parquet dates and amounts as decimals with four
+//! fractional digits, which covers the exponent of every ISO currency.
+//! The lines of split records are the repeated `splits` group.
+
+use std::{io::Write, path::Path, sync::Arc};
+
+use chrono::NaiveDate;
+use miette::{Context, IntoDiagnostic, Result};
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type},
+    errors::ParquetError,
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+
+use rusty_money::{iso::Currency, Money};
+
+use super::{OutFile, Output};
+use crate::homebank::Record;
+
+const SCHEMA: &str = "
+message record {
+    REQUIRED INT32 date (DATE);
+    REQUIRED INT32 paymode;
+    REQUIRED BYTE_ARRAY info (UTF8);
+    REQUIRED BYTE_ARRAY payee (UTF8);
+    REQUIRED BYTE_ARRAY memo (UTF8);
+    REQUIRED INT64 amount (DECIMAL(18, 4));
+    REQUIRED BYTE_ARRAY currency (UTF8);
+    REQUIRED BYTE_ARRAY category (UTF8);
+    REPEATED BYTE_ARRAY tags (UTF8);
+    REQUIRED BYTE_ARRAY iban (UTF8);
+    REPEATED GROUP splits {
+        REQUIRED INT64 amount (DECIMAL(18, 4));
+        REQUIRED BYTE_ARRAY category (UTF8);
+        REQUIRED BYTE_ARRAY memo (UTF8);
+    }
+}
+";
+const SCALE: u32 = 4;
+
+pub struct ParquetOutput {
+    file: Option<OutFile>,
+    records: Vec<Record>,
+}
+
+impl ParquetOutput {
+    pub fn create(path: &Path, encrypt_to: &[String]) -> Result<Self> {
+        let file = OutFile::create(path, encrypt_to)?;
+
+        Ok(Self {
+            file: Some(file),
+            records: Vec::new(),
+        })
+    }
+}
+
+impl Output for ParquetOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let Some(mut file) = self.file.take() else {
+            return Ok(());
+        };
+        // The writer flushes after each row group, gpg takes one flush only
+        let mut buffer = Vec::new();
+        write_records(&mut buffer, &self.records)
+            .into_diagnostic()
+            .wrap_err("Failed building parquet file")?;
+        file.write_all(&buffer)
+            .and_then(|_| file.flush())
+            .into_diagnostic()
+            .wrap_err("Failed writing parquet file")
+    }
+}
+
+fn write_records<W: Write + Send>(file: W, records: &[Record]) -> Result<(), ParquetError> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(file, schema, props)?;
+    let mut group = writer.next_row_group()?;
+
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default();
+    let text = |f: fn(&Record) -> &str| -> Vec<ByteArray> {
+        records.iter().map(|r| ByteArray::from(f(r))).collect()
+    };
+
+    let dates: Vec<i32> = records
+        .iter()
+        .map(|r| (r.date - epoch).num_days() as i32)
+        .collect();
+    let paymodes: Vec<i32> = records.iter().map(|r| r.payment as i32).collect();
+    let amounts: Vec<i64> = records.iter().map(|r| decimal(&r.amount)).collect();
+
+    // Tags are a repeated column: a record without tags still needs one
+    // undefined entry and every tag after the first continues the same row
+    let mut tags = Vec::new();
+    let mut tag_defs = Vec::new();
+    let mut tag_reps = Vec::new();
+    for record in records {
+        if record.tags.is_empty() {
+            tag_defs.push(0);
+            tag_reps.push(0);
+        }
+        for (idx, tag) in record.tags.iter().enumerate() {
+            tags.push(ByteArray::from(tag.as_str()));
+            tag_defs.push(1);
+            tag_reps.push(if idx == 0 { 0 } else { 1 });
+        }
+    }
+
+    // The split lines repeat the same way, all three columns sharing levels
+    let mut split_amounts = Vec::new();
+    let mut split_categories = Vec::new();
+    let mut split_memos = Vec::new();
+    let mut split_defs = Vec::new();
+    let mut split_reps = Vec::new();
+    for record in records {
+        if record.splits.is_empty() {
+            split_defs.push(0);
+            split_reps.push(0);
+        }
+        for (idx, line) in record.splits.iter().enumerate() {
+            split_amounts.push(decimal(&line.amount));
+            split_categories.push(ByteArray::from(line.category.as_str()));
+            split_memos.push(ByteArray::from(line.memo.as_str()));
+            split_defs.push(1);
+            split_reps.push(if idx == 0 { 0 } else { 1 });
+        }
+    }
+
+    let mut column = 0;
+    while let Some(mut col) = group.next_column()? {
+        match column {
+            0 => col.typed::<Int32Type>().write_batch(&dates, None, None)?,
+            1 => col
+                .typed::<Int32Type>()
+                .write_batch(&paymodes, None, None)?,
+            2 => col
+                .typed::<ByteArrayType>()
+                .write_batch(&text(|r| &r.info), None, None)?,
+            3 => col
+                .typed::<ByteArrayType>()
+                .write_batch(&text(|r| &r.payee), None, None)?,
+            4 => col
+                .typed::<ByteArrayType>()
+                .write_batch(&text(|r| &r.memo), None, None)?,
+            5 => col.typed::<Int64Type>().write_batch(&amounts, None, None)?,
+            6 => col.typed::<ByteArrayType>().write_batch(
+                &text(|r| r.amount.currency().iso_alpha_code),
+                None,
+                None,
+            )?,
+            7 => col
+                .typed::<ByteArrayType>()
+                .write_batch(&text(|r| &r.category), None, None)?,
+            8 => {
+                col.typed::<ByteArrayType>()
+                    .write_batch(&tags, Some(&tag_defs), Some(&tag_reps))?
+            }
+            9 => col
+                .typed::<ByteArrayType>()
+                .write_batch(&text(|r| &r.iban), None, None)?,
+            10 => col.typed::<Int64Type>().write_batch(
+                &split_amounts,
+                Some(&split_defs),
+                Some(&split_reps),
+            )?,
+            11 => col.typed::<ByteArrayType>().write_batch(
+                &split_categories,
+                Some(&split_defs),
+                Some(&split_reps),
+            )?,
+            _ => col.typed::<ByteArrayType>().write_batch(
+                &split_memos,
+                Some(&split_defs),
+                Some(&split_reps),
+            )?,
+        };
+        col.close()?;
+        column += 1;
+    }
+
+    group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+fn decimal(money: &Money<'_, Currency>) -> i64 {
+    let mut amount = *money.amount();
+    amount.rescale(SCALE);
+    amount.mantissa() as i64
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::{Payment, SplitLine};
+
+    #[test]
+    fn test_roundtrip() {
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        };
+        let tagged = Record {
+            tags: vec!["a".to_string(), "b".to_string()],
+            splits: vec![
+                SplitLine {
+                    amount: Money::from_str("-25,00", EUR).unwrap(),
+                    category: String::new(),
+                    memo: String::new(),
+                },
+                SplitLine {
+                    amount: Money::from_str("-0,88", EUR).unwrap(),
+                    category: "Fees".to_string(),
+                    memo: "Fee".to_string(),
+                },
+            ],
+            ..record.clone()
+        };
+        record.amount = Money::from_str("1.000", EUR).unwrap();
+
+        let path = std::env::temp_dir().join(format!("hbconv-{}.parquet", std::process::id()));
+        let mut output = ParquetOutput::create(&path, &[]).unwrap();
+        output.write(&record).unwrap();
+        output.write(&tagged).unwrap();
+        output.finish().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let rows: Vec<String> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().to_string())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].contains("date: 2024-03-07"), "{}", rows[0]);
+        assert!(rows[0].contains("amount: 1000.0000"), "{}", rows[0]);
+        assert!(rows[1].contains("amount: -25.8800"), "{}", rows[1]);
+        assert!(rows[1].contains(r#"tags: ["a", "b"]"#), "{}", rows[1]);
+        assert!(rows[0].contains("splits: []"), "{}", rows[0]);
+        assert!(
+            rows[1].contains(r#"{amount: -0.8800, category: "Fees", memo: "Fee"}"#),
+            "{}",
+            rows[1]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
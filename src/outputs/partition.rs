@@ -0,0 +1,213 @@
+//! Spreading the records over several files of each output.
+//!
+//! With `--split` every output path is a template, `{account}`, `{year}`
+//! and `{month}` being replaced per file. `homebank-{year}-{month}.csv`
+//! then gets one file per statement period. Outputs pushing to an API
+//! have no path to expand, they get all records at once.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use clap::ValueEnum;
+use miette::{miette, Result};
+use serde::{Deserialize, Serialize};
+
+use super::OutputFormat;
+use crate::homebank::Record;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Split {
+    /// One file per month
+    Monthly,
+    /// One file per input file, or zip entry, the records were read from
+    ByAccount,
+}
+
+/// Records belonging into one set of output files.
+pub struct Part {
+    pub outputs: Vec<PathBuf>,
+    pub records: Vec<Record>,
+}
+
+impl Split {
+    fn placeholders(&self) -> &'static [&'static str] {
+        match self {
+            Split::Monthly => &["{year}", "{month}"],
+            Split::ByAccount => &["{account}"],
+        }
+    }
+
+    /// Groups `records` and expands the output templates for every group,
+    /// in order of the groups. `account` names the account of the input, for
+    /// records not telling their source. API outputs come last, as one part
+    /// with all records.
+    pub fn partition(
+        &self,
+        records: Vec<Record>,
+        templates: &[PathBuf],
+        account: &str,
+    ) -> Result<Vec<Part>> {
+        let (apis, templates): (Vec<_>, Vec<_>) = templates
+            .iter()
+            .cloned()
+            .partition(|t| OutputFormat::detect(t).is_api());
+        for template in &templates {
+            let text = template.to_string_lossy();
+            if let Some(missing) = self.placeholders().iter().find(|p| !text.contains(**p)) {
+                return Err(miette!(
+                    help = "Without it all parts would end up in the same file",
+                    "Output {} lacks {} to split by",
+                    text,
+                    missing
+                ));
+            }
+        }
+
+        let api = (!apis.is_empty()).then(|| Part {
+            outputs: apis,
+            records: records.clone(),
+        });
+        let mut groups: BTreeMap<String, Vec<Record>> = BTreeMap::new();
+        for record in records {
+            let key = match self {
+                Split::Monthly => record.date.format("%Y-%m").to_string(),
+                Split::ByAccount if !record.source.is_empty() => record.source.clone(),
+                Split::ByAccount => account.to_string(),
+            };
+            groups.entry(key).or_default().push(record);
+        }
+
+        let mut parts: Vec<Part> = groups
+            .into_iter()
+            .filter(|_| !templates.is_empty())
+            .map(|(key, records)| {
+                let first = &records[0];
+                let year = first.date.format("%Y").to_string();
+                let month = first.date.format("%m").to_string();
+                let account = match self {
+                    Split::Monthly => account,
+                    Split::ByAccount => &key,
+                };
+                let outputs = templates
+                    .iter()
+                    .map(|t| {
+                        let path = t
+                            .to_string_lossy()
+                            .replace("{account}", account)
+                            .replace("{year}", &year)
+                            .replace("{month}", &month);
+                        PathBuf::from(path)
+                    })
+                    .collect();
+                Part { outputs, records }
+            })
+            .collect();
+        parts.extend(api);
+        Ok(parts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    fn record(month: u32, source: &str) -> Record {
+        Record {
+            date: NaiveDate::from_ymd_opt(2024, month, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            source: source.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_monthly() {
+        let templates = [PathBuf::from("homebank-{account}-{year}-{month}.csv")];
+        let records = vec![record(4, ""), record(3, ""), record(4, "")];
+
+        let parts = Split::Monthly
+            .partition(records.clone(), &templates, "giro")
+            .unwrap();
+        let files: Vec<_> = parts
+            .iter()
+            .map(|p| (p.outputs[0].to_string_lossy().into_owned(), p.records.len()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("homebank-giro-2024-03.csv".to_string(), 1),
+                ("homebank-giro-2024-04.csv".to_string(), 2),
+            ]
+        );
+
+        let templates = [PathBuf::from("homebank-{account}.csv")];
+        assert!(Split::Monthly
+            .partition(records, &templates, "giro")
+            .is_err());
+    }
+
+    #[test]
+    fn test_api_outputs() {
+        let templates = [
+            PathBuf::from("hb-{year}-{month}.csv"),
+            PathBuf::from("firefly3"),
+        ];
+        let records = vec![record(4, ""), record(3, ""), record(4, "")];
+
+        let parts = Split::Monthly
+            .partition(records, &templates, "giro")
+            .unwrap();
+        let files: Vec<_> = parts
+            .iter()
+            .map(|p| (p.outputs.clone(), p.records.len()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (vec![PathBuf::from("hb-2024-03.csv")], 1),
+                (vec![PathBuf::from("hb-2024-04.csv")], 2),
+                (vec![PathBuf::from("firefly3")], 3),
+            ]
+        );
+
+        let parts = Split::Monthly
+            .partition(vec![record(4, "")], &templates[1..], "giro")
+            .unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].outputs, vec![PathBuf::from("firefly3")]);
+    }
+
+    #[test]
+    fn test_by_account() {
+        let templates = [PathBuf::from("homebank-{account}.csv")];
+        let records = vec![
+            record(3, "giro"),
+            record(3, "visa"),
+            record(4, "giro"),
+            record(4, ""),
+        ];
+
+        let parts = Split::ByAccount
+            .partition(records, &templates, "main")
+            .unwrap();
+        let files: Vec<_> = parts
+            .iter()
+            .map(|p| (p.outputs[0].to_string_lossy().into_owned(), p.records.len()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("homebank-giro.csv".to_string(), 2),
+                ("homebank-main.csv".to_string(), 1),
+                ("homebank-visa.csv".to_string(), 1),
+            ]
+        );
+    }
+}
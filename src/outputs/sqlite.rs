@@ -0,0 +1,311 @@
+//! SQLite database collecting the records of many runs.
+//!
+//! Records are appended to the `transactions` table. Every row carries a
+//! hash of the record fingerprint and its occurrence within the run, so
+//! converting overlapping exports into the same database adds every
+//! transaction only once. The lines of split records go into the `splits`
+//! table, referring to their transaction by its hash. When reapplying, the
+//! payment, category, tags and split lines of known rows are updated.
+
+use std::{collections::HashMap, path::Path};
+
+use chrono::Utc;
+use miette::{Context, IntoDiagnostic, Result};
+use rusqlite::{params, Connection};
+use tracing::info;
+
+use super::Output;
+use crate::homebank::Record;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS transactions (
+    id INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL UNIQUE,
+    date TEXT NOT NULL,
+    paymode INTEGER NOT NULL,
+    info TEXT NOT NULL,
+    payee TEXT NOT NULL,
+    memo TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    currency TEXT NOT NULL,
+    category TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    iban TEXT NOT NULL,
+    imported_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS splits (
+    hash TEXT NOT NULL REFERENCES transactions (hash),
+    line INTEGER NOT NULL,
+    amount TEXT NOT NULL,
+    currency TEXT NOT NULL,
+    category TEXT NOT NULL,
+    memo TEXT NOT NULL,
+    PRIMARY KEY (hash, line)
+);
+";
+
+pub struct SqliteOutput {
+    conn: Connection,
+    /// Update known rows instead of skipping them
+    replace: bool,
+    seen: HashMap<String, usize>,
+    inserted: usize,
+    updated: usize,
+    duplicates: usize,
+}
+
+impl SqliteOutput {
+    pub fn create(path: &Path, replace: bool) -> Result<Self> {
+        let conn = Connection::open(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed opening database {}", path.display()))?;
+        Self::with_connection(conn, replace)
+    }
+
+    fn with_connection(conn: Connection, replace: bool) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .into_diagnostic()
+            .wrap_err("Failed creating transactions table")?;
+        conn.execute_batch("BEGIN")
+            .into_diagnostic()
+            .wrap_err("Failed starting transaction")?;
+
+        Ok(Self {
+            conn,
+            replace,
+            seen: HashMap::new(),
+            inserted: 0,
+            updated: 0,
+            duplicates: 0,
+        })
+    }
+
+    /// Updates the row of `hash` to `record`, dropping its split lines.
+    /// Returns whether there is one.
+    fn update(&self, hash: &str, record: &Record) -> Result<bool> {
+        let updated = self
+            .conn
+            .prepare_cached(
+                "UPDATE transactions SET paymode = ?2, category = ?3, tags = ?4 WHERE hash = ?1",
+            )
+            .and_then(|mut stmt| {
+                stmt.execute(params![
+                    hash,
+                    record.payment as u8,
+                    record.category,
+                    record.tags.join(" "),
+                ])
+            })
+            .into_diagnostic()
+            .wrap_err("Failed updating record")?;
+        if updated == 0 {
+            return Ok(false);
+        }
+
+        self.conn
+            .prepare_cached("DELETE FROM splits WHERE hash = ?1")
+            .and_then(|mut stmt| stmt.execute(params![hash]))
+            .into_diagnostic()
+            .wrap_err("Failed removing split lines")?;
+        Ok(true)
+    }
+
+    /// Inserts `record` unless a row has its `hash`. Returns whether it was
+    /// inserted.
+    fn insert(&self, hash: &str, record: &Record) -> Result<bool> {
+        let exponent = record.amount.currency().exponent as usize;
+        let inserted = self
+            .conn
+            .prepare_cached(
+                "INSERT OR IGNORE INTO transactions
+                (hash, date, paymode, info, payee, memo, amount, currency, category, tags, iban, imported_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )
+            .and_then(|mut stmt| {
+                stmt.execute(params![
+                    hash,
+                    record.date.to_string(),
+                    record.payment as u8,
+                    record.info,
+                    record.payee,
+                    record.memo,
+                    format!("{:.*}", exponent, record.amount.amount()),
+                    record.amount.currency().iso_alpha_code,
+                    record.category,
+                    record.tags.join(" "),
+                    record.iban,
+                    Utc::now().to_rfc3339(),
+                ])
+            })
+            .into_diagnostic()
+            .wrap_err("Failed inserting record")?;
+        Ok(inserted > 0)
+    }
+}
+
+impl Output for SqliteOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let hash = record.occurrence_id(&mut self.seen);
+
+        if self.replace && self.update(&hash, record)? {
+            self.updated += 1;
+        } else if self.insert(&hash, record)? {
+            self.inserted += 1;
+        } else {
+            self.duplicates += 1;
+            return Ok(());
+        }
+
+        for (idx, line) in record.splits.iter().enumerate() {
+            let exponent = line.amount.currency().exponent as usize;
+            self.conn
+                .prepare_cached(
+                    "INSERT INTO splits (hash, line, amount, currency, category, memo)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )
+                .and_then(|mut stmt| {
+                    stmt.execute(params![
+                        hash,
+                        idx as i64,
+                        format!("{:.*}", exponent, line.amount.amount()),
+                        line.amount.currency().iso_alpha_code,
+                        line.category,
+                        line.memo,
+                    ])
+                })
+                .into_diagnostic()
+                .wrap_err("Failed inserting split line")?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.conn
+            .execute_batch("COMMIT")
+            .into_diagnostic()
+            .wrap_err("Failed committing records")?;
+        info!(
+            inserted = self.inserted,
+            updated = self.updated,
+            duplicates = self.duplicates,
+            "Wrote records to database"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::{Payment, SplitLine};
+
+    #[test]
+    fn test_append_skips_known() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        let mut output =
+            SqliteOutput::with_connection(Connection::open_in_memory().unwrap(), false).unwrap();
+        output.write(&record).unwrap();
+        output.write(&record).unwrap();
+        output.finish().unwrap();
+        assert_eq!(output.inserted, 2);
+
+        // The same export again adds nothing
+        let mut output = SqliteOutput::with_connection(output.conn, false).unwrap();
+        output.write(&record).unwrap();
+        output.write(&record).unwrap();
+        output.finish().unwrap();
+        assert_eq!(output.inserted, 0);
+        assert_eq!(output.duplicates, 2);
+
+        let amount: String = output
+            .conn
+            .query_row("SELECT amount FROM transactions LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(amount, "-25.88");
+    }
+
+    #[test]
+    fn test_replace_updates_known() {
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        let mut output =
+            SqliteOutput::with_connection(Connection::open_in_memory().unwrap(), false).unwrap();
+        output.write(&record).unwrap();
+        output.finish().unwrap();
+
+        // A changed rule categorizes the same transaction on reapply
+        record.category = "Food".to_string();
+        let mut output = SqliteOutput::with_connection(output.conn, true).unwrap();
+        output.write(&record).unwrap();
+        output.finish().unwrap();
+        assert_eq!((output.inserted, output.updated), (0, 1));
+
+        let category: String = output
+            .conn
+            .query_row("SELECT category FROM transactions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(category, "Food");
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("-10,35", EUR).unwrap(),
+            splits: vec![
+                SplitLine {
+                    amount: Money::from_str("-10,00", EUR).unwrap(),
+                    category: String::new(),
+                    memo: "Order".to_string(),
+                },
+                SplitLine {
+                    amount: Money::from_str("-0,35", EUR).unwrap(),
+                    category: "Fees:PayPal".to_string(),
+                    memo: "PayPal fee".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut output =
+            SqliteOutput::with_connection(Connection::open_in_memory().unwrap(), false).unwrap();
+        output.write(&record).unwrap();
+        output.finish().unwrap();
+
+        let lines: Vec<(String, String)> = output
+            .conn
+            .prepare("SELECT amount, category FROM splits ORDER BY line")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                ("-10.00".to_string(), String::new()),
+                ("-0.35".to_string(), "Fees:PayPal".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//! Appending to an existing HomeBank `.xhb` file.
+//!
+//! Instead of a csv file to import by hand, the records are added as
+//! operations to the account `--xhb-account` of the given HomeBank file.
+//! Payees and categories are looked up by name and created if missing,
+//! `Food:Groceries` becoming the subcategory `Groceries` of `Food`. Split
+//! records keep their category lines. Records already in the account, with
+//! the same date, amount, payee and info, are left out, so running again
+//! does not add them twice. When reapplying they are updated instead,
+//! keeping their flags. The file is copied to `<file>.bak` before it gets
+//! rewritten.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::Datelike;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use quick_xml::{
+    events::{BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+use rust_decimal::Decimal;
+
+use super::{Output, OutputOptions};
+use crate::homebank::Record;
+
+pub struct XhbOutput {
+    path: PathBuf,
+    file: XhbFile,
+    account: u32,
+    /// Update the operations already in the account instead of leaving them
+    replace: bool,
+    records: Vec<Record>,
+}
+
+/// The parsed file, with the keys of everything operations refer to.
+struct XhbFile {
+    events: Vec<Event<'static>>,
+    accounts: HashMap<String, u32>,
+    payees: HashMap<String, u32>,
+    /// Categories by parent key (0 for top level ones) and name
+    categories: HashMap<(u32, String), u32>,
+    /// Operations by account, date, amount, payee and info, with the
+    /// positions of them among the events
+    operations: HashMap<Operation, Vec<usize>>,
+    next_payee: u32,
+    next_category: u32,
+    /// Payees and categories created while appending
+    definitions: Vec<BytesStart<'static>>,
+}
+
+/// What tells an operation apart from the others of its account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Operation {
+    account: u32,
+    date: String,
+    amount: Decimal,
+    payee: u32,
+    info: String,
+}
+
+impl XhbOutput {
+    pub fn create(path: &Path, options: &OutputOptions) -> Result<Self> {
+        let name = options
+            .xhb_account
+            .as_deref()
+            .ok_or_else(|| miette!("Appending to a HomeBank file needs --xhb-account"))?;
+
+        let content = fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed reading HomeBank file {}", path.display()))?;
+        let file = XhbFile::parse(&content)
+            .wrap_err_with(|| format!("Failed parsing HomeBank file {}", path.display()))?;
+
+        let account = *file.accounts.get(name).ok_or_else(|| {
+            let mut known: Vec<_> = file.accounts.keys().map(String::as_str).collect();
+            known.sort();
+            miette!(
+                help = format!("Accounts in the file: {}", known.join(", ")),
+                "No account named '{}' in {}",
+                name,
+                path.display()
+            )
+        })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            account,
+            replace: options.replace,
+            records: Vec::new(),
+        })
+    }
+}
+
+impl Output for XhbOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let content = self
+            .file
+            .append(self.account, &self.records, self.replace)?;
+
+        let mut backup = self.path.as_os_str().to_owned();
+        backup.push(".bak");
+        fs::copy(&self.path, &backup)
+            .into_diagnostic()
+            .wrap_err("Failed backing up HomeBank file")?;
+
+        // Write next to the original first, so a failure leaves it untouched
+        let mut partial = self.path.as_os_str().to_owned();
+        partial.push(".partial");
+        fs::write(&partial, content)
+            .into_diagnostic()
+            .wrap_err("Failed writing HomeBank file")?;
+        fs::rename(&partial, &self.path)
+            .into_diagnostic()
+            .wrap_err("Failed replacing HomeBank file")
+    }
+}
+
+impl XhbFile {
+    fn parse(content: &str) -> Result<Self> {
+        let mut file = Self {
+            events: Vec::new(),
+            accounts: HashMap::new(),
+            payees: HashMap::new(),
+            categories: HashMap::new(),
+            operations: HashMap::new(),
+            next_payee: 1,
+            next_category: 1,
+            definitions: Vec::new(),
+        };
+
+        let mut reader = Reader::from_str(content);
+        loop {
+            let event = reader.read_event().into_diagnostic()?;
+            match &event {
+                Event::Eof => break,
+                Event::Start(e) | Event::Empty(e) => file.index(e, file.events.len())?,
+                _ => {}
+            }
+            file.events.push(event.into_owned());
+        }
+
+        Ok(file)
+    }
+
+    /// Keeps the keys of `element`, the event at `at`.
+    fn index(&mut self, element: &BytesStart, at: usize) -> Result<()> {
+        let attr = |name| -> Result<Option<String>> {
+            element
+                .try_get_attribute(name)
+                .into_diagnostic()?
+                .map(|a| a.unescape_value().map(|v| v.into_owned()))
+                .transpose()
+                .into_diagnostic()
+        };
+        let key = |value: Option<String>| -> Result<u32> {
+            value
+                .unwrap_or_default()
+                .parse()
+                .into_diagnostic()
+                .wrap_err("Invalid key")
+        };
+
+        match element.name().as_ref() {
+            b"account" => {
+                let key = key(attr("key")?)?;
+                self.accounts.insert(attr("name")?.unwrap_or_default(), key);
+            }
+            b"pay" => {
+                let key = key(attr("key")?)?;
+                self.payees.insert(attr("name")?.unwrap_or_default(), key);
+                self.next_payee = self.next_payee.max(key + 1);
+            }
+            b"cat" => {
+                let key = key(attr("key")?)?;
+                let parent = attr("parent")?.map(|p| p.parse().unwrap_or(0)).unwrap_or(0);
+                self.categories
+                    .insert((parent, attr("name")?.unwrap_or_default()), key);
+                self.next_category = self.next_category.max(key + 1);
+            }
+            b"ope" => {
+                let operation = Operation {
+                    account: key(attr("account")?)?,
+                    date: attr("date")?.unwrap_or_default(),
+                    amount: Decimal::from_str(&attr("amount")?.unwrap_or_default())
+                        .into_diagnostic()
+                        .wrap_err("Invalid amount")?
+                        .normalize(),
+                    payee: attr("payee")?.map(|p| p.parse().unwrap_or(0)).unwrap_or(0),
+                    info: attr("info")?.unwrap_or_default(),
+                };
+                self.operations.entry(operation).or_default().push(at);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn payee(&mut self, name: &str) -> u32 {
+        if name.is_empty() {
+            return 0;
+        }
+        if let Some(key) = self.payees.get(name) {
+            return *key;
+        }
+
+        let key = self.next_payee;
+        self.next_payee += 1;
+        self.payees.insert(name.to_string(), key);
+        let key_text = key.to_string();
+        self.definitions.push(
+            BytesStart::new("pay")
+                .with_attributes([("key", key_text.as_str()), ("name", name)])
+                .into_owned(),
+        );
+        key
+    }
+
+    fn category(&mut self, path: &str) -> u32 {
+        let mut parent = 0;
+        for name in path.split(':').filter(|n| !n.is_empty()) {
+            if let Some(key) = self.categories.get(&(parent, name.to_string())) {
+                parent = *key;
+                continue;
+            }
+
+            let key = self.next_category;
+            self.next_category += 1;
+            self.categories.insert((parent, name.to_string()), key);
+            let (key_text, parent_text) = (key.to_string(), parent.to_string());
+            let mut cat = BytesStart::new("cat").with_attributes([("key", key_text.as_str())]);
+            if parent != 0 {
+                // Flag 1 marks a subcategory
+                cat.push_attribute(("parent", parent_text.as_str()));
+                cat.push_attribute(("flags", "1"));
+            }
+            cat.push_attribute(("name", name));
+            self.definitions.push(cat.into_owned());
+            parent = key;
+        }
+        parent
+    }
+
+    /// Renders the file with `records` added to `account`, those it already
+    /// has left out or, to `replace` them, updated.
+    fn append(&mut self, account: u32, records: &[Record], replace: bool) -> Result<Vec<u8>> {
+        let mut operations = Vec::new();
+        let mut replaced = HashMap::new();
+        for record in records {
+            let date = record.date.num_days_from_ce().to_string();
+            let existing = Operation {
+                account,
+                date: date.clone(),
+                amount: record.amount.amount().normalize(),
+                payee: self.payees.get(&record.payee).copied().unwrap_or(0),
+                info: record.info.clone(),
+            };
+            // Each one in the file stands for one of identical records
+            let known = self
+                .operations
+                .get_mut(&existing)
+                .filter(|at| !at.is_empty())
+                .map(|at| at.remove(0));
+
+            if known.is_some() && !replace {
+                continue;
+            }
+
+            let values = self.values(account, date, record);
+            match known {
+                Some(at) => {
+                    if let Event::Start(e) | Event::Empty(e) = &self.events[at] {
+                        replaced.insert(at, merge(e, &values));
+                    }
+                }
+                None => {
+                    let mut ope = BytesStart::new("ope");
+                    for (name, value) in &values {
+                        if !value.is_empty() {
+                            ope.push_attribute((*name, value.as_str()));
+                        }
+                    }
+                    operations.push(ope.into_owned());
+                }
+            }
+        }
+
+        // Payees and categories go behind the existing ones, before the
+        // favourites and operations, the operations at the very end
+        let definitions_at = self
+            .events
+            .iter()
+            .position(|e| match e {
+                Event::Start(e) | Event::Empty(e) => {
+                    matches!(e.name().as_ref(), b"fav" | b"ope")
+                }
+                Event::End(e) => e.name().as_ref() == b"homebank",
+                _ => false,
+            })
+            .ok_or_else(|| miette!("Not a HomeBank file"))?;
+        let operations_at = self
+            .events
+            .iter()
+            .rposition(|e| matches!(e, Event::End(e) if e.name().as_ref() == b"homebank"))
+            .ok_or_else(|| miette!("Not a HomeBank file"))?;
+
+        let mut writer = Writer::new(Vec::new());
+        for (idx, event) in self.events.iter().enumerate() {
+            if idx == definitions_at {
+                for element in &self.definitions {
+                    write_element(&mut writer, element)?;
+                }
+            }
+            if idx == operations_at {
+                for element in &operations {
+                    write_element(&mut writer, element)?;
+                }
+            }
+            let event = match (replaced.get(&idx), event) {
+                (Some(ope), Event::Start(_)) => Event::Start(ope.borrow()),
+                (Some(ope), _) => Event::Empty(ope.borrow()),
+                (None, event) => event.clone(),
+            };
+            writer.write_event(event).into_diagnostic()?;
+        }
+
+        Ok(writer.into_inner())
+    }
+
+    /// The attributes of the operation of `record` in `account` on `date`,
+    /// empty ones left out when writing.
+    fn values(
+        &mut self,
+        account: u32,
+        date: String,
+        record: &Record,
+    ) -> Vec<(&'static str, String)> {
+        let payee = self.payee(&record.payee);
+        let category = match record.splits.is_empty() {
+            true => self.category(&record.category),
+            false => 0,
+        };
+        let exponent = record.amount.currency().exponent as usize;
+        let mut splits = [Vec::new(), Vec::new(), Vec::new()];
+        for line in &record.splits {
+            let exponent = line.amount.currency().exponent as usize;
+            splits[0].push(self.category(&line.category).to_string());
+            splits[1].push(format!("{:.*}", exponent, line.amount.amount()));
+            splits[2].push(line.memo.clone());
+        }
+        let [scat, samt, smem] = splits.map(|values| values.join("||"));
+
+        vec![
+            ("date", date),
+            ("amount", format!("{:.*}", exponent, record.amount.amount())),
+            ("account", account.to_string()),
+            ("paymode", (record.payment as u8).to_string()),
+            ("flags", "0".to_string()),
+            ("payee", payee.to_string()),
+            ("category", category.to_string()),
+            ("wording", record.memo.clone()),
+            ("info", record.info.clone()),
+            ("tags", record.tags.join(" ")),
+            ("scat", scat),
+            ("samt", samt),
+            ("smem", smem),
+        ]
+    }
+}
+
+/// The operation `ope` with the attributes of `values`. Its flags, like the
+/// reconciled state, and attributes beyond them are kept.
+fn merge(ope: &BytesStart, values: &[(&str, String)]) -> BytesStart<'static> {
+    let mut merged = BytesStart::new("ope");
+    for attr in ope.attributes().flatten() {
+        let name = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        match values.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) if name != "flags" => {
+                if !value.is_empty() {
+                    merged.push_attribute((name.as_str(), value.as_str()));
+                }
+            }
+            _ => merged.push_attribute(attr),
+        }
+    }
+    for (name, value) in values {
+        let known = ope
+            .attributes()
+            .flatten()
+            .any(|a| a.key.as_ref() == name.as_bytes());
+        if !known && !value.is_empty() {
+            merged.push_attribute((*name, value.as_str()));
+        }
+    }
+    merged.into_owned()
+}
+
+fn write_element(writer: &mut Writer<Vec<u8>>, element: &BytesStart) -> Result<()> {
+    writer
+        .write_event(Event::Empty(element.borrow()))
+        .and_then(|_| writer.write_event(Event::Text(BytesText::new("\n"))))
+        .into_diagnostic()
+        .wrap_err("Failed writing HomeBank element")
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::{Payment, SplitLine};
+
+    #[test]
+    fn test_append() {
+        let content = r#"<?xml version="1.0"?>
+<homebank v="1.3999999999999999" d="050504">
+<properties title="Home" curr="1"/>
+<cur key="1" flags="0" iso="EUR" name="Euro" symb="€" syprf="0" dchar="," gchar="." frac="2"/>
+<account key="1" pos="1" type="1" curr="1" name="Girokonto"/>
+<pay key="1" name="Woopsie"/>
+<cat key="1" name="Food"/>
+<ope date="738951" amount="-1" account="1" paymode="8" flags="0" payee="1" category="1"/>
+</homebank>
+"#;
+        let mut file = XhbFile::parse(content).unwrap();
+        assert_eq!(file.accounts["Girokonto"], 1);
+
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie & co".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            category: "Food:Groceries".to_string(),
+            tags: vec!["fun".to_string()],
+            ..Default::default()
+        };
+        let other = Record {
+            payee: "Other".to_string(),
+            category: String::new(),
+            tags: Vec::new(),
+            ..record.clone()
+        };
+
+        let written = file.append(1, &[record, other], false).unwrap();
+        let expected = r#"<?xml version="1.0"?>
+<homebank v="1.3999999999999999" d="050504">
+<properties title="Home" curr="1"/>
+<cur key="1" flags="0" iso="EUR" name="Euro" symb="€" syprf="0" dchar="," gchar="." frac="2"/>
+<account key="1" pos="1" type="1" curr="1" name="Girokonto"/>
+<pay key="1" name="Woopsie"/>
+<cat key="1" name="Food"/>
+<cat key="2" parent="1" flags="1" name="Groceries"/>
+<pay key="2" name="Other"/>
+<ope date="738951" amount="-1" account="1" paymode="8" flags="0" payee="1" category="1"/>
+<ope date="738952" amount="-25.88" account="1" paymode="8" flags="0" payee="1" category="2" wording="Doopsie &amp; co" tags="fun"/>
+<ope date="738952" amount="-25.88" account="1" paymode="8" flags="0" payee="2" category="0" wording="Doopsie &amp; co"/>
+</homebank>
+"#;
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_append_again_and_splits() {
+        let content = r#"<homebank v="1.3999999999999999" d="050504">
+<account key="1" pos="1" type="1" curr="1" name="Girokonto"/>
+<pay key="1" name="Woopsie"/>
+<ope date="738951" amount="-1" account="1" paymode="8" flags="0" payee="1" category="0"/>
+</homebank>
+"#;
+        let mut file = XhbFile::parse(content).unwrap();
+
+        // Already in the file once, so only the second one is added
+        let known = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 6).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("-1,00", EUR).unwrap(),
+            ..Default::default()
+        };
+        let line = |amount, category: &str, memo: &str| SplitLine {
+            amount: Money::from_str(amount, EUR).unwrap(),
+            category: category.to_string(),
+            memo: memo.to_string(),
+        };
+        let split = Record {
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            splits: vec![
+                line("-20,00", "Food", "Lunch"),
+                line("-5,88", "Fees", "Fee"),
+            ],
+            ..known.clone()
+        };
+
+        let written = file
+            .append(1, &[known.clone(), known, split], false)
+            .unwrap();
+        let expected = r#"<homebank v="1.3999999999999999" d="050504">
+<account key="1" pos="1" type="1" curr="1" name="Girokonto"/>
+<pay key="1" name="Woopsie"/>
+<cat key="1" name="Food"/>
+<cat key="2" name="Fees"/>
+<ope date="738951" amount="-1" account="1" paymode="8" flags="0" payee="1" category="0"/>
+<ope date="738951" amount="-1.00" account="1" paymode="8" flags="0" payee="1" category="0"/>
+<ope date="738951" amount="-25.88" account="1" paymode="8" flags="0" payee="1" category="0" scat="1||2" samt="-20.00||-5.88" smem="Lunch||Fee"/>
+</homebank>
+"#;
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_replace() {
+        let content = r#"<homebank v="1.3999999999999999" d="050504">
+<account key="1" pos="1" type="1" curr="1" name="Girokonto"/>
+<pay key="1" name="Woopsie"/>
+<ope date="738951" amount="-1" account="1" paymode="8" flags="2" payee="1" category="0" wording="Old"/>
+</homebank>
+"#;
+        let mut file = XhbFile::parse(content).unwrap();
+
+        // A changed rule categorizes a reconciled operation
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 6).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("-1,00", EUR).unwrap(),
+            category: "Food".to_string(),
+            ..Default::default()
+        };
+
+        let written = file.append(1, &[record], true).unwrap();
+        let expected = r#"<homebank v="1.3999999999999999" d="050504">
+<account key="1" pos="1" type="1" curr="1" name="Girokonto"/>
+<pay key="1" name="Woopsie"/>
+<cat key="1" name="Food"/>
+<ope date="738951" amount="-1.00" account="1" paymode="8" flags="2" payee="1" category="1"/>
+</homebank>
+"#;
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+    }
+}
@@ -0,0 +1,130 @@
+//! Excel workbook for reviewing a month before importing it.
+//!
+//! Dates and amounts are written as typed cells, so they sort and sum in
+//! Excel and LibreOffice. The header row is frozen and has an auto filter.
+//! Split records get a row for each line.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use miette::{Context, IntoDiagnostic, Result};
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+
+use super::{OutFile, Output};
+use crate::homebank::Record;
+
+const HEADER: [&str; 8] = [
+    "Date", "Payee", "Memo", "Amount", "Currency", "Category", "Tags", "Info",
+];
+
+pub struct XlsxOutput {
+    path: PathBuf,
+    encrypt_to: Vec<String>,
+    records: Vec<Record>,
+}
+
+impl XlsxOutput {
+    pub fn create(path: &Path, encrypt_to: &[String]) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            encrypt_to: encrypt_to.to_vec(),
+            records: Vec::new(),
+        })
+    }
+}
+
+impl Output for XlsxOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.records.extend(record.lines());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let mut workbook = workbook(&self.records)
+            .into_diagnostic()
+            .wrap_err("Failed building workbook")?;
+        let buffer = workbook
+            .save_to_buffer()
+            .into_diagnostic()
+            .wrap_err("Failed building workbook")?;
+        let mut file = OutFile::create(&self.path, &self.encrypt_to)?;
+        file.write_all(&buffer)
+            .and_then(|_| file.flush())
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed writing {}", self.path.display()))
+    }
+}
+
+fn workbook(records: &[Record]) -> Result<Workbook, XlsxError> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet().set_name("Records")?;
+
+    let bold = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+
+    for (col, title) in HEADER.iter().enumerate() {
+        sheet.write_with_format(0, col as u16, *title, &bold)?;
+    }
+
+    for (idx, record) in records.iter().enumerate() {
+        let row = idx as u32 + 1;
+        let exponent = record.amount.currency().exponent as usize;
+        let amount = Format::new().set_num_format(match exponent {
+            0 => "#,##0".to_string(),
+            n => format!("#,##0.{}", "0".repeat(n)),
+        });
+
+        sheet.write_with_format(row, 0, &record.date, &date)?;
+        sheet.write(row, 1, &record.payee)?;
+        sheet.write(row, 2, &record.memo)?;
+        sheet.write_with_format(
+            row,
+            3,
+            record.amount.amount().to_f64().unwrap_or_default(),
+            &amount,
+        )?;
+        sheet.write(row, 4, record.amount.currency().iso_alpha_code)?;
+        sheet.write(row, 5, &record.category)?;
+        sheet.write(row, 6, record.tags.join(" "))?;
+        sheet.write(row, 7, &record.info)?;
+    }
+
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, records.len() as u32, HEADER.len() as u16 - 1)?;
+    sheet.set_column_width(0, 12)?;
+    sheet.set_column_width(1, 30)?;
+    sheet.set_column_width(2, 50)?;
+    sheet.set_column_width(3, 12)?;
+
+    Ok(workbook)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_workbook() {
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        let buffer = workbook(&[record.clone(), record])
+            .unwrap()
+            .save_to_buffer()
+            .unwrap();
+        assert!(buffer.starts_with(b"PK"));
+    }
+}
@@ -0,0 +1,106 @@
+//! CSV file for the file based import of You Need A Budget.
+//!
+//! YNAB expects US dates and decimals and splits the amount into an outflow
+//! and an inflow column, both positive.
+
+use std::path::Path;
+
+use csv::Writer;
+use miette::{Context, IntoDiagnostic, Result};
+use serde::Serialize;
+
+use super::{OutFile, Output};
+use crate::homebank::Record;
+
+pub struct YnabOutput {
+    writer: Writer<OutFile>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct YnabRow {
+    date: String,
+    payee: String,
+    memo: String,
+    outflow: String,
+    inflow: String,
+}
+
+impl YnabOutput {
+    pub fn create(path: &Path, encrypt_to: &[String]) -> Result<Self> {
+        let writer = Writer::from_writer(OutFile::create(path, encrypt_to)?);
+
+        Ok(Self { writer })
+    }
+}
+
+impl Output for YnabOutput {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.writer
+            .serialize(YnabRow::from(record))
+            .into_diagnostic()
+            .wrap_err("Failed writing YNAB row")
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing output")
+    }
+}
+
+impl From<&Record> for YnabRow {
+    fn from(record: &Record) -> Self {
+        let exponent = record.amount.currency().exponent as usize;
+        let amount = format!("{:.*}", exponent, record.amount.amount().abs());
+        let (outflow, inflow) = if record.amount.is_negative() {
+            (amount, String::new())
+        } else {
+            (String::new(), amount)
+        };
+
+        Self {
+            date: record.date.format("%m/%d/%Y").to_string(),
+            payee: record.payee.clone(),
+            memo: record.memo.clone(),
+            outflow,
+            inflow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    #[test]
+    fn test_rows() {
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: "Doopsie, twice".to_string(),
+            amount: Money::from_str("-1.025,8", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        let mut writer = Writer::from_writer(Vec::new());
+        writer.serialize(YnabRow::from(&record)).unwrap();
+        record.amount = Money::from_str("12", EUR).unwrap();
+        writer.serialize(YnabRow::from(&record)).unwrap();
+
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            written,
+            "Date,Payee,Memo,Outflow,Inflow\n\
+            03/07/2024,Woopsie,\"Doopsie, twice\",1025.80,\n\
+            03/07/2024,Woopsie,\"Doopsie, twice\",,12.00\n"
+        );
+    }
+}
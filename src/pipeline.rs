@@ -0,0 +1,222 @@
+//! The stages every conversion runs through.
+//!
+//! Records are decoded and parsed from the input, run through the
+//! transformation stages and finally written. The order of the
+//! transformation stages is configurable and each of them may be disabled.
+
+use std::collections::HashSet;
+
+use miette::{miette, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    enrich::PayeeLookup, homebank::Record, rates::Conversion, repair, rules::Rules, split::Splitter,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// Reading the input in its encoding
+    Decode,
+    /// Turning the input rows into records
+    Parse,
+    /// Fixing broken text encodings in payee and memo
+    Repair,
+    /// Enriching payees through `--payee-lookup-cmd`
+    Lookup,
+    /// Applying categorization rules
+    Rules,
+    /// Splitting records by sub-amounts in their memo
+    Split,
+    /// Converting amounts with `--convert-to`
+    Convert,
+    /// Dropping records with the same fingerprint
+    Dedupe,
+    /// Sorting records by date
+    Sort,
+    /// Writing the outputs
+    Write,
+}
+
+impl Stage {
+    pub const DEFAULT: [Stage; 8] = [
+        Stage::Decode,
+        Stage::Parse,
+        Stage::Repair,
+        Stage::Lookup,
+        Stage::Rules,
+        Stage::Split,
+        Stage::Convert,
+        Stage::Write,
+    ];
+
+    fn is_fixed(&self) -> bool {
+        matches!(self, Stage::Decode | Stage::Parse | Stage::Write)
+    }
+
+    /// Position of the stage relative to the fixed ones.
+    fn rank(&self) -> u8 {
+        match self {
+            Stage::Decode => 0,
+            Stage::Parse => 1,
+            Stage::Write => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// A validated order of stages.
+#[derive(Debug)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+/// Everything the transformation stages need to run.
+#[derive(Default)]
+pub struct Transforms {
+    pub lookup: Option<PayeeLookup>,
+    pub rules: Rules,
+    pub splitter: Splitter,
+    pub conversion: Option<Conversion>,
+    /// Number of records dropped by the dedupe stage
+    pub duplicates: usize,
+}
+
+impl Pipeline {
+    pub fn new(stages: Vec<Stage>) -> Result<Self> {
+        let mut seen = HashSet::new();
+        for stage in &stages {
+            if !seen.insert(stage) {
+                return Err(miette!("Pipeline stage {:?} is listed twice", stage));
+            }
+        }
+
+        // Leaving out the fixed stages is fine, but if they are listed they
+        // have to be where they always run.
+        let misplaced = stages
+            .windows(2)
+            .find(|pair| pair[0].rank() > pair[1].rank())
+            .map(|pair| pair[1]);
+
+        if let Some(stage) = misplaced {
+            return Err(miette!(
+                help = "The order is always decode, parse, <transformations>, write",
+                "Pipeline stage {:?} is out of order",
+                stage
+            ));
+        }
+
+        Ok(Self {
+            stages: stages.into_iter().filter(|s| !s.is_fixed()).collect(),
+        })
+    }
+
+    /// The transformation stages in the order they run.
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// Drops a transformation stage from the pipeline.
+    pub fn disable(&mut self, stage: Stage) {
+        self.stages.retain(|s| *s != stage);
+    }
+
+    pub fn run(&self, mut records: Vec<Record>, t: &mut Transforms) -> Result<Vec<Record>> {
+        for stage in &self.stages {
+            debug!(?stage, count = records.len(), "Running pipeline stage");
+            match stage {
+                Stage::Repair => records.iter_mut().for_each(repair::repair_record),
+                Stage::Lookup => {
+                    if let Some(lookup) = &mut t.lookup {
+                        records.iter_mut().for_each(|r| lookup.enrich(r));
+                    }
+                }
+                Stage::Rules => records.iter_mut().for_each(|r| t.rules.apply(r)),
+                Stage::Split => {
+                    records = records
+                        .into_iter()
+                        .flat_map(|r| t.splitter.split(r))
+                        .collect();
+                }
+                Stage::Convert => {
+                    if let Some(conversion) = &t.conversion {
+                        conversion.run(&mut records)?;
+                    }
+                }
+                Stage::Dedupe => {
+                    let before = records.len();
+                    records = dedupe(records);
+                    t.duplicates += before - records.len();
+                }
+                Stage::Sort => records.sort_by_key(|r| r.date),
+                Stage::Decode | Stage::Parse | Stage::Write => {}
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+fn dedupe(records: Vec<Record>) -> Vec<Record> {
+    let mut seen = HashSet::new();
+    records
+        .into_iter()
+        .filter(|record| {
+            let unique = seen.insert(record.fingerprint());
+            if !unique {
+                warn!(date = %record.date, payee = %record.payee, amount = %record.amount, "Dropping duplicate record");
+            }
+            unique
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    fn record(day: u32) -> Record {
+        Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        use Stage::*;
+
+        assert!(Pipeline::new(Stage::DEFAULT.to_vec()).is_ok());
+        assert!(Pipeline::new(vec![Dedupe, Rules]).is_ok());
+        assert!(Pipeline::new(vec![Decode, Parse, Sort, Write]).is_ok());
+
+        assert!(Pipeline::new(vec![Rules, Rules]).is_err());
+        assert!(Pipeline::new(vec![Parse, Decode, Rules]).is_err());
+        assert!(Pipeline::new(vec![Rules, Parse]).is_err());
+        assert!(Pipeline::new(vec![Write, Rules]).is_err());
+        assert!(Pipeline::new(vec![Write, Decode]).is_err());
+    }
+
+    #[test]
+    fn test_dedupe_then_sort() {
+        let pipeline = Pipeline::new(vec![Stage::Dedupe, Stage::Sort]).unwrap();
+        let mut transforms = Transforms::default();
+
+        let records = pipeline
+            .run(vec![record(8), record(7), record(8)], &mut transforms)
+            .unwrap();
+
+        let days: Vec<_> = records.iter().map(|r| r.date.to_string()).collect();
+        assert_eq!(days, vec!["2024-03-07", "2024-03-08"]);
+        assert_eq!(transforms.duplicates, 1);
+    }
+}
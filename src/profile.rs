@@ -0,0 +1,161 @@
+//! Per account defaults.
+//!
+//! Profiles live in the config file, one per account, and are picked with
+//! `--profile`:
+//!
+//! ```toml
+//! [profiles.visa]
+//! payment = "CreditCard"
+//! category = "Card"
+//! tags = ["visa"]
+//! sign = "inverted"
+//! ```
+//!
+//! They are applied right after parsing, so rules can still override the
+//! category and payment.
+
+use std::collections::BTreeMap;
+
+use miette::{miette, Result};
+use rusty_money::Money;
+use serde::Deserialize;
+
+use crate::homebank::{Payment, Record};
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Payment of records the export does not state one for
+    pub payment: Payment,
+    /// Category of records not having one yet
+    pub category: Option<String>,
+    /// Tags added to every record
+    pub tags: Vec<String>,
+    pub sign: Sign,
+}
+
+/// How the export signs amounts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sign {
+    /// Expenses are negative
+    #[default]
+    Normal,
+    /// Expenses are positive, as in many credit card statements
+    Inverted,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            payment: Payment::ElectronicPayment,
+            category: None,
+            tags: Vec::new(),
+            sign: Sign::Normal,
+        }
+    }
+}
+
+impl Profile {
+    /// Takes the profile `name` out of the configured ones, the defaults are
+    /// used without a name.
+    pub fn select(mut profiles: BTreeMap<String, Profile>, name: Option<&str>) -> Result<Self> {
+        let Some(name) = name else {
+            return Ok(Self::default());
+        };
+
+        profiles.remove(name).ok_or_else(|| {
+            let known: Vec<_> = profiles.keys().map(String::as_str).collect();
+            miette!(
+                help = format!("Configured profiles: {}", known.join(", ")),
+                "Unknown profile '{}'",
+                name
+            )
+        })
+    }
+
+    pub fn apply(&self, record: &mut Record) {
+        if record.payment == Payment::None {
+            record.payment = self.payment;
+        }
+        if let (true, Some(category)) = (record.category.is_empty(), &self.category) {
+            record.category.clone_from(category);
+        }
+        for tag in &self.tags {
+            if !record.tags.contains(tag) {
+                record.tags.push(tag.clone());
+            }
+        }
+        if self.sign == Sign::Inverted {
+            record.map_amounts(|money| Money::from_decimal(-*money.amount(), money.currency()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::homebank::SplitLine;
+
+    #[test]
+    fn test_apply() {
+        let profiles: BTreeMap<String, Profile> = toml::from_str(
+            r#"
+            [visa]
+            payment = "CreditCard"
+            category = "Card"
+            tags = ["visa"]
+            sign = "inverted"
+            "#,
+        )
+        .unwrap();
+        assert!(Profile::select(BTreeMap::new(), Some("visa")).is_err());
+        let profile = Profile::select(profiles, Some("visa")).unwrap();
+
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("25,88", EUR).unwrap(),
+            tags: vec!["visa".to_string()],
+            ..Default::default()
+        };
+        profile.apply(&mut record);
+
+        assert_eq!(record.payment, Payment::CreditCard);
+        assert_eq!(record.category, "Card");
+        assert_eq!(record.tags, vec!["visa".to_string()]);
+        assert_eq!(record.amount, Money::from_str("-25,88", EUR).unwrap());
+    }
+
+    #[test]
+    fn test_apply_splits() {
+        let profile = Profile {
+            sign: Sign::Inverted,
+            ..Profile::default()
+        };
+        let line = |amount, category: &str| SplitLine {
+            amount: Money::from_str(amount, EUR).unwrap(),
+            category: category.to_string(),
+            memo: String::new(),
+        };
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::CreditCard,
+            payee: "Woopsie".to_string(),
+            amount: Money::from_str("25,88", EUR).unwrap(),
+            splits: vec![line("20,00", "Food"), line("5,88", "Drinks")],
+            ..Default::default()
+        };
+        profile.apply(&mut record);
+
+        assert_eq!(record.amount, Money::from_str("-25,88", EUR).unwrap());
+        assert_eq!(
+            record.splits,
+            vec![line("-20,00", "Food"), line("-5,88", "Drinks")]
+        );
+    }
+}
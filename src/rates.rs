@@ -0,0 +1,398 @@
+//! Currency conversion using the ECB euro foreign exchange reference rates.
+//!
+//! Rates are kept in a local cache (`$XDG_CACHE_HOME/hbconv/ecb-rates.csv`)
+//! and only the missing days get fetched. Once cached a rate never changes,
+//! which keeps conversions reproducible. Without network access the cache is
+//! used as is and every record is converted with the rate of the nearest
+//! available date.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::Read,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use chrono::{Days, NaiveDate};
+use csv::{ReaderBuilder, WriterBuilder};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use rust_decimal::Decimal;
+use rusty_money::{
+    iso::{self, Currency},
+    Money,
+};
+use serde::{Deserialize, Serialize};
+
+use tracing::{debug, warn};
+
+use crate::{homebank::Record, logging};
+
+const ECB_URL: &str = "https://data-api.ecb.europa.eu/service/data/EXR/D..EUR.SP00.A";
+
+/// ECB rates, quoted as units of the currency per one euro.
+pub struct RateCache {
+    path: Option<PathBuf>,
+    rates: BTreeMap<NaiveDate, HashMap<String, Decimal>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedRate {
+    date: NaiveDate,
+    currency: String,
+    rate: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+struct EcbObservation {
+    #[serde(rename = "CURRENCY")]
+    currency: String,
+    #[serde(rename = "TIME_PERIOD")]
+    time_period: String,
+    #[serde(rename = "OBS_VALUE")]
+    obs_value: String,
+}
+
+impl RateCache {
+    /// Loads the cache from the user's cache directory.
+    pub fn load() -> Result<Self> {
+        let path = dirs::cache_dir()
+            .ok_or_else(|| miette!("Could not determine a cache directory"))?
+            .join("hbconv")
+            .join("ecb-rates.csv");
+
+        let mut cache = Self {
+            path: Some(path.clone()),
+            rates: BTreeMap::new(),
+        };
+
+        if path.exists() {
+            let file = File::open(&path)
+                .into_diagnostic()
+                .wrap_err("Failed opening exchange rate cache")?;
+            for rate in ReaderBuilder::new().from_reader(file).into_deserialize() {
+                let rate: CachedRate = rate
+                    .into_diagnostic()
+                    .wrap_err("Failed reading exchange rate cache")?;
+                cache.insert(rate.date, rate.currency, rate.rate);
+            }
+        }
+
+        Ok(cache)
+    }
+
+    fn insert(&mut self, date: NaiveDate, currency: String, rate: Decimal) {
+        self.rates.entry(date).or_default().insert(currency, rate);
+    }
+
+    /// Makes sure the cache covers `from..=to`, fetching whatever is missing.
+    /// A failing download is not fatal, the cached rates are used instead.
+    pub fn update(&mut self, from: NaiveDate, to: NaiveDate) -> Result<()> {
+        let mut missing = Vec::new();
+        match (self.rates.keys().next(), self.rates.keys().next_back()) {
+            (Some(&first), Some(&last)) => {
+                if from < first {
+                    missing.push((from, first - Days::new(1)));
+                }
+                if to > last {
+                    missing.push((last + Days::new(1), to));
+                }
+            }
+            _ => missing.push((from, to)),
+        }
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        for (from, to) in missing {
+            if let Err(err) = self.fetch(from, to) {
+                warn!(
+                    error = %logging::chain(&err),
+                    "Failed fetching exchange rates, using cached rates only"
+                );
+                return Ok(());
+            }
+        }
+
+        self.store()
+    }
+
+    fn fetch(&mut self, from: NaiveDate, to: NaiveDate) -> Result<()> {
+        debug!(%from, %to, "Fetching ECB exchange rates");
+        let response = ureq::get(ECB_URL)
+            .query("format", "csvdata")
+            .query("startPeriod", &from.format("%Y-%m-%d").to_string())
+            .query("endPeriod", &to.format("%Y-%m-%d").to_string())
+            .call()
+            .into_diagnostic()
+            .wrap_err("Failed requesting ECB exchange rates")?;
+
+        self.read_ecb(response.into_reader())
+    }
+
+    fn read_ecb<R: Read>(&mut self, rdr: R) -> Result<()> {
+        for observation in ReaderBuilder::new().from_reader(rdr).into_deserialize() {
+            let observation: EcbObservation = observation
+                .into_diagnostic()
+                .wrap_err("Failed parsing ECB exchange rates")?;
+
+            // Days without a fixing come with an empty value
+            if observation.obs_value.is_empty() {
+                continue;
+            }
+
+            let date = NaiveDate::parse_from_str(&observation.time_period, "%Y-%m-%d")
+                .into_diagnostic()
+                .wrap_err("Failed converting ECB date")?;
+            let rate = Decimal::from_str(&observation.obs_value)
+                .into_diagnostic()
+                .wrap_err("Failed converting ECB rate")?;
+            self.insert(date, observation.currency, rate);
+        }
+
+        Ok(())
+    }
+
+    fn store(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .into_diagnostic()
+                .wrap_err("Failed creating cache directory")?;
+        }
+
+        let mut writer = WriterBuilder::new()
+            .from_path(path)
+            .into_diagnostic()
+            .wrap_err("Failed opening exchange rate cache for writing")?;
+        for (date, rates) in &self.rates {
+            let mut rates: Vec<_> = rates.iter().collect();
+            rates.sort();
+            for (currency, rate) in rates {
+                writer
+                    .serialize(CachedRate {
+                        date: *date,
+                        currency: currency.clone(),
+                        rate: *rate,
+                    })
+                    .into_diagnostic()
+                    .wrap_err("Failed writing exchange rate cache")?;
+            }
+        }
+
+        writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("Failed flushing exchange rate cache")
+    }
+
+    /// Rate of `currency` per euro on `date`, or on the closest date having
+    /// one. Earlier dates win ties.
+    fn rate(&self, currency: &str, date: NaiveDate) -> Option<Decimal> {
+        if currency == "EUR" {
+            return Some(Decimal::ONE);
+        }
+
+        let before = self
+            .rates
+            .range(..=date)
+            .rev()
+            .find_map(|(d, rates)| rates.get(currency).map(|r| (*d, *r)));
+        let after = self
+            .rates
+            .range(date..)
+            .find_map(|(d, rates)| rates.get(currency).map(|r| (*d, *r)));
+
+        match (before, after) {
+            (Some((b, rate_b)), Some((a, rate_a))) => {
+                if date - b <= a - date {
+                    Some(rate_b)
+                } else {
+                    Some(rate_a)
+                }
+            }
+            (Some((_, rate)), None) | (None, Some((_, rate))) => Some(rate),
+            (None, None) => None,
+        }
+    }
+
+    /// Converts the amount of `record` into `target`.
+    pub fn convert(&self, record: &mut Record, target: &'static Currency) -> Result<()> {
+        let source = record.amount.currency();
+        if source == target {
+            return Ok(());
+        }
+
+        let from = self
+            .rate(source.iso_alpha_code, record.date)
+            .ok_or_else(|| {
+                miette!(
+                    "No exchange rate for {} around {}",
+                    source.iso_alpha_code,
+                    record.date
+                )
+            })?;
+        let to = self
+            .rate(target.iso_alpha_code, record.date)
+            .ok_or_else(|| {
+                miette!(
+                    "No exchange rate for {} around {}",
+                    target.iso_alpha_code,
+                    record.date
+                )
+            })?;
+
+        record.map_amounts(|money| {
+            let amount = (*money.amount() / from * to).round_dp(target.exponent);
+            Money::from_decimal(amount, target)
+        });
+
+        // Rounded on their own the lines can miss the total by a cent, the
+        // last one takes up the difference so the split still balances
+        let lines: Decimal = record.splits.iter().map(|l| *l.amount.amount()).sum();
+        if let Some(last) = record.splits.last_mut() {
+            let amount = *last.amount.amount() + *record.amount.amount() - lines;
+            last.amount = Money::from_decimal(amount, target);
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts all records into one currency.
+pub struct Conversion {
+    target: &'static Currency,
+    offline: bool,
+}
+
+impl Conversion {
+    pub fn new(target: &str, offline: bool) -> Result<Self> {
+        Ok(Self {
+            target: find_currency(target)?,
+            offline,
+        })
+    }
+
+    pub fn run(&self, records: &mut [Record]) -> Result<()> {
+        let mut cache = RateCache::load()?;
+
+        let from = records.iter().map(|r| r.date).min();
+        let to = records.iter().map(|r| r.date).max();
+        if let (false, Some(from), Some(to)) = (self.offline, from, to) {
+            // Reach back a bit so records after holidays still have a rate
+            cache.update(from - Days::new(7), to)?;
+        }
+
+        for record in records {
+            cache.convert(record, self.target)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Looks up an ISO currency by its code.
+pub fn find_currency(code: &str) -> Result<&'static Currency> {
+    iso::find(&code.to_uppercase()).ok_or_else(|| miette!("Unknown currency '{}'", code))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::{EUR, USD};
+
+    use super::*;
+    use crate::homebank::{Payment, SplitLine};
+
+    fn cache() -> RateCache {
+        let mut cache = RateCache {
+            path: None,
+            rates: BTreeMap::new(),
+        };
+        let ecb = "KEY,FREQ,CURRENCY,CURRENCY_DENOM,EXR_TYPE,EXR_SUFFIX,TIME_PERIOD,OBS_VALUE\n\
+            EXR.D.USD.EUR.SP00.A,D,USD,EUR,SP00,A,2024-03-07,1.0893\n\
+            EXR.D.USD.EUR.SP00.A,D,USD,EUR,SP00,A,2024-03-08,1.0937\n\
+            EXR.D.USD.EUR.SP00.A,D,USD,EUR,SP00,A,2024-03-11,1.0923\n\
+            EXR.D.USD.EUR.SP00.A,D,USD,EUR,SP00,A,2024-03-12,\n";
+        cache.read_ecb(ecb.as_bytes()).unwrap();
+        cache
+    }
+
+    #[test]
+    fn test_nearest_rate() {
+        let cache = cache();
+        let date = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+
+        assert_eq!(cache.rate("USD", date(8)), Decimal::from_str("1.0937").ok());
+        // Saturday is closer to friday, sunday closer to monday
+        assert_eq!(cache.rate("USD", date(9)), Decimal::from_str("1.0937").ok());
+        assert_eq!(
+            cache.rate("USD", date(10)),
+            Decimal::from_str("1.0923").ok()
+        );
+        // Missing values do not count as a rate
+        assert_eq!(
+            cache.rate("USD", date(14)),
+            Decimal::from_str("1.0923").ok()
+        );
+        assert_eq!(cache.rate("JPY", date(8)), None);
+    }
+
+    #[test]
+    fn test_convert() {
+        let cache = cache();
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            amount: Money::from_str("-100,00", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        cache.convert(&mut record, USD).unwrap();
+        assert_eq!(record.amount, Money::from_str("-108.93", USD).unwrap());
+
+        cache.convert(&mut record, EUR).unwrap();
+        assert_eq!(record.amount, Money::from_str("-100,00", EUR).unwrap());
+    }
+
+    #[test]
+    fn test_convert_splits() {
+        let cache = cache();
+        let line = |amount: &str, currency, category: &str| SplitLine {
+            amount: Money::from_str(amount, currency).unwrap(),
+            category: category.to_string(),
+            memo: String::new(),
+        };
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            amount: Money::from_str("-100,00", EUR).unwrap(),
+            splits: vec![line("-60,00", EUR, "Food"), line("-40,00", EUR, "Drinks")],
+            ..Default::default()
+        };
+
+        cache.convert(&mut record, USD).unwrap();
+        assert_eq!(record.amount, Money::from_str("-108.93", USD).unwrap());
+        assert_eq!(
+            record.splits,
+            vec![line("-65.36", USD, "Food"), line("-43.57", USD, "Drinks")]
+        );
+
+        // 0.054465 rounds down twice, the total of 0.10893 up
+        let mut record = Record {
+            amount: Money::from_str("-0,10", EUR).unwrap(),
+            splits: vec![line("-0,05", EUR, "Food"), line("-0,05", EUR, "Drinks")],
+            ..record
+        };
+        cache.convert(&mut record, USD).unwrap();
+        assert_eq!(record.amount, Money::from_str("-0.11", USD).unwrap());
+        assert_eq!(
+            record.splits,
+            vec![line("-0.05", USD, "Food"), line("-0.06", USD, "Drinks")]
+        );
+    }
+}
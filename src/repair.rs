@@ -0,0 +1,82 @@
+//! Repair of text that went through the wrong encoding somewhere.
+//!
+//! Banks regularly produce exports where UTF-8 text was decoded as
+//! Windows-1252 ("Ã¤" instead of "ä") or as EUC-KR ("w채hrung" instead of
+//! "währung"), sometimes more than once. Such text is detected by
+//! re-encoding it with the wrong encoding: if the resulting bytes are valid
+//! UTF-8 and differ from the input, that's almost certainly what was meant.
+//! Correct text with umlauts never survives that round-trip, as a single
+//! Windows-1252 umlaut byte is not valid UTF-8.
+
+use encoding_rs::{Encoding, EUC_KR, WINDOWS_1252};
+use tracing::trace;
+
+use crate::homebank::Record;
+
+/// Encodings correct UTF-8 text is commonly misread as.
+const MISREAD_AS: [&Encoding; 2] = [WINDOWS_1252, EUC_KR];
+
+/// How often text may have been misread before we give up.
+const MAX_ROUNDS: usize = 3;
+
+/// Repairs payee and memo of a record in place.
+pub fn repair_record(record: &mut Record) {
+    if let Some(payee) = repair(&record.payee) {
+        trace!(from = %record.payee, to = %payee, "Repaired payee encoding");
+        record.payee = payee;
+    }
+    if let Some(memo) = repair(&record.memo) {
+        trace!(from = %record.memo, to = %memo, "Repaired memo encoding");
+        record.memo = memo;
+    }
+}
+
+/// Returns the repaired text, or `None` if nothing looked broken.
+pub fn repair(text: &str) -> Option<String> {
+    let mut repaired: Option<String> = None;
+
+    for _ in 0..MAX_ROUNDS {
+        let current = repaired.as_deref().unwrap_or(text);
+        match undo_misread(current) {
+            Some(fixed) => repaired = Some(fixed),
+            None => break,
+        }
+    }
+
+    repaired
+}
+
+fn undo_misread(text: &str) -> Option<String> {
+    if text.is_ascii() {
+        return None;
+    }
+
+    MISREAD_AS.iter().find_map(|encoding| {
+        let (bytes, _, unmappable) = encoding.encode(text);
+        if unmappable {
+            return None;
+        }
+
+        match std::str::from_utf8(&bytes) {
+            Ok(fixed) if fixed != text => Some(fixed.to_string()),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_repair() {
+        assert_eq!(repair("MÃ¼ller GmbH").as_deref(), Some("Müller GmbH"));
+        assert_eq!(repair("w채hrung").as_deref(), Some("währung"));
+        // Misread twice
+        assert_eq!(repair("MÃƒÂ¼ller").as_deref(), Some("Müller"));
+        assert_eq!(repair("Bäckerei Müller für Brötchen"), None);
+        assert_eq!(repair("Rewe Markt"), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Machine readable report about a single conversion run.
+//!
+//! The report is meant as an audit trail: it pins down exactly which files
+//! went in, with which options they were converted and what came out.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use miette::{Context, IntoDiagnostic, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::pipeline::Stage;
+
+#[derive(Debug, Serialize)]
+pub struct RunReport<'a, O: Serialize> {
+    pub version: &'static str,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub options: &'a O,
+    /// Config and rule files in effect
+    pub config_files: Vec<HashedFile>,
+    pub stages: Vec<Stage>,
+    pub inputs: Vec<HashedFile>,
+    /// Detected formats, with the layout for those reading several
+    pub format: String,
+    pub counts: Counts,
+    pub skipped: Vec<Skipped>,
+    pub outputs: Vec<HashedFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HashedFile {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Counts {
+    pub read: usize,
+    pub skipped: usize,
+    pub duplicates: usize,
+    pub written: usize,
+}
+
+/// An input row that could not be converted.
+#[derive(Debug, Serialize)]
+pub struct Skipped {
+    /// Position of the row among all rows the input parser produced
+    pub index: usize,
+    pub error: String,
+}
+
+impl<'a, O: Serialize> RunReport<'a, O> {
+    pub fn new(options: &'a O, format: String) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            started_at: Utc::now(),
+            finished_at: None,
+            options,
+            config_files: Vec::new(),
+            stages: Vec::new(),
+            inputs: Vec::new(),
+            format,
+            counts: Counts::default(),
+            skipped: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn write(mut self, path: &Path) -> Result<()> {
+        self.finished_at = Some(Utc::now());
+
+        let mut file = File::create(path)
+            .into_diagnostic()
+            .wrap_err("Failed creating report file")?;
+        serde_json::to_writer_pretty(&mut file, &self)
+            .into_diagnostic()
+            .wrap_err("Failed writing report")?;
+        writeln!(file).into_diagnostic()?;
+
+        Ok(())
+    }
+}
+
+impl HashedFile {
+    /// The hash of `content`, already read from `path`.
+    pub fn of(path: &Path, content: &[u8]) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            sha256: format!("{:x}", Sha256::digest(content)),
+        }
+    }
+
+    pub fn new(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed opening {} for hashing", path.display()))?;
+
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed hashing {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            sha256: format!("{:x}", hasher.finalize()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_hash_file() {
+        let path = std::env::temp_dir().join(format!("hbconv-report-{}", std::process::id()));
+        fs::write(&path, b"abc").unwrap();
+
+        let hashed = HashedFile::new(&path).unwrap();
+        assert_eq!(
+            hashed.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,275 @@
+//! Interactive review of converted records.
+//!
+//! Every record is shown on the terminal and the user can set its category
+//! and tags before it gets written. Each decision is appended to a session
+//! file right away, so an aborted review can be picked up again with
+//! `--resume` instead of starting over.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{homebank::Record, inputs::url};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Decision {
+    key: String,
+    category: String,
+    tags: Vec<String>,
+}
+
+pub struct Review {
+    session: PathBuf,
+    decisions: HashMap<String, Decision>,
+}
+
+/// Result of a review run.
+pub enum Outcome {
+    /// Every record has been reviewed.
+    Done(Vec<Record>),
+    /// The user quit before reviewing every record.
+    Aborted,
+}
+
+impl Review {
+    /// Default location of the session file for a given input.
+    pub fn session_path(input: &Path) -> PathBuf {
+        // Downloaded inputs keep their session in the working directory
+        let input = match url::url(input) {
+            Some(_) => Path::new(input.file_name().unwrap_or_default()),
+            None => input,
+        };
+        let mut name = input.as_os_str().to_owned();
+        name.push(".review.jsonl");
+        PathBuf::from(name)
+    }
+
+    pub fn open(session: PathBuf, resume: bool) -> Result<Self> {
+        let mut decisions = HashMap::new();
+
+        if resume {
+            let file = File::open(&session)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed opening review session {}", session.display()))?;
+
+            for line in BufReader::new(file).lines() {
+                let line = line
+                    .into_diagnostic()
+                    .wrap_err("Failed reading review session")?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let decision: Decision = serde_json::from_str(&line)
+                    .into_diagnostic()
+                    .wrap_err("Failed parsing review session")?;
+                decisions.insert(decision.key.clone(), decision);
+            }
+        } else if session.exists() {
+            return Err(miette!(
+                help = "Pass --resume to continue it or delete the file to start over",
+                "Found an unfinished review session at {}",
+                session.display()
+            ));
+        }
+
+        Ok(Self { session, decisions })
+    }
+
+    /// Walks through all records, prompting on `output` and reading answers
+    /// from `input`. Records decided in an earlier session are not asked again.
+    pub fn run<I: BufRead, O: Write>(
+        mut self,
+        records: Vec<Record>,
+        mut input: I,
+        mut output: O,
+    ) -> Result<Outcome> {
+        let mut session = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.session)
+            .into_diagnostic()
+            .wrap_err("Failed opening review session for writing")?;
+
+        let total = records.len();
+        let mut reviewed = Vec::with_capacity(total);
+
+        let mut seen = HashMap::new();
+        for (idx, mut record) in records.into_iter().enumerate() {
+            let key = record.occurrence_id(&mut seen);
+
+            if let Some(decision) = self.decisions.get(&key) {
+                decision.apply(&mut record);
+                reviewed.push(record);
+                continue;
+            }
+
+            writeln!(
+                output,
+                "[{}/{}] {}  {}  {}\n        {}",
+                idx + 1,
+                total,
+                record.date,
+                record.amount,
+                record.payee,
+                record.memo
+            )
+            .into_diagnostic()?;
+            write!(
+                output,
+                "category | tags [{} | {}] (enter keeps, q quits): ",
+                record.category,
+                record.tags.join(" ")
+            )
+            .into_diagnostic()?;
+            output.flush().into_diagnostic()?;
+
+            let mut answer = String::new();
+            let read = input
+                .read_line(&mut answer)
+                .into_diagnostic()
+                .wrap_err("Failed reading answer")?;
+            let answer = answer.trim();
+
+            if read == 0 || answer == "q" {
+                writeln!(
+                    output,
+                    "\nReview aborted, {} decisions saved to {}. Rerun with --resume to continue.",
+                    self.decisions.len(),
+                    self.session.display()
+                )
+                .into_diagnostic()?;
+                return Ok(Outcome::Aborted);
+            }
+
+            let decision = Decision::parse(key, answer, &record);
+            decision.apply(&mut record);
+
+            let line = serde_json::to_string(&decision).into_diagnostic()?;
+            writeln!(session, "{}", line)
+                .into_diagnostic()
+                .wrap_err("Failed writing review session")?;
+            session.flush().into_diagnostic()?;
+
+            self.decisions.insert(decision.key.clone(), decision);
+            reviewed.push(record);
+        }
+
+        drop(session);
+        fs::remove_file(&self.session)
+            .into_diagnostic()
+            .wrap_err("Failed removing finished review session")?;
+
+        Ok(Outcome::Done(reviewed))
+    }
+}
+
+impl Decision {
+    /// Parses an answer of the form `category | tag1 tag2`. Either part may be
+    /// left out to keep the current value.
+    fn parse(key: String, answer: &str, record: &Record) -> Self {
+        let (category, tags) = match answer.split_once('|') {
+            Some((category, tags)) => (category.trim(), Some(tags)),
+            None => (answer, None),
+        };
+
+        let category = if category.is_empty() {
+            record.category.clone()
+        } else {
+            category.to_string()
+        };
+        let tags = match tags {
+            Some(tags) => tags.split_whitespace().map(str::to_string).collect(),
+            None => record.tags.clone(),
+        };
+
+        Self {
+            key,
+            category,
+            tags,
+        }
+    }
+
+    fn apply(&self, record: &mut Record) {
+        record.category.clone_from(&self.category);
+        record.tags.clone_from(&self.tags);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+    use crate::homebank::Payment;
+
+    fn record(memo: &str) -> Record {
+        Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "Woopsie".to_string(),
+            memo: memo.to_string(),
+            amount: Money::from_str("-25,88", EUR).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resume_after_abort() {
+        let session =
+            std::env::temp_dir().join(format!("hbconv-review-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&session);
+        let records = vec![record("one"), record("two")];
+
+        let review = Review::open(session.clone(), false).unwrap();
+        let outcome = review
+            .run(
+                records.clone(),
+                &b"Food:Groceries | weekly\nq\n"[..],
+                Vec::new(),
+            )
+            .unwrap();
+        assert!(matches!(outcome, Outcome::Aborted));
+        assert!(Review::open(session.clone(), false).is_err());
+
+        let review = Review::open(session.clone(), true).unwrap();
+        let outcome = review.run(records, &b"Leisure\n"[..], Vec::new()).unwrap();
+        let Outcome::Done(reviewed) = outcome else {
+            panic!("Review did not finish");
+        };
+
+        assert_eq!(reviewed[0].category, "Food:Groceries");
+        assert_eq!(reviewed[0].tags, vec!["weekly".to_string()]);
+        assert_eq!(reviewed[1].category, "Leisure");
+        assert!(!session.exists());
+    }
+
+    #[test]
+    fn test_identical_records() {
+        let session =
+            std::env::temp_dir().join(format!("hbconv-review-twins-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&session);
+
+        let review = Review::open(session.clone(), false).unwrap();
+        let outcome = review
+            .run(
+                vec![record("one"), record("one")],
+                &b"Food\nLeisure\n"[..],
+                Vec::new(),
+            )
+            .unwrap();
+        let Outcome::Done(reviewed) = outcome else {
+            panic!("Review did not finish");
+        };
+
+        assert_eq!(reviewed[0].category, "Food");
+        assert_eq!(reviewed[1].category, "Leisure");
+    }
+}
@@ -0,0 +1,186 @@
+//! Rules assigning categories, tags and cleaned up payees to records.
+//!
+//! A rule matches if all of its given patterns match. Every matching rule
+//! is applied in order, so later rules override the category, payee and
+//! payment set by earlier ones, while tags accumulate.
+//!
+//! ```toml
+//! [[rules]]
+//! payee = "(?i)rewe|edeka"
+//! category = "Food:Groceries"
+//! tags = ["groceries"]
+//! ```
+
+use std::path::Path;
+
+use miette::{Context, IntoDiagnostic, Result};
+use regex::Regex;
+use serde::Deserialize;
+use tracing::trace;
+
+use crate::{
+    config,
+    homebank::{Payment, Record},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    // patterns
+    pub payee: Option<String>,
+    pub memo: Option<String>,
+    pub info: Option<String>,
+    pub iban: Option<String>,
+    // actions
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub set_payee: Option<String>,
+    pub payment: Option<Payment>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    rules: Vec<Rule>,
+}
+
+struct CompiledRule {
+    payee: Option<Regex>,
+    memo: Option<Regex>,
+    info: Option<Regex>,
+    iban: Option<Regex>,
+    rule: Rule,
+}
+
+#[derive(Default)]
+pub struct Rules {
+    rules: Vec<CompiledRule>,
+}
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>) -> Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(CompiledRule::new)
+            .collect::<Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Appends the rules of a separate rule file.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let file: RuleFile = config::read_toml(path)?;
+        for rule in file.rules {
+            self.rules.push(
+                CompiledRule::new(rule)
+                    .wrap_err_with(|| format!("Invalid rule in {}", path.display()))?,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, record: &mut Record) {
+        for rule in &self.rules {
+            if rule.matches(record) {
+                trace!(payee = %record.payee, ?rule.rule, "Rule matched");
+                rule.apply(record);
+            }
+        }
+    }
+}
+
+impl CompiledRule {
+    fn new(rule: Rule) -> Result<Self> {
+        let compile = |pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .into_diagnostic()
+                .wrap_err("Invalid rule pattern")
+        };
+
+        Ok(Self {
+            payee: compile(&rule.payee)?,
+            memo: compile(&rule.memo)?,
+            info: compile(&rule.info)?,
+            iban: compile(&rule.iban)?,
+            rule,
+        })
+    }
+
+    fn matches(&self, record: &Record) -> bool {
+        [
+            (&self.payee, &record.payee),
+            (&self.memo, &record.memo),
+            (&self.info, &record.info),
+            (&self.iban, &record.iban),
+        ]
+        .iter()
+        .all(|(pattern, field)| pattern.as_ref().is_none_or(|p| p.is_match(field)))
+    }
+
+    fn apply(&self, record: &mut Record) {
+        if let Some(category) = &self.rule.category {
+            record.category.clone_from(category);
+        }
+        if let Some(payee) = &self.rule.set_payee {
+            record.payee.clone_from(payee);
+        }
+        if let Some(payment) = self.rule.payment {
+            record.payment = payment;
+        }
+        for tag in &self.rule.tags {
+            if !record.tags.contains(tag) {
+                record.tags.push(tag.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::{iso::EUR, Money};
+
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let rules: RuleFile = toml::from_str(
+            r#"
+            [[rules]]
+            payee = "(?i)rewe"
+            category = "Food:Groceries"
+            tags = ["groceries"]
+
+            [[rules]]
+            payee = "(?i)rewe"
+            memo = "Pfand"
+            category = "Food:Deposit"
+            tags = ["groceries", "deposit"]
+
+            [[rules]]
+            payee = "Edeka"
+            category = "Food:Other"
+            "#,
+        )
+        .unwrap();
+        let rules = Rules::new(rules.rules).unwrap();
+
+        let mut record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::ElectronicPayment,
+            payee: "REWE Markt GmbH".to_string(),
+            memo: "Pfandrueckgabe".to_string(),
+            amount: Money::from_str("1,25", EUR).unwrap(),
+            ..Default::default()
+        };
+        rules.apply(&mut record);
+
+        assert_eq!(record.category, "Food:Deposit");
+        assert_eq!(record.tags, vec!["groceries", "deposit"]);
+    }
+}
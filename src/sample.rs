@@ -0,0 +1,225 @@
+//! Synthetic exports of every format, for trying rules files, outputs and
+//! HomeBank import settings without real data.
+//!
+//! The transactions are made up from a handful of everyday payees, the
+//! writing of each format lives beside its reading in `inputs`.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use chrono::{Days, NaiveDate};
+use fastrand::Rng;
+use miette::{Context, IntoDiagnostic, Result};
+use rust_decimal::Decimal;
+
+use crate::{anonymize::mod97, inputs::Format};
+
+/// Options for generating a sample export.
+#[derive(Debug, clap::Args)]
+pub struct Sample {
+    #[arg(short, long, value_enum)]
+    pub format: Format,
+    /// Number of transactions
+    #[arg(long, default_value_t = 20)]
+    pub rows: usize,
+    /// Day of the first transaction
+    #[arg(long, default_value = "2024-01-02")]
+    pub from: NaiveDate,
+    /// Seed of the transactions, the same seed gives the same sample
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// File the sample is written to [default: stdout]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl Sample {
+    pub fn run(&self) -> Result<()> {
+        let mut rng = self.seed.map_or_else(Rng::new, Rng::with_seed);
+        let sample = self.format.sample(&rows(&mut rng, self.rows, self.from))?;
+
+        match &self.output {
+            Some(path) => fs::write(path, sample)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed writing {}", path.display())),
+            None => io::stdout()
+                .write_all(&sample)
+                .into_diagnostic()
+                .wrap_err("Failed writing sample"),
+        }
+    }
+}
+
+/// Kinds of transactions, with their German booking text and GVC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    DirectDebit,
+    Card,
+    StandingOrder,
+    Transfer,
+    Salary,
+    Cash,
+}
+
+impl Kind {
+    pub fn booking_text(&self) -> &'static str {
+        match self {
+            Kind::DirectDebit => "SEPA Lastschrift",
+            Kind::Card => "Kartenzahlung",
+            Kind::StandingOrder => "Dauerauftrag",
+            Kind::Transfer => "SEPA Überweisung",
+            Kind::Salary => "Gehalt/Rente",
+            Kind::Cash => "Bargeldauszahlung",
+        }
+    }
+
+    pub fn gvc(&self) -> &'static str {
+        match self {
+            Kind::DirectDebit => "105",
+            Kind::Card => "106",
+            Kind::StandingOrder => "152",
+            Kind::Transfer => "116",
+            Kind::Salary => "153",
+            Kind::Cash => "083",
+        }
+    }
+}
+
+/// A made up transaction.
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub date: NaiveDate,
+    /// Amount with two fractional digits, negative leaving the account
+    pub amount: Decimal,
+    pub kind: Kind,
+    pub payee: String,
+    /// Empty for cash
+    pub iban: String,
+    pub purpose: String,
+    pub reference: String,
+}
+
+impl Row {
+    /// The amount as written by German banks, as in `-25,88`.
+    pub fn amount_de(&self) -> String {
+        self.amount.to_string().replace('.', ",")
+    }
+}
+
+/// Payee, kind, purpose and range of the amount in cents.
+const PAYEES: &[(&str, Kind, &str, i64, i64)] = &[
+    (
+        "Stadtwerke Musterstadt",
+        Kind::DirectDebit,
+        "Abschlag Strom",
+        -9000,
+        -6000,
+    ),
+    ("REWE Markt GmbH", Kind::Card, "Einkauf", -12000, -800),
+    ("Tankstelle Nord", Kind::Card, "Tanken", -9000, -3000),
+    (
+        "Hausverwaltung Beispiel",
+        Kind::StandingOrder,
+        "Miete",
+        -95000,
+        -95000,
+    ),
+    (
+        "Erika Mustermann",
+        Kind::Transfer,
+        "Geschenk",
+        -10000,
+        -2000,
+    ),
+    (
+        "Arbeitgeber GmbH",
+        Kind::Salary,
+        "Lohn/Gehalt",
+        250000,
+        350000,
+    ),
+    ("Geldautomat", Kind::Cash, "Auszahlung", -20000, -5000),
+    ("Streaming Dienst AG", Kind::DirectDebit, "Abo", -1799, -999),
+];
+
+/// `count` transactions one to three days apart, starting on `from`.
+pub fn rows(rng: &mut Rng, count: usize, from: NaiveDate) -> Vec<Row> {
+    let ibans: Vec<String> = PAYEES.iter().map(|_| iban(rng)).collect();
+
+    let mut date = from;
+    (0..count)
+        .map(|_| {
+            let idx = rng.usize(0..PAYEES.len());
+            let (payee, kind, purpose, low, high) = PAYEES[idx];
+            let row = Row {
+                date,
+                amount: Decimal::new(rng.i64(low..=high), 2),
+                kind,
+                payee: payee.to_string(),
+                iban: match kind {
+                    Kind::Cash => String::new(),
+                    _ => ibans[idx].clone(),
+                },
+                purpose: purpose.to_string(),
+                reference: (0..10).map(|_| rng.char('0'..='9')).collect(),
+            };
+            date = date
+                .checked_add_days(Days::new(rng.u64(1..=3)))
+                .unwrap_or(date);
+            row
+        })
+        .collect()
+}
+
+/// A German IBAN with a valid checksum.
+fn iban(rng: &mut Rng) -> String {
+    let bban: String = (0..18).map(|_| rng.char('0'..='9')).collect();
+    let check = 98 - mod97(&format!("{}DE00", bban));
+    format!("DE{:02}{}", check, bban)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use clap::ValueEnum;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_samples() {
+        let mut rng = Rng::with_seed(7);
+        let rows = rows(&mut rng, 12, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(
+            mod97(&format!("{}{}", &rows[0].iban[4..], &rows[0].iban[..4])),
+            1
+        );
+
+        for format in Format::value_variants() {
+            let Ok(sample) = format.sample(&rows) else {
+                continue;
+            };
+            assert_eq!(
+                Format::detect(&sample).map(|f| f.name()),
+                Some(format.name())
+            );
+
+            let records = format
+                .read(Box::new(Cursor::new(sample)))
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(records.len(), rows.len(), "{}", format.name());
+            assert_eq!(records[0].date, rows[0].date, "{}", format.name());
+            assert_eq!(
+                *records[0].amount.amount(),
+                rows[0].amount,
+                "{}",
+                format.name()
+            );
+        }
+    }
+}
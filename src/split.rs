@@ -0,0 +1,349 @@
+//! Splitting records by sub-amounts mentioned in their memo.
+//!
+//! Loan and annuity bookings often itemize their parts in the memo, like
+//! "davon Zinsen 1,23 EUR, Tilgung 400,00 EUR". Split rules pick those
+//! amounts out and turn each into a record of its own, with the sign of the
+//! original booking. Whatever is left over stays with the original record,
+//! which is dropped if nothing is left.
+//!
+//! With `inline = true` the record stays a single split transaction
+//! instead, with one category line per part and one for the remainder.
+//!
+//! ```toml
+//! [[splits]]
+//! memo = "(?i)darlehen"
+//!
+//! [[splits.parts]]
+//! pattern = "Zinsen (?<amount>[0-9.]+,[0-9]{2}) EUR"
+//! category = "Loan:Interest"
+//!
+//! [[splits.parts]]
+//! pattern = "Tilgung (?<amount>[0-9.]+,[0-9]{2}) EUR"
+//! category = "Loan:Repayment"
+//! ```
+
+use miette::{miette, Context, IntoDiagnostic, Result};
+use regex::Regex;
+use rust_decimal::Decimal;
+use rusty_money::Money;
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::homebank::{Record, SplitLine};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SplitRule {
+    /// Only records whose memo matches are split
+    pub memo: Option<String>,
+    pub parts: Vec<SplitPart>,
+    /// Keep one record with a category line per part
+    #[serde(default)]
+    pub inline: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SplitPart {
+    /// Pattern with a named `amount` group
+    pub pattern: String,
+    pub category: String,
+    /// Memo of the split off record, the original memo if unset
+    pub memo: Option<String>,
+}
+
+struct CompiledRule {
+    memo: Option<Regex>,
+    parts: Vec<(Regex, SplitPart)>,
+    inline: bool,
+}
+
+#[derive(Default)]
+pub struct Splitter {
+    rules: Vec<CompiledRule>,
+}
+
+impl Splitter {
+    pub fn new(rules: Vec<SplitRule>) -> Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let memo = rule
+                    .memo
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .into_diagnostic()
+                    .wrap_err("Invalid split memo pattern")?;
+                let parts = rule
+                    .parts
+                    .into_iter()
+                    .map(|part| {
+                        let pattern = Regex::new(&part.pattern)
+                            .into_diagnostic()
+                            .wrap_err("Invalid split pattern")?;
+                        if pattern.capture_names().all(|n| n != Some("amount")) {
+                            return Err(miette!(
+                                "Split pattern '{}' has no `amount` group",
+                                part.pattern
+                            ));
+                        }
+                        Ok((pattern, part))
+                    })
+                    .collect::<Result<_>>()?;
+
+                Ok(CompiledRule {
+                    memo,
+                    parts,
+                    inline: rule.inline,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Splits a record by the first rule that matches its memo.
+    pub fn split(&self, record: Record) -> Vec<Record> {
+        let rule = self
+            .rules
+            .iter()
+            .find(|r| r.memo.as_ref().is_none_or(|m| m.is_match(&record.memo)));
+
+        match rule {
+            Some(rule) => match rule.split(&record) {
+                Ok(records) => records,
+                Err(err) => {
+                    warn!(memo = %record.memo, "{:?}", err);
+                    vec![record]
+                }
+            },
+            None => vec![record],
+        }
+    }
+}
+
+/// The split `lines` a record already has, less the `parts` split off by a
+/// rule. The parts come out of the lines without a category first, like the
+/// payment itself beside a fee, then out of the others in order.
+fn carve(lines: &[SplitLine], parts: Decimal) -> Vec<SplitLine> {
+    let mut lines = lines.to_vec();
+    let mut left = parts;
+    let mut order: Vec<_> = (0..lines.len()).collect();
+    order.sort_by_key(|i| !lines[*i].category.is_empty());
+    for i in order {
+        let amount = *lines[i].amount.amount();
+        if left == Decimal::ZERO || amount.is_sign_negative() != parts.is_sign_negative() {
+            continue;
+        }
+        let taken = match parts.is_sign_negative() {
+            true => amount.max(left),
+            false => amount.min(left),
+        };
+        lines[i].amount = Money::from_decimal(amount - taken, lines[i].amount.currency());
+        left -= taken;
+    }
+    lines.retain(|l| *l.amount.amount() != Decimal::ZERO);
+    lines
+}
+
+impl CompiledRule {
+    fn split(&self, record: &Record) -> Result<Vec<Record>> {
+        let currency = record.amount.currency();
+        let negative = record.amount.is_negative();
+
+        let mut records = Vec::new();
+        let mut rest = *record.amount.amount();
+
+        for (pattern, part) in &self.parts {
+            for captures in pattern.captures_iter(&record.memo) {
+                let text = &captures["amount"];
+                let amount = *Money::from_str(text, currency)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Failed parsing split amount '{}'", text))?
+                    .amount();
+                let amount = if negative { -amount } else { amount };
+                rest -= amount;
+
+                let mut split = record.clone();
+                split.amount = Money::from_decimal(amount, currency);
+                split.splits.clear();
+                split.category.clone_from(&part.category);
+                if let Some(memo) = &part.memo {
+                    split.memo.clone_from(memo);
+                }
+                records.push(split);
+            }
+        }
+
+        if records.is_empty() {
+            return Ok(vec![record.clone()]);
+        }
+        if rest.is_sign_negative() != negative && rest != Decimal::ZERO {
+            return Err(miette!("Split amounts exceed the booked amount"));
+        }
+
+        debug!(memo = %record.memo, parts = records.len(), %rest, "Split record");
+        if self.inline {
+            let mut lines: Vec<_> = records
+                .into_iter()
+                .map(|r| SplitLine {
+                    amount: r.amount,
+                    category: r.category,
+                    memo: r.memo,
+                })
+                .collect();
+            match record.splits.is_empty() {
+                true if rest != Decimal::ZERO => lines.push(SplitLine {
+                    amount: Money::from_decimal(rest, currency),
+                    category: record.category.clone(),
+                    memo: record.memo.clone(),
+                }),
+                true => {}
+                false => lines.extend(carve(&record.splits, *record.amount.amount() - rest)),
+            }
+            let mut split = record.clone();
+            split.splits = lines;
+            return Ok(vec![split]);
+        }
+        if rest != Decimal::ZERO {
+            let mut remainder = record.clone();
+            remainder.amount = Money::from_decimal(rest, currency);
+            // Lines the record came with, like a fee, stay with what is left
+            remainder.splits = carve(&record.splits, *record.amount.amount() - rest);
+            records.push(remainder);
+        }
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use rusty_money::iso::EUR;
+
+    use super::*;
+    use crate::{config::Config, homebank::Payment};
+
+    #[test]
+    fn test_split() {
+        let config: Config = toml::from_str(
+            r#"
+            [[splits]]
+            memo = "(?i)darlehen"
+
+            [[splits.parts]]
+            pattern = "Zinsen (?<amount>[0-9.]+,[0-9]{2}) EUR"
+            category = "Loan:Interest"
+
+            [[splits.parts]]
+            pattern = "Tilgung (?<amount>[0-9.]+,[0-9]{2}) EUR"
+            category = "Loan:Repayment"
+            "#,
+        )
+        .unwrap();
+        let splitter = Splitter::new(config.splits.clone()).unwrap();
+
+        let record = Record {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            payment: Payment::DirectDebit,
+            payee: "Bank".to_string(),
+            memo: "Darlehen 123 davon Zinsen 1,23 EUR, Tilgung 400,00 EUR".to_string(),
+            amount: Money::from_str("-401,23", EUR).unwrap(),
+            ..Default::default()
+        };
+
+        let records = splitter.split(record.clone());
+        let parts: Vec<_> = records
+            .iter()
+            .map(|r| (r.category.as_str(), r.amount.to_string()))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("Loan:Interest", "-€1,23".to_string()),
+                ("Loan:Repayment", "-€400,00".to_string())
+            ]
+        );
+
+        let mut other = record.clone();
+        other.memo = "Miete".to_string();
+        assert_eq!(splitter.split(other).len(), 1);
+
+        let inline = Splitter::new(vec![SplitRule {
+            inline: true,
+            ..config.splits[0].clone()
+        }])
+        .unwrap();
+        let records = inline.split(Record {
+            amount: Money::from_str("-402,23", EUR).unwrap(),
+            ..record
+        });
+        assert_eq!(records.len(), 1);
+        let lines: Vec<_> = records[0]
+            .splits
+            .iter()
+            .map(|l| (l.category.as_str(), l.amount.to_string()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("Loan:Interest", "-€1,23".to_string()),
+                ("Loan:Repayment", "-€400,00".to_string()),
+                ("", "-€1,00".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_with_lines() {
+        let rule = SplitRule {
+            memo: Some("(?i)darlehen".to_string()),
+            inline: false,
+            parts: vec![SplitPart {
+                pattern: "Zinsen (?<amount>[0-9.]+,[0-9]{2}) EUR".to_string(),
+                category: "Loan:Interest".to_string(),
+                memo: Some("Zinsen".to_string()),
+            }],
+        };
+        let line = |amount, category: &str, memo: &str| SplitLine {
+            amount: Money::from_str(amount, EUR).unwrap(),
+            category: category.to_string(),
+            memo: memo.to_string(),
+        };
+        let record = Record {
+            memo: "Darlehen 123 davon Zinsen 1,23 EUR".to_string(),
+            amount: Money::from_str("-402,23", EUR).unwrap(),
+            splits: vec![line("-401,23", "", ""), line("-1,00", "Fees:Bank", "Fee")],
+            ..Default::default()
+        };
+
+        let records = Splitter::new(vec![rule.clone()])
+            .unwrap()
+            .split(record.clone());
+        assert_eq!(records.len(), 2);
+        assert!(records[0].splits.is_empty());
+        assert_eq!(records[1].amount, Money::from_str("-401,00", EUR).unwrap());
+        assert_eq!(
+            records[1].splits,
+            vec![line("-400,00", "", ""), line("-1,00", "Fees:Bank", "Fee")]
+        );
+
+        let inline = Splitter::new(vec![SplitRule {
+            inline: true,
+            ..rule
+        }])
+        .unwrap();
+        let records = inline.split(record);
+        assert_eq!(
+            records[0].splits,
+            vec![
+                line("-1,23", "Loan:Interest", "Zinsen"),
+                line("-400,00", "", ""),
+                line("-1,00", "Fees:Bank", "Fee")
+            ]
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Watching a directory and converting new bank exports as they arrive.
+//!
+//! The watch file lists which exports to pick up, by file name or by the
+//! detected format, and how to convert them:
+//!
+//! ```toml
+//! # Converted exports are moved here
+//! move_to = "imported"
+//! # Outputs of matches without their own
+//! output = ["homebank.csv"]
+//!
+//! [[match]]
+//! name = "Umsaetze_*.csv"
+//! profile = "giro"
+//!
+//! [[match]]
+//! format = "camt"
+//! profile = "tagesgeld"
+//! output = ["tagesgeld.ledger"]
+//! ```
+//!
+//! Relative paths are relative to the watch file. Outputs are appended to,
+//! so every export adds its records to the same files. An export is only
+//! picked up once its size stopped changing, browsers write downloads in
+//! several steps. Failed exports stay where they are and are tried again
+//! once they change.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use glob::Pattern;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Deserialize;
+use tracing::{debug, error, info};
+
+use crate::{
+    config,
+    convert::{self, Args},
+    inputs::{self, Format, InputOptions},
+};
+
+/// Options for watching a directory.
+#[derive(Debug, clap::Args)]
+#[command(mut_arg("input", |a| a.value_name("DIR").help("Directory to watch for new exports")))]
+#[command(mut_arg("output", |a| a.required(false)))]
+// `--rules` is the watch file here
+#[command(mut_arg("rules", |a| a.long("convert-rules")))]
+pub struct Watch {
+    /// Watch file matching exports to profiles and outputs
+    #[arg(long = "rules", env = "HBCONV_WATCH")]
+    pub watch_file: PathBuf,
+    /// Seconds between looking for new exports
+    #[arg(long, default_value_t = 10)]
+    pub interval: u64,
+    /// Convert the exports already there and exit
+    #[arg(long)]
+    pub once: bool,
+    #[command(flatten)]
+    pub args: Args,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WatchFile {
+    /// Directory converted exports are moved to
+    move_to: PathBuf,
+    /// Outputs of matches without their own
+    #[serde(default)]
+    output: Vec<PathBuf>,
+    #[serde(rename = "match")]
+    matches: Vec<Match>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Match {
+    /// Glob the file name has to match
+    name: Option<String>,
+    /// Format the content has to be detected as, and is read as
+    format: Option<Format>,
+    profile: Option<String>,
+    #[serde(default)]
+    output: Vec<PathBuf>,
+}
+
+impl Match {
+    /// Whether the export at `path`, detected as `detected`, is matched.
+    fn matches(&self, path: &Path, detected: Option<&Format>) -> Result<bool> {
+        if let Some(name) = &self.name {
+            let pattern = Pattern::new(name)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Invalid file name glob '{}'", name))?;
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !pattern.matches(&file_name) {
+                return Ok(false);
+            }
+        }
+        Ok(match &self.format {
+            Some(format) => detected.is_some_and(|d| d.name() == format.name()),
+            None => self.name.is_some(),
+        })
+    }
+}
+
+/// Size and modification time, telling whether a file changed.
+type Stamp = (u64, Option<SystemTime>);
+
+impl Watch {
+    pub fn run(mut self) -> Result<()> {
+        let mut watch: WatchFile = config::read_toml(&self.watch_file)?;
+        let base = self
+            .watch_file
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        watch.move_to = base.join(&watch.move_to);
+        for output in watch
+            .output
+            .iter_mut()
+            .chain(watch.matches.iter_mut().flat_map(|m| m.output.iter_mut()))
+        {
+            *output = base.join(&*output);
+        }
+        if watch.output.is_empty() {
+            watch.output.clone_from(&self.args.output);
+        }
+        self.args.output_options.append = true;
+
+        let dir = self.args.input.clone();
+        info!(dir = %dir.display(), "Watching for exports");
+        let mut seen: HashMap<PathBuf, Stamp> = HashMap::new();
+        // Exports not converted, and unchanged since then
+        let mut passed: HashMap<PathBuf, Stamp> = HashMap::new();
+        loop {
+            for path in exports(&dir)? {
+                let stamp = stamp(&path);
+                // Still being written otherwise
+                let settled = self.once || seen.get(&path) == Some(&stamp);
+                seen.insert(path.clone(), stamp);
+                if !settled || passed.get(&path) == Some(&stamp) {
+                    continue;
+                }
+
+                match self.convert(&watch, &path) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        passed.insert(path, stamp);
+                    }
+                    Err(err) => {
+                        error!(path = %path.display(), "{:?}", err);
+                        passed.insert(path, stamp);
+                    }
+                }
+            }
+            seen.retain(|path, _| path.exists());
+            passed.retain(|path, _| path.exists());
+
+            if self.once {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_secs(self.interval));
+        }
+    }
+
+    /// Converts the export if a match picks it up and moves it away.
+    fn convert(&self, watch: &WatchFile, path: &Path) -> Result<bool> {
+        let detected = inputs::read(path, &[])
+            .and_then(|content| {
+                inputs::open(
+                    path,
+                    content,
+                    None,
+                    None,
+                    None,
+                    false,
+                    &InputOptions::default(),
+                )
+            })
+            .ok()
+            .and_then(|inputs| inputs.into_iter().next())
+            .map(|input| input.format);
+        let mut matched = None;
+        for m in &watch.matches {
+            if m.matches(path, detected.as_ref())? {
+                matched = Some(m);
+                break;
+            }
+        }
+        let Some(matched) = matched else {
+            debug!(path = %path.display(), "No match for file");
+            return Ok(false);
+        };
+
+        let mut args = self.args.clone();
+        args.input = path.to_path_buf();
+        args.format = matched.format.clone().or(args.format);
+        args.profile = matched.profile.clone().or(args.profile);
+        args.output = match matched.output.is_empty() {
+            true => watch.output.clone(),
+            false => matched.output.clone(),
+        };
+        if args.output.is_empty() {
+            return Err(miette!("No outputs for {}", path.display()));
+        }
+        convert::run(&args)?;
+
+        fs::create_dir_all(&watch.move_to)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed creating {}", watch.move_to.display()))?;
+        let moved = free_path(&watch.move_to, path);
+        fs::rename(path, &moved)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed moving {} to {}", path.display(), moved.display()))?;
+        info!(path = %path.display(), to = %moved.display(), "Converted export");
+        Ok(true)
+    }
+}
+
+/// The files in `dir`, leaving out hidden files and unfinished downloads.
+fn exports(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(dir)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed reading {}", dir.display()))?;
+
+    let mut exports = Vec::new();
+    for entry in entries {
+        let path = entry.into_diagnostic()?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let partial = [".part", ".crdownload", ".download", ".tmp"]
+            .iter()
+            .any(|e| name.ends_with(e));
+        if path.is_file() && !name.starts_with('.') && !partial {
+            exports.push(path);
+        }
+    }
+    exports.sort();
+    Ok(exports)
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok();
+    (
+        metadata.as_ref().map_or(0, |m| m.len()),
+        metadata.and_then(|m| m.modified().ok()),
+    )
+}
+
+/// A path in `dir` for the file, not overwriting earlier ones of the same name.
+fn free_path(dir: &Path, path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    let mut moved = dir.join(name);
+    let mut count = 1;
+    while moved.exists() {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        moved = match path.extension() {
+            Some(extension) => dir.join(format!(
+                "{}-{}.{}",
+                stem,
+                count,
+                extension.to_string_lossy()
+            )),
+            None => dir.join(format!("{}-{}", stem, count)),
+        };
+        count += 1;
+    }
+    moved
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_match() {
+        let by_name = Match {
+            name: Some("Umsaetze_*.csv".to_string()),
+            format: None,
+            profile: Some("giro".to_string()),
+            output: Vec::new(),
+        };
+        let by_format = Match {
+            name: None,
+            format: Some(Format::Camt),
+            profile: None,
+            output: Vec::new(),
+        };
+        let path = Path::new("/home/max/Downloads/Umsaetze_2024-03.csv");
+
+        assert!(by_name.matches(path, None).unwrap());
+        assert!(!by_name.matches(Path::new("statement.xml"), None).unwrap());
+        assert!(by_format
+            .matches(Path::new("statement.xml"), Some(&Format::Camt))
+            .unwrap());
+        assert!(!by_format.matches(path, Some(&Format::Postbank)).unwrap());
+
+        let dir = std::env::temp_dir().join(format!("hbconv-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("export.csv"), "").unwrap();
+        assert_eq!(
+            free_path(&dir, Path::new("/tmp/export.csv")),
+            dir.join("export-1.csv")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}